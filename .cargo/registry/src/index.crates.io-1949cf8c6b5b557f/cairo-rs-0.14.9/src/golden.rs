@@ -0,0 +1,483 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Golden-image tests for custom drawing code.
+//!
+//! [`GoldenTest::check()`] renders a closure to an image surface and compares
+//! it with a reference PNG. On mismatch the rendered image and a diff image
+//! are written next to the reference. Set the `CAIRO_BLESS` environment
+//! variable to store the rendered images as the new references instead.
+//!
+//! ```no_run
+//! use cairo::golden::GoldenTest;
+//!
+//! GoldenTest::new("tests/golden")
+//!     .tolerance(2)
+//!     .check("red-square", 32, 32, |cr| {
+//!         cr.set_source_rgb(1.0, 0.0, 0.0);
+//!         cr.rectangle(8.0, 8.0, 16.0, 16.0);
+//!         cr.fill()
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::{Context, Error, Format, ImageSurface, IoError};
+
+/// The environment variable that makes [`GoldenTest::check()`] write
+/// references instead of comparing with them.
+pub const BLESS_ENV_VAR: &str = "CAIRO_BLESS";
+
+#[derive(Error, Debug)]
+pub enum GoldenError {
+    #[error("Reference image {0} does not exist, set CAIRO_BLESS=1 to create it")]
+    Missing(PathBuf),
+    #[error("Rendered image is {actual:?} but the reference is {expected:?}")]
+    SizeMismatch {
+        expected: (i32, i32),
+        actual: (i32, i32),
+    },
+    #[error("{} pixels differ from {reference}, see {diff}", .comparison.different_pixels)]
+    Mismatch {
+        reference: PathBuf,
+        diff: PathBuf,
+        comparison: Comparison,
+    },
+    #[error("Cairo error: {0}")]
+    Cairo(#[from] Error),
+    #[error(transparent)]
+    Io(#[from] IoError),
+}
+
+impl From<std::io::Error> for GoldenError {
+    fn from(err: std::io::Error) -> Self {
+        GoldenError::Io(IoError::Io(err))
+    }
+}
+
+/// The result of comparing two images, see [`GoldenTest::compare()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    /// The number of pixels that differ beyond the tolerances.
+    pub different_pixels: usize,
+    /// The largest difference of a single channel over all pixels.
+    pub max_channel_difference: u8,
+    /// The largest difference of the alpha channel over all pixels.
+    pub max_alpha_difference: u8,
+    /// The largest CIE76 colour difference (ΔE) over all pixels, where about
+    /// 2.3 is just noticeable.
+    pub max_perceptual_difference: f64,
+}
+
+/// Renders images and compares them with reference PNGs in a directory.
+#[derive(Debug, Clone)]
+pub struct GoldenTest {
+    dir: PathBuf,
+    tolerance: Option<u8>,
+    perceptual_threshold: Option<f64>,
+    max_different_pixels: usize,
+    bless: bool,
+}
+
+impl GoldenTest {
+    /// Uses the reference images in `dir`, blessing them if the
+    /// [`BLESS_ENV_VAR`] environment variable is set.
+    ///
+    /// By default images must match exactly.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            tolerance: None,
+            perceptual_threshold: None,
+            max_different_pixels: 0,
+            bless: std::env::var_os(BLESS_ENV_VAR).map_or(false, |value| value != "0"),
+        }
+    }
+
+    /// Accepts pixels whose channels, including alpha, all differ by at most
+    /// `tolerance`.
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// Accepts pixels whose colour difference (ΔE) is at most `threshold`,
+    /// which tolerates rendering differences that are hard to see.
+    ///
+    /// Alpha is compared separately against the
+    /// [`tolerance()`](Self::tolerance), zero by default. If both are set a
+    /// pixel differs when it fails either of them.
+    pub fn perceptual_threshold(mut self, threshold: f64) -> Self {
+        self.perceptual_threshold = Some(threshold);
+        self
+    }
+
+    // Whether a pixel counts as different given the largest difference of
+    // its colour channels, of its alpha channel and its colour difference.
+    fn differs(&self, channel_difference: u8, alpha_difference: u8, perceptual: f64) -> bool {
+        let tolerance = self.tolerance.unwrap_or(0);
+        let colour_differs = match self.perceptual_threshold {
+            Some(threshold) => {
+                perceptual > threshold
+                    || self
+                        .tolerance
+                        .map_or(false, |tolerance| channel_difference > tolerance)
+            }
+            None => channel_difference > tolerance,
+        };
+        colour_differs || alpha_difference > tolerance
+    }
+
+    /// Accepts images where up to `count` pixels differ.
+    pub fn max_different_pixels(mut self, count: usize) -> Self {
+        self.max_different_pixels = count;
+        self
+    }
+
+    /// Overrides the [`BLESS_ENV_VAR`] environment variable.
+    pub fn bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    pub fn reference_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.png", name))
+    }
+
+    /// Renders `draw` to a transparent [`ARgb32`](Format::ARgb32) surface.
+    pub fn render<F: FnOnce(&Context) -> Result<(), Error>>(
+        width: i32,
+        height: i32,
+        draw: F,
+    ) -> Result<ImageSurface, Error> {
+        let surface = ImageSurface::create(Format::ARgb32, width, height)?;
+        {
+            let cr = Context::new(&surface)?;
+            draw(&cr)?;
+        }
+        surface.flush();
+        Ok(surface)
+    }
+
+    /// Renders `draw` and compares the result with the reference image
+    /// `name`, see [`check_surface()`](Self::check_surface).
+    pub fn check<F: FnOnce(&Context) -> Result<(), Error>>(
+        &self,
+        name: &str,
+        width: i32,
+        height: i32,
+        draw: F,
+    ) -> Result<(), GoldenError> {
+        let surface = Self::render(width, height, draw)?;
+        self.check_surface(name, &surface)
+    }
+
+    /// Compares `surface` with the reference image `name`.
+    ///
+    /// On mismatch `name.actual.png` and `name.diff.png` are written next to
+    /// the reference, the latter showing differing pixels in red over a faded
+    /// copy of the reference.
+    pub fn check_surface(&self, name: &str, surface: &ImageSurface) -> Result<(), GoldenError> {
+        let reference_path = self.reference_path(name);
+        let actual_path = self.dir.join(format!("{}.actual.png", name));
+        let diff_path = self.dir.join(format!("{}.diff.png", name));
+
+        if self.bless {
+            std::fs::create_dir_all(&self.dir)?;
+            write_png(surface, &reference_path)?;
+            let _ = std::fs::remove_file(&actual_path);
+            let _ = std::fs::remove_file(&diff_path);
+            return Ok(());
+        }
+        if !reference_path.exists() {
+            return Err(GoldenError::Missing(reference_path));
+        }
+
+        let reference = read_png(&reference_path)?;
+        let (comparison, diff) = self.compare_with_diff(&reference, surface)?;
+        if comparison.different_pixels <= self.max_different_pixels {
+            let _ = std::fs::remove_file(&actual_path);
+            let _ = std::fs::remove_file(&diff_path);
+            return Ok(());
+        }
+
+        write_png(surface, &actual_path)?;
+        write_png(&diff, &diff_path)?;
+        Err(GoldenError::Mismatch {
+            reference: reference_path,
+            diff: diff_path,
+            comparison,
+        })
+    }
+
+    /// Compares two images of the same size with the tolerances of this test.
+    pub fn compare(
+        &self,
+        expected: &ImageSurface,
+        actual: &ImageSurface,
+    ) -> Result<Comparison, GoldenError> {
+        self.compare_with_diff(expected, actual)
+            .map(|(comparison, _)| comparison)
+    }
+
+    fn compare_with_diff(
+        &self,
+        expected: &ImageSurface,
+        actual: &ImageSurface,
+    ) -> Result<(Comparison, ImageSurface), GoldenError> {
+        let size = |surface: &ImageSurface| (surface.width(), surface.height());
+        if size(expected) != size(actual) {
+            return Err(GoldenError::SizeMismatch {
+                expected: size(expected),
+                actual: size(actual),
+            });
+        }
+        let (width, height) = size(expected);
+        let expected = argb32_pixels(expected)?;
+        let actual = argb32_pixels(actual)?;
+
+        let mut comparison = Comparison {
+            different_pixels: 0,
+            max_channel_difference: 0,
+            max_alpha_difference: 0,
+            max_perceptual_difference: 0.0,
+        };
+        let mut diff = Vec::with_capacity(expected.len());
+        for (&e, &a) in expected.iter().zip(actual.iter()) {
+            let difference = |shift: u32| {
+                ((e >> shift) as u8 as i32 - (a >> shift) as u8 as i32).unsigned_abs() as u8
+            };
+            let channel_difference = difference(0).max(difference(8)).max(difference(16));
+            let alpha_difference = difference(24);
+            let perceptual_difference = delta_e(lab_over_white(e), lab_over_white(a));
+            comparison.max_channel_difference = comparison
+                .max_channel_difference
+                .max(channel_difference)
+                .max(alpha_difference);
+            comparison.max_alpha_difference = comparison.max_alpha_difference.max(alpha_difference);
+            comparison.max_perceptual_difference = comparison
+                .max_perceptual_difference
+                .max(perceptual_difference);
+
+            if self.differs(channel_difference, alpha_difference, perceptual_difference) {
+                comparison.different_pixels += 1;
+                diff.push(0xffff_0000);
+            } else {
+                // Faded gray version of the reference for context.
+                let [l, _, _] = lab_over_white(e);
+                let gray = (255.0 - (100.0 - l) * 0.6).round() as u32;
+                diff.push(0xff00_0000 | gray << 16 | gray << 8 | gray);
+            }
+        }
+
+        let stride = Format::ARgb32.stride_for_width(width as u32)?;
+        let mut data = vec![0; stride as usize * height as usize];
+        for (row, pixels) in data
+            .chunks_exact_mut(stride as usize)
+            .zip(diff.chunks_exact(width as usize))
+        {
+            for (out, pixel) in row.chunks_exact_mut(4).zip(pixels) {
+                out.copy_from_slice(&pixel.to_ne_bytes());
+            }
+        }
+        let diff = ImageSurface::create_for_data(data, Format::ARgb32, width, height, stride)?;
+        Ok((comparison, diff))
+    }
+}
+
+// Converts any surface to premultiplied ARGB pixels without row padding.
+fn argb32_pixels(surface: &ImageSurface) -> Result<Vec<u32>, Error> {
+    let width = surface.width();
+    let height = surface.height();
+    let mut converted = ImageSurface::create(Format::ARgb32, width, height)?;
+    {
+        let cr = Context::new(&converted)?;
+        cr.set_source_surface(surface, 0.0, 0.0)?;
+        cr.set_operator(crate::Operator::Source);
+        cr.paint()?;
+    }
+
+    let stride = converted.stride() as usize;
+    let data = converted.data().map_err(|_| Error::SurfaceFinished)?;
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in data.chunks(stride).take(height as usize) {
+        for pixel in row.chunks_exact(4).take(width as usize) {
+            pixels.push(u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]));
+        }
+    }
+    Ok(pixels)
+}
+
+// CIELAB coordinates of a premultiplied ARGB pixel composited over white.
+fn lab_over_white(argb: u32) -> [f64; 3] {
+    let alpha = (argb >> 24) as u8 as f64 / 255.0;
+    let channel = |shift: u32| {
+        let c = (argb >> shift) as u8 as f64 / 255.0 + 1.0 - alpha;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (channel(16), channel(8), channel(0));
+
+    // Relative to the D65 white point.
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn delta_e(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+fn read_png(path: &Path) -> Result<ImageSurface, IoError> {
+    let mut file = File::open(path)?;
+    #[cfg(any(feature = "png", feature = "dox"))]
+    {
+        ImageSurface::create_from_png(&mut file)
+    }
+    #[cfg(not(any(feature = "png", feature = "dox")))]
+    {
+        ImageSurface::decode_png(&mut file)
+    }
+}
+
+fn write_png(surface: &ImageSurface, path: &Path) -> Result<(), IoError> {
+    let mut file = File::create(path)?;
+    #[cfg(any(feature = "png", feature = "dox"))]
+    {
+        surface.write_to_png(&mut file)
+    }
+    #[cfg(not(any(feature = "png", feature = "dox")))]
+    {
+        surface.encode_png(&mut file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(red: f64) -> impl FnOnce(&Context) -> Result<(), Error> {
+        move |cr| {
+            cr.set_source_rgb(red, 0.0, 0.0);
+            cr.rectangle(2.0, 2.0, 4.0, 4.0);
+            cr.fill()
+        }
+    }
+
+    #[test]
+    fn compare_with_tolerances() {
+        let expected = GoldenTest::render(8, 8, square(1.0)).unwrap();
+        let actual = GoldenTest::render(8, 8, square(0.98)).unwrap();
+
+        let exact = GoldenTest::new(".").compare(&expected, &actual).unwrap();
+        assert_eq!(exact.different_pixels, 16);
+        assert_eq!(exact.max_channel_difference, 5);
+        assert!(exact.max_perceptual_difference > 0.0);
+        assert!(exact.max_perceptual_difference < 2.3);
+
+        let tolerant = GoldenTest::new(".").tolerance(5);
+        assert_eq!(
+            tolerant
+                .compare(&expected, &actual)
+                .unwrap()
+                .different_pixels,
+            0
+        );
+        let perceptual = GoldenTest::new(".").perceptual_threshold(2.3);
+        assert_eq!(
+            perceptual
+                .compare(&expected, &actual)
+                .unwrap()
+                .different_pixels,
+            0
+        );
+
+        // Either check failing makes a pixel different.
+        let both = GoldenTest::new(".").tolerance(5).perceptual_threshold(0.1);
+        assert_eq!(
+            both.compare(&expected, &actual).unwrap().different_pixels,
+            16
+        );
+
+        let smaller = GoldenTest::render(4, 8, square(1.0)).unwrap();
+        assert!(matches!(
+            GoldenTest::new(".").compare(&expected, &smaller),
+            Err(GoldenError::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn compare_alpha() {
+        let white = |alpha: f64| {
+            move |cr: &Context| {
+                cr.set_source_rgba(1.0, 1.0, 1.0, alpha);
+                cr.paint()
+            }
+        };
+        let expected = GoldenTest::render(2, 2, white(1.0)).unwrap();
+        let actual = GoldenTest::render(2, 2, white(0.9)).unwrap();
+
+        // Over white both look the same, but alpha still has to match.
+        let perceptual = GoldenTest::new(".").perceptual_threshold(2.3);
+        let comparison = perceptual.compare(&expected, &actual).unwrap();
+        assert!(comparison.max_perceptual_difference < 0.1);
+        assert_eq!(comparison.max_alpha_difference, 25);
+        assert_eq!(comparison.different_pixels, 4);
+
+        let tolerant = perceptual.tolerance(25);
+        assert_eq!(
+            tolerant
+                .compare(&expected, &actual)
+                .unwrap()
+                .different_pixels,
+            0
+        );
+    }
+
+    #[test]
+    fn bless_and_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let test = GoldenTest::new(dir.path()).bless(false);
+
+        assert!(matches!(
+            test.check("square", 8, 8, square(1.0)),
+            Err(GoldenError::Missing(_))
+        ));
+        test.clone()
+            .bless(true)
+            .check("square", 8, 8, square(1.0))
+            .unwrap();
+        assert!(dir.path().join("square.png").exists());
+        test.check("square", 8, 8, square(1.0)).unwrap();
+
+        match test.check("square", 8, 8, square(0.5)) {
+            Err(GoldenError::Mismatch {
+                diff, comparison, ..
+            }) => {
+                assert_eq!(comparison.different_pixels, 16);
+                assert!(diff.exists());
+                assert!(dir.path().join("square.actual.png").exists());
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        test.clone()
+            .max_different_pixels(16)
+            .check("square", 8, 8, square(0.5))
+            .unwrap();
+        assert!(!dir.path().join("square.diff.png").exists());
+    }
+}
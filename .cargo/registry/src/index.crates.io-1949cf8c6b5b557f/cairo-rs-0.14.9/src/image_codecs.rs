@@ -0,0 +1,415 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::io::{self, Read};
+
+use crate::error::{BorrowError, IoError};
+use crate::{Error, Format, ImageSurface};
+
+// Same rounding as cairo's own PNG routines, so that images round-trip to
+// identical pixels whichever implementation is used.
+fn premultiply(alpha: u8, color: u8) -> u8 {
+    let temp = alpha as u32 * color as u32 + 0x80;
+    ((temp + (temp >> 8)) >> 8) as u8
+}
+
+fn unpremultiply(alpha: u8, color: u8) -> u8 {
+    if alpha == 0 {
+        0
+    } else {
+        ((color as u32 * 255 + alpha as u32 / 2) / alpha as u32).min(255) as u8
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> IoError {
+    IoError::Io(io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+// The 8 bits per sample layouts that decoders produce and encoders take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+}
+
+impl Layout {
+    fn channels(self) -> usize {
+        match self {
+            Layout::Gray => 1,
+            Layout::GrayAlpha => 2,
+            Layout::Rgb => 3,
+            Layout::Rgba => 4,
+        }
+    }
+
+    fn has_alpha(self) -> bool {
+        self == Layout::GrayAlpha || self == Layout::Rgba
+    }
+}
+
+// Converts straight alpha pixels with rows of `row_len` bytes into an
+// `ARgb32` surface, or an `Rgb24` one for opaque layouts.
+fn surface_from_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    row_len: usize,
+    layout: Layout,
+) -> Result<ImageSurface, IoError> {
+    if width > i32::MAX as u32 || height > i32::MAX as u32 {
+        return Err(Error::InvalidSize.into());
+    }
+    let format = if layout.has_alpha() {
+        Format::ARgb32
+    } else {
+        Format::Rgb24
+    };
+    let stride = format.stride_for_width(width)? as usize;
+    if pixels.len() < row_len * height as usize {
+        return Err(invalid_data("truncated image data"));
+    }
+
+    let mut data = vec![0; stride * height as usize];
+    for (src, dst) in pixels.chunks(row_len).zip(data.chunks_exact_mut(stride)) {
+        let src = src.chunks_exact(layout.channels()).take(width as usize);
+        for (pixel, out) in src.zip(dst.chunks_exact_mut(4)) {
+            let (r, g, b, a) = match layout {
+                Layout::Gray => (pixel[0], pixel[0], pixel[0], 0xff),
+                Layout::GrayAlpha => (pixel[0], pixel[0], pixel[0], pixel[1]),
+                Layout::Rgb => (pixel[0], pixel[1], pixel[2], 0xff),
+                Layout::Rgba => (pixel[0], pixel[1], pixel[2], pixel[3]),
+            };
+            let argb = (a as u32) << 24
+                | (premultiply(a, r) as u32) << 16
+                | (premultiply(a, g) as u32) << 8
+                | premultiply(a, b) as u32;
+            out.copy_from_slice(&argb.to_ne_bytes());
+        }
+    }
+
+    Ok(ImageSurface::create_for_data(
+        data,
+        format,
+        width as i32,
+        height as i32,
+        stride as i32,
+    )?)
+}
+
+// Returns the tightly packed, straight alpha pixels of `surface`.
+#[cfg(any(feature = "codec-png", feature = "dox", test))]
+fn pixels_from_surface(surface: &ImageSurface) -> Result<(Layout, Vec<u8>), IoError> {
+    let layout = match surface.format() {
+        Format::ARgb32 => Layout::Rgba,
+        Format::Rgb24 => Layout::Rgb,
+        // Like cairo, store the alpha values of masks as gray levels.
+        Format::A8 => Layout::Gray,
+        _ => return Err(Error::InvalidFormat.into()),
+    };
+    let width = surface.width() as usize;
+    let height = surface.height() as usize;
+    let stride = surface.stride() as usize;
+
+    let mut pixels = Vec::with_capacity(width * height * layout.channels());
+    surface
+        .with_data(|data| {
+            for row in data.chunks(stride).take(height) {
+                if layout == Layout::Gray {
+                    pixels.extend_from_slice(&row[..width]);
+                    continue;
+                }
+                for pixel in row.chunks_exact(4).take(width) {
+                    let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                    let a = (argb >> 24) as u8;
+                    let (r, g, b) = ((argb >> 16) as u8, (argb >> 8) as u8, argb as u8);
+                    if layout == Layout::Rgb {
+                        pixels.extend_from_slice(&[r, g, b]);
+                    } else {
+                        pixels.extend_from_slice(&[
+                            unpremultiply(a, r),
+                            unpremultiply(a, g),
+                            unpremultiply(a, b),
+                            a,
+                        ]);
+                    }
+                }
+            }
+        })
+        .map_err(|err| match err {
+            BorrowError::Cairo(err) => IoError::Cairo(err),
+            BorrowError::NonExclusive => IoError::Cairo(Error::SurfaceFinished),
+        })?;
+    Ok((layout, pixels))
+}
+
+impl ImageSurface {
+    /// Decodes a PNG image without using cairo's PNG support.
+    ///
+    /// Unlike [`create_from_png()`](Self::create_from_png), this does not
+    /// depend on libcairo being built with PNG support. Images with an alpha
+    /// channel result in an [`ARgb32`](Format::ARgb32) surface, all others in
+    /// an [`Rgb24`](Format::Rgb24) surface.
+    #[cfg(any(feature = "codec-png", feature = "dox"))]
+    pub fn decode_png<R: Read>(stream: &mut R) -> Result<ImageSurface, IoError> {
+        let mut decoder = png_crate::Decoder::new(stream);
+        decoder.set_transformations(
+            png_crate::Transformations::EXPAND | png_crate::Transformations::STRIP_16,
+        );
+        let mut reader = decoder.read_info().map_err(io::Error::from)?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).map_err(io::Error::from)?;
+
+        let layout = match info.color_type {
+            png_crate::ColorType::Grayscale => Layout::Gray,
+            png_crate::ColorType::GrayscaleAlpha => Layout::GrayAlpha,
+            png_crate::ColorType::Rgb => Layout::Rgb,
+            png_crate::ColorType::Rgba => Layout::Rgba,
+            png_crate::ColorType::Indexed => {
+                return Err(invalid_data("indexed PNG was not expanded"))
+            }
+        };
+        surface_from_pixels(&pixels, info.width, info.height, info.line_size, layout)
+    }
+
+    /// Encodes the surface as a PNG image without using cairo's PNG support.
+    ///
+    /// Only [`ARgb32`](Format::ARgb32), [`Rgb24`](Format::Rgb24) and
+    /// [`A8`](Format::A8) surfaces are supported.
+    #[cfg(any(feature = "codec-png", feature = "dox"))]
+    pub fn encode_png<W: io::Write>(&self, stream: &mut W) -> Result<(), IoError> {
+        let (layout, pixels) = pixels_from_surface(self)?;
+        let mut encoder =
+            png_crate::Encoder::new(stream, self.width() as u32, self.height() as u32);
+        encoder.set_color(match layout {
+            Layout::Gray => png_crate::ColorType::Grayscale,
+            Layout::GrayAlpha => png_crate::ColorType::GrayscaleAlpha,
+            Layout::Rgb => png_crate::ColorType::Rgb,
+            Layout::Rgba => png_crate::ColorType::Rgba,
+        });
+        encoder.set_depth(png_crate::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::from)?;
+        writer.write_image_data(&pixels).map_err(io::Error::from)?;
+        writer.finish().map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    /// Decodes a baseline or progressive JPEG image into an
+    /// [`Rgb24`](Format::Rgb24) surface.
+    #[cfg(any(feature = "codec-jpeg", feature = "dox"))]
+    pub fn decode_jpeg<R: Read>(stream: &mut R) -> Result<ImageSurface, IoError> {
+        let mut decoder = jpeg_decoder::Decoder::new(io::BufReader::new(stream));
+        let pixels = decoder.decode().map_err(invalid_data)?;
+        let info = decoder
+            .info()
+            .ok_or_else(|| invalid_data("missing JPEG header"))?;
+        let width = info.width as u32;
+        let height = info.height as u32;
+
+        match info.pixel_format {
+            jpeg_decoder::PixelFormat::L8 => {
+                surface_from_pixels(&pixels, width, height, width as usize, Layout::Gray)
+            }
+            jpeg_decoder::PixelFormat::L16 => {
+                // Samples are big-endian, keep the most significant byte.
+                let pixels: Vec<u8> = pixels.chunks_exact(2).map(|sample| sample[0]).collect();
+                surface_from_pixels(&pixels, width, height, width as usize, Layout::Gray)
+            }
+            jpeg_decoder::PixelFormat::RGB24 => {
+                surface_from_pixels(&pixels, width, height, width as usize * 3, Layout::Rgb)
+            }
+            jpeg_decoder::PixelFormat::CMYK32 => {
+                let mut rgb = Vec::with_capacity(pixels.len() / 4 * 3);
+                for cmyk in pixels.chunks_exact(4) {
+                    let k = 255 - cmyk[3] as u32;
+                    let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+                    rgb.extend_from_slice(&[channel(cmyk[0]), channel(cmyk[1]), channel(cmyk[2])]);
+                }
+                surface_from_pixels(&rgb, width, height, width as usize * 3, Layout::Rgb)
+            }
+        }
+    }
+
+    /// Decodes a lossy or lossless WebP image. Animated images are decoded
+    /// to their first frame.
+    #[cfg(any(feature = "codec-webp", feature = "dox"))]
+    pub fn decode_webp<R: Read>(stream: &mut R) -> Result<ImageSurface, IoError> {
+        // The decoder needs to seek around the RIFF chunks.
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+        let mut decoder =
+            image_webp::WebPDecoder::new(io::Cursor::new(data)).map_err(invalid_data)?;
+        let (width, height) = decoder.dimensions();
+        let layout = if decoder.has_alpha() {
+            Layout::Rgba
+        } else {
+            Layout::Rgb
+        };
+        let len = decoder
+            .output_buffer_size()
+            .ok_or_else(|| invalid_data("WebP image is too large"))?;
+        let mut pixels = vec![0; len];
+        decoder.read_image(&mut pixels).map_err(invalid_data)?;
+        surface_from_pixels(
+            &pixels,
+            width,
+            height,
+            width as usize * layout.channels(),
+            layout,
+        )
+    }
+
+    /// Decodes an image, detecting its format from the data.
+    ///
+    /// Only the formats whose `codec-*` feature is enabled are supported,
+    /// anything else fails with an [`InvalidData`](io::ErrorKind::InvalidData)
+    /// error.
+    #[cfg(any(
+        feature = "codec-png",
+        feature = "codec-jpeg",
+        feature = "codec-webp",
+        feature = "dox"
+    ))]
+    pub fn decode_image<R: Read>(stream: &mut R) -> Result<ImageSurface, IoError> {
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+        let mut data = &data[..];
+
+        #[cfg(any(feature = "codec-png", feature = "dox"))]
+        {
+            if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+                return Self::decode_png(&mut data);
+            }
+        }
+        #[cfg(any(feature = "codec-jpeg", feature = "dox"))]
+        {
+            if data.starts_with(b"\xff\xd8\xff") {
+                return Self::decode_jpeg(&mut data);
+            }
+        }
+        #[cfg(any(feature = "codec-webp", feature = "dox"))]
+        {
+            if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+                return Self::decode_webp(&mut data);
+            }
+        }
+
+        Err(invalid_data("unsupported image format"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn premultiplication_round_trips() {
+        for alpha in 1..=255 {
+            for color in 0..=255 {
+                let premultiplied = premultiply(alpha, color);
+                assert!(premultiplied <= alpha);
+                let restored = unpremultiply(alpha, premultiplied);
+                // Precision is lost at low alpha, but never more than the
+                // premultiplied value can represent.
+                assert!((restored as i32 - color as i32).abs() <= (255 / alpha as i32 + 1) / 2);
+            }
+        }
+        assert_eq!(unpremultiply(0, 0), 0);
+        assert_eq!(premultiply(255, 200), 200);
+    }
+
+    #[test]
+    fn pixels_round_trip_through_surfaces() {
+        let pixels = [
+            255, 0, 0, 255, 0, 255, 0, 128, //
+            0, 0, 255, 0, 10, 20, 30, 255,
+        ];
+        let surface = surface_from_pixels(&pixels, 2, 2, 8, Layout::Rgba).unwrap();
+        assert_eq!(surface.format(), Format::ARgb32);
+        let (layout, round_tripped) = pixels_from_surface(&surface).unwrap();
+        assert_eq!(layout, Layout::Rgba);
+        // Fully transparent pixels lose their colour.
+        assert_eq!(
+            round_tripped,
+            [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255]
+        );
+
+        let gray =
+            surface_from_pixels(&[7, 8, 9, 0, 10, 11, 12, 0], 3, 2, 4, Layout::Gray).unwrap();
+        assert_eq!(gray.format(), Format::Rgb24);
+        let (layout, rgb) = pixels_from_surface(&gray).unwrap();
+        assert_eq!(layout, Layout::Rgb);
+        assert_eq!(&rgb[..6], &[7, 7, 7, 8, 8, 8]);
+        assert_eq!(&rgb[9..12], &[10, 10, 10]);
+    }
+
+    #[cfg(feature = "codec-png")]
+    #[test]
+    fn png_round_trip() {
+        let surface = ImageSurface::create(Format::ARgb32, 3, 2).unwrap();
+        {
+            let cr = crate::Context::new(&surface).unwrap();
+            cr.set_source_rgba(1.0, 0.5, 0.0, 0.5);
+            cr.paint().unwrap();
+        }
+
+        let mut png = Vec::new();
+        surface.encode_png(&mut png).unwrap();
+        let decoded = ImageSurface::decode_png(&mut &png[..]).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        assert_eq!(decoded.format(), Format::ARgb32);
+
+        let (_, original) = pixels_from_surface(&surface).unwrap();
+        let (_, decoded) = pixels_from_surface(&decoded).unwrap();
+        assert_eq!(original, decoded);
+
+        let detected = ImageSurface::decode_image(&mut &png[..]).unwrap();
+        assert_eq!(detected.width(), 3);
+        assert!(ImageSurface::decode_image(&mut &b"GIF89a"[..]).is_err());
+    }
+
+    #[cfg(feature = "codec-jpeg")]
+    #[test]
+    fn jpeg_decoding() {
+        // A red and a blue 8×8 block.
+        let jpeg = include_bytes!("../tests/two-colours.jpg");
+        let decoded = ImageSurface::decode_jpeg(&mut &jpeg[..]).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+        assert_eq!(decoded.format(), Format::Rgb24);
+
+        let (_, pixels) = pixels_from_surface(&decoded).unwrap();
+        let close = |pixel: &[u8], expected: [u8; 3]| {
+            pixel
+                .iter()
+                .zip(&expected)
+                .all(|(&a, &b)| (a as i32 - b as i32).abs() <= 2)
+        };
+        assert!(close(&pixels[..3], [255, 0, 0]), "{:?}", &pixels[..3]);
+        assert!(close(&pixels[15 * 3..16 * 3], [0, 0, 255]));
+
+        let detected = ImageSurface::decode_image(&mut &jpeg[..]).unwrap();
+        assert_eq!(detected.width(), 16);
+        assert!(ImageSurface::decode_jpeg(&mut &jpeg[..100]).is_err());
+    }
+
+    #[cfg(feature = "codec-webp")]
+    #[test]
+    fn webp_decoding() {
+        // The pixels of `pixels_round_trip_through_surfaces()`, losslessly
+        // encoded.
+        let webp = include_bytes!("../tests/four-pixels.webp");
+        let decoded = ImageSurface::decode_webp(&mut &webp[..]).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 2));
+        assert_eq!(decoded.format(), Format::ARgb32);
+
+        let (_, pixels) = pixels_from_surface(&decoded).unwrap();
+        assert_eq!(
+            pixels,
+            [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255]
+        );
+
+        let detected = ImageSurface::decode_image(&mut &webp[..]).unwrap();
+        assert_eq!(detected.format(), Format::ARgb32);
+        assert!(ImageSurface::decode_webp(&mut &webp[..20]).is_err());
+    }
+}
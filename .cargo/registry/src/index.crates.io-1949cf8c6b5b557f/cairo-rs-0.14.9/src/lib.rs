@@ -20,6 +20,16 @@
 //! * **svg** - Rendering SVG documents
 //! * **ps** - Rendering PostScript documents
 //!
+//! ## Image codec features
+//!
+//! These do not depend on how libcairo was built.
+//!
+//! * **codec-png** - Reading and writing PNG images in Rust
+//! * **codec-jpeg** - Reading JPEG images
+//! * **codec-webp** - Reading WebP images
+//!
+//! The [`golden`] test utilities need either **png** or **codec-png**.
+//!
 //! ## Cairo API version features
 //!
 //! * **v1_16** - Use Cairo 1.16 APIs
@@ -118,6 +128,10 @@ pub use crate::context::{Context, RectangleList};
 
 pub use crate::paths::{Path, PathSegment, PathSegments};
 
+pub use crate::scene::{Paint, Scene, Shape, Stroke};
+
+pub use crate::svg_path::{parse_svg_path, SvgPathError};
+
 pub use crate::device::Device;
 
 pub use crate::enums::*;
@@ -135,6 +149,7 @@ pub use crate::font::{
 
 pub use crate::matrices::Matrix;
 
+pub use crate::recording::{ColorStop, DrawOp, Recorder, Recording, RecordingError};
 pub use crate::recording_surface::RecordingSurface;
 pub use crate::rectangle::Rectangle;
 pub use crate::rectangle_int::RectangleInt;
@@ -176,17 +191,29 @@ mod device;
 mod enums;
 mod error;
 mod font;
+#[cfg(any(feature = "png", feature = "codec-png", feature = "dox"))]
+pub mod golden;
 mod image_surface;
 #[cfg(any(feature = "png", feature = "dox"))]
 mod image_surface_png;
+#[cfg(any(
+    feature = "codec-png",
+    feature = "codec-jpeg",
+    feature = "codec-webp",
+    feature = "dox"
+))]
+mod image_codecs;
 mod matrices;
 mod paths;
 mod patterns;
+pub mod recording;
 mod recording_surface;
 mod rectangle;
 mod rectangle_int;
 mod region;
+mod scene;
 mod surface;
+mod svg_path;
 #[cfg(any(feature = "xcb", feature = "dox"))]
 mod xcb;
 
@@ -0,0 +1,1459 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Serializable recordings of drawing operations.
+//!
+//! A [`Recorder`] draws onto a [`RecordingSurface`] and captures each call as
+//! a [`DrawOp`]. The resulting [`Recording`] can be turned into bytes and
+//! replayed onto any context.
+
+use std::convert::TryFrom;
+use thiserror::Error;
+
+use crate::{
+    Antialias, Content, Context, Error, Extend, FillRule, Filter, FontOptions, FontSlant,
+    FontWeight, Format, Glyph, HintMetrics, HintStyle, ImageSurface, LineCap, LineJoin,
+    LinearGradient, Matrix, Operator, Path, PathSegment, Pattern, PatternType, RadialGradient,
+    RecordingSurface, Rectangle, SolidPattern, SubpixelOrder, SurfacePattern,
+};
+
+const MAGIC: &[u8; 4] = b"CRRC";
+const VERSION: u8 = 1;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RecordingError {
+    #[error("Data is not a serialized recording")]
+    BadMagic,
+    #[error("Unsupported recording version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Recording data ends unexpectedly")]
+    UnexpectedEnd,
+    #[error("Unknown operation {0} in recording")]
+    UnknownOperation(u8),
+    #[error("Invalid recording data: {0}")]
+    Invalid(&'static str),
+    #[error("Recording has a length that does not fit in 32 bits")]
+    TooLarge,
+}
+
+/// A colour stop of a gradient: offset, red, green, blue and alpha.
+pub type ColorStop = (f64, f64, f64, f64, f64);
+
+/// A source pattern captured by a [`Recorder`].
+#[derive(Debug, Clone)]
+pub enum Source {
+    Rgba(f64, f64, f64, f64),
+    Linear {
+        points: (f64, f64, f64, f64),
+        stops: Vec<ColorStop>,
+        extend: Extend,
+        matrix: Matrix,
+    },
+    Radial {
+        circles: (f64, f64, f64, f64, f64, f64),
+        stops: Vec<ColorStop>,
+        extend: Extend,
+        matrix: Matrix,
+    },
+    /// The pixels of an image surface, with rows stored without padding.
+    Image {
+        format: Format,
+        width: i32,
+        height: i32,
+        data: Vec<u8>,
+        extend: Extend,
+        filter: Filter,
+        matrix: Matrix,
+    },
+}
+
+impl Source {
+    /// Captures `pattern`. Only solid, gradient and image surface patterns
+    /// are supported.
+    pub fn from_pattern(pattern: &Pattern) -> Result<Self, Error> {
+        match pattern.type_() {
+            PatternType::Solid => {
+                let (red, green, blue, alpha) = SolidPattern::try_from(pattern.clone())
+                    .map_err(|_| Error::PatternTypeMismatch)?
+                    .rgba()?;
+                Ok(Source::Rgba(red, green, blue, alpha))
+            }
+            PatternType::LinearGradient => {
+                let gradient = LinearGradient::try_from(pattern.clone())
+                    .map_err(|_| Error::PatternTypeMismatch)?;
+                let mut stops = Vec::new();
+                for index in 0..gradient.color_stop_count()? {
+                    stops.push(gradient.color_stop_rgba(index)?);
+                }
+                Ok(Source::Linear {
+                    points: gradient.linear_points()?,
+                    stops,
+                    extend: pattern.extend(),
+                    matrix: pattern.matrix(),
+                })
+            }
+            PatternType::RadialGradient => {
+                let gradient = RadialGradient::try_from(pattern.clone())
+                    .map_err(|_| Error::PatternTypeMismatch)?;
+                let mut stops = Vec::new();
+                for index in 0..gradient.color_stop_count()? {
+                    stops.push(gradient.color_stop_rgba(index)?);
+                }
+                Ok(Source::Radial {
+                    circles: gradient.radial_circles()?,
+                    stops,
+                    extend: pattern.extend(),
+                    matrix: pattern.matrix(),
+                })
+            }
+            PatternType::Surface => {
+                let surface = SurfacePattern::try_from(pattern.clone())
+                    .map_err(|_| Error::PatternTypeMismatch)?
+                    .surface()?;
+                let image =
+                    ImageSurface::try_from(surface).map_err(|_| Error::SurfaceTypeMismatch)?;
+                let (format, data) = image_pixels(&image)?;
+                Ok(Source::Image {
+                    format,
+                    width: image.width(),
+                    height: image.height(),
+                    data,
+                    extend: pattern.extend(),
+                    filter: pattern.filter(),
+                    matrix: pattern.matrix(),
+                })
+            }
+            _ => Err(Error::PatternTypeMismatch),
+        }
+    }
+
+    /// Recreates the pattern.
+    pub fn to_pattern(&self) -> Result<Pattern, Error> {
+        let pattern = match *self {
+            Source::Rgba(red, green, blue, alpha) => {
+                Pattern::clone(&SolidPattern::from_rgba(red, green, blue, alpha))
+            }
+            Source::Linear {
+                points: (x0, y0, x1, y1),
+                ref stops,
+                extend,
+                matrix,
+            } => {
+                let gradient = LinearGradient::new(x0, y0, x1, y1);
+                for &(offset, red, green, blue, alpha) in stops {
+                    gradient.add_color_stop_rgba(offset, red, green, blue, alpha);
+                }
+                gradient.set_extend(extend);
+                gradient.set_matrix(matrix);
+                Pattern::clone(&gradient)
+            }
+            Source::Radial {
+                circles: (x0, y0, r0, x1, y1, r1),
+                ref stops,
+                extend,
+                matrix,
+            } => {
+                let gradient = RadialGradient::new(x0, y0, r0, x1, y1, r1);
+                for &(offset, red, green, blue, alpha) in stops {
+                    gradient.add_color_stop_rgba(offset, red, green, blue, alpha);
+                }
+                gradient.set_extend(extend);
+                gradient.set_matrix(matrix);
+                Pattern::clone(&gradient)
+            }
+            Source::Image {
+                format,
+                width,
+                height,
+                ref data,
+                extend,
+                filter,
+                matrix,
+            } => {
+                let image = image_from_pixels(format, width, height, data)?;
+                let pattern = SurfacePattern::create(&image);
+                pattern.set_extend(extend);
+                pattern.set_filter(filter);
+                pattern.set_matrix(matrix);
+                Pattern::clone(&pattern)
+            }
+        };
+        pattern.status()?;
+        Ok(pattern)
+    }
+}
+
+// Copies the pixels of `image` without row padding.
+fn image_pixels(image: &ImageSurface) -> Result<(Format, Vec<u8>), Error> {
+    let format = image.format();
+    let row_len = row_len(format, image.width())?;
+    let stride = image.stride() as usize;
+    let height = image.height() as usize;
+    let mut pixels = Vec::with_capacity(row_len * height);
+    image
+        .with_data(|data| {
+            for row in data.chunks(stride).take(height) {
+                pixels.extend_from_slice(&row[..row_len]);
+            }
+        })
+        .map_err(|_| Error::SurfaceFinished)?;
+    Ok((format, pixels))
+}
+
+fn image_from_pixels(
+    format: Format,
+    width: i32,
+    height: i32,
+    pixels: &[u8],
+) -> Result<ImageSurface, Error> {
+    let row_len = row_len(format, width)?;
+    if height < 0 {
+        return Err(Error::InvalidSize);
+    }
+    match row_len.checked_mul(height as usize) {
+        Some(len) if len == pixels.len() => (),
+        _ => return Err(Error::InvalidSize),
+    }
+    let stride = format.stride_for_width(width as u32)?;
+    let len = (stride as usize)
+        .checked_mul(height as usize)
+        .ok_or(Error::InvalidSize)?;
+    let mut data = vec![0; len];
+    if row_len > 0 {
+        for (row, src) in data
+            .chunks_exact_mut(stride as usize)
+            .zip(pixels.chunks_exact(row_len))
+        {
+            row[..row_len].copy_from_slice(src);
+        }
+    }
+    ImageSurface::create_for_data(data, format, width, height, stride)
+}
+
+fn row_len(format: Format, width: i32) -> Result<usize, Error> {
+    if width < 0 {
+        return Err(Error::InvalidSize);
+    }
+    let bits = match format {
+        Format::ARgb32 | Format::Rgb24 | Format::Rgb30 => 32,
+        Format::Rgb16_565 => 16,
+        Format::A8 => 8,
+        Format::A1 => 1,
+        _ => return Err(Error::InvalidFormat),
+    };
+    Ok((width as usize * bits + 7) / 8)
+}
+
+/// A drawing operation captured by a [`Recorder`].
+///
+/// Each variant corresponds to the [`Context`] method of the same name.
+#[derive(Debug, Clone)]
+pub enum DrawOp {
+    Save,
+    Restore,
+    Transform(Matrix),
+    SetSource(Source),
+    SetLineWidth(f64),
+    SetLineCap(LineCap),
+    SetLineJoin(LineJoin),
+    SetMiterLimit(f64),
+    SetDash(Vec<f64>, f64),
+    SetFillRule(FillRule),
+    SetOperator(Operator),
+    SetAntialias(Antialias),
+    NewPath,
+    NewSubPath,
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    CurveTo(f64, f64, f64, f64, f64, f64),
+    Rectangle(f64, f64, f64, f64),
+    Arc(f64, f64, f64, f64, f64),
+    ArcNegative(f64, f64, f64, f64, f64),
+    ClosePath,
+    Fill,
+    FillPreserve,
+    Stroke,
+    StrokePreserve,
+    Clip,
+    ClipPreserve,
+    ResetClip,
+    Paint,
+    PaintWithAlpha(f64),
+    Mask(Source),
+    PushGroup,
+    PopGroupToSource,
+    SelectFontFace(String, FontSlant, FontWeight),
+    SetFontSize(f64),
+    ShowText(String),
+    ShowGlyphs(Vec<Glyph>),
+    SetMatrix(Matrix),
+    IdentityMatrix,
+    TextPath(String),
+    /// Corresponds to [`Context::set_font_options()`]. Only the antialias,
+    /// subpixel order, hint style and hint metrics settings are captured.
+    SetFontOptions(Antialias, SubpixelOrder, HintStyle, HintMetrics),
+}
+
+/// A serializable list of drawing operations.
+///
+/// Recordings are created with a [`Recorder`], turned into bytes with
+/// [`to_bytes()`](Self::to_bytes) and replayed onto any context, for example
+/// in another process, with [`replay()`](Self::replay).
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cairo::recording::{Recorder, Recording};
+/// use cairo::Content;
+///
+/// let recorder = Recorder::new(Content::ColorAlpha, None)?;
+/// recorder.set_source_rgb(0.2, 0.4, 0.8);
+/// recorder.rectangle(0.0, 0.0, 16.0, 16.0);
+/// recorder.fill()?;
+/// let bytes = recorder.finish().to_bytes()?;
+///
+/// let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 16, 16)?;
+/// let cr = cairo::Context::new(&surface)?;
+/// Recording::from_bytes(&bytes)?.replay(&cr)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    extents: Option<Rectangle>,
+    ops: Vec<DrawOp>,
+}
+
+impl Recording {
+    pub fn new(extents: Option<Rectangle>, ops: Vec<DrawOp>) -> Self {
+        Self { extents, ops }
+    }
+
+    /// The extents the recording was made with, `None` if unbounded.
+    pub fn extents(&self) -> Option<Rectangle> {
+        self.extents
+    }
+
+    pub fn ops(&self) -> &[DrawOp] {
+        &self.ops
+    }
+
+    /// Draws the operations onto `cr`, leaving its state unchanged.
+    ///
+    /// Unbalanced [`Restore`](DrawOp::Restore) or
+    /// [`PopGroupToSource`](DrawOp::PopGroupToSource) operations fail rather
+    /// than affect the caller's state; groups left open are discarded.
+    pub fn replay(&self, cr: &Context) -> Result<(), Error> {
+        cr.save()?;
+        let mut saves = Vec::new();
+        let mut groups = 0;
+        let result = self
+            .ops
+            .iter()
+            .try_for_each(|op| replay_op(cr, op, &mut saves, &mut groups));
+        // Each entry of `saves` is the number of groups that were open when
+        // the state was saved.
+        for saved_groups in saves.into_iter().rev() {
+            for _ in 0..groups {
+                let _ = cr.pop_group();
+            }
+            cr.restore()?;
+            groups = saved_groups;
+        }
+        for _ in 0..groups {
+            let _ = cr.pop_group();
+        }
+        cr.restore()?;
+        result
+    }
+
+    /// Replays the operations onto a new recording surface with the
+    /// recording's extents.
+    pub fn to_recording_surface(&self, content: Content) -> Result<RecordingSurface, Error> {
+        let surface = RecordingSurface::create(content, self.extents)?;
+        {
+            let cr = Context::new(&surface)?;
+            self.replay(&cr)?;
+        }
+        Ok(surface)
+    }
+
+    /// Serializes the recording into a compact binary format.
+    ///
+    /// Fails with [`RecordingError::TooLarge`] if the number of operations or
+    /// the length of a string, dash array or image exceeds `u32::MAX`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, RecordingError> {
+        let mut encoder = Encoder(MAGIC.to_vec());
+        encoder.u8(VERSION);
+        match self.extents {
+            Some(extents) => {
+                encoder.u8(1);
+                encoder.f64(extents.x);
+                encoder.f64(extents.y);
+                encoder.f64(extents.width);
+                encoder.f64(extents.height);
+            }
+            None => encoder.u8(0),
+        }
+        encoder.u32(self.ops.len())?;
+        for op in &self.ops {
+            encoder.op(op)?;
+        }
+        Ok(encoder.0)
+    }
+
+    /// Deserializes a recording created by [`to_bytes()`](Self::to_bytes).
+    pub fn from_bytes(data: &[u8]) -> Result<Self, RecordingError> {
+        if data.len() < MAGIC.len() || &data[..MAGIC.len()] != MAGIC {
+            return Err(RecordingError::BadMagic);
+        }
+        let mut decoder = Decoder {
+            data,
+            pos: MAGIC.len(),
+        };
+        let version = decoder.u8()?;
+        if version != VERSION {
+            return Err(RecordingError::UnsupportedVersion(version));
+        }
+        let extents = match decoder.u8()? {
+            0 => None,
+            1 => Some(Rectangle {
+                x: decoder.f64()?,
+                y: decoder.f64()?,
+                width: decoder.f64()?,
+                height: decoder.f64()?,
+            }),
+            _ => return Err(RecordingError::Invalid("extents flag")),
+        };
+        let count = decoder.u32()?;
+        // Every operation takes at least one byte.
+        if count > data.len() {
+            return Err(RecordingError::UnexpectedEnd);
+        }
+        let mut ops = Vec::with_capacity(count);
+        for _ in 0..count {
+            ops.push(decoder.op()?);
+        }
+        if decoder.pos != data.len() {
+            return Err(RecordingError::Invalid("trailing data"));
+        }
+        Ok(Self { extents, ops })
+    }
+}
+
+fn replay_op(
+    cr: &Context,
+    op: &DrawOp,
+    saves: &mut Vec<usize>,
+    groups: &mut usize,
+) -> Result<(), Error> {
+    match *op {
+        DrawOp::Save => {
+            cr.save()?;
+            saves.push(*groups);
+            *groups = 0;
+        }
+        DrawOp::Restore => {
+            if *groups > 0 {
+                return Err(Error::InvalidRestore);
+            }
+            *groups = saves.pop().ok_or(Error::InvalidRestore)?;
+            cr.restore()?;
+        }
+        DrawOp::Transform(matrix) => cr.transform(matrix),
+        DrawOp::SetSource(ref source) => cr.set_source(&source.to_pattern()?)?,
+        DrawOp::SetLineWidth(width) => cr.set_line_width(width),
+        DrawOp::SetLineCap(cap) => cr.set_line_cap(cap),
+        DrawOp::SetLineJoin(join) => cr.set_line_join(join),
+        DrawOp::SetMiterLimit(limit) => cr.set_miter_limit(limit),
+        DrawOp::SetDash(ref dashes, offset) => cr.set_dash(dashes, offset),
+        DrawOp::SetFillRule(rule) => cr.set_fill_rule(rule),
+        DrawOp::SetOperator(operator) => cr.set_operator(operator),
+        DrawOp::SetAntialias(antialias) => cr.set_antialias(antialias),
+        DrawOp::NewPath => cr.new_path(),
+        DrawOp::NewSubPath => cr.new_sub_path(),
+        DrawOp::MoveTo(x, y) => cr.move_to(x, y),
+        DrawOp::LineTo(x, y) => cr.line_to(x, y),
+        DrawOp::CurveTo(x1, y1, x2, y2, x3, y3) => cr.curve_to(x1, y1, x2, y2, x3, y3),
+        DrawOp::Rectangle(x, y, width, height) => cr.rectangle(x, y, width, height),
+        DrawOp::Arc(xc, yc, radius, angle1, angle2) => cr.arc(xc, yc, radius, angle1, angle2),
+        DrawOp::ArcNegative(xc, yc, radius, angle1, angle2) => {
+            cr.arc_negative(xc, yc, radius, angle1, angle2)
+        }
+        DrawOp::ClosePath => cr.close_path(),
+        DrawOp::Fill => cr.fill()?,
+        DrawOp::FillPreserve => cr.fill_preserve()?,
+        DrawOp::Stroke => cr.stroke()?,
+        DrawOp::StrokePreserve => cr.stroke_preserve()?,
+        DrawOp::Clip => cr.clip(),
+        DrawOp::ClipPreserve => cr.clip_preserve(),
+        DrawOp::ResetClip => cr.reset_clip(),
+        DrawOp::Paint => cr.paint()?,
+        DrawOp::PaintWithAlpha(alpha) => cr.paint_with_alpha(alpha)?,
+        DrawOp::Mask(ref source) => cr.mask(&source.to_pattern()?)?,
+        DrawOp::PushGroup => {
+            cr.push_group();
+            *groups += 1;
+        }
+        DrawOp::PopGroupToSource => {
+            if *groups == 0 {
+                return Err(Error::InvalidPopGroup);
+            }
+            *groups -= 1;
+            cr.pop_group_to_source()?;
+        }
+        DrawOp::SelectFontFace(ref family, slant, weight) => {
+            cr.select_font_face(family, slant, weight)
+        }
+        DrawOp::SetFontSize(size) => cr.set_font_size(size),
+        DrawOp::ShowText(ref text) => cr.show_text(text)?,
+        DrawOp::ShowGlyphs(ref glyphs) => cr.show_glyphs(glyphs)?,
+        DrawOp::SetMatrix(matrix) => cr.set_matrix(matrix),
+        DrawOp::IdentityMatrix => cr.identity_matrix(),
+        DrawOp::TextPath(ref text) => cr.text_path(text),
+        DrawOp::SetFontOptions(antialias, subpixel_order, hint_style, hint_metrics) => {
+            let mut options = FontOptions::new()?;
+            options.set_antialias(antialias);
+            options.set_subpixel_order(subpixel_order);
+            options.set_hint_style(hint_style);
+            options.set_hint_metrics(hint_metrics);
+            cr.set_font_options(&options);
+        }
+    }
+    Ok(())
+}
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: usize) -> Result<(), RecordingError> {
+        let value = u32::try_from(value).map_err(|_| RecordingError::TooLarge)?;
+        self.0.extend_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64s(&mut self, values: &[f64]) {
+        for &value in values {
+            self.f64(value);
+        }
+    }
+
+    fn bytes(&mut self, value: &[u8]) -> Result<(), RecordingError> {
+        self.u32(value.len())?;
+        self.0.extend_from_slice(value);
+        Ok(())
+    }
+
+    fn matrix(&mut self, matrix: Matrix) {
+        self.f64s(&[
+            matrix.xx, matrix.yx, matrix.xy, matrix.yy, matrix.x0, matrix.y0,
+        ]);
+    }
+
+    fn stops(&mut self, stops: &[ColorStop]) -> Result<(), RecordingError> {
+        self.u32(stops.len())?;
+        for &(offset, red, green, blue, alpha) in stops {
+            self.f64s(&[offset, red, green, blue, alpha]);
+        }
+        Ok(())
+    }
+
+    fn source(&mut self, source: &Source) -> Result<(), RecordingError> {
+        match *source {
+            Source::Rgba(red, green, blue, alpha) => {
+                self.u8(0);
+                self.f64s(&[red, green, blue, alpha]);
+            }
+            Source::Linear {
+                points: (x0, y0, x1, y1),
+                ref stops,
+                extend,
+                matrix,
+            } => {
+                self.u8(1);
+                self.f64s(&[x0, y0, x1, y1]);
+                self.stops(stops)?;
+                self.i32(extend.into());
+                self.matrix(matrix);
+            }
+            Source::Radial {
+                circles: (x0, y0, r0, x1, y1, r1),
+                ref stops,
+                extend,
+                matrix,
+            } => {
+                self.u8(2);
+                self.f64s(&[x0, y0, r0, x1, y1, r1]);
+                self.stops(stops)?;
+                self.i32(extend.into());
+                self.matrix(matrix);
+            }
+            Source::Image {
+                format,
+                width,
+                height,
+                ref data,
+                extend,
+                filter,
+                matrix,
+            } => {
+                self.u8(3);
+                self.i32(format.into());
+                self.i32(width);
+                self.i32(height);
+                self.bytes(data)?;
+                self.i32(extend.into());
+                self.i32(filter.into());
+                self.matrix(matrix);
+            }
+        }
+        Ok(())
+    }
+
+    fn op(&mut self, op: &DrawOp) -> Result<(), RecordingError> {
+        match *op {
+            DrawOp::Save => self.u8(0),
+            DrawOp::Restore => self.u8(1),
+            DrawOp::Transform(matrix) => {
+                self.u8(2);
+                self.matrix(matrix);
+            }
+            DrawOp::SetSource(ref source) => {
+                self.u8(3);
+                self.source(source)?;
+            }
+            DrawOp::SetLineWidth(width) => {
+                self.u8(4);
+                self.f64(width);
+            }
+            DrawOp::SetLineCap(cap) => {
+                self.u8(5);
+                self.i32(cap.into());
+            }
+            DrawOp::SetLineJoin(join) => {
+                self.u8(6);
+                self.i32(join.into());
+            }
+            DrawOp::SetMiterLimit(limit) => {
+                self.u8(7);
+                self.f64(limit);
+            }
+            DrawOp::SetDash(ref dashes, offset) => {
+                self.u8(8);
+                self.u32(dashes.len())?;
+                self.f64s(dashes);
+                self.f64(offset);
+            }
+            DrawOp::SetFillRule(rule) => {
+                self.u8(9);
+                self.i32(rule.into());
+            }
+            DrawOp::SetOperator(operator) => {
+                self.u8(10);
+                self.i32(operator.into());
+            }
+            DrawOp::SetAntialias(antialias) => {
+                self.u8(11);
+                self.i32(antialias.into());
+            }
+            DrawOp::NewPath => self.u8(12),
+            DrawOp::NewSubPath => self.u8(13),
+            DrawOp::MoveTo(x, y) => {
+                self.u8(14);
+                self.f64s(&[x, y]);
+            }
+            DrawOp::LineTo(x, y) => {
+                self.u8(15);
+                self.f64s(&[x, y]);
+            }
+            DrawOp::CurveTo(x1, y1, x2, y2, x3, y3) => {
+                self.u8(16);
+                self.f64s(&[x1, y1, x2, y2, x3, y3]);
+            }
+            DrawOp::Rectangle(x, y, width, height) => {
+                self.u8(17);
+                self.f64s(&[x, y, width, height]);
+            }
+            DrawOp::Arc(xc, yc, radius, angle1, angle2) => {
+                self.u8(18);
+                self.f64s(&[xc, yc, radius, angle1, angle2]);
+            }
+            DrawOp::ArcNegative(xc, yc, radius, angle1, angle2) => {
+                self.u8(19);
+                self.f64s(&[xc, yc, radius, angle1, angle2]);
+            }
+            DrawOp::ClosePath => self.u8(20),
+            DrawOp::Fill => self.u8(21),
+            DrawOp::FillPreserve => self.u8(22),
+            DrawOp::Stroke => self.u8(23),
+            DrawOp::StrokePreserve => self.u8(24),
+            DrawOp::Clip => self.u8(25),
+            DrawOp::ClipPreserve => self.u8(26),
+            DrawOp::ResetClip => self.u8(27),
+            DrawOp::Paint => self.u8(28),
+            DrawOp::PaintWithAlpha(alpha) => {
+                self.u8(29);
+                self.f64(alpha);
+            }
+            DrawOp::Mask(ref source) => {
+                self.u8(30);
+                self.source(source)?;
+            }
+            DrawOp::PushGroup => self.u8(31),
+            DrawOp::PopGroupToSource => self.u8(32),
+            DrawOp::SelectFontFace(ref family, slant, weight) => {
+                self.u8(33);
+                self.bytes(family.as_bytes())?;
+                self.i32(slant.into());
+                self.i32(weight.into());
+            }
+            DrawOp::SetFontSize(size) => {
+                self.u8(34);
+                self.f64(size);
+            }
+            DrawOp::ShowText(ref text) => {
+                self.u8(35);
+                self.bytes(text.as_bytes())?;
+            }
+            DrawOp::ShowGlyphs(ref glyphs) => {
+                self.u8(36);
+                self.u32(glyphs.len())?;
+                for glyph in glyphs {
+                    // `c_ulong` is only 32 bits wide on Windows.
+                    self.u64(glyph.index as _);
+                    self.f64s(&[glyph.x, glyph.y]);
+                }
+            }
+            DrawOp::SetMatrix(matrix) => {
+                self.u8(37);
+                self.matrix(matrix);
+            }
+            DrawOp::IdentityMatrix => self.u8(38),
+            DrawOp::TextPath(ref text) => {
+                self.u8(39);
+                self.bytes(text.as_bytes())?;
+            }
+            DrawOp::SetFontOptions(antialias, subpixel_order, hint_style, hint_metrics) => {
+                self.u8(40);
+                self.i32(antialias.into());
+                self.i32(subpixel_order.into());
+                self.i32(hint_style.into());
+                self.i32(hint_metrics.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RecordingError> {
+        if self.data.len() - self.pos < len {
+            return Err(RecordingError::UnexpectedEnd);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, RecordingError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, RecordingError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn i32(&mut self) -> Result<i32, RecordingError> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, RecordingError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn f64(&mut self) -> Result<f64, RecordingError> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    // Reads a length that is followed by `len * item_size` bytes.
+    fn len(&mut self, item_size: usize) -> Result<usize, RecordingError> {
+        let len = self.u32()?;
+        if (self.data.len() - self.pos) / item_size < len {
+            return Err(RecordingError::UnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], RecordingError> {
+        let len = self.len(1)?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, RecordingError> {
+        let bytes = self.bytes()?;
+        std::str::from_utf8(bytes)
+            .map(String::from)
+            .map_err(|_| RecordingError::Invalid("string is not UTF-8"))
+    }
+
+    fn matrix(&mut self) -> Result<Matrix, RecordingError> {
+        Ok(Matrix::new(
+            self.f64()?,
+            self.f64()?,
+            self.f64()?,
+            self.f64()?,
+            self.f64()?,
+            self.f64()?,
+        ))
+    }
+
+    fn stops(&mut self) -> Result<Vec<ColorStop>, RecordingError> {
+        let len = self.len(5 * 8)?;
+        let mut stops = Vec::with_capacity(len);
+        for _ in 0..len {
+            stops.push((
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+            ));
+        }
+        Ok(stops)
+    }
+
+    fn source(&mut self) -> Result<Source, RecordingError> {
+        Ok(match self.u8()? {
+            0 => Source::Rgba(self.f64()?, self.f64()?, self.f64()?, self.f64()?),
+            1 => Source::Linear {
+                points: (self.f64()?, self.f64()?, self.f64()?, self.f64()?),
+                stops: self.stops()?,
+                extend: self.i32()?.into(),
+                matrix: self.matrix()?,
+            },
+            2 => Source::Radial {
+                circles: (
+                    self.f64()?,
+                    self.f64()?,
+                    self.f64()?,
+                    self.f64()?,
+                    self.f64()?,
+                    self.f64()?,
+                ),
+                stops: self.stops()?,
+                extend: self.i32()?.into(),
+                matrix: self.matrix()?,
+            },
+            3 => Source::Image {
+                format: self.i32()?.into(),
+                width: self.i32()?,
+                height: self.i32()?,
+                data: self.bytes()?.to_vec(),
+                extend: self.i32()?.into(),
+                filter: self.i32()?.into(),
+                matrix: self.matrix()?,
+            },
+            _ => return Err(RecordingError::Invalid("source type")),
+        })
+    }
+
+    fn op(&mut self) -> Result<DrawOp, RecordingError> {
+        Ok(match self.u8()? {
+            0 => DrawOp::Save,
+            1 => DrawOp::Restore,
+            2 => DrawOp::Transform(self.matrix()?),
+            3 => DrawOp::SetSource(self.source()?),
+            4 => DrawOp::SetLineWidth(self.f64()?),
+            5 => DrawOp::SetLineCap(self.i32()?.into()),
+            6 => DrawOp::SetLineJoin(self.i32()?.into()),
+            7 => DrawOp::SetMiterLimit(self.f64()?),
+            8 => {
+                let len = self.len(8)?;
+                let mut dashes = Vec::with_capacity(len);
+                for _ in 0..len {
+                    dashes.push(self.f64()?);
+                }
+                DrawOp::SetDash(dashes, self.f64()?)
+            }
+            9 => DrawOp::SetFillRule(self.i32()?.into()),
+            10 => DrawOp::SetOperator(self.i32()?.into()),
+            11 => DrawOp::SetAntialias(self.i32()?.into()),
+            12 => DrawOp::NewPath,
+            13 => DrawOp::NewSubPath,
+            14 => DrawOp::MoveTo(self.f64()?, self.f64()?),
+            15 => DrawOp::LineTo(self.f64()?, self.f64()?),
+            16 => DrawOp::CurveTo(
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+            ),
+            17 => DrawOp::Rectangle(self.f64()?, self.f64()?, self.f64()?, self.f64()?),
+            18 => DrawOp::Arc(
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+            ),
+            19 => DrawOp::ArcNegative(
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+                self.f64()?,
+            ),
+            20 => DrawOp::ClosePath,
+            21 => DrawOp::Fill,
+            22 => DrawOp::FillPreserve,
+            23 => DrawOp::Stroke,
+            24 => DrawOp::StrokePreserve,
+            25 => DrawOp::Clip,
+            26 => DrawOp::ClipPreserve,
+            27 => DrawOp::ResetClip,
+            28 => DrawOp::Paint,
+            29 => DrawOp::PaintWithAlpha(self.f64()?),
+            30 => DrawOp::Mask(self.source()?),
+            31 => DrawOp::PushGroup,
+            32 => DrawOp::PopGroupToSource,
+            33 => DrawOp::SelectFontFace(self.string()?, self.i32()?.into(), self.i32()?.into()),
+            34 => DrawOp::SetFontSize(self.f64()?),
+            35 => DrawOp::ShowText(self.string()?),
+            36 => {
+                let len = self.len(3 * 8)?;
+                let mut glyphs = Vec::with_capacity(len);
+                for _ in 0..len {
+                    glyphs.push(Glyph {
+                        index: self.u64()? as _,
+                        x: self.f64()?,
+                        y: self.f64()?,
+                    });
+                }
+                DrawOp::ShowGlyphs(glyphs)
+            }
+            37 => DrawOp::SetMatrix(self.matrix()?),
+            38 => DrawOp::IdentityMatrix,
+            39 => DrawOp::TextPath(self.string()?),
+            40 => DrawOp::SetFontOptions(
+                self.i32()?.into(),
+                self.i32()?.into(),
+                self.i32()?.into(),
+                self.i32()?.into(),
+            ),
+            op => return Err(RecordingError::UnknownOperation(op)),
+        })
+    }
+}
+
+/// Draws onto a [`RecordingSurface`] while capturing the operations as a
+/// [`Recording`].
+///
+/// Only calls made through the recorder's own methods are captured, one
+/// [`DrawOp`] each. These mirror the [`Context`] methods of the same name and
+/// cover:
+///
+/// - state: `save`, `restore`
+/// - transformations: `transform`, `translate`, `scale`, `rotate`,
+///   `set_matrix`, `identity_matrix`
+/// - sources and masks: `set_source`, `set_source_rgb(a)`,
+///   `set_source_surface`, `mask`, `push_group`, `pop_group_to_source`
+/// - stroke and fill parameters: `set_line_width`, `set_line_cap`,
+///   `set_line_join`, `set_miter_limit`, `set_dash`, `set_fill_rule`,
+///   `set_operator`, `set_antialias`
+/// - paths: `new_path`, `new_sub_path`, `move_to`, `line_to`, `curve_to`,
+///   `rectangle`, `arc`, `arc_negative`, `close_path`, `append_path`,
+///   `text_path`
+/// - drawing and clipping: `fill(_preserve)`, `stroke(_preserve)`,
+///   `clip(_preserve)`, `reset_clip`, `paint`, `paint_with_alpha`
+/// - text: `select_font_face`, `set_font_size`, `set_font_options`,
+///   `show_text`, `show_glyphs`
+///
+/// Anything drawn through another [`Context`], for example one created for
+/// [`surface()`](Self::surface), is not captured. Relative path operations
+/// and font faces other than toy font faces are not supported. Sources must be
+/// solid colours, gradients or image surfaces, see [`Source::from_pattern()`].
+#[derive(Debug)]
+pub struct Recorder {
+    surface: RecordingSurface,
+    cr: Context,
+    extents: Option<Rectangle>,
+    ops: std::cell::RefCell<Vec<DrawOp>>,
+}
+
+impl Recorder {
+    pub fn new(content: Content, extents: Option<Rectangle>) -> Result<Self, Error> {
+        let surface = RecordingSurface::create(content, extents)?;
+        let cr = Context::new(&surface)?;
+        Ok(Self {
+            surface,
+            cr,
+            extents,
+            ops: Default::default(),
+        })
+    }
+
+    /// The surface the operations are drawn onto, for example to query its
+    /// [`ink_extents()`](RecordingSurface::ink_extents).
+    pub fn surface(&self) -> &RecordingSurface {
+        &self.surface
+    }
+
+    pub fn finish(self) -> Recording {
+        Recording {
+            extents: self.extents,
+            ops: self.ops.into_inner(),
+        }
+    }
+
+    fn push(&self, op: DrawOp) {
+        self.ops.borrow_mut().push(op);
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        self.cr.save()?;
+        self.push(DrawOp::Save);
+        Ok(())
+    }
+
+    pub fn restore(&self) -> Result<(), Error> {
+        self.cr.restore()?;
+        self.push(DrawOp::Restore);
+        Ok(())
+    }
+
+    pub fn transform(&self, matrix: Matrix) {
+        self.cr.transform(matrix);
+        self.push(DrawOp::Transform(matrix));
+    }
+
+    pub fn translate(&self, tx: f64, ty: f64) {
+        self.transform(Matrix::new(1.0, 0.0, 0.0, 1.0, tx, ty));
+    }
+
+    pub fn scale(&self, sx: f64, sy: f64) {
+        self.transform(Matrix::new(sx, 0.0, 0.0, sy, 0.0, 0.0));
+    }
+
+    pub fn rotate(&self, angle: f64) {
+        let (sin, cos) = angle.sin_cos();
+        self.transform(Matrix::new(cos, sin, -sin, cos, 0.0, 0.0));
+    }
+
+    pub fn set_matrix(&self, matrix: Matrix) {
+        self.cr.set_matrix(matrix);
+        self.push(DrawOp::SetMatrix(matrix));
+    }
+
+    pub fn identity_matrix(&self) {
+        self.cr.identity_matrix();
+        self.push(DrawOp::IdentityMatrix);
+    }
+
+    pub fn set_source_rgb(&self, red: f64, green: f64, blue: f64) {
+        self.set_source_rgba(red, green, blue, 1.0);
+    }
+
+    pub fn set_source_rgba(&self, red: f64, green: f64, blue: f64, alpha: f64) {
+        self.cr.set_source_rgba(red, green, blue, alpha);
+        self.push(DrawOp::SetSource(Source::Rgba(red, green, blue, alpha)));
+    }
+
+    pub fn set_source(&self, source: &Pattern) -> Result<(), Error> {
+        let captured = Source::from_pattern(source)?;
+        self.cr.set_source(source)?;
+        self.push(DrawOp::SetSource(captured));
+        Ok(())
+    }
+
+    pub fn set_source_surface(&self, surface: &ImageSurface, x: f64, y: f64) -> Result<(), Error> {
+        let pattern = SurfacePattern::create(surface);
+        pattern.set_matrix(Matrix::new(1.0, 0.0, 0.0, 1.0, -x, -y));
+        self.set_source(&pattern)
+    }
+
+    pub fn set_line_width(&self, width: f64) {
+        self.cr.set_line_width(width);
+        self.push(DrawOp::SetLineWidth(width));
+    }
+
+    pub fn set_line_cap(&self, cap: LineCap) {
+        self.cr.set_line_cap(cap);
+        self.push(DrawOp::SetLineCap(cap));
+    }
+
+    pub fn set_line_join(&self, join: LineJoin) {
+        self.cr.set_line_join(join);
+        self.push(DrawOp::SetLineJoin(join));
+    }
+
+    pub fn set_miter_limit(&self, limit: f64) {
+        self.cr.set_miter_limit(limit);
+        self.push(DrawOp::SetMiterLimit(limit));
+    }
+
+    pub fn set_dash(&self, dashes: &[f64], offset: f64) {
+        self.cr.set_dash(dashes, offset);
+        self.push(DrawOp::SetDash(dashes.to_vec(), offset));
+    }
+
+    pub fn set_fill_rule(&self, fill_rule: FillRule) {
+        self.cr.set_fill_rule(fill_rule);
+        self.push(DrawOp::SetFillRule(fill_rule));
+    }
+
+    pub fn set_operator(&self, op: Operator) {
+        self.cr.set_operator(op);
+        self.push(DrawOp::SetOperator(op));
+    }
+
+    pub fn set_antialias(&self, antialias: Antialias) {
+        self.cr.set_antialias(antialias);
+        self.push(DrawOp::SetAntialias(antialias));
+    }
+
+    pub fn new_path(&self) {
+        self.cr.new_path();
+        self.push(DrawOp::NewPath);
+    }
+
+    pub fn new_sub_path(&self) {
+        self.cr.new_sub_path();
+        self.push(DrawOp::NewSubPath);
+    }
+
+    pub fn move_to(&self, x: f64, y: f64) {
+        self.cr.move_to(x, y);
+        self.push(DrawOp::MoveTo(x, y));
+    }
+
+    pub fn line_to(&self, x: f64, y: f64) {
+        self.cr.line_to(x, y);
+        self.push(DrawOp::LineTo(x, y));
+    }
+
+    pub fn curve_to(&self, x1: f64, y1: f64, x2: f64, y2: f64, x3: f64, y3: f64) {
+        self.cr.curve_to(x1, y1, x2, y2, x3, y3);
+        self.push(DrawOp::CurveTo(x1, y1, x2, y2, x3, y3));
+    }
+
+    pub fn rectangle(&self, x: f64, y: f64, width: f64, height: f64) {
+        self.cr.rectangle(x, y, width, height);
+        self.push(DrawOp::Rectangle(x, y, width, height));
+    }
+
+    pub fn arc(&self, xc: f64, yc: f64, radius: f64, angle1: f64, angle2: f64) {
+        self.cr.arc(xc, yc, radius, angle1, angle2);
+        self.push(DrawOp::Arc(xc, yc, radius, angle1, angle2));
+    }
+
+    pub fn arc_negative(&self, xc: f64, yc: f64, radius: f64, angle1: f64, angle2: f64) {
+        self.cr.arc_negative(xc, yc, radius, angle1, angle2);
+        self.push(DrawOp::ArcNegative(xc, yc, radius, angle1, angle2));
+    }
+
+    pub fn close_path(&self) {
+        self.cr.close_path();
+        self.push(DrawOp::ClosePath);
+    }
+
+    /// Appends `path`, which is stored as its individual segments.
+    pub fn append_path(&self, path: &Path) {
+        for segment in path.iter() {
+            match segment {
+                PathSegment::MoveTo((x, y)) => self.move_to(x, y),
+                PathSegment::LineTo((x, y)) => self.line_to(x, y),
+                PathSegment::CurveTo((x1, y1), (x2, y2), (x3, y3)) => {
+                    self.curve_to(x1, y1, x2, y2, x3, y3)
+                }
+                PathSegment::ClosePath => self.close_path(),
+            }
+        }
+    }
+
+    pub fn fill(&self) -> Result<(), Error> {
+        self.cr.fill()?;
+        self.push(DrawOp::Fill);
+        Ok(())
+    }
+
+    pub fn fill_preserve(&self) -> Result<(), Error> {
+        self.cr.fill_preserve()?;
+        self.push(DrawOp::FillPreserve);
+        Ok(())
+    }
+
+    pub fn stroke(&self) -> Result<(), Error> {
+        self.cr.stroke()?;
+        self.push(DrawOp::Stroke);
+        Ok(())
+    }
+
+    pub fn stroke_preserve(&self) -> Result<(), Error> {
+        self.cr.stroke_preserve()?;
+        self.push(DrawOp::StrokePreserve);
+        Ok(())
+    }
+
+    pub fn clip(&self) {
+        self.cr.clip();
+        self.push(DrawOp::Clip);
+    }
+
+    pub fn clip_preserve(&self) {
+        self.cr.clip_preserve();
+        self.push(DrawOp::ClipPreserve);
+    }
+
+    pub fn reset_clip(&self) {
+        self.cr.reset_clip();
+        self.push(DrawOp::ResetClip);
+    }
+
+    pub fn paint(&self) -> Result<(), Error> {
+        self.cr.paint()?;
+        self.push(DrawOp::Paint);
+        Ok(())
+    }
+
+    pub fn paint_with_alpha(&self, alpha: f64) -> Result<(), Error> {
+        self.cr.paint_with_alpha(alpha)?;
+        self.push(DrawOp::PaintWithAlpha(alpha));
+        Ok(())
+    }
+
+    pub fn mask(&self, pattern: &Pattern) -> Result<(), Error> {
+        let captured = Source::from_pattern(pattern)?;
+        self.cr.mask(pattern)?;
+        self.push(DrawOp::Mask(captured));
+        Ok(())
+    }
+
+    pub fn push_group(&self) {
+        self.cr.push_group();
+        self.push(DrawOp::PushGroup);
+    }
+
+    pub fn pop_group_to_source(&self) -> Result<(), Error> {
+        self.cr.pop_group_to_source()?;
+        self.push(DrawOp::PopGroupToSource);
+        Ok(())
+    }
+
+    pub fn select_font_face(&self, family: &str, slant: FontSlant, weight: FontWeight) {
+        self.cr.select_font_face(family, slant, weight);
+        self.push(DrawOp::SelectFontFace(family.to_owned(), slant, weight));
+    }
+
+    pub fn set_font_size(&self, size: f64) {
+        self.cr.set_font_size(size);
+        self.push(DrawOp::SetFontSize(size));
+    }
+
+    /// Sets the font options; only the settings listed on
+    /// [`DrawOp::SetFontOptions`] are captured.
+    pub fn set_font_options(&self, options: &FontOptions) {
+        self.cr.set_font_options(options);
+        self.push(DrawOp::SetFontOptions(
+            options.antialias(),
+            options.subpixel_order(),
+            options.hint_style(),
+            options.hint_metrics(),
+        ));
+    }
+
+    pub fn text_path(&self, text: &str) {
+        self.cr.text_path(text);
+        self.push(DrawOp::TextPath(text.to_owned()));
+    }
+
+    pub fn show_text(&self, text: &str) -> Result<(), Error> {
+        self.cr.show_text(text)?;
+        self.push(DrawOp::ShowText(text.to_owned()));
+        Ok(())
+    }
+
+    pub fn show_glyphs(&self, glyphs: &[Glyph]) -> Result<(), Error> {
+        self.cr.show_glyphs(glyphs)?;
+        self.push(DrawOp::ShowGlyphs(glyphs.to_vec()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(recording: &Recording) -> Vec<u8> {
+        let mut surface = ImageSurface::create(Format::ARgb32, 16, 16).unwrap();
+        {
+            let cr = Context::new(&surface).unwrap();
+            recording.replay(&cr).unwrap();
+            assert_eq!(cr.matrix(), Matrix::identity());
+        }
+        let data = surface.data().unwrap().to_vec();
+        data
+    }
+
+    fn record() -> Recording {
+        let recorder = Recorder::new(
+            Content::ColorAlpha,
+            Some(Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: 16.0,
+                height: 16.0,
+            }),
+        )
+        .unwrap();
+
+        let gradient = LinearGradient::new(0.0, 0.0, 16.0, 0.0);
+        gradient.add_color_stop_rgb(0.0, 1.0, 0.0, 0.0);
+        gradient.add_color_stop_rgb(1.0, 0.0, 0.0, 1.0);
+        recorder.set_source(&gradient).unwrap();
+        recorder.paint().unwrap();
+
+        let image = ImageSurface::create(Format::ARgb32, 2, 2).unwrap();
+        {
+            let cr = Context::new(&image).unwrap();
+            cr.set_source_rgb(0.0, 1.0, 0.0);
+            cr.paint().unwrap();
+        }
+        recorder.save().unwrap();
+        recorder.translate(4.0, 4.0);
+        recorder.rectangle(0.0, 0.0, 8.0, 8.0);
+        recorder.clip();
+        recorder.set_source_surface(&image, 0.0, 0.0).unwrap();
+        recorder.paint_with_alpha(0.5).unwrap();
+        recorder.restore().unwrap();
+
+        recorder.set_source_rgba(1.0, 1.0, 1.0, 0.8);
+        recorder.set_line_width(2.0);
+        recorder.set_dash(&[2.0, 1.0], 0.0);
+        recorder.arc(8.0, 8.0, 6.0, 0.0, std::f64::consts::PI);
+        recorder.stroke().unwrap();
+
+        let mut options = FontOptions::new().unwrap();
+        options.set_antialias(Antialias::None);
+        options.set_hint_style(HintStyle::Full);
+        recorder.set_font_options(&options);
+        recorder.set_matrix(Matrix::new(2.0, 0.0, 0.0, 2.0, 1.0, 1.0));
+        recorder.text_path("a");
+        recorder.fill().unwrap();
+        recorder.identity_matrix();
+        recorder
+            .show_glyphs(&[Glyph {
+                index: 12,
+                x: 1.0,
+                y: 14.0,
+            }])
+            .unwrap();
+
+        let (_, _, width, height) = recorder.surface().ink_extents();
+        assert!(width > 0.0 && height > 0.0);
+        recorder.finish()
+    }
+
+    #[test]
+    fn round_trip() {
+        let recording = record();
+        assert_eq!(recording.ops().len(), 20);
+        let bytes = recording.to_bytes().unwrap();
+
+        let decoded = Recording::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes().unwrap(), bytes);
+        match decoded.ops()[14] {
+            DrawOp::SetFontOptions(Antialias::None, _, HintStyle::Full, _) => (),
+            ref op => panic!("unexpected operation {:?}", op),
+        }
+        assert_eq!(decoded.extents().unwrap().width, 16.0);
+        assert_eq!(render(&decoded), render(&recording));
+
+        // The replay matches drawing onto the recording surface directly.
+        let surface = decoded.to_recording_surface(Content::ColorAlpha).unwrap();
+        let mut image = ImageSurface::create(Format::ARgb32, 16, 16).unwrap();
+        {
+            let cr = Context::new(&image).unwrap();
+            cr.set_source_surface(&surface, 0.0, 0.0).unwrap();
+            cr.paint().unwrap();
+        }
+        assert_eq!(image.data().unwrap().to_vec(), render(&recording));
+    }
+
+    #[test]
+    fn invalid_data() {
+        let bytes = record().to_bytes().unwrap();
+        assert_eq!(
+            Recording::from_bytes(b"PNG!").unwrap_err(),
+            RecordingError::BadMagic
+        );
+        for len in 4..bytes.len() {
+            assert!(Recording::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut unknown = Recording::default().to_bytes().unwrap();
+        unknown[6..10].copy_from_slice(&1u32.to_le_bytes());
+        unknown.push(200);
+        assert_eq!(
+            Recording::from_bytes(&unknown).unwrap_err(),
+            RecordingError::UnknownOperation(200)
+        );
+    }
+
+    #[test]
+    fn unbalanced_replay() {
+        let recording = Recording::new(None, vec![DrawOp::Restore]);
+        let surface = ImageSurface::create(Format::ARgb32, 1, 1).unwrap();
+        let cr = Context::new(&surface).unwrap();
+        assert_eq!(recording.replay(&cr), Err(Error::InvalidRestore));
+
+        let recording = Recording::new(
+            None,
+            vec![
+                DrawOp::Save,
+                DrawOp::PushGroup,
+                DrawOp::Transform(Matrix::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0)),
+            ],
+        );
+        recording.replay(&cr).unwrap();
+        assert_eq!(cr.matrix(), Matrix::identity());
+        cr.paint().unwrap();
+    }
+
+    #[test]
+    fn invalid_image_size() {
+        let source = Source::Image {
+            format: Format::ARgb32,
+            width: i32::MAX,
+            height: i32::MAX,
+            data: Vec::new(),
+            extend: Extend::None,
+            filter: Filter::Good,
+            matrix: Matrix::identity(),
+        };
+        assert_eq!(source.to_pattern().unwrap_err(), Error::InvalidSize);
+    }
+}
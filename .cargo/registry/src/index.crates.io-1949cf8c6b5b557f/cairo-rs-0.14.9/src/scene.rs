@@ -0,0 +1,363 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::svg_path::{append_segments, parse_svg_path, SvgPathError};
+use crate::{Context, Error, FillRule, LineCap, LineJoin, Matrix, PathSegment, Pattern};
+
+/// How a [`Shape`] is filled or stroked.
+#[derive(Debug, Clone)]
+pub enum Paint {
+    Rgba(f64, f64, f64, f64),
+    Pattern(Pattern),
+}
+
+impl Paint {
+    pub fn rgb(red: f64, green: f64, blue: f64) -> Self {
+        Paint::Rgba(red, green, blue, 1.0)
+    }
+
+    fn apply(&self, cr: &Context) -> Result<(), Error> {
+        match *self {
+            Paint::Rgba(red, green, blue, alpha) => {
+                cr.set_source_rgba(red, green, blue, alpha);
+                Ok(())
+            }
+            Paint::Pattern(ref pattern) => cr.set_source(pattern),
+        }
+    }
+}
+
+impl From<Pattern> for Paint {
+    fn from(pattern: Pattern) -> Self {
+        Paint::Pattern(pattern)
+    }
+}
+
+/// The outline of a [`Shape`].
+#[derive(Debug, Clone)]
+pub struct Stroke {
+    paint: Paint,
+    width: f64,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    dashes: Vec<f64>,
+    dash_offset: f64,
+}
+
+impl Stroke {
+    pub fn new<P: Into<Paint>>(paint: P, width: f64) -> Self {
+        Self {
+            paint: paint.into(),
+            width,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+
+    pub fn line_cap(mut self, line_cap: LineCap) -> Self {
+        self.line_cap = line_cap;
+        self
+    }
+
+    pub fn line_join(mut self, line_join: LineJoin) -> Self {
+        self.line_join = line_join;
+        self
+    }
+
+    pub fn dash(mut self, dashes: &[f64], offset: f64) -> Self {
+        self.dashes = dashes.to_vec();
+        self.dash_offset = offset;
+        self
+    }
+
+    fn apply(&self, cr: &Context) -> Result<(), Error> {
+        self.paint.apply(cr)?;
+        cr.set_line_width(self.width);
+        cr.set_line_cap(self.line_cap);
+        cr.set_line_join(self.line_join);
+        cr.set_dash(&self.dashes, self.dash_offset);
+        Ok(())
+    }
+}
+
+/// A path with a fill, a stroke and a transformation, see [`Scene`].
+#[derive(Debug, Clone)]
+pub struct Shape {
+    segments: Vec<PathSegment>,
+    fill: Option<Paint>,
+    fill_rule: FillRule,
+    stroke: Option<Stroke>,
+    transform: Matrix,
+    opacity: f64,
+}
+
+impl Shape {
+    /// Creates a shape that draws nothing until a fill or stroke is set.
+    pub fn new(segments: Vec<PathSegment>) -> Self {
+        Self {
+            segments,
+            fill: None,
+            fill_rule: FillRule::Winding,
+            stroke: None,
+            transform: Matrix::identity(),
+            opacity: 1.0,
+        }
+    }
+
+    /// Creates a shape from SVG path data, see
+    /// [`parse_svg_path()`](crate::parse_svg_path).
+    pub fn from_svg(data: &str) -> Result<Self, SvgPathError> {
+        Ok(Self::new(parse_svg_path(data)?))
+    }
+
+    pub fn rectangle(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self::new(vec![
+            PathSegment::MoveTo((x, y)),
+            PathSegment::LineTo((x + width, y)),
+            PathSegment::LineTo((x + width, y + height)),
+            PathSegment::LineTo((x, y + height)),
+            PathSegment::ClosePath,
+        ])
+    }
+
+    pub fn circle(xc: f64, yc: f64, radius: f64) -> Self {
+        // Control point distance of a quarter circle Bézier approximation.
+        let k = 0.552_284_749_830_793_4 * radius;
+        let (l, t, r, b) = (xc - radius, yc - radius, xc + radius, yc + radius);
+        Self::new(vec![
+            PathSegment::MoveTo((r, yc)),
+            PathSegment::CurveTo((r, yc + k), (xc + k, b), (xc, b)),
+            PathSegment::CurveTo((xc - k, b), (l, yc + k), (l, yc)),
+            PathSegment::CurveTo((l, yc - k), (xc - k, t), (xc, t)),
+            PathSegment::CurveTo((xc + k, t), (r, yc - k), (r, yc)),
+            PathSegment::ClosePath,
+        ])
+    }
+
+    pub fn fill<P: Into<Paint>>(mut self, paint: P) -> Self {
+        self.fill = Some(paint.into());
+        self
+    }
+
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = Some(stroke);
+        self
+    }
+
+    /// Sets the transformation of the path, which also applies to the line
+    /// width and patterns. Replaces any earlier transformation.
+    pub fn transform(mut self, matrix: Matrix) -> Self {
+        self.transform = matrix;
+        self
+    }
+
+    pub fn opacity(mut self, opacity: f64) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Draws the shape onto `cr`, leaving its state unchanged.
+    pub fn draw(&self, cr: &Context) -> Result<(), Error> {
+        cr.save()?;
+        let result = (|| {
+            if self.opacity < 1.0 {
+                cr.push_group();
+                let result = self.draw_unsaved(cr);
+                // Pop the group whatever happened to keep the stack balanced.
+                let pattern = cr.pop_group();
+                result?;
+                cr.set_source(&pattern?)?;
+                cr.paint_with_alpha(self.opacity)
+            } else {
+                self.draw_unsaved(cr)
+            }
+        })();
+        let restored = cr.restore();
+        result.and(restored)
+    }
+
+    fn draw_unsaved(&self, cr: &Context) -> Result<(), Error> {
+        cr.transform(self.transform);
+        cr.new_path();
+        append_segments(cr, &self.segments);
+        if let Some(ref fill) = self.fill {
+            fill.apply(cr)?;
+            cr.set_fill_rule(self.fill_rule);
+            cr.fill_preserve()?;
+        }
+        if let Some(ref stroke) = self.stroke {
+            stroke.apply(cr)?;
+            cr.stroke_preserve()?;
+        }
+        cr.new_path();
+        Ok(())
+    }
+}
+
+/// A retained list of shapes that can be replayed onto any [`Context`], for
+/// example an icon built from SVG path data.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cairo::{Paint, Scene, Shape, Stroke};
+///
+/// let icon = Scene::new()
+///     .view_box(0.0, 0.0, 24.0, 24.0)
+///     .shape(Shape::circle(12.0, 12.0, 10.0).fill(Paint::rgb(0.2, 0.4, 0.8)))
+///     .shape(
+///         Shape::from_svg("M8 12 l3 3 l5 -6")?
+///             .stroke(Stroke::new(Paint::rgb(1.0, 1.0, 1.0), 2.0)),
+///     );
+///
+/// let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 48, 48)?;
+/// let cr = cairo::Context::new(&surface)?;
+/// icon.draw_fitted(&cr, 0.0, 0.0, 48.0, 48.0)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Scene {
+    shapes: Vec<Shape>,
+    view_box: Option<(f64, f64, f64, f64)>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a shape on top of the existing ones.
+    pub fn shape(mut self, shape: Shape) -> Self {
+        self.shapes.push(shape);
+        self
+    }
+
+    pub fn push(&mut self, shape: Shape) {
+        self.shapes.push(shape);
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    /// Sets the area of the scene that [`draw_fitted()`](Self::draw_fitted)
+    /// maps to its target rectangle.
+    pub fn view_box(mut self, x: f64, y: f64, width: f64, height: f64) -> Self {
+        self.view_box = Some((x, y, width, height));
+        self
+    }
+
+    /// Draws all shapes in user space of `cr`, leaving its state unchanged.
+    pub fn draw(&self, cr: &Context) -> Result<(), Error> {
+        for shape in &self.shapes {
+            shape.draw(cr)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the view box scaled to fit into the given rectangle, keeping the
+    /// aspect ratio and centering it.
+    ///
+    /// Without a view box this only translates the scene to `x`, `y`.
+    pub fn draw_fitted(
+        &self,
+        cr: &Context,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> Result<(), Error> {
+        let mut matrix = Matrix::identity();
+        match self.view_box {
+            Some((vx, vy, vw, vh)) if vw > 0.0 && vh > 0.0 => {
+                let scale = (width / vw).min(height / vh);
+                matrix.translate(
+                    x + (width - vw * scale) / 2.0,
+                    y + (height - vh * scale) / 2.0,
+                );
+                matrix.scale(scale, scale);
+                matrix.translate(-vx, -vy);
+            }
+            _ => matrix.translate(x, y),
+        }
+
+        cr.save()?;
+        cr.transform(matrix);
+        let result = self.draw(cr);
+        let restored = cr.restore();
+        result.and(restored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, ImageSurface};
+
+    fn pixel(surface: &mut ImageSurface, x: usize, y: usize) -> u32 {
+        let stride = surface.stride() as usize;
+        let data = surface.data().unwrap();
+        let offset = y * stride + x * 4;
+        u32::from_ne_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    }
+
+    #[test]
+    fn replays_shapes() {
+        let scene = Scene::new()
+            .view_box(0.0, 0.0, 10.0, 10.0)
+            .shape(Shape::rectangle(0.0, 0.0, 10.0, 10.0).fill(Paint::rgb(1.0, 0.0, 0.0)))
+            .shape(
+                Shape::from_svg("M0 0 h5 v5 h-5 z")
+                    .unwrap()
+                    .fill(Paint::rgb(0.0, 0.0, 1.0))
+                    .transform(Matrix::new(1.0, 0.0, 0.0, 1.0, 5.0, 5.0)),
+            );
+        assert_eq!(scene.shapes().len(), 2);
+
+        let mut surface = ImageSurface::create(Format::ARgb32, 20, 20).unwrap();
+        {
+            let cr = Context::new(&surface).unwrap();
+            cr.set_source_rgb(0.0, 1.0, 0.0);
+            scene.draw_fitted(&cr, 0.0, 0.0, 20.0, 20.0).unwrap();
+            // The state of the context is restored.
+            assert_eq!(cr.matrix(), Matrix::identity());
+            assert!(!cr.has_current_point().unwrap());
+        }
+
+        assert_eq!(pixel(&mut surface, 2, 2), 0xffff0000);
+        assert_eq!(pixel(&mut surface, 15, 15), 0xff0000ff);
+        assert_eq!(pixel(&mut surface, 9, 9), 0xffff0000);
+    }
+
+    #[test]
+    fn strokes_and_opacity() {
+        let mut surface = ImageSurface::create(Format::ARgb32, 20, 20).unwrap();
+        {
+            let cr = Context::new(&surface).unwrap();
+            Shape::from_svg("M0 10 H20")
+                .unwrap()
+                .stroke(Stroke::new(Paint::rgb(1.0, 1.0, 1.0), 4.0))
+                .opacity(0.5)
+                .draw(&cr)
+                .unwrap();
+        }
+
+        assert!((0x7f..=0x80).contains(&(pixel(&mut surface, 10, 10) >> 24)));
+        assert_eq!(pixel(&mut surface, 10, 5), 0);
+    }
+}
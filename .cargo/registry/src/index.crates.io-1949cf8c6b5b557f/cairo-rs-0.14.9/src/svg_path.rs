@@ -0,0 +1,551 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::f64::consts::PI;
+use thiserror::Error;
+
+use crate::{Context, Format, ImageSurface, Path, PathSegment};
+
+/// An error in SVG path data, see [`parse_svg_path()`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid SVG path data at byte {position}: {reason}")]
+pub struct SvgPathError {
+    /// The byte offset into the path data.
+    pub position: usize,
+    pub reason: &'static str,
+}
+
+/// Parses the data of an SVG `<path>` element, the `d` attribute, into
+/// segments that cairo can draw.
+///
+/// All commands are supported in their absolute and relative forms.
+/// Quadratic Béziers are converted to cubic ones and elliptical arcs to
+/// sequences of cubic Béziers.
+///
+/// ```
+/// let segments = cairo::parse_svg_path("M 0 0 h 10 v 10 z").unwrap();
+/// assert_eq!(
+///     segments,
+///     [
+///         cairo::PathSegment::MoveTo((0.0, 0.0)),
+///         cairo::PathSegment::LineTo((10.0, 0.0)),
+///         cairo::PathSegment::LineTo((10.0, 10.0)),
+///         cairo::PathSegment::ClosePath,
+///     ]
+/// );
+/// ```
+pub fn parse_svg_path(data: &str) -> Result<Vec<PathSegment>, SvgPathError> {
+    Parser::new(data).parse()
+}
+
+// Adds `segments` to the current path of `cr`.
+pub(crate) fn append_segments(cr: &Context, segments: &[PathSegment]) {
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo((x, y)) => cr.move_to(x, y),
+            PathSegment::LineTo((x, y)) => cr.line_to(x, y),
+            PathSegment::CurveTo((x1, y1), (x2, y2), (x3, y3)) => {
+                cr.curve_to(x1, y1, x2, y2, x3, y3)
+            }
+            PathSegment::ClosePath => cr.close_path(),
+        }
+    }
+}
+
+impl Path {
+    /// Creates a path from SVG path data, see [`parse_svg_path()`].
+    pub fn from_svg(data: &str) -> Result<Path, SvgPathError> {
+        let segments = parse_svg_path(data)?;
+        // Paths can only be created by a context, so build it on a scratch
+        // surface.
+        let surface = ImageSurface::create(Format::A8, 0, 0).expect("Failed to create a surface");
+        let cr = Context::new(&surface).expect("Failed to create a context");
+        append_segments(&cr, &segments);
+        Ok(cr.copy_path().expect("Failed to copy the path"))
+    }
+}
+
+impl Context {
+    /// Appends SVG path data to the current path, see [`parse_svg_path()`].
+    ///
+    /// The current path is left unchanged if the data is invalid.
+    pub fn append_svg_path(&self, data: &str) -> Result<(), SvgPathError> {
+        let segments = parse_svg_path(data)?;
+        append_segments(self, &segments);
+        Ok(())
+    }
+}
+
+type Point = (f64, f64);
+
+// The control point of the previous segment, for the smooth curve commands.
+#[derive(Clone, Copy)]
+enum Control {
+    None,
+    Cubic(Point),
+    Quadratic(Point),
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    segments: Vec<PathSegment>,
+    current: Point,
+    subpath_start: Point,
+    control: Control,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a str) -> Self {
+        Parser {
+            data: data.as_bytes(),
+            pos: 0,
+            segments: Vec::new(),
+            current: (0.0, 0.0),
+            subpath_start: (0.0, 0.0),
+            control: Control::None,
+        }
+    }
+
+    fn error(&self, reason: &'static str) -> SvgPathError {
+        SvgPathError {
+            position: self.pos,
+            reason,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(c) = self.data.get(self.pos) {
+            if c.is_ascii_whitespace() || *c == b',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn at_number(&self) -> bool {
+        matches!(
+            self.data.get(self.pos),
+            Some(b'0'..=b'9') | Some(b'+') | Some(b'-') | Some(b'.')
+        )
+    }
+
+    fn number(&mut self) -> Result<f64, SvgPathError> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let start = parser.pos;
+            while let Some(b'0'..=b'9') = parser.data.get(parser.pos) {
+                parser.pos += 1;
+            }
+            parser.pos > start
+        };
+
+        if let Some(b'+') | Some(b'-') = self.data.get(self.pos) {
+            self.pos += 1;
+        }
+        let mut has_digits = digits(self);
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            has_digits |= digits(self);
+        }
+        if !has_digits {
+            self.pos = start;
+            return Err(self.error("expected a number"));
+        }
+        if let Some(b'e') | Some(b'E') = self.data.get(self.pos) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = self.data.get(self.pos) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = mantissa_end;
+            }
+        }
+
+        // Only ASCII was consumed, so this is valid UTF-8.
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap();
+        text.parse().map_err(|_| SvgPathError {
+            position: start,
+            reason: "expected a number",
+        })
+    }
+
+    fn point(&mut self, relative: bool) -> Result<Point, SvgPathError> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(if relative {
+            (self.current.0 + x, self.current.1 + y)
+        } else {
+            (x, y)
+        })
+    }
+
+    // Arc flags are single digits that don't need to be separated from the
+    // next value.
+    fn flag(&mut self) -> Result<bool, SvgPathError> {
+        self.skip_separators();
+        let flag = match self.data.get(self.pos) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(self.error("expected an arc flag")),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+
+    fn parse(mut self) -> Result<Vec<PathSegment>, SvgPathError> {
+        let mut command = None;
+        loop {
+            self.skip_separators();
+            let c = match self.data.get(self.pos) {
+                Some(c) => *c,
+                None => break,
+            };
+
+            if c.is_ascii_alphabetic() {
+                if command.is_none() && c != b'M' && c != b'm' {
+                    return Err(self.error("path data must start with a move"));
+                }
+                self.pos += 1;
+                command = Some(c);
+            } else if !self.at_number() {
+                return Err(self.error("expected a command"));
+            }
+
+            match command {
+                // Coordinates following a move are implicit lines.
+                Some(b'M') | Some(b'm') if !c.is_ascii_alphabetic() => {
+                    let relative = command == Some(b'm');
+                    self.command(if relative { b'l' } else { b'L' })?;
+                }
+                Some(b'Z') | Some(b'z') if !c.is_ascii_alphabetic() => {
+                    return Err(self.error("expected a command"));
+                }
+                Some(command) => self.command(command)?,
+                None => return Err(self.error("path data must start with a move")),
+            }
+        }
+        Ok(self.segments)
+    }
+
+    fn line_to(&mut self, point: Point) {
+        self.segments.push(PathSegment::LineTo(point));
+        self.current = point;
+        self.control = Control::None;
+    }
+
+    fn curve_to(&mut self, c1: Point, c2: Point, end: Point) {
+        self.segments.push(PathSegment::CurveTo(c1, c2, end));
+        self.current = end;
+        self.control = Control::Cubic(c2);
+    }
+
+    fn quadratic_to(&mut self, control: Point, end: Point) {
+        let start = self.current;
+        let c1 = (
+            start.0 + 2.0 / 3.0 * (control.0 - start.0),
+            start.1 + 2.0 / 3.0 * (control.1 - start.1),
+        );
+        let c2 = (
+            end.0 + 2.0 / 3.0 * (control.0 - end.0),
+            end.1 + 2.0 / 3.0 * (control.1 - end.1),
+        );
+        self.segments.push(PathSegment::CurveTo(c1, c2, end));
+        self.current = end;
+        self.control = Control::Quadratic(control);
+    }
+
+    // The reflection of the previous control point of the same kind of curve,
+    // or the current point.
+    fn reflected(&self, control: Option<Point>) -> Point {
+        match control {
+            Some((x, y)) => (2.0 * self.current.0 - x, 2.0 * self.current.1 - y),
+            None => self.current,
+        }
+    }
+
+    fn command(&mut self, command: u8) -> Result<(), SvgPathError> {
+        let relative = command.is_ascii_lowercase();
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                let point = self.point(relative)?;
+                self.segments.push(PathSegment::MoveTo(point));
+                self.current = point;
+                self.subpath_start = point;
+                self.control = Control::None;
+            }
+            b'L' => {
+                let point = self.point(relative)?;
+                self.line_to(point);
+            }
+            b'H' => {
+                let x = self.number()?;
+                let x = if relative { self.current.0 + x } else { x };
+                self.line_to((x, self.current.1));
+            }
+            b'V' => {
+                let y = self.number()?;
+                let y = if relative { self.current.1 + y } else { y };
+                self.line_to((self.current.0, y));
+            }
+            b'C' => {
+                let c1 = self.point(relative)?;
+                let c2 = self.point(relative)?;
+                let end = self.point(relative)?;
+                self.curve_to(c1, c2, end);
+            }
+            b'S' => {
+                let c1 = match self.control {
+                    Control::Cubic(control) => self.reflected(Some(control)),
+                    _ => self.reflected(None),
+                };
+                let c2 = self.point(relative)?;
+                let end = self.point(relative)?;
+                self.curve_to(c1, c2, end);
+            }
+            b'Q' => {
+                let control = self.point(relative)?;
+                let end = self.point(relative)?;
+                self.quadratic_to(control, end);
+            }
+            b'T' => {
+                let control = match self.control {
+                    Control::Quadratic(control) => self.reflected(Some(control)),
+                    _ => self.reflected(None),
+                };
+                let end = self.point(relative)?;
+                self.quadratic_to(control, end);
+            }
+            b'A' => {
+                let rx = self.number()?;
+                let ry = self.number()?;
+                let rotation = self.number()?;
+                let large_arc = self.flag()?;
+                let sweep = self.flag()?;
+                let end = self.point(relative)?;
+                self.arc_to(rx, ry, rotation, large_arc, sweep, end);
+            }
+            b'Z' => {
+                self.segments.push(PathSegment::ClosePath);
+                self.current = self.subpath_start;
+                self.control = Control::None;
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unknown command"));
+            }
+        }
+        Ok(())
+    }
+
+    // Converts an endpoint parameterized elliptical arc to cubic Béziers, as
+    // described in appendix B.2 of the SVG 2 specification.
+    fn arc_to(
+        &mut self,
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        end: Point,
+    ) {
+        let start = self.current;
+        if start == end {
+            return;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(end);
+            return;
+        }
+
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let dx = (start.0 - end.0) / 2.0;
+        let dy = (start.1 - end.1) / 2.0;
+        let x1 = cos * dx + sin * dy;
+        let y1 = -sin * dx + cos * dy;
+
+        // Scale up radii that are too small to reach the end point.
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+        if large_arc == sweep {
+            coefficient = -coefficient;
+        }
+        let cx1 = coefficient * rx * y1 / ry;
+        let cy1 = -coefficient * ry * x1 / rx;
+        let cx = cos * cx1 - sin * cy1 + (start.0 + end.0) / 2.0;
+        let cy = sin * cx1 + cos * cy1 + (start.1 + end.1) / 2.0;
+
+        let angle =
+            |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+        let start_angle = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut sweep_angle = angle(
+            (x1 - cx1) / rx,
+            (y1 - cy1) / ry,
+            (-x1 - cx1) / rx,
+            (-y1 - cy1) / ry,
+        );
+        if !sweep && sweep_angle > 0.0 {
+            sweep_angle -= 2.0 * PI;
+        } else if sweep && sweep_angle < 0.0 {
+            sweep_angle += 2.0 * PI;
+        }
+
+        // Each Bézier approximates at most a quarter of the ellipse.
+        let n = (sweep_angle.abs() / (PI / 2.0) - 1e-9).ceil().max(1.0) as usize;
+        let delta = sweep_angle / n as f64;
+        let t = 4.0 / 3.0 * (delta / 4.0).tan();
+        let map = |(x, y): Point| {
+            (
+                cx + rx * cos * x - ry * sin * y,
+                cy + rx * sin * x + ry * cos * y,
+            )
+        };
+        for i in 0..n {
+            let (sin1, cos1) = (start_angle + i as f64 * delta).sin_cos();
+            let (sin2, cos2) = (start_angle + (i + 1) as f64 * delta).sin_cos();
+            let c1 = map((cos1 - t * sin1, sin1 + t * cos1));
+            let c2 = map((cos2 + t * sin2, sin2 - t * cos2));
+            let point = if i + 1 == n { end } else { map((cos2, sin2)) };
+            self.curve_to(c1, c2, point);
+        }
+        self.control = Control::None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(segment: &PathSegment, expected: &PathSegment) {
+        let points = |segment: &PathSegment| match *segment {
+            PathSegment::MoveTo(p) | PathSegment::LineTo(p) => vec![p],
+            PathSegment::CurveTo(p1, p2, p3) => vec![p1, p2, p3],
+            PathSegment::ClosePath => vec![],
+        };
+        assert_eq!(
+            std::mem::discriminant(segment),
+            std::mem::discriminant(expected),
+            "{:?} != {:?}",
+            segment,
+            expected
+        );
+        for (a, b) in points(segment).iter().zip(points(expected).iter()) {
+            assert!(
+                (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6,
+                "{:?} != {:?}",
+                segment,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn lines_and_relative_commands() {
+        let segments = parse_svg_path("M10,10 l5-5 H30 v.5e1 m-1 1 2 2 Z L0 0").unwrap();
+        assert_eq!(
+            segments,
+            [
+                PathSegment::MoveTo((10.0, 10.0)),
+                PathSegment::LineTo((15.0, 5.0)),
+                PathSegment::LineTo((30.0, 5.0)),
+                PathSegment::LineTo((30.0, 10.0)),
+                PathSegment::MoveTo((29.0, 11.0)),
+                PathSegment::LineTo((31.0, 13.0)),
+                PathSegment::ClosePath,
+                PathSegment::LineTo((0.0, 0.0)),
+            ]
+        );
+        // Numbers may run into each other.
+        assert_eq!(
+            parse_svg_path("M.5.5-1-1").unwrap(),
+            [
+                PathSegment::MoveTo((0.5, 0.5)),
+                PathSegment::LineTo((-1.0, -1.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn curves() {
+        let segments = parse_svg_path("M0 0 Q 3 3 6 0 T 12 0 C 12 3 15 3 15 0 s 3 -3 3 0").unwrap();
+        let expected = [
+            PathSegment::MoveTo((0.0, 0.0)),
+            PathSegment::CurveTo((2.0, 2.0), (4.0, 2.0), (6.0, 0.0)),
+            PathSegment::CurveTo((8.0, -2.0), (10.0, -2.0), (12.0, 0.0)),
+            PathSegment::CurveTo((12.0, 3.0), (15.0, 3.0), (15.0, 0.0)),
+            PathSegment::CurveTo((15.0, -3.0), (18.0, -3.0), (18.0, 0.0)),
+        ];
+        assert_eq!(segments.len(), expected.len());
+        for (segment, expected) in segments.iter().zip(expected.iter()) {
+            assert_close(segment, expected);
+        }
+    }
+
+    #[test]
+    fn arcs() {
+        // A half circle of radius 10 around (10, 0), with packed flags.
+        let segments = parse_svg_path("M0 0 a10 10 0 01 20 0").unwrap();
+        assert_eq!(segments.len(), 3);
+        let k = 4.0 / 3.0 * (PI / 8.0).tan() * 10.0;
+        assert_close(
+            &segments[1],
+            &PathSegment::CurveTo((0.0, -k), (10.0 - k, -10.0), (10.0, -10.0)),
+        );
+        assert_close(
+            &segments[2],
+            &PathSegment::CurveTo((10.0 + k, -10.0), (20.0, -k), (20.0, 0.0)),
+        );
+
+        // Radii too small to reach the end point are scaled up, which gives
+        // the lower half circle here.
+        let segments = parse_svg_path("M0 0 A1 1 0 0 0 20 0").unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_close(
+            &segments[1],
+            &PathSegment::CurveTo((0.0, k), (10.0 - k, 10.0), (10.0, 10.0)),
+        );
+        // Zero radii give a straight line.
+        assert_eq!(
+            parse_svg_path("M0 0 A0 5 0 0 0 20 0").unwrap()[1],
+            PathSegment::LineTo((20.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |data| parse_svg_path(data).unwrap_err();
+        assert_eq!(error("L 1 1").position, 0);
+        assert_eq!(error("M 1 1 L 2").reason, "expected a number");
+        assert_eq!(error("M 1 1 L 2").position, 9);
+        assert_eq!(error("M 1 1 X 2 2").position, 6);
+        assert_eq!(error("M0 0 A1 1 0 2 0 1 1").reason, "expected an arc flag");
+        assert_eq!(error("M0 0 Z 1").position, 7);
+        assert!(parse_svg_path("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn append_to_context() {
+        let path = Path::from_svg("M1 2 L3 4 Z").unwrap();
+        let segments: Vec<_> = path.iter().collect();
+        assert_eq!(segments[0], PathSegment::MoveTo((1.0, 2.0)));
+        assert_eq!(segments[1], PathSegment::LineTo((3.0, 4.0)));
+        assert_eq!(segments[2], PathSegment::ClosePath);
+
+        let surface = ImageSurface::create(Format::ARgb32, 10, 10).unwrap();
+        let cr = Context::new(&surface).unwrap();
+        assert!(cr.append_svg_path("M 0 0 L").is_err());
+        assert!(!cr.has_current_point().unwrap());
+        cr.append_svg_path("M 2 2 h 4 v 4 h -4 z").unwrap();
+        assert_eq!(cr.fill_extents().unwrap(), (2.0, 2.0, 6.0, 6.0));
+    }
+}
@@ -0,0 +1,453 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::keys::{constants, Key};
+use crate::{Display, EventKey, Keymap, ModifierType};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The conventions for writing a [`KeyCombination`] as a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyNotation {
+    /// GTK accelerators, e.g. `<Super><Shift>q`.
+    Gtk,
+    /// Hyprland binds, e.g. `SUPER+SHIFT+Q`, or `SUPER SHIFT, Q` as in a
+    /// `bind` line.
+    ///
+    /// Hyprland has no Hyper and Meta modifiers: Hyper is written as `MOD3`
+    /// and Meta as `ALT`.
+    Hyprland,
+    /// Emacs key descriptions, e.g. `s-S-q` or `C-x`.
+    ///
+    /// Alt and Meta are both written as `M-`, and `A-` is read as Alt.
+    Emacs,
+}
+
+// The modifiers of a combination, in the order they are written.
+const MODIFIERS: &[(ModifierType, &str, &str, &str)] = &[
+    (ModifierType::SUPER_MASK, "<Super>", "SUPER", "s-"),
+    (ModifierType::HYPER_MASK, "<Hyper>", "MOD3", "H-"),
+    (ModifierType::META_MASK, "<Meta>", "ALT", "M-"),
+    (ModifierType::CONTROL_MASK, "<Control>", "CTRL", "C-"),
+    (ModifierType::MOD1_MASK, "<Alt>", "ALT", "M-"),
+    (ModifierType::SHIFT_MASK, "<Shift>", "SHIFT", "S-"),
+];
+
+/// A key with the modifiers held while pressing it, as used for shortcuts.
+///
+/// The key is stored in lower case and only Shift, Control, Alt, Super,
+/// Hyper and Meta are kept, so combinations parsed from different spellings
+/// compare equal. Modifiers are always written in the same order: Super,
+/// Hyper, Meta, Control, Alt and Shift.
+///
+/// ```no_run
+/// use gdk::{KeyCombination, KeyNotation};
+///
+/// let combination = KeyCombination::parse("<Shift><Super>q", KeyNotation::Gtk).unwrap();
+/// assert_eq!(combination.to_notation(KeyNotation::Hyprland), "SUPER+SHIFT+Q");
+/// assert_eq!(combination.to_notation(KeyNotation::Emacs), "s-S-q");
+/// assert_eq!(combination, "SUPER SHIFT, Q".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyCombination {
+    key: Key,
+    modifiers: ModifierType,
+}
+
+impl KeyCombination {
+    pub fn new(key: Key, modifiers: ModifierType) -> Self {
+        skip_assert_initialized!();
+        let key = if key == constants::ISO_Left_Tab {
+            constants::Tab
+        } else {
+            key.to_lower()
+        };
+        Self {
+            key,
+            modifiers: modifiers & Self::modifier_mask(),
+        }
+    }
+
+    /// The modifiers a combination can hold.
+    pub fn modifier_mask() -> ModifierType {
+        skip_assert_initialized!();
+        MODIFIERS
+            .iter()
+            .fold(ModifierType::empty(), |mask, (modifier, ..)| {
+                mask | *modifier
+            })
+    }
+
+    pub fn key(&self) -> Key {
+        self.key.clone()
+    }
+
+    pub fn modifiers(&self) -> ModifierType {
+        self.modifiers
+    }
+
+    /// The combination of a key press, or `None` if only a modifier was
+    /// pressed.
+    ///
+    /// Uses the keymap of the event's display. See
+    /// [`from_event_with_keymap`](Self::from_event_with_keymap).
+    pub fn from_event(event: &EventKey) -> Option<Self> {
+        skip_assert_initialized!();
+        let display = match event.window() {
+            Some(window) => window.display(),
+            None => Display::default()?,
+        };
+        Self::from_event_with_keymap(event, &Keymap::for_display(&display)?)
+    }
+
+    /// The combination of a key press, or `None` if only a modifier was
+    /// pressed.
+    ///
+    /// The key is the one on the base level of the event's layout, so that
+    /// Shift+1 gives `SHIFT+1` like in Hyprland, rather than `!`, and a
+    /// Cyrillic layout gives Cyrillic letters. Real modifiers like Mod4 are
+    /// turned into the virtual ones they are mapped to, like Super, which is
+    /// how they are reported on Wayland.
+    pub fn from_event_with_keymap(event: &EventKey, keymap: &Keymap) -> Option<Self> {
+        skip_assert_initialized!();
+        if event.is_modifier() {
+            return None;
+        }
+        let key = keymap
+            .translate_keyboard_state(
+                u32::from(event.hardware_keycode()),
+                ModifierType::empty(),
+                i32::from(event.group()),
+            )
+            .map_or_else(|| event.keyval(), |(keyval, ..)| Key::from(keyval));
+
+        let mut modifiers = event.state();
+        keymap.add_virtual_modifiers(&mut modifiers);
+        // Meta is usually on the same real modifier as Alt, and Hyper on the
+        // same as Super, in which case they would always come in pairs.
+        let virtual_mask =
+            ModifierType::SUPER_MASK | ModifierType::HYPER_MASK | ModifierType::META_MASK;
+        let real = |modifier: ModifierType| {
+            let mut state = modifier;
+            keymap.map_virtual_modifiers(&mut state);
+            state - virtual_mask
+        };
+        for &(duplicate, original) in &[
+            (ModifierType::META_MASK, ModifierType::MOD1_MASK),
+            (ModifierType::HYPER_MASK, ModifierType::SUPER_MASK),
+        ] {
+            if modifiers.contains(duplicate | original) && real(duplicate) == real(original) {
+                modifiers.remove(duplicate);
+            }
+        }
+        Some(Self::new(key, modifiers))
+    }
+
+    pub fn parse(s: &str, notation: KeyNotation) -> Result<Self, KeyCombinationParseError> {
+        skip_assert_initialized!();
+        match notation {
+            KeyNotation::Gtk => parse_gtk(s),
+            KeyNotation::Hyprland => match s.find(',') {
+                Some(comma) => Self::parse_hyprland_bind(&s[..comma], &s[comma + 1..]),
+                None => match s.rfind('+') {
+                    Some(plus) if plus + 1 < s.len() => {
+                        Self::parse_hyprland_bind(&s[..plus], &s[plus + 1..])
+                    }
+                    // A trailing `+` after a separator is the key, e.g. `CTRL++`.
+                    Some(plus) if s[..plus].ends_with(is_hyprland_separator) => {
+                        Self::parse_hyprland_bind(&s[..plus], "+")
+                    }
+                    _ => Self::parse_hyprland_bind("", s),
+                },
+            },
+            KeyNotation::Emacs => parse_emacs(s),
+        }
+    }
+
+    /// Parses the modifiers and key of a Hyprland `bind` line, e.g. `SUPER
+    /// SHIFT` and `Q`.
+    ///
+    /// `CAPS`, `MOD2` and `MOD5` are accepted but dropped, since a
+    /// [`KeyCombination`] only keeps Shift, Control, Alt, Super, Hyper and
+    /// Meta.
+    pub fn parse_hyprland_bind(
+        modifiers: &str,
+        key: &str,
+    ) -> Result<Self, KeyCombinationParseError> {
+        skip_assert_initialized!();
+        let mut mask = ModifierType::empty();
+        for name in modifiers
+            .split(is_hyprland_separator)
+            .filter(|name| !name.is_empty())
+        {
+            mask |= match name.to_ascii_uppercase().as_str() {
+                "SHIFT" => ModifierType::SHIFT_MASK,
+                "CTRL" | "CONTROL" => ModifierType::CONTROL_MASK,
+                "ALT" | "MOD1" => ModifierType::MOD1_MASK,
+                "CAPS" => ModifierType::LOCK_MASK,
+                "MOD2" => ModifierType::MOD2_MASK,
+                "MOD3" => ModifierType::HYPER_MASK,
+                "SUPER" | "WIN" | "LOGO" | "MOD4" => ModifierType::SUPER_MASK,
+                "MOD5" => ModifierType::MOD5_MASK,
+                _ => return Err(KeyCombinationParseError::new("modifier", name)),
+            };
+        }
+        Ok(Self::new(key_from_name(key.trim())?, mask))
+    }
+
+    pub fn to_notation(&self, notation: KeyNotation) -> String {
+        let mut s = String::new();
+        match notation {
+            KeyNotation::Gtk => {
+                for (_, modifier, ..) in self.written_modifiers(notation) {
+                    s.push_str(modifier);
+                }
+                s.push_str(&key_name(&self.key));
+            }
+            KeyNotation::Hyprland => {
+                for (_, _, modifier, _) in self.written_modifiers(notation) {
+                    s.push_str(modifier);
+                    s.push('+');
+                }
+                s.push_str(&hyprland_key_name(&self.key));
+            }
+            KeyNotation::Emacs => {
+                for (.., modifier) in self.written_modifiers(notation) {
+                    s.push_str(modifier);
+                }
+                s.push_str(&emacs_key_name(&self.key));
+            }
+        }
+        s
+    }
+
+    /// The modifiers and key as written in a Hyprland `bind` line, e.g.
+    /// `SUPER SHIFT, Q`.
+    pub fn to_hyprland_bind(&self) -> String {
+        let modifiers = self
+            .written_modifiers(KeyNotation::Hyprland)
+            .map(|(_, _, modifier, _)| *modifier)
+            .collect::<Vec<_>>();
+        format!("{}, {}", modifiers.join(" "), hyprland_key_name(&self.key))
+    }
+
+    fn written_modifiers(
+        &self,
+        notation: KeyNotation,
+    ) -> impl Iterator<Item = &'static (ModifierType, &'static str, &'static str, &'static str)>
+    {
+        let mut modifiers = self.modifiers;
+        // Notations without a name of their own for Meta write it like Alt.
+        if notation != KeyNotation::Gtk && modifiers.contains(ModifierType::MOD1_MASK) {
+            modifiers.remove(ModifierType::META_MASK);
+        }
+        MODIFIERS
+            .iter()
+            .filter(move |(modifier, ..)| modifiers.contains(*modifier))
+    }
+}
+
+impl fmt::Display for KeyCombination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_notation(KeyNotation::Gtk))
+    }
+}
+
+impl FromStr for KeyCombination {
+    type Err = KeyCombinationParseError;
+
+    /// Parses a combination in any [`KeyNotation`], trying GTK, Hyprland
+    /// and Emacs in that order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        skip_assert_initialized!();
+        Self::parse(s, KeyNotation::Gtk)
+            .or_else(|_| Self::parse(s, KeyNotation::Hyprland))
+            .or_else(|_| Self::parse(s, KeyNotation::Emacs))
+            .map_err(|_| KeyCombinationParseError::new("key combination", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCombinationParseError {
+    what: &'static str,
+    input: String,
+}
+
+impl KeyCombinationParseError {
+    fn new(what: &'static str, input: &str) -> Self {
+        Self {
+            what,
+            input: input.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for KeyCombinationParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid {} `{}`", self.what, self.input)
+    }
+}
+
+impl Error for KeyCombinationParseError {}
+
+fn parse_gtk(s: &str) -> Result<KeyCombination, KeyCombinationParseError> {
+    let mut rest = s.trim();
+    let mut mask = ModifierType::empty();
+    while rest.starts_with('<') {
+        let end = rest
+            .find('>')
+            .ok_or_else(|| KeyCombinationParseError::new("accelerator", s))?;
+        mask |= match rest[1..end].to_ascii_lowercase().as_str() {
+            "shift" | "shft" => ModifierType::SHIFT_MASK,
+            "control" | "ctrl" | "ctl" | "primary" => ModifierType::CONTROL_MASK,
+            "alt" | "mod1" => ModifierType::MOD1_MASK,
+            "super" | "mod4" => ModifierType::SUPER_MASK,
+            "hyper" => ModifierType::HYPER_MASK,
+            "meta" => ModifierType::META_MASK,
+            _ => return Err(KeyCombinationParseError::new("modifier", &rest[..=end])),
+        };
+        rest = &rest[end + 1..];
+    }
+    Ok(KeyCombination::new(key_from_name(rest)?, mask))
+}
+
+fn parse_emacs(s: &str) -> Result<KeyCombination, KeyCombinationParseError> {
+    let mut rest = s.trim();
+    let mut mask = ModifierType::empty();
+    // The key itself can be `-`, as in `C--`.
+    while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        mask |= match rest.as_bytes()[0] {
+            b'A' | b'M' => ModifierType::MOD1_MASK,
+            b'C' => ModifierType::CONTROL_MASK,
+            b'H' => ModifierType::HYPER_MASK,
+            b'S' => ModifierType::SHIFT_MASK,
+            b's' => ModifierType::SUPER_MASK,
+            _ => return Err(KeyCombinationParseError::new("modifier", &rest[..2])),
+        };
+        rest = &rest[2..];
+    }
+    let key = match rest {
+        "RET" => constants::Return,
+        "SPC" => constants::space,
+        "TAB" => constants::Tab,
+        "ESC" => constants::Escape,
+        "DEL" => constants::BackSpace,
+        "<prior>" => constants::Page_Up,
+        "<next>" => constants::Page_Down,
+        _ if rest.len() > 2 && rest.starts_with('<') && rest.ends_with('>') => {
+            key_from_name(&rest[1..rest.len() - 1].replace('-', "_"))?
+        }
+        _ => {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => {
+                    // Emacs writes Shift+A as `A`.
+                    if c.is_uppercase() {
+                        mask |= ModifierType::SHIFT_MASK;
+                    }
+                    Key::from_unicode(c)
+                }
+                _ => return Err(KeyCombinationParseError::new("key", rest)),
+            }
+        }
+    };
+    Ok(KeyCombination::new(key, mask))
+}
+
+fn is_hyprland_separator(c: char) -> bool {
+    c.is_whitespace() || c == '_' || c == '+'
+}
+
+fn is_valid(key: &Key) -> bool {
+    **key != 0 && *key != constants::VoidSymbol
+}
+
+// Looks up a key name like GTK, or ignoring case like Hyprland, e.g.
+// `return`, `page_up` or `kp_add`. A single character is its own key.
+fn key_from_name(name: &str) -> Result<Key, KeyCombinationParseError> {
+    fn capitalize(word: &str) -> String {
+        let mut word = word.to_owned();
+        if let Some(first) = word.get_mut(..1) {
+            first.make_ascii_uppercase();
+        }
+        word
+    }
+
+    if name.is_empty() {
+        return Err(KeyCombinationParseError::new("key", name));
+    }
+    let lower = name.to_ascii_lowercase();
+    let title = lower
+        .split('_')
+        .map(capitalize)
+        .collect::<Vec<_>>()
+        .join("_");
+    let keypad = match lower.strip_prefix("kp_") {
+        Some(rest) => format!("KP_{}", capitalize(rest)),
+        None => String::new(),
+    };
+    let candidates = [
+        name.to_owned(),
+        lower,
+        title,
+        name.to_ascii_uppercase(),
+        keypad,
+    ];
+    if let Some(key) = candidates
+        .iter()
+        .filter(|name| !name.is_empty())
+        .map(|name| Key::from_name(name))
+        .find(is_valid)
+    {
+        return Ok(key);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Key::from_unicode(c)),
+        _ => Err(KeyCombinationParseError::new("key", name)),
+    }
+}
+
+fn key_name(key: &Key) -> String {
+    // Keys with several names, of which GDK may give any.
+    let preferred = [
+        (constants::Page_Up, "Page_Up"),
+        (constants::Page_Down, "Page_Down"),
+        (constants::KP_Page_Up, "KP_Page_Up"),
+        (constants::KP_Page_Down, "KP_Page_Down"),
+        (constants::Mode_switch, "Mode_switch"),
+    ];
+    if let Some((_, name)) = preferred.iter().find(|(preferred, _)| preferred == key) {
+        return (*name).to_owned();
+    }
+    match key.name() {
+        Some(name) => name.to_string(),
+        None => format!("{:#x}", **key),
+    }
+}
+
+fn hyprland_key_name(key: &Key) -> String {
+    match key.to_unicode() {
+        Some(c) if c.is_ascii_alphanumeric() => c.to_ascii_uppercase().to_string(),
+        _ => key_name(key),
+    }
+}
+
+fn emacs_key_name(key: &Key) -> String {
+    let special = [
+        (constants::Return, "RET"),
+        (constants::space, "SPC"),
+        (constants::Tab, "TAB"),
+        (constants::Escape, "ESC"),
+        (constants::BackSpace, "DEL"),
+        (constants::Page_Up, "<prior>"),
+        (constants::Page_Down, "<next>"),
+    ];
+    if let Some((_, name)) = special.iter().find(|(special, _)| special == key) {
+        return (*name).to_owned();
+    }
+    match key.to_unicode() {
+        Some(c) if !c.is_control() => c.to_string(),
+        _ => format!("<{}>", key_name(key).to_lowercase().replace('_', "-")),
+    }
+}
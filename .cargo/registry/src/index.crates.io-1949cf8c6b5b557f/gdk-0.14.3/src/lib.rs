@@ -69,6 +69,7 @@ mod frame_clock;
 mod frame_timings;
 mod functions;
 mod geometry;
+mod key_combination;
 mod keymap;
 mod keymap_key;
 pub mod keys;
@@ -123,6 +124,7 @@ pub use crate::event_visibility::EventVisibility;
 pub use crate::event_window_state::EventWindowState;
 pub use crate::functions::*;
 pub use crate::geometry::Geometry;
+pub use crate::key_combination::{KeyCombination, KeyCombinationParseError, KeyNotation};
 pub use crate::keymap_key::KeymapKey;
 pub use crate::rectangle::Rectangle;
 pub use crate::rgba::{RgbaParseError, RGBA};
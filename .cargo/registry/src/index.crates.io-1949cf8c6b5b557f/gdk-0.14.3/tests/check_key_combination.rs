@@ -0,0 +1,71 @@
+use gdk::{KeyCombination, KeyNotation, ModifierType};
+
+#[test]
+fn check_key_combination() {
+    let combination = KeyCombination::parse("<Shift><Super>q", KeyNotation::Gtk).unwrap();
+
+    assert_eq!(*combination.key(), *gdk::keys::constants::q);
+    assert_eq!(
+        combination.modifiers(),
+        ModifierType::SUPER_MASK | ModifierType::SHIFT_MASK
+    );
+    assert_eq!(combination.to_string(), "<Super><Shift>q");
+    assert_eq!(
+        combination.to_notation(KeyNotation::Hyprland),
+        "SUPER+SHIFT+Q"
+    );
+    assert_eq!(combination.to_hyprland_bind(), "SUPER SHIFT, Q");
+    assert_eq!(combination.to_notation(KeyNotation::Emacs), "s-S-q");
+
+    assert_eq!(combination, "SUPER SHIFT, Q".parse().unwrap());
+    assert_eq!(combination, "super_shift+q".parse().unwrap());
+    assert_eq!(combination, "s-Q".parse().unwrap());
+
+    let combination = KeyCombination::parse("C-<page-up>", KeyNotation::Emacs).unwrap();
+    assert_eq!(combination.to_notation(KeyNotation::Emacs), "C-<prior>");
+    assert_eq!(
+        combination.to_notation(KeyNotation::Gtk),
+        "<Control>Page_Up"
+    );
+    assert_eq!(combination, "<Control>Prior".parse().unwrap());
+    assert_eq!(
+        KeyCombination::parse("KP_Next", KeyNotation::Gtk)
+            .unwrap()
+            .to_string(),
+        "KP_Page_Down"
+    );
+
+    // Lock modifiers are accepted but not kept.
+    let combination = KeyCombination::parse_hyprland_bind("SUPER CAPS MOD2 MOD5", "Q").unwrap();
+    assert_eq!(combination.modifiers(), ModifierType::SUPER_MASK);
+    assert_eq!(combination, "SUPER, Q".parse().unwrap());
+
+    // A trailing `+` after a separator is the key.
+    let plus = KeyCombination::new(gdk::keys::constants::plus, ModifierType::CONTROL_MASK);
+    assert_eq!(
+        KeyCombination::parse("CTRL++", KeyNotation::Hyprland).unwrap(),
+        plus
+    );
+    assert_eq!(
+        KeyCombination::parse("CTRL +", KeyNotation::Hyprland).unwrap(),
+        plus
+    );
+    assert_eq!(
+        KeyCombination::parse(
+            &plus.to_notation(KeyNotation::Hyprland),
+            KeyNotation::Hyprland
+        )
+        .unwrap(),
+        plus
+    );
+    assert_eq!(
+        *KeyCombination::parse("+", KeyNotation::Hyprland)
+            .unwrap()
+            .key(),
+        *gdk::keys::constants::plus
+    );
+    assert!(KeyCombination::parse("CTRL+", KeyNotation::Hyprland).is_err());
+
+    assert!(KeyCombination::parse("FOO, Q", KeyNotation::Hyprland).is_err());
+    assert!("<Foo>q".parse::<KeyCombination>().is_err());
+}
@@ -0,0 +1,270 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::{Colorspace, Pixbuf, Rgb};
+
+// Image operations work on a premultiplied floating point copy of the pixels,
+// so that blurring does not bleed the colour of transparent pixels and the
+// source pixbuf is never modified. The result is always a new pixbuf.
+#[derive(Debug, Clone)]
+struct Image {
+    width: usize,
+    height: usize,
+    has_alpha: bool,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Image {
+    fn from_pixbuf(pixbuf: &Pixbuf) -> Image {
+        assert_eq!(
+            pixbuf.bits_per_sample(),
+            8,
+            "only 8 bits per sample are supported"
+        );
+        let width = pixbuf.width() as usize;
+        let height = pixbuf.height() as usize;
+        let rowstride = pixbuf.rowstride() as usize;
+        let n_channels = pixbuf.n_channels() as usize;
+        let has_alpha = pixbuf.has_alpha();
+        let bytes = pixbuf
+            .read_pixel_bytes()
+            .expect("failed to read the pixels");
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = &bytes[y * rowstride..y * rowstride + width * n_channels];
+            for pixel in row.chunks_exact(n_channels) {
+                let alpha = if has_alpha {
+                    pixel[3] as f32 / 255.0
+                } else {
+                    1.0
+                };
+                pixels.push([
+                    pixel[0] as f32 / 255.0 * alpha,
+                    pixel[1] as f32 / 255.0 * alpha,
+                    pixel[2] as f32 / 255.0 * alpha,
+                    alpha,
+                ]);
+            }
+        }
+
+        Image {
+            width,
+            height,
+            has_alpha,
+            pixels,
+        }
+    }
+
+    fn into_pixbuf(self) -> Pixbuf {
+        let n_channels = if self.has_alpha { 4 } else { 3 };
+        let rowstride = (self.width * n_channels + 3) & !3;
+        let mut data = vec![0u8; rowstride * self.height];
+
+        for (y, row) in self.pixels.chunks_exact(self.width).enumerate() {
+            let out = &mut data[y * rowstride..y * rowstride + self.width * n_channels];
+            for (pixel, out) in row.iter().zip(out.chunks_exact_mut(n_channels)) {
+                let alpha = pixel[3].clamp(0.0, 1.0);
+                let unpremultiply = |c: f32| {
+                    if alpha > 0.0 {
+                        to_u8(c / alpha)
+                    } else {
+                        0
+                    }
+                };
+                out[0] = unpremultiply(pixel[0]);
+                out[1] = unpremultiply(pixel[1]);
+                out[2] = unpremultiply(pixel[2]);
+                if self.has_alpha {
+                    out[3] = to_u8(alpha);
+                }
+            }
+        }
+
+        Pixbuf::from_mut_slice(
+            data,
+            Colorspace::Rgb,
+            self.has_alpha,
+            8,
+            self.width as i32,
+            self.height as i32,
+            rowstride as i32,
+        )
+    }
+
+    // Applies `f` to the straight alpha colour channels of every pixel.
+    fn map_colors<F: Fn([f32; 3]) -> [f32; 3]>(&mut self, f: F) {
+        for pixel in &mut self.pixels {
+            let alpha = pixel[3];
+            if alpha <= 0.0 {
+                continue;
+            }
+            let [r, g, b] = f([pixel[0] / alpha, pixel[1] / alpha, pixel[2] / alpha]);
+            pixel[0] = r.clamp(0.0, 1.0) * alpha;
+            pixel[1] = g.clamp(0.0, 1.0) * alpha;
+            pixel[2] = b.clamp(0.0, 1.0) * alpha;
+        }
+    }
+
+    // Convolves the image with `kernel` horizontally and then vertically,
+    // extending the edge pixels outwards.
+    fn convolve_separable(&mut self, kernel: &[f32]) {
+        let radius = (kernel.len() / 2) as isize;
+        let (width, height) = (self.width as isize, self.height as isize);
+        let mut scratch = vec![[0.0; 4]; self.pixels.len()];
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                for (i, weight) in kernel.iter().enumerate() {
+                    let sx = (x + i as isize - radius).max(0).min(width - 1);
+                    let pixel = &self.pixels[(y * width + sx) as usize];
+                    for c in 0..4 {
+                        sum[c] += pixel[c] * weight;
+                    }
+                }
+                scratch[(y * width + x) as usize] = sum;
+            }
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                for (i, weight) in kernel.iter().enumerate() {
+                    let sy = (y + i as isize - radius).max(0).min(height - 1);
+                    let pixel = &scratch[(sy * width + x) as usize];
+                    for c in 0..4 {
+                        sum[c] += pixel[c] * weight;
+                    }
+                }
+                self.pixels[(y * width + x) as usize] = sum;
+            }
+        }
+    }
+}
+
+fn to_u8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn luma([r, g, b]: [f32; 3]) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn gaussian_kernel(sigma: f64) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as isize;
+    let mut kernel: Vec<f32> = (-radius..=radius)
+        .map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp() as f32)
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|weight| *weight /= sum);
+    kernel
+}
+
+// The fraction of the pixel at (`x`, `y`) that lies inside a rectangle of
+// `width` × `height` with corners rounded by `radius`.
+fn rounded_rect_coverage(x: usize, y: usize, width: f64, height: f64, radius: f64) -> f32 {
+    let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+    let cx = if px < radius {
+        radius
+    } else if px > width - radius {
+        width - radius
+    } else {
+        return 1.0;
+    };
+    let cy = if py < radius {
+        radius
+    } else if py > height - radius {
+        height - radius
+    } else {
+        return 1.0;
+    };
+    let distance = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+    (radius - distance + 0.5).clamp(0.0, 1.0) as f32
+}
+
+impl Pixbuf {
+    /// Returns a copy blurred with a Gaussian of standard deviation `sigma`
+    /// pixels.
+    pub fn gaussian_blur(&self, sigma: f64) -> Pixbuf {
+        assert!(sigma >= 0.0, "sigma must not be negative");
+        let mut image = Image::from_pixbuf(self);
+        if sigma > 0.0 {
+            image.convolve_separable(&gaussian_kernel(sigma));
+        }
+        image.into_pixbuf()
+    }
+
+    /// Returns a copy where every pixel is the average of the
+    /// `(2 * radius + 1)²` pixels around it.
+    pub fn box_blur(&self, radius: u32) -> Pixbuf {
+        let mut image = Image::from_pixbuf(self);
+        if radius > 0 {
+            let size = 2 * radius as usize + 1;
+            image.convolve_separable(&vec![1.0 / size as f32; size]);
+        }
+        image.into_pixbuf()
+    }
+
+    /// Returns a copy with adjusted brightness and contrast.
+    ///
+    /// `brightness` is added to every channel, so `-1.0` gives black, `0.0`
+    /// leaves the image unchanged and `1.0` gives white. `contrast` scales the
+    /// channels around mid-grey, so `0.0` gives grey and `1.0` leaves the
+    /// image unchanged.
+    pub fn brightness_contrast(&self, brightness: f64, contrast: f64) -> Pixbuf {
+        let (brightness, contrast) = (brightness as f32, contrast as f32);
+        let mut image = Image::from_pixbuf(self);
+        image.map_colors(|rgb| {
+            let adjust = |c: f32| (c - 0.5) * contrast + 0.5 + brightness;
+            [adjust(rgb[0]), adjust(rgb[1]), adjust(rgb[2])]
+        });
+        image.into_pixbuf()
+    }
+
+    /// Returns a grayscale copy, using the Rec. 709 luma coefficients.
+    pub fn grayscale(&self) -> Pixbuf {
+        let mut image = Image::from_pixbuf(self);
+        image.map_colors(|rgb| {
+            let luma = luma(rgb);
+            [luma, luma, luma]
+        });
+        image.into_pixbuf()
+    }
+
+    /// Returns a copy tinted towards `color`, where `amount` goes from `0.0`
+    /// (unchanged) to `1.0` (monochrome in shades of `color`).
+    pub fn tint(&self, color: Rgb, amount: f64) -> Pixbuf {
+        let amount = amount.clamp(0.0, 1.0) as f32;
+        let tint = [
+            color.red as f32 / 255.0,
+            color.green as f32 / 255.0,
+            color.blue as f32 / 255.0,
+        ];
+        let mut image = Image::from_pixbuf(self);
+        image.map_colors(|rgb| {
+            let luma = luma(rgb);
+            let mix = |i: usize| rgb[i] + (luma * tint[i] - rgb[i]) * amount;
+            [mix(0), mix(1), mix(2)]
+        });
+        image.into_pixbuf()
+    }
+
+    /// Returns a copy with the corners cut off to a radius of `radius`
+    /// pixels, with anti-aliased edges.
+    ///
+    /// The result always has an alpha channel.
+    pub fn round_corners(&self, radius: f64) -> Pixbuf {
+        let mut image = Image::from_pixbuf(self);
+        image.has_alpha = true;
+        let (width, height) = (image.width as f64, image.height as f64);
+        let radius = radius.max(0.0).min(width / 2.0).min(height / 2.0);
+        if radius > 0.0 {
+            for (i, pixel) in image.pixels.iter_mut().enumerate() {
+                let coverage =
+                    rounded_rect_coverage(i % image.width, i / image.width, width, height, radius);
+                pixel.iter_mut().for_each(|c| *c *= coverage);
+            }
+        }
+        image.into_pixbuf()
+    }
+}
@@ -17,11 +17,17 @@ pub use glib;
 #[allow(unused_imports)]
 mod auto;
 
+mod filters;
+mod palette;
 mod pixbuf;
 mod pixbuf_animation;
 mod pixbuf_animation_iter;
+mod pixels;
 pub mod prelude;
+pub mod thumbnail;
 
 pub use crate::auto::*;
 
+pub use self::palette::{Palette, PaletteOptions, Quantizer, Rgb, Swatch};
 pub use self::pixbuf_animation_iter::PixbufAnimationIter;
+pub use self::pixels::{PixelFormat, PixelRows, PixelsMut, Rgba};
@@ -0,0 +1,558 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::Pixbuf;
+use std::fmt;
+
+/// An opaque sRGB colour.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb::new(0, 0, 0);
+    pub const WHITE: Rgb = Rgb::new(255, 255, 255);
+
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue }
+    }
+
+    /// Relative luminance as defined by WCAG 2, between 0 (black) and
+    /// 1 (white).
+    pub fn relative_luminance(&self) -> f64 {
+        let [r, g, b] = self.to_linear();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// WCAG 2 contrast ratio between two colours, between 1 and 21.
+    pub fn contrast_ratio(&self, other: &Rgb) -> f64 {
+        let a = self.relative_luminance();
+        let b = other.relative_luminance();
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Returns the colour closest to `self` in lightness that reaches a
+    /// contrast ratio of at least `ratio` against `background`.
+    ///
+    /// Hue and chroma are kept as far as the sRGB gamut allows. If the ratio
+    /// cannot be reached at all, black or white is returned, whichever
+    /// contrasts more.
+    pub fn with_contrast(&self, background: &Rgb, ratio: f64) -> Rgb {
+        if self.contrast_ratio(background) >= ratio {
+            return *self;
+        }
+        let lighter = self.light_variant(background, ratio);
+        let darker = self.dark_variant(background, ratio);
+        let lightness = |color: &Rgb| Oklab::from_rgb(*color).l;
+        let own = lightness(self);
+        match (lighter, darker) {
+            (Some(lighter), Some(darker)) => {
+                if lightness(&lighter) - own <= own - lightness(&darker) {
+                    lighter
+                } else {
+                    darker
+                }
+            }
+            (Some(color), None) | (None, Some(color)) => color,
+            (None, None) => {
+                if Rgb::WHITE.contrast_ratio(background) >= Rgb::BLACK.contrast_ratio(background) {
+                    Rgb::WHITE
+                } else {
+                    Rgb::BLACK
+                }
+            }
+        }
+    }
+
+    /// Returns the darkest lighter variant of `self` that reaches a contrast
+    /// ratio of at least `ratio` against `background`, if any.
+    pub fn light_variant(&self, background: &Rgb, ratio: f64) -> Option<Rgb> {
+        self.variant(background, ratio, LIGHTNESS_STEP)
+    }
+
+    /// Returns the lightest darker variant of `self` that reaches a contrast
+    /// ratio of at least `ratio` against `background`, if any.
+    pub fn dark_variant(&self, background: &Rgb, ratio: f64) -> Option<Rgb> {
+        self.variant(background, ratio, -LIGHTNESS_STEP)
+    }
+
+    fn variant(&self, background: &Rgb, ratio: f64, step: f64) -> Option<Rgb> {
+        let mut lab = Oklab::from_rgb(*self);
+        loop {
+            let color = lab.to_rgb();
+            if color.contrast_ratio(background) >= ratio {
+                return Some(color);
+            }
+            if !(0.0..=1.0).contains(&(lab.l + step)) {
+                // Pure black and white are the last resort in each direction.
+                let last = if step > 0.0 { Rgb::WHITE } else { Rgb::BLACK };
+                return Some(last).filter(|last| last.contrast_ratio(background) >= ratio);
+            }
+            lab.l += step;
+        }
+    }
+
+    fn to_linear(self) -> [f64; 3] {
+        fn channel(value: u8) -> f64 {
+            let c = f64::from(value) / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        [channel(self.red), channel(self.green), channel(self.blue)]
+    }
+
+    fn from_linear([r, g, b]: [f64; 3]) -> Self {
+        fn channel(c: f64) -> u8 {
+            let c = c.clamp(0.0, 1.0);
+            let c = if c <= 0.003_130_8 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            };
+            (c * 255.0).round() as u8
+        }
+        Rgb::new(channel(r), channel(g), channel(b))
+    }
+}
+
+impl fmt::Display for Rgb {
+    /// Formats the colour as `#rrggbb`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+const LIGHTNESS_STEP: f64 = 0.005;
+
+// Colours are clustered in the OKLab space, where Euclidean distances
+// roughly match perceived colour differences.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Oklab {
+    l: f64,
+    a: f64,
+    b: f64,
+}
+
+impl Oklab {
+    fn from_rgb(color: Rgb) -> Self {
+        let [r, g, b] = color.to_linear();
+        let l = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+        let m = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+        let s = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+        Oklab {
+            l: 0.210_454_255_3 * l + 0.793_617_785 * m - 0.004_072_046_8 * s,
+            a: 1.977_998_495_1 * l - 2.428_592_205 * m + 0.450_593_709_9 * s,
+            b: 0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766 * s,
+        }
+    }
+
+    fn to_rgb(self) -> Rgb {
+        let l = (self.l + 0.396_337_777_4 * self.a + 0.215_803_757_3 * self.b).powi(3);
+        let m = (self.l - 0.105_561_345_8 * self.a - 0.063_854_172_8 * self.b).powi(3);
+        let s = (self.l - 0.089_484_177_5 * self.a - 1.291_485_548 * self.b).powi(3);
+        Rgb::from_linear([
+            4.076_741_662_1 * l - 3.307_711_591_3 * m + 0.230_969_929_2 * s,
+            -1.268_438_004_6 * l + 2.609_757_401_1 * m - 0.341_319_396_5 * s,
+            -0.004_196_086_3 * l - 0.703_418_614_7 * m + 1.707_614_701 * s,
+        ])
+    }
+
+    fn chroma(&self) -> f64 {
+        self.a.hypot(self.b)
+    }
+
+    fn axis(&self, axis: usize) -> f64 {
+        match axis {
+            0 => self.l,
+            1 => self.a,
+            _ => self.b,
+        }
+    }
+
+    fn distance_squared(&self, other: &Oklab) -> f64 {
+        (self.l - other.l).powi(2) + (self.a - other.a).powi(2) + (self.b - other.b).powi(2)
+    }
+}
+
+/// Algorithm used to cluster the pixels of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantizer {
+    /// Recursively splits the colour space at the median of its widest axis.
+    /// Fast and deterministic, but tends to favour large uniform areas.
+    MedianCut,
+    /// Refines the median cut result with k-means clustering, which gives
+    /// more representative colours for photographs.
+    KMeans,
+}
+
+/// Parameters for [`Pixbuf::palette_with_options()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaletteOptions {
+    colors: usize,
+    quantizer: Quantizer,
+    max_samples: usize,
+    min_alpha: u8,
+    iterations: usize,
+}
+
+impl Default for PaletteOptions {
+    fn default() -> Self {
+        Self {
+            colors: 8,
+            quantizer: Quantizer::KMeans,
+            max_samples: 100_000,
+            min_alpha: 128,
+            iterations: 10,
+        }
+    }
+}
+
+impl PaletteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of colours to extract. Defaults to 8.
+    pub fn colors(mut self, colors: usize) -> Self {
+        assert!(colors > 0, "Palette needs at least one colour");
+        self.colors = colors;
+        self
+    }
+
+    /// Defaults to [`Quantizer::KMeans`].
+    pub fn quantizer(mut self, quantizer: Quantizer) -> Self {
+        self.quantizer = quantizer;
+        self
+    }
+
+    /// Larger images are subsampled evenly down to about this many pixels.
+    /// Defaults to 100 000.
+    pub fn max_samples(mut self, max_samples: usize) -> Self {
+        self.max_samples = max_samples.max(1);
+        self
+    }
+
+    /// Pixels with a lower alpha value are ignored. Defaults to 128.
+    pub fn min_alpha(mut self, min_alpha: u8) -> Self {
+        self.min_alpha = min_alpha;
+        self
+    }
+
+    /// Number of k-means refinement rounds. Defaults to 10.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+}
+
+/// A colour of a [`Palette`] together with its share of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Swatch {
+    pub color: Rgb,
+    /// Number of sampled pixels represented by this colour.
+    pub population: u32,
+    /// `population` relative to all sampled pixels, between 0 and 1.
+    pub weight: f64,
+}
+
+/// Dominant colours of an image, most common first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    swatches: Vec<Swatch>,
+}
+
+impl Palette {
+    pub fn swatches(&self) -> &[Swatch] {
+        &self.swatches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.swatches.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.swatches.len()
+    }
+
+    /// The most common colour.
+    pub fn dominant(&self) -> Option<Rgb> {
+        self.swatches.first().map(|swatch| swatch.color)
+    }
+
+    /// A colourful swatch suitable as accent colour, preferring saturated
+    /// colours that still cover a noticeable part of the image.
+    ///
+    /// Falls back to the dominant colour for greyscale images.
+    pub fn accent(&self) -> Option<Rgb> {
+        self.swatches
+            .iter()
+            .map(|swatch| {
+                let chroma = Oklab::from_rgb(swatch.color).chroma();
+                (chroma * swatch.weight.sqrt(), swatch.color)
+            })
+            .filter(|(score, _)| *score > 0.0)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+            .map(|(_, color)| color)
+            .or_else(|| self.dominant())
+    }
+
+    /// The lightest and darkest swatches, useful as theme background and
+    /// foreground base colours.
+    pub fn lightest_and_darkest(&self) -> Option<(Rgb, Rgb)> {
+        let lightness = |swatch: &&Swatch| Oklab::from_rgb(swatch.color).l;
+        let lightest = self
+            .swatches
+            .iter()
+            .max_by(|a, b| lightness(a).partial_cmp(&lightness(b)).unwrap())?;
+        let darkest = self
+            .swatches
+            .iter()
+            .min_by(|a, b| lightness(a).partial_cmp(&lightness(b)).unwrap())?;
+        Some((lightest.color, darkest.color))
+    }
+}
+
+impl Pixbuf {
+    /// Extracts up to `colors` dominant colours with the default
+    /// [`PaletteOptions`].
+    pub fn palette(&self, colors: usize) -> Palette {
+        self.palette_with_options(&PaletteOptions::new().colors(colors))
+    }
+
+    /// Extracts the dominant colours of the image.
+    ///
+    /// Scale large images down first, e.g. with
+    /// [`Pixbuf::from_file_at_scale()`], as only the pixel sampling depends
+    /// on the image size.
+    pub fn palette_with_options(&self, options: &PaletteOptions) -> Palette {
+        let bytes = match self.read_pixel_bytes() {
+            Some(bytes) => bytes,
+            None => return Palette::default(),
+        };
+        let histogram = Histogram::from_pixels(
+            &bytes,
+            self.width() as usize,
+            self.height() as usize,
+            self.rowstride() as usize,
+            self.n_channels() as usize,
+            self.has_alpha(),
+            options,
+        );
+        histogram.palette(options)
+    }
+}
+
+// Pixels are counted in a 5 bits per channel histogram, which is plenty for
+// clustering and bounds the work independently of the image size.
+#[derive(Debug, Clone, Copy)]
+struct Bin {
+    lab: Oklab,
+    count: u32,
+}
+
+struct Histogram {
+    bins: Vec<Bin>,
+    total: u32,
+}
+
+impl Histogram {
+    fn from_pixels(
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+        rowstride: usize,
+        n_channels: usize,
+        has_alpha: bool,
+        options: &PaletteOptions,
+    ) -> Self {
+        let mut counts = vec![0u32; 1 << 15];
+        let mut sums = vec![[0u64; 3]; 1 << 15];
+
+        let step = ((width * height) as f64 / options.max_samples as f64)
+            .sqrt()
+            .max(1.0) as usize;
+        for y in (0..height).step_by(step) {
+            let row = &pixels[y * rowstride..];
+            for x in (0..width).step_by(step) {
+                let pixel = &row[x * n_channels..x * n_channels + n_channels];
+                if has_alpha && pixel[3] < options.min_alpha {
+                    continue;
+                }
+                let index = (usize::from(pixel[0]) >> 3) << 10
+                    | (usize::from(pixel[1]) >> 3) << 5
+                    | usize::from(pixel[2]) >> 3;
+                counts[index] += 1;
+                for (sum, value) in sums[index].iter_mut().zip(pixel) {
+                    *sum += u64::from(*value);
+                }
+            }
+        }
+
+        let mut total = 0;
+        let bins = counts
+            .iter()
+            .zip(&sums)
+            .filter(|(count, _)| **count > 0)
+            .map(|(&count, sum)| {
+                total += count;
+                let mean = |i: usize| (sum[i] / u64::from(count)) as u8;
+                Bin {
+                    lab: Oklab::from_rgb(Rgb::new(mean(0), mean(1), mean(2))),
+                    count,
+                }
+            })
+            .collect();
+
+        Histogram { bins, total }
+    }
+
+    fn palette(&self, options: &PaletteOptions) -> Palette {
+        if self.bins.is_empty() {
+            return Palette::default();
+        }
+
+        let mut clusters = median_cut(&self.bins, options.colors);
+        if options.quantizer == Quantizer::KMeans {
+            clusters = k_means(&self.bins, clusters, options.iterations);
+        }
+
+        let mut swatches = clusters
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(lab, population)| Swatch {
+                color: lab.to_rgb(),
+                population,
+                weight: f64::from(population) / f64::from(self.total),
+            })
+            .collect::<Vec<_>>();
+        swatches.sort_by_key(|swatch| std::cmp::Reverse(swatch.population));
+
+        Palette { swatches }
+    }
+}
+
+fn mean(bins: &[Bin]) -> (Oklab, u32) {
+    let mut sum = Oklab::default();
+    let mut count = 0u32;
+    for bin in bins {
+        let weight = f64::from(bin.count);
+        sum.l += bin.lab.l * weight;
+        sum.a += bin.lab.a * weight;
+        sum.b += bin.lab.b * weight;
+        count += bin.count;
+    }
+    let count_f = f64::from(count.max(1));
+    (
+        Oklab {
+            l: sum.l / count_f,
+            a: sum.a / count_f,
+            b: sum.b / count_f,
+        },
+        count,
+    )
+}
+
+// Returns the widest axis of the box and its extent.
+fn widest_axis(bins: &[Bin]) -> (usize, f64) {
+    (0..3)
+        .map(|axis| {
+            let (min, max) = bins.iter().fold((f64::MAX, f64::MIN), |(min, max), bin| {
+                let value = bin.lab.axis(axis);
+                (min.min(value), max.max(value))
+            });
+            (axis, max - min)
+        })
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .unwrap()
+}
+
+fn median_cut(bins: &[Bin], colors: usize) -> Vec<(Oklab, u32)> {
+    let mut boxes = vec![bins.to_vec()];
+
+    while boxes.len() < colors {
+        // Split the box with the largest population-weighted extent.
+        let candidate = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, bins)| bins.len() > 1)
+            .map(|(i, bins)| {
+                let population = bins.iter().map(|bin| f64::from(bin.count)).sum::<f64>();
+                (i, widest_axis(bins).1 * population)
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        let index = match candidate {
+            Some((index, extent)) if extent > 0.0 => index,
+            _ => break,
+        };
+
+        let mut bins = boxes.swap_remove(index);
+        let (axis, _) = widest_axis(&bins);
+        bins.sort_by(|a, b| a.lab.axis(axis).partial_cmp(&b.lab.axis(axis)).unwrap());
+
+        let half = bins.iter().map(|bin| u64::from(bin.count)).sum::<u64>() / 2;
+        let mut seen = 0;
+        let mut split = bins.len() - 1;
+        for (i, bin) in bins.iter().enumerate() {
+            seen += u64::from(bin.count);
+            if seen >= half {
+                split = i + 1;
+                break;
+            }
+        }
+        let split = split.max(1).min(bins.len() - 1);
+
+        let upper = bins.split_off(split);
+        boxes.push(bins);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|bins| mean(bins)).collect()
+}
+
+fn k_means(bins: &[Bin], mut clusters: Vec<(Oklab, u32)>, iterations: usize) -> Vec<(Oklab, u32)> {
+    let mut assignment = vec![0usize; bins.len()];
+
+    for _ in 0..iterations {
+        let mut changed = false;
+        for (bin, assigned) in bins.iter().zip(assignment.iter_mut()) {
+            let nearest = clusters
+                .iter()
+                .enumerate()
+                .min_by(|a, b| {
+                    let da = bin.lab.distance_squared(&(a.1).0);
+                    let db = bin.lab.distance_squared(&(b.1).0);
+                    da.partial_cmp(&db).unwrap()
+                })
+                .unwrap()
+                .0;
+            if *assigned != nearest {
+                *assigned = nearest;
+                changed = true;
+            }
+        }
+
+        let mut members = vec![Vec::new(); clusters.len()];
+        for (bin, assigned) in bins.iter().zip(&assignment) {
+            members[*assigned].push(*bin);
+        }
+        for (cluster, members) in clusters.iter_mut().zip(&members) {
+            if !members.is_empty() {
+                *cluster = mean(members);
+            } else {
+                cluster.1 = 0;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    clusters
+}
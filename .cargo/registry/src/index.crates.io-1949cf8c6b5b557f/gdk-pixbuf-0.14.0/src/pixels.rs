@@ -0,0 +1,295 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::translate::*;
+
+use std::marker::PhantomData;
+use std::mem;
+use std::slice;
+
+use crate::{Pixbuf, Rgb};
+
+/// An sRGB colour with a straight (non-premultiplied) alpha channel.
+///
+/// This is the pixel type of [`PixelsMut`] views of pixbufs with an alpha
+/// channel.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgba {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl Rgba {
+    pub const TRANSPARENT: Rgba = Rgba::new(0, 0, 0, 0);
+
+    pub const fn new(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    pub fn rgb(&self) -> Rgb {
+        Rgb::new(self.red, self.green, self.blue)
+    }
+
+    /// Multiplies the colour channels by the alpha channel.
+    pub fn premultiply(&self) -> Rgba {
+        let mul = |c: u8| ((c as u32 * self.alpha as u32 + 127) / 255) as u8;
+        Rgba::new(mul(self.red), mul(self.green), mul(self.blue), self.alpha)
+    }
+
+    /// Divides the colour channels by the alpha channel, the inverse of
+    /// [`premultiply()`](Self::premultiply).
+    ///
+    /// Fully transparent pixels become transparent black.
+    pub fn unpremultiply(&self) -> Rgba {
+        if self.alpha == 0 {
+            return Rgba::TRANSPARENT;
+        }
+        let alpha = self.alpha as u32;
+        let div = |c: u8| ((c as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        Rgba::new(div(self.red), div(self.green), div(self.blue), self.alpha)
+    }
+}
+
+impl From<Rgb> for Rgba {
+    fn from(rgb: Rgb) -> Self {
+        Rgba::new(rgb.red, rgb.green, rgb.blue, 255)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for crate::Rgb {}
+    impl Sealed for super::Rgba {}
+}
+
+/// A pixel layout of 8 bits per sample pixbufs, either [`Rgb`] or [`Rgba`].
+pub trait PixelFormat: sealed::Sealed + Copy + 'static {
+    const N_CHANNELS: usize;
+    const HAS_ALPHA: bool;
+}
+
+impl PixelFormat for Rgb {
+    const N_CHANNELS: usize = 3;
+    const HAS_ALPHA: bool = false;
+}
+
+impl PixelFormat for Rgba {
+    const N_CHANNELS: usize = 4;
+    const HAS_ALPHA: bool = true;
+}
+
+// Both pixel types are `repr(C)` structs of `u8`s, so they have an alignment
+// of 1, no padding and every bit pattern is valid.
+fn cast_row<P: PixelFormat>(row: &[u8]) -> &[P] {
+    debug_assert_eq!(mem::size_of::<P>(), P::N_CHANNELS);
+    debug_assert_eq!(row.len() % P::N_CHANNELS, 0);
+    unsafe { slice::from_raw_parts(row.as_ptr() as *const P, row.len() / P::N_CHANNELS) }
+}
+
+fn cast_row_mut<P: PixelFormat>(row: &mut [u8]) -> &mut [P] {
+    debug_assert_eq!(mem::size_of::<P>(), P::N_CHANNELS);
+    debug_assert_eq!(row.len() % P::N_CHANNELS, 0);
+    unsafe { slice::from_raw_parts_mut(row.as_mut_ptr() as *mut P, row.len() / P::N_CHANNELS) }
+}
+
+fn matches_format<P: PixelFormat>(pixbuf: &Pixbuf) -> bool {
+    pixbuf.bits_per_sample() == 8
+        && pixbuf.n_channels() as usize == P::N_CHANNELS
+        && pixbuf.has_alpha() == P::HAS_ALPHA
+}
+
+/// A read-only view of the pixels of a [`Pixbuf`], see [`Pixbuf::rows()`].
+#[derive(Debug, Clone, Copy)]
+pub struct PixelRows<'a, P: PixelFormat> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    rowstride: usize,
+    format: PhantomData<P>,
+}
+
+impl<'a, P: PixelFormat> PixelRows<'a, P> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels of row `y`, without the rowstride padding.
+    pub fn row(&self, y: usize) -> &'a [P] {
+        assert!(y < self.height, "y must be less than the height");
+        let start = y * self.rowstride;
+        cast_row(&self.data[start..start + self.width * P::N_CHANNELS])
+    }
+
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &'a [P]> + '_ {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> P {
+        self.row(y)[x]
+    }
+}
+
+/// A mutable view of the pixels of a [`Pixbuf`], see [`Pixbuf::pixels_mut()`].
+///
+/// Rows are exposed without the rowstride padding, so the last row being
+/// shorter than the rowstride is handled transparently.
+#[derive(Debug)]
+pub struct PixelsMut<'a, P: PixelFormat> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+    rowstride: usize,
+    format: PhantomData<P>,
+}
+
+impl<'a, P: PixelFormat> PixelsMut<'a, P> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn row_range(&self, y: usize) -> std::ops::Range<usize> {
+        assert!(y < self.height, "y must be less than the height");
+        let start = y * self.rowstride;
+        start..start + self.width * P::N_CHANNELS
+    }
+
+    pub fn row(&self, y: usize) -> &[P] {
+        let range = self.row_range(y);
+        cast_row(&self.data[range])
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut [P] {
+        let range = self.row_range(y);
+        cast_row_mut(&mut self.data[range])
+    }
+
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[P]> + '_ {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [P]> + '_ {
+        let row_len = self.width * P::N_CHANNELS;
+        self.data
+            .chunks_mut(self.rowstride)
+            .take(self.height)
+            .map(move |row| cast_row_mut(&mut row[..row_len]))
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> P {
+        self.row(y)[x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, pixel: P) {
+        self.row_mut(y)[x] = pixel;
+    }
+
+    /// Calls `f` on every pixel.
+    pub fn for_each_mut<F: FnMut(&mut P)>(&mut self, mut f: F) {
+        for row in self.rows_mut() {
+            row.iter_mut().for_each(&mut f);
+        }
+    }
+}
+
+impl<'a> PixelsMut<'a, Rgba> {
+    /// Converts all pixels to premultiplied alpha.
+    ///
+    /// GdkPixbuf itself always uses straight alpha, so call
+    /// [`unpremultiply()`](Self::unpremultiply) before handing the pixbuf
+    /// back to other code.
+    pub fn premultiply(&mut self) {
+        self.for_each_mut(|pixel| *pixel = pixel.premultiply());
+    }
+
+    pub fn unpremultiply(&mut self) {
+        self.for_each_mut(|pixel| *pixel = pixel.unpremultiply());
+    }
+}
+
+impl Pixbuf {
+    // The number of bytes the pixel data of a pixbuf in format `P` covers.
+    fn pixel_data_len<P: PixelFormat>(&self) -> usize {
+        let width = self.width() as usize;
+        let height = self.height() as usize;
+        if width == 0 || height == 0 {
+            return 0;
+        }
+        (height - 1) * self.rowstride() as usize + width * P::N_CHANNELS
+    }
+
+    /// Returns a typed, read-only view of the pixels without copying them,
+    /// or `None` if the pixbuf is not in format `P` with 8 bits per sample.
+    ///
+    /// The pixels must not be modified, e.g. with [`put_pixel()`](Self::put_pixel)
+    /// or [`fill()`](Self::fill), while the view is alive.
+    pub fn rows<P: PixelFormat>(&self) -> Option<PixelRows<'_, P>> {
+        if !matches_format::<P>(self) {
+            return None;
+        }
+        let len = self.pixel_data_len::<P>();
+        let data = if len == 0 {
+            &[][..]
+        } else {
+            unsafe {
+                let data = slice::from_raw_parts(
+                    ffi::gdk_pixbuf_read_pixels(self.to_glib_none().0),
+                    self.byte_length(),
+                );
+                assert!(data.len() >= len);
+                data
+            }
+        };
+        Some(PixelRows {
+            data,
+            width: self.width() as usize,
+            height: self.height() as usize,
+            rowstride: self.rowstride() as usize,
+            format: PhantomData,
+        })
+    }
+
+    /// Returns a typed, mutable view of the pixels without copying them, or
+    /// `None` if the pixbuf is not in format `P` with 8 bits per sample.
+    ///
+    /// # Safety
+    ///
+    /// The pixels must not be read or modified other than through the view
+    /// while it is alive. This includes other references to the pixbuf,
+    /// sub-pixbufs sharing its pixels and pixbufs it shares its pixels with,
+    /// and views returned by [`rows()`](Self::rows).
+    pub unsafe fn pixels_mut<P: PixelFormat>(&mut self) -> Option<PixelsMut<'_, P>> {
+        if !matches_format::<P>(self) {
+            return None;
+        }
+        let len = self.pixel_data_len::<P>();
+        let data = if len == 0 {
+            &mut [][..]
+        } else {
+            let data = self.pixels();
+            assert!(data.len() >= len);
+            data
+        };
+        Some(PixelsMut {
+            data,
+            width: self.width() as usize,
+            height: self.height() as usize,
+            rowstride: self.rowstride() as usize,
+            format: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,310 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Thumbnails following the freedesktop.org [Thumbnail Managing Standard].
+//!
+//! Thumbnails are PNG files named after the MD5 checksum of the URI of the
+//! original file. They record the URI and modification time of the original
+//! in `Thumb::URI` and `Thumb::MTime` text chunks, so that stale thumbnails
+//! can be detected and other applications can share the cache.
+//!
+//! [Thumbnail Managing Standard]: https://specifications.freedesktop.org/thumbnail-spec/latest/
+
+use gio::prelude::*;
+use glib::Error;
+
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use crate::{Colorspace, Pixbuf};
+
+const URI_KEY: &str = "tEXt::Thumb::URI";
+const MTIME_KEY: &str = "tEXt::Thumb::MTime";
+
+/// The sizes defined by the thumbnail specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ThumbnailSize {
+    /// At most 128×128 pixels.
+    Normal,
+    /// At most 256×256 pixels.
+    Large,
+    /// At most 512×512 pixels.
+    XLarge,
+    /// At most 1024×1024 pixels.
+    XXLarge,
+}
+
+impl ThumbnailSize {
+    const ALL: [ThumbnailSize; 4] = [
+        ThumbnailSize::Normal,
+        ThumbnailSize::Large,
+        ThumbnailSize::XLarge,
+        ThumbnailSize::XXLarge,
+    ];
+
+    /// The maximum width and height in pixels.
+    pub fn pixels(self) -> i32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+            ThumbnailSize::XLarge => 512,
+            ThumbnailSize::XXLarge => 1024,
+        }
+    }
+
+    /// The name of the cache directory for this size.
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+            ThumbnailSize::XLarge => "x-large",
+            ThumbnailSize::XXLarge => "xx-large",
+        }
+    }
+}
+
+/// A thumbnail cache directory, usually `$XDG_CACHE_HOME/thumbnails`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThumbnailCache {
+    root: PathBuf,
+    app_name: String,
+}
+
+impl ThumbnailCache {
+    /// Uses the shared thumbnail cache of the user.
+    ///
+    /// `app_name` names the directory for failure markers, which the
+    /// specification recommends to include the application version, e.g.
+    /// `"wallpaper-picker-1.2"`.
+    pub fn new(app_name: &str) -> Self {
+        Self::with_root(glib::user_cache_dir().join("thumbnails"), app_name)
+    }
+
+    /// Uses the thumbnail cache at `root` instead of the shared one.
+    pub fn with_root<P: AsRef<Path>>(root: P, app_name: &str) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            app_name: app_name.to_string(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path where the thumbnail of `uri` is stored, whether it exists or
+    /// not.
+    pub fn thumbnail_path(&self, uri: &str, size: ThumbnailSize) -> PathBuf {
+        self.root.join(size.dir_name()).join(thumbnail_name(uri))
+    }
+
+    /// The path of the marker recording that thumbnailing `uri` failed.
+    pub fn fail_path(&self, uri: &str) -> PathBuf {
+        self.root
+            .join("fail")
+            .join(&self.app_name)
+            .join(thumbnail_name(uri))
+    }
+
+    /// Returns the path of an up-to-date thumbnail of `file` of at least
+    /// `size`, if there is one.
+    pub fn lookup<P: AsRef<Path>>(&self, file: P, size: ThumbnailSize) -> Option<PathBuf> {
+        let source = Source::new(file.as_ref()).ok()?;
+        ThumbnailSize::ALL
+            .iter()
+            .filter(|candidate| **candidate >= size)
+            .map(|candidate| self.thumbnail_path(&source.uri, *candidate))
+            .find(|path| source.is_described_by(path))
+    }
+
+    /// Whether generating a thumbnail of `file` failed before and the file
+    /// was not modified since.
+    pub fn has_failed<P: AsRef<Path>>(&self, file: P) -> bool {
+        match Source::new(file.as_ref()) {
+            Ok(source) => source.is_described_by(&self.fail_path(&source.uri)),
+            Err(_) => false,
+        }
+    }
+
+    /// Generates and stores a thumbnail of `file`, even if an up-to-date one
+    /// exists, and returns its path.
+    ///
+    /// Images smaller than `size` are stored unscaled. If the image cannot be
+    /// loaded, a failure marker is stored instead, see
+    /// [`has_failed()`](Self::has_failed).
+    pub fn generate<P: AsRef<Path>>(&self, file: P, size: ThumbnailSize) -> Result<PathBuf, Error> {
+        let file = file.as_ref();
+        let source = Source::new(file)?;
+
+        let dimensions = Pixbuf::file_info(file).map(|(_, width, height)| (width, height));
+        let loaded = match dimensions {
+            Some((width, height)) if width <= size.pixels() && height <= size.pixels() => {
+                Pixbuf::from_file(file)
+            }
+            _ => Pixbuf::from_file_at_scale(file, size.pixels(), size.pixels(), true),
+        };
+        let pixbuf = match loaded {
+            Ok(pixbuf) => pixbuf,
+            Err(err) => {
+                // The marker is only an optimisation, so failing to write it
+                // is not worth reporting over the original error.
+                let _ = self.write_fail_marker(&source);
+                return Err(err);
+            }
+        };
+
+        let path = self.thumbnail_path(&source.uri, size);
+        let dimensions = dimensions.map(|(width, height)| (width.to_string(), height.to_string()));
+        let mut options = source.text_chunks();
+        if let Some((ref width, ref height)) = dimensions {
+            options.push(("tEXt::Thumb::Image::Width", width.as_str()));
+            options.push(("tEXt::Thumb::Image::Height", height.as_str()));
+        }
+        save_atomically(&pixbuf, &path, &options)?;
+        Ok(path)
+    }
+
+    /// Returns the thumbnail of `file`, generating it if there is no
+    /// up-to-date one.
+    pub fn load<P: AsRef<Path>>(&self, file: P, size: ThumbnailSize) -> Result<Pixbuf, Error> {
+        let path = match self.lookup(&file, size) {
+            Some(path) => path,
+            None => self.generate(&file, size)?,
+        };
+        Pixbuf::from_file(path)
+    }
+
+    /// Generates thumbnails of `files` on a thread pool and resolves to the
+    /// thumbnail paths in the same order.
+    ///
+    /// The threads are taken from GLib's shared threads, so concurrent
+    /// batches do not each start their own.
+    ///
+    /// Up-to-date thumbnails are not regenerated, and files with a failure
+    /// marker fail again without being loaded.
+    #[allow(clippy::type_complexity)]
+    pub fn generate_batch_future(
+        &self,
+        files: Vec<PathBuf>,
+        size: ThumbnailSize,
+    ) -> Pin<Box<dyn Future<Output = Vec<Result<PathBuf, Error>>> + 'static>> {
+        let cache = self.clone();
+        Box::pin(async move {
+            let pool = match glib::ThreadPool::new_shared(Some(glib::num_processors().max(1))) {
+                Ok(pool) => pool,
+                Err(err) => return files.iter().map(|_| Err(err.clone())).collect(),
+            };
+
+            let mut jobs = Vec::with_capacity(files.len());
+            for file in files {
+                let cache = cache.clone();
+                jobs.push(pool.push_future(move || {
+                    if let Some(path) = cache.lookup(&file, size) {
+                        Ok(path)
+                    } else if cache.has_failed(&file) {
+                        Err(Error::new(
+                            gio::IOErrorEnum::Failed,
+                            &format!("Thumbnailing {} failed before", file.display()),
+                        ))
+                    } else {
+                        cache.generate(&file, size)
+                    }
+                }));
+            }
+
+            let mut results = Vec::with_capacity(jobs.len());
+            for job in jobs {
+                results.push(match job {
+                    Ok(job) => job.await,
+                    Err(err) => Err(err),
+                });
+            }
+            results
+        })
+    }
+
+    fn write_fail_marker(&self, source: &Source) -> Result<(), Error> {
+        let pixbuf = Pixbuf::new(Colorspace::Rgb, true, 8, 1, 1)
+            .ok_or_else(|| Error::new(gio::IOErrorEnum::Failed, "Failed to create a pixbuf"))?;
+        pixbuf.fill(0);
+        save_atomically(&pixbuf, &self.fail_path(&source.uri), &source.text_chunks())
+    }
+}
+
+fn thumbnail_name(uri: &str) -> String {
+    let checksum = glib::compute_checksum_for_string(glib::ChecksumType::Md5, uri)
+        .expect("MD5 is always supported");
+    format!("{}.png", checksum)
+}
+
+// The URI and modification time that a thumbnail has to record to be
+// up-to-date.
+struct Source {
+    uri: String,
+    mtime: String,
+}
+
+impl Source {
+    fn new(file: &Path) -> Result<Self, Error> {
+        let file = gio::File::for_path(file);
+        let info = file.query_info(
+            "time::modified",
+            gio::FileQueryInfoFlags::NONE,
+            None::<&gio::Cancellable>,
+        )?;
+        Ok(Source {
+            uri: file.uri().to_string(),
+            mtime: info.attribute_uint64("time::modified").to_string(),
+        })
+    }
+
+    fn text_chunks(&self) -> Vec<(&'static str, &str)> {
+        vec![(URI_KEY, &self.uri), (MTIME_KEY, &self.mtime)]
+    }
+
+    fn is_described_by(&self, thumbnail: &Path) -> bool {
+        match Pixbuf::from_file(thumbnail) {
+            Ok(pixbuf) => {
+                pixbuf.option(URI_KEY).as_deref() == Some(self.uri.as_str())
+                    && pixbuf.option(MTIME_KEY).as_deref() == Some(self.mtime.as_str())
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// Writes to a temporary file first and renames it, so that other processes
+// never see partially written thumbnails.
+fn save_atomically(pixbuf: &Pixbuf, path: &Path, options: &[(&str, &str)]) -> Result<(), Error> {
+    let dir = path.parent().expect("thumbnail paths have a parent");
+    if glib::mkdir_with_parents(dir, 0o700) != 0 {
+        return Err(Error::new(
+            gio::IOErrorEnum::Failed,
+            &format!(
+                "Failed to create {}: {}",
+                dir.display(),
+                io::Error::last_os_error()
+            ),
+        ));
+    }
+
+    let tmp_path = path.with_extension(format!("png.{:08x}", glib::random_int()));
+    pixbuf.savev(&tmp_path, "png", options)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600));
+    }
+
+    let result = gio::File::for_path(&tmp_path).move_(
+        &gio::File::for_path(path),
+        gio::FileCopyFlags::OVERWRITE,
+        None::<&gio::Cancellable>,
+        None,
+    );
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
@@ -0,0 +1,56 @@
+use gdk_pixbuf::*;
+
+fn two_colour_pixbuf() -> Pixbuf {
+    // Top three quarters red, bottom quarter blue.
+    let pixbuf = Pixbuf::new(Colorspace::Rgb, true, 8, 40, 40).unwrap();
+    pixbuf.fill(0xdc_14_1e_ff);
+    pixbuf
+        .new_subpixbuf(0, 30, 40, 10)
+        .unwrap()
+        .fill(0x14_28_c8_ff);
+    pixbuf
+}
+
+#[test]
+fn dominant_colours() {
+    for quantizer in &[Quantizer::MedianCut, Quantizer::KMeans] {
+        let palette = two_colour_pixbuf()
+            .palette_with_options(&PaletteOptions::new().colors(4).quantizer(*quantizer));
+
+        assert_eq!(palette.len(), 2);
+        let swatches = palette.swatches();
+        assert_eq!(swatches[0].color, Rgb::new(0xdc, 0x14, 0x1e));
+        assert_eq!(swatches[0].population, 1200);
+        assert!((swatches[0].weight - 0.75).abs() < 1e-9);
+        assert_eq!(swatches[1].color, Rgb::new(0x14, 0x28, 0xc8));
+        assert_eq!(palette.dominant(), Some(swatches[0].color));
+    }
+}
+
+#[test]
+fn transparent_pixels_are_ignored() {
+    let pixbuf = Pixbuf::new(Colorspace::Rgb, true, 8, 10, 10).unwrap();
+    pixbuf.fill(0xff_ff_ff_00);
+    assert!(pixbuf.palette(4).is_empty());
+
+    pixbuf.put_pixel(3, 3, 0, 128, 0, 255);
+    let palette = pixbuf.palette(4);
+    assert_eq!(palette.dominant(), Some(Rgb::new(0, 128, 0)));
+    assert_eq!(palette.accent(), Some(Rgb::new(0, 128, 0)));
+}
+
+#[test]
+fn contrast_helpers() {
+    assert!((Rgb::BLACK.contrast_ratio(&Rgb::WHITE) - 21.0).abs() < 1e-9);
+    assert_eq!(Rgb::new(0x20, 0x90, 0x90).to_string(), "#209090");
+
+    let background = Rgb::new(0x1e, 0x1e, 0x2e);
+    let accent = Rgb::new(0x20, 0x90, 0x90);
+    let readable = accent.with_contrast(&background, 7.0);
+    assert!(readable.contrast_ratio(&background) >= 7.0);
+    assert!(readable.relative_luminance() > accent.relative_luminance());
+
+    let dark = accent.dark_variant(&Rgb::WHITE, 4.5).unwrap();
+    assert!(dark.contrast_ratio(&Rgb::WHITE) >= 4.5);
+    assert_eq!(accent.light_variant(&Rgb::WHITE, 4.5), None);
+}
@@ -0,0 +1,86 @@
+use gdk_pixbuf::*;
+
+const ORANGE: Rgba = Rgba::new(200, 100, 50, 255);
+
+fn orange_pixbuf(has_alpha: bool) -> Pixbuf {
+    let pixbuf = Pixbuf::new(Colorspace::Rgb, has_alpha, 8, 7, 5).unwrap();
+    pixbuf.fill(0xc8_64_32_ff);
+    pixbuf
+}
+
+#[test]
+fn typed_views() {
+    let mut pixbuf = orange_pixbuf(true);
+    assert!(unsafe { pixbuf.pixels_mut::<Rgb>() }.is_none());
+
+    {
+        let mut pixels = unsafe { pixbuf.pixels_mut::<Rgba>() }.unwrap();
+        assert_eq!(pixels.rows().len(), 5);
+        assert!(pixels.rows().all(|row| row.len() == 7));
+        assert_eq!(pixels.pixel(6, 4), ORANGE);
+        pixels.set_pixel(6, 4, Rgba::TRANSPARENT);
+    }
+
+    let rows = pixbuf.rows::<Rgba>().unwrap();
+    assert_eq!(rows.pixel(6, 4), Rgba::TRANSPARENT);
+    assert_eq!(rows.row(0), &[ORANGE; 7][..]);
+    assert!(pixbuf.rows::<Rgb>().is_none());
+
+    let pixel = Rgba::new(100, 50, 0, 128);
+    assert_eq!(pixel.premultiply(), Rgba::new(50, 25, 0, 128));
+    assert_eq!(pixel.premultiply().unpremultiply(), pixel);
+}
+
+#[test]
+fn blurs() {
+    let mut pixbuf = orange_pixbuf(true);
+    unsafe { pixbuf.pixels_mut::<Rgba>() }
+        .unwrap()
+        .set_pixel(0, 0, Rgba::TRANSPARENT);
+
+    // Blurring happens in premultiplied space, so the transparent pixel only
+    // lowers the alpha of its neighbours but not their colour.
+    let blurred = pixbuf.box_blur(1);
+    let blurred = blurred.rows::<Rgba>().unwrap();
+    assert_eq!(blurred.pixel(1, 1).rgb(), ORANGE.rgb());
+    assert!(blurred.pixel(1, 1).alpha < 255);
+    assert_eq!(blurred.pixel(6, 4), ORANGE);
+
+    let blurred = pixbuf.gaussian_blur(1.0);
+    let blurred = blurred.rows::<Rgba>().unwrap();
+    assert!(blurred.pixel(0, 0).alpha > 0);
+    assert_eq!(blurred.pixel(6, 4), ORANGE);
+}
+
+#[test]
+fn colour_adjustments() {
+    let pixbuf = orange_pixbuf(false);
+
+    let unchanged = pixbuf.brightness_contrast(0.0, 1.0);
+    assert_eq!(unchanged.rows::<Rgb>().unwrap().pixel(3, 3), ORANGE.rgb());
+    let white = pixbuf.brightness_contrast(1.0, 1.0);
+    assert_eq!(white.rows::<Rgb>().unwrap().pixel(3, 3), Rgb::WHITE);
+
+    let gray = pixbuf.grayscale().rows::<Rgb>().unwrap().pixel(3, 3);
+    assert_eq!((gray.red, gray.red), (gray.green, gray.blue));
+
+    let red = pixbuf.tint(Rgb::new(255, 0, 0), 1.0);
+    let red = red.rows::<Rgb>().unwrap().pixel(3, 3);
+    assert_eq!((red.green, red.blue), (0, 0));
+    assert_eq!(red.red, gray.red);
+}
+
+#[test]
+fn rounded_corners() {
+    let pixbuf = Pixbuf::new(Colorspace::Rgb, false, 8, 20, 10).unwrap();
+    pixbuf.fill(0xff_ff_ff_ff);
+
+    let rounded = pixbuf.round_corners(4.0);
+    assert!(rounded.has_alpha());
+    let rows = rounded.rows::<Rgba>().unwrap();
+    for &(x, y) in &[(0, 0), (19, 0), (0, 9), (19, 9)] {
+        assert_eq!(rows.pixel(x, y).alpha, 0);
+    }
+    assert_eq!(rows.pixel(10, 0).alpha, 255);
+    assert_eq!(rows.pixel(0, 5).alpha, 255);
+}
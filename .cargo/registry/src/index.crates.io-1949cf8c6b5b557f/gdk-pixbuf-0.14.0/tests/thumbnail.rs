@@ -0,0 +1,115 @@
+use gdk_pixbuf::thumbnail::{ThumbnailCache, ThumbnailSize};
+use gdk_pixbuf::*;
+use gio::prelude::*;
+
+use std::path::{Path, PathBuf};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "gdk-pixbuf-thumbnail-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_image(path: &Path, width: i32, height: i32) {
+    let pixbuf = Pixbuf::new(Colorspace::Rgb, false, 8, width, height).unwrap();
+    pixbuf.fill(0x33_66_99_ff);
+    pixbuf.savev(path, "png", &[]).unwrap();
+}
+
+#[test]
+fn generate_and_lookup() {
+    let dir = temp_dir("lookup");
+    let cache = ThumbnailCache::with_root(dir.join("thumbnails"), "test-1.0");
+    let image = dir.join("wallpaper.png");
+    write_image(&image, 400, 200);
+
+    assert_eq!(cache.lookup(&image, ThumbnailSize::Normal), None);
+    let path = cache.generate(&image, ThumbnailSize::Normal).unwrap();
+
+    let uri = gio::File::for_path(&image).uri();
+    assert_eq!(path, cache.thumbnail_path(&uri, ThumbnailSize::Normal));
+    assert!(path.starts_with(dir.join("thumbnails").join("normal")));
+    assert_eq!(
+        path.file_name().unwrap().to_str().unwrap(),
+        format!(
+            "{}.png",
+            glib::compute_checksum_for_string(glib::ChecksumType::Md5, &uri).unwrap()
+        )
+    );
+
+    let thumbnail = Pixbuf::from_file(&path).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (128, 64));
+    assert_eq!(thumbnail.option("tEXt::Thumb::URI").unwrap(), uri);
+    assert_eq!(
+        thumbnail
+            .option("tEXt::Thumb::Image::Width")
+            .unwrap()
+            .as_str(),
+        "400"
+    );
+    assert!(thumbnail.option("tEXt::Thumb::MTime").is_some());
+
+    assert_eq!(cache.lookup(&image, ThumbnailSize::Normal), Some(path));
+    assert_eq!(cache.lookup(&image, ThumbnailSize::Large), None);
+
+    // Thumbnails of modified files are stale.
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    write_image(&image, 400, 200);
+    assert_eq!(cache.lookup(&image, ThumbnailSize::Normal), None);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn failure_markers() {
+    let dir = temp_dir("fail");
+    let cache = ThumbnailCache::with_root(dir.join("thumbnails"), "test-1.0");
+    let broken = dir.join("broken.png");
+    std::fs::write(&broken, b"not an image").unwrap();
+
+    assert!(!cache.has_failed(&broken));
+    assert!(cache.generate(&broken, ThumbnailSize::Large).is_err());
+    assert!(cache.has_failed(&broken));
+
+    let uri = gio::File::for_path(&broken).uri();
+    assert!(cache
+        .fail_path(&uri)
+        .starts_with(dir.join("thumbnails").join("fail").join("test-1.0")));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn batch_generation() {
+    let dir = temp_dir("batch");
+    let cache = ThumbnailCache::with_root(dir.join("thumbnails"), "test-1.0");
+    let small = dir.join("small.png");
+    let large = dir.join("large.png");
+    write_image(&small, 16, 16);
+    write_image(&large, 1000, 1000);
+    let missing = dir.join("missing.png");
+
+    let results = glib::MainContext::new().block_on(cache.generate_batch_future(
+        vec![small.clone(), large.clone(), missing],
+        ThumbnailSize::Large,
+    ));
+
+    assert_eq!(results.len(), 3);
+    let small_thumbnail = Pixbuf::from_file(results[0].as_ref().unwrap()).unwrap();
+    assert_eq!(small_thumbnail.width(), 16);
+    let large_thumbnail = Pixbuf::from_file(results[1].as_ref().unwrap()).unwrap();
+    assert_eq!(large_thumbnail.width(), 256);
+    assert!(results[2].is_err());
+
+    assert_eq!(
+        cache.lookup(&small, ThumbnailSize::Large).as_ref(),
+        results[0].as_ref().ok()
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,1530 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Typed D-Bus proxies and server skeletons generated from introspection XML.
+//!
+//! [`Generator`] is meant to be used from a build script. It parses the XML
+//! with [`DBusNodeInfo`] and emits Rust source containing, for every
+//! interface:
+//!
+//! * a `<Name>Proxy` client wrapping a [`DBusProxy`], with an `async` method
+//!   per D-Bus method, a getter and a [`PropertyStream`] per property, an
+//!   `async` setter per writable property and a [`SignalStream`] per signal;
+//! * a `<Name>Server` trait to implement on the service side, and a
+//!   `<Name>Skeleton` that registers an implementation with
+//!   [`register_object()`] and emits its signals.
+//!
+//! ```no_run
+//! // build.rs
+//! gio::dbus_codegen::Generator::from_file("dbus/org.freedesktop.UPower.Device.xml")
+//!     .unwrap()
+//!     .strip_prefix("org.freedesktop.")
+//!     .generate_to_out_dir("upower.rs");
+//! ```
+//!
+//! ```ignore
+//! // src/upower.rs
+//! include!(concat!(env!("OUT_DIR"), "/upower.rs"));
+//!
+//! let device = UPowerDeviceProxy::for_bus(
+//!     gio::BusType::System,
+//!     "org.freedesktop.UPower",
+//!     "/org/freedesktop/UPower/devices/DisplayDevice",
+//! )
+//! .await?;
+//! println!("{:?}", device.percentage());
+//! ```
+//!
+//! D-Bus types are mapped to the Rust types that implement [`FromVariant`] and
+//! [`ToVariant`] for them. Object paths and signatures are passed as `&str`
+//! and returned as `String`. Containers that can't be represented that way,
+//! e.g. arrays of object paths, are passed around as plain [`Variant`]s.
+//!
+//! Methods and properties whose names clash with the fixed members of the
+//! proxy (`new`, `for_bus`, `from_proxy`, `proxy` and `interface_info`) get
+//! an `_` appended, like names that are Rust keywords. Any other clash
+//! between generated names makes [`Generator::generate()`] fail.
+//!
+//! [`FromVariant`]: glib::FromVariant
+//! [`ToVariant`]: glib::ToVariant
+
+use crate::prelude::*;
+use crate::{
+    DBusArgInfo, DBusCallFlags, DBusConnection, DBusInterfaceInfo, DBusMethodInvocation,
+    DBusNodeInfo, DBusProxy, IOErrorEnum, RegistrationId,
+};
+use futures_channel::mpsc;
+use futures_core::stream::Stream;
+use futures_core::task::{Context, Poll};
+use glib::translate::*;
+use glib::{SignalHandlerId, ToVariant, Variant};
+use std::boxed::Box as Box_;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+/// Generates Rust bindings for the interfaces of a D-Bus introspection document.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    xml: String,
+    prefixes: Vec<String>,
+    interfaces: Vec<String>,
+    client: bool,
+    server: bool,
+}
+
+impl Generator {
+    pub fn new(xml: &str) -> Self {
+        Self {
+            xml: xml.to_owned(),
+            prefixes: Vec::new(),
+            interfaces: Vec::new(),
+            client: true,
+            server: true,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::new(&fs::read_to_string(path)?))
+    }
+
+    /// Strips `prefix` from interface names before deriving type names.
+    ///
+    /// By default only the last component of the interface name is used, so
+    /// `org.freedesktop.UPower.Device` becomes `Device`. After stripping
+    /// `org.freedesktop.` the remaining components are joined to `UPowerDevice`.
+    pub fn strip_prefix(mut self, prefix: &str) -> Self {
+        self.prefixes.push(prefix.to_owned());
+        self
+    }
+
+    /// Only generates code for the named interface. May be called several times.
+    ///
+    /// All interfaces of the document are generated if this is never called.
+    pub fn interface(mut self, name: &str) -> Self {
+        self.interfaces.push(name.to_owned());
+        self
+    }
+
+    /// Whether to generate client proxies. Enabled by default.
+    pub fn client(mut self, client: bool) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Whether to generate server traits and skeletons. Enabled by default.
+    pub fn server(mut self, server: bool) -> Self {
+        self.server = server;
+        self
+    }
+
+    /// Returns the generated Rust source.
+    pub fn generate(&self) -> Result<String, glib::Error> {
+        let node = DBusNodeInfo::for_xml(&self.xml)?;
+        let mut interfaces = node.interfaces();
+        if !self.interfaces.is_empty() {
+            for name in &self.interfaces {
+                if !interfaces.iter().any(|iface| iface.name() == name) {
+                    return Err(glib::Error::new(
+                        IOErrorEnum::NotFound,
+                        &format!("Interface {} not found in introspection data", name),
+                    ));
+                }
+            }
+            interfaces.retain(|iface| self.interfaces.iter().any(|name| iface.name() == name));
+        }
+
+        let mut out = String::new();
+        out.push_str("// This file was generated by gio::dbus_codegen\n// DO NOT EDIT\n");
+        for iface in interfaces {
+            let iface = Interface::new(&iface, &self.type_name(iface.name()))?;
+            if self.client {
+                iface.write_proxy(&mut out);
+            }
+            if self.server {
+                iface.write_server(&mut out);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Call from build script to write the generated bindings to `target`,
+    /// relative to `OUT_DIR`, for use with `include!`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the introspection data is invalid or the file can't be written.
+    pub fn generate_to_out_dir(&self, target: &str) {
+        let out_dir = env::var("OUT_DIR").unwrap();
+        let code = self
+            .generate()
+            .unwrap_or_else(|err| panic!("Failed to generate D-Bus bindings: {}", err));
+        fs::write(Path::new(&out_dir).join(target), code).unwrap();
+    }
+
+    fn type_name(&self, interface_name: &str) -> String {
+        let stripped = self
+            .prefixes
+            .iter()
+            .find_map(|prefix| interface_name.strip_prefix(prefix.as_str()));
+        match stripped {
+            Some(rest) => rest.split('.').map(camel_case).collect(),
+            None => camel_case(interface_name.rsplit('.').next().unwrap_or(interface_name)),
+        }
+    }
+}
+
+/// Creates an object path variant (`o`), failing if `path` is not a valid object path.
+pub fn object_path_variant(path: &str) -> Result<Variant, glib::Error> {
+    unsafe {
+        if from_glib(glib::ffi::g_variant_is_object_path(path.to_glib_none().0)) {
+            Ok(from_glib_none(glib::ffi::g_variant_new_object_path(
+                path.to_glib_none().0,
+            )))
+        } else {
+            Err(glib::Error::new(
+                IOErrorEnum::InvalidArgument,
+                "Invalid D-Bus object path",
+            ))
+        }
+    }
+}
+
+/// Creates a type signature variant (`g`), failing if `signature` is not a valid signature.
+pub fn signature_variant(signature: &str) -> Result<Variant, glib::Error> {
+    unsafe {
+        if from_glib(glib::ffi::g_variant_is_signature(
+            signature.to_glib_none().0,
+        )) {
+            Ok(from_glib_none(glib::ffi::g_variant_new_signature(
+                signature.to_glib_none().0,
+            )))
+        } else {
+            Err(glib::Error::new(
+                IOErrorEnum::InvalidArgument,
+                "Invalid D-Bus type signature",
+            ))
+        }
+    }
+}
+
+/// Turns the result of converting a reply value into an error if the
+/// conversion failed. Used by generated code.
+pub fn reply_value<T>(value: Option<T>, member: &str) -> Result<T, glib::Error> {
+    value.ok_or_else(|| {
+        glib::Error::new(
+            IOErrorEnum::InvalidData,
+            &format!("Unexpected reply type for {}", member),
+        )
+    })
+}
+
+/// Creates an error that is returned to D-Bus callers as the error `name`,
+/// e.g. `org.freedesktop.DBus.Error.InvalidArgs`.
+pub fn dbus_error(name: &str, message: &str) -> glib::Error {
+    unsafe {
+        from_glib_full(ffi::g_dbus_error_new_for_dbus_error(
+            name.to_glib_none().0,
+            message.to_glib_none().0,
+        ))
+    }
+}
+
+/// Exports an object at `object_path` on `connection`. Used by generated code.
+///
+/// Unlike [`DBusConnection::register_object()`], the property accessors can
+/// fail; their errors are returned to the caller of `Get` or `Set`.
+pub fn register_object<MethodCall, GetProperty, SetProperty>(
+    connection: &DBusConnection,
+    object_path: &str,
+    interface_info: &DBusInterfaceInfo,
+    method_call: MethodCall,
+    get_property: GetProperty,
+    set_property: SetProperty,
+) -> Result<RegistrationId, glib::Error>
+where
+    MethodCall: Fn(&str, Variant, DBusMethodInvocation) + Send + Sync + 'static,
+    GetProperty: Fn(&str) -> Result<Variant, glib::Error> + Send + Sync + 'static,
+    SetProperty: Fn(&str, Variant) -> Result<(), glib::Error> + Send + Sync + 'static,
+{
+    connection.register_object_fallible(
+        object_path,
+        interface_info,
+        move |_sender, method_name, parameters, invocation| {
+            method_call(method_name, parameters, invocation)
+        },
+        get_property,
+        set_property,
+    )
+}
+
+/// Sets a property of the remote object behind `proxy` through
+/// `org.freedesktop.DBus.Properties.Set`.
+pub fn set_property(
+    proxy: &DBusProxy,
+    property_name: &str,
+    value: Variant,
+) -> Pin<Box_<dyn Future<Output = Result<(), glib::Error>> + 'static>> {
+    let connection = proxy.connection();
+    let name = proxy.name_owner().or_else(|| proxy.g_name());
+    let object_path = proxy.object_path();
+    let parameters = (proxy.interface_name().as_str(), property_name, value).to_variant();
+    let timeout = proxy.default_timeout();
+    Box_::pin(async move {
+        connection
+            .call_future(
+                name.as_deref(),
+                &object_path,
+                "org.freedesktop.DBus.Properties",
+                "Set",
+                Some(&parameters),
+                None,
+                DBusCallFlags::NONE,
+                timeout,
+            )
+            .await?;
+        Ok(())
+    })
+}
+
+/// Emits `org.freedesktop.DBus.Properties.PropertiesChanged` for the given
+/// properties of `interface_name` at `object_path`.
+pub fn emit_properties_changed(
+    connection: &DBusConnection,
+    object_path: &str,
+    interface_name: &str,
+    changed: &[(&str, Variant)],
+) -> Result<(), glib::Error> {
+    let changed: HashMap<String, Variant> = changed
+        .iter()
+        .map(|(name, value)| (String::from(*name), value.clone()))
+        .collect();
+    let parameters = (interface_name, changed, Vec::<String>::new()).to_variant();
+    connection.emit_signal(
+        None,
+        object_path,
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        Some(&parameters),
+    )
+}
+
+/// Stream of the emissions of one signal of a [`DBusProxy`].
+///
+/// Emissions whose arguments can't be converted to `T` are skipped. The
+/// signal handler is disconnected when the stream is dropped.
+#[must_use = "streams do nothing unless polled"]
+pub struct SignalStream<T> {
+    proxy: DBusProxy,
+    handler: Option<SignalHandlerId>,
+    receiver: mpsc::UnboundedReceiver<Variant>,
+    convert: fn(&Variant) -> Option<T>,
+}
+
+impl<T> SignalStream<T> {
+    pub fn new(proxy: &DBusProxy, signal_name: &str, convert: fn(&Variant) -> Option<T>) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let signal_name = signal_name.to_owned();
+        let handler = proxy.connect_g_signal(move |_, _, name, parameters| {
+            if name == signal_name {
+                let _ = sender.unbounded_send(parameters.clone());
+            }
+        });
+        Self {
+            proxy: proxy.clone(),
+            handler: Some(handler),
+            receiver,
+            convert,
+        }
+    }
+}
+
+impl<T> Stream for SignalStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.receiver).poll_next(cx) {
+                Poll::Ready(Some(parameters)) => {
+                    if let Some(item) = (this.convert)(&parameters) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Drop for SignalStream<T> {
+    fn drop(&mut self) {
+        if let Some(handler) = self.handler.take() {
+            self.proxy.disconnect(handler);
+        }
+    }
+}
+
+/// Stream of the values of one cached property of a [`DBusProxy`].
+///
+/// Yields the new value every time the property changes, or `None` if it was
+/// invalidated or has a different type than expected. The current value is
+/// not yielded, read it from the proxy first. The signal handler is
+/// disconnected when the stream is dropped.
+#[must_use = "streams do nothing unless polled"]
+pub struct PropertyStream<T> {
+    proxy: DBusProxy,
+    handler: Option<SignalHandlerId>,
+    receiver: mpsc::UnboundedReceiver<Option<Variant>>,
+    convert: fn(&Variant) -> Option<T>,
+}
+
+impl<T> PropertyStream<T> {
+    pub fn new(proxy: &DBusProxy, property_name: &str, convert: fn(&Variant) -> Option<T>) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let property_name = property_name.to_owned();
+        let last = Mutex::new(proxy.cached_property(&property_name));
+        let handler = proxy
+            .connect("g-properties-changed", false, move |values| {
+                let proxy = values[0]
+                    .get::<DBusProxy>()
+                    .expect("g-properties-changed emitted by a non-DBusProxy");
+                let current = proxy.cached_property(&property_name);
+                let mut last = last.lock().unwrap();
+                if *last != current {
+                    *last = current.clone();
+                    let _ = sender.unbounded_send(current);
+                }
+                None
+            })
+            .expect("DBusProxy has no g-properties-changed signal");
+        Self {
+            proxy: proxy.clone(),
+            handler: Some(handler),
+            receiver,
+            convert,
+        }
+    }
+}
+
+impl<T> Stream for PropertyStream<T> {
+    type Item = Option<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Option<T>>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.receiver).poll_next(cx) {
+            Poll::Ready(Some(value)) => {
+                Poll::Ready(Some(value.and_then(|value| (this.convert)(&value))))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for PropertyStream<T> {
+    fn drop(&mut self) {
+        if let Some(handler) = self.handler.take() {
+            self.proxy.disconnect(handler);
+        }
+    }
+}
+
+// Members every generated proxy has.
+const PROXY_MEMBERS: &[&str] = &["new", "for_bus", "from_proxy", "proxy", "interface_info"];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn camel_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            continue;
+        }
+        if c.is_ascii_uppercase() && i > 0 && !out.ends_with('_') {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).map_or(false, |n| n.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_lower)
+            {
+                out.push('_');
+            }
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    let out = out.trim_end_matches('_').to_owned();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", out)
+    } else if RUST_KEYWORDS.contains(&out.as_str()) {
+        format!("{}_", out)
+    } else {
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Sig {
+    Basic(char),
+    Variant,
+    Array(Box_<Sig>),
+    Dict(Box_<Sig>, Box_<Sig>),
+    Struct(Vec<Sig>),
+}
+
+impl Sig {
+    // Parses one complete type from the front of `s`. The signature has
+    // already been validated by GDBus.
+    fn parse(s: &str) -> (Sig, &str) {
+        let mut chars = s.chars();
+        let c = chars.next().expect("empty D-Bus signature");
+        let rest = chars.as_str();
+        match c {
+            'v' => (Sig::Variant, rest),
+            'a' if rest.starts_with('{') => {
+                let (key, rest) = Sig::parse(&rest[1..]);
+                let (value, rest) = Sig::parse(rest);
+                (Sig::Dict(Box_::new(key), Box_::new(value)), &rest[1..])
+            }
+            'a' => {
+                let (item, rest) = Sig::parse(rest);
+                (Sig::Array(Box_::new(item)), rest)
+            }
+            '(' => {
+                let mut fields = Vec::new();
+                let mut rest = rest;
+                while !rest.starts_with(')') {
+                    let (field, r) = Sig::parse(rest);
+                    fields.push(field);
+                    rest = r;
+                }
+                (Sig::Struct(fields), &rest[1..])
+            }
+            c => (Sig::Basic(c), rest),
+        }
+    }
+
+    // The Rust type implementing `FromVariant` and `ToVariant` with exactly
+    // this signature, if there is one.
+    fn rust_type(&self) -> Option<String> {
+        match self {
+            Sig::Basic(c) => Some(
+                match c {
+                    'b' => "bool",
+                    'y' => "u8",
+                    'n' => "i16",
+                    'q' => "u16",
+                    'i' => "i32",
+                    'u' => "u32",
+                    'x' => "i64",
+                    't' => "u64",
+                    'd' => "f64",
+                    's' => "String",
+                    _ => return None,
+                }
+                .to_owned(),
+            ),
+            Sig::Variant => Some("gio::glib::Variant".to_owned()),
+            Sig::Array(item) => Some(format!("Vec<{}>", item.rust_type()?)),
+            // Floating point numbers are valid D-Bus dict keys but not `Hash`.
+            Sig::Dict(key, _) if **key == Sig::Basic('d') => None,
+            Sig::Dict(key, value) => Some(format!(
+                "std::collections::HashMap<{}, {}>",
+                key.rust_type()?,
+                value.rust_type()?
+            )),
+            Sig::Struct(fields) if !fields.is_empty() && fields.len() <= 16 => {
+                let fields = fields
+                    .iter()
+                    .map(Sig::rust_type)
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!("({},)", fields.join(", ")))
+            }
+            Sig::Struct(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conversion {
+    // Plain `FromVariant`/`ToVariant` types.
+    Typed,
+    // `v`, boxed and unboxed by the `Variant` conversions.
+    Boxed,
+    // Object paths and signatures, passed as strings.
+    ObjectPath,
+    Signature,
+    // Anything else is passed through as a `Variant` of the right type.
+    Raw,
+}
+
+#[derive(Debug, Clone)]
+struct Type {
+    owned: String,
+    conversion: Conversion,
+}
+
+impl Type {
+    fn new(signature: &str) -> Self {
+        let (sig, _) = Sig::parse(signature);
+        let (owned, conversion) = match sig {
+            Sig::Variant => ("gio::glib::Variant".to_owned(), Conversion::Boxed),
+            Sig::Basic('o') => ("String".to_owned(), Conversion::ObjectPath),
+            Sig::Basic('g') => ("String".to_owned(), Conversion::Signature),
+            sig => match sig.rust_type() {
+                Some(owned) => (owned, Conversion::Typed),
+                None => ("gio::glib::Variant".to_owned(), Conversion::Raw),
+            },
+        };
+        Self { owned, conversion }
+    }
+
+    fn is_copy(&self) -> bool {
+        self.conversion == Conversion::Typed
+            && matches!(
+                self.owned.as_str(),
+                "bool" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "f64"
+            )
+    }
+
+    // Type of the argument of generated client methods.
+    fn param(&self) -> String {
+        if self.is_copy() {
+            self.owned.clone()
+        } else if self.owned == "String" {
+            "&str".to_owned()
+        } else if let Some(item) = self
+            .owned
+            .strip_prefix("Vec<")
+            .and_then(|s| s.strip_suffix('>'))
+        {
+            format!("&[{}]", item)
+        } else {
+            format!("&{}", self.owned)
+        }
+    }
+
+    // Expression converting `expr` to a `Variant`. `expr` is a param if
+    // `fallible` is true and may use `?`, otherwise it is an owned value.
+    fn to_variant(&self, expr: &str, fallible: bool) -> String {
+        let (borrow, unwrap) = if fallible {
+            ("", "?")
+        } else {
+            ("&", ".expect(\"invalid D-Bus value\")")
+        };
+        match self.conversion {
+            Conversion::Typed | Conversion::Boxed => {
+                format!("gio::glib::ToVariant::to_variant(&{})", expr)
+            }
+            Conversion::ObjectPath => {
+                format!(
+                    "gio::dbus_codegen::object_path_variant({}{}){}",
+                    borrow, expr, unwrap
+                )
+            }
+            Conversion::Signature => {
+                format!(
+                    "gio::dbus_codegen::signature_variant({}{}){}",
+                    borrow, expr, unwrap
+                )
+            }
+            Conversion::Raw => format!("{}.clone()", expr),
+        }
+    }
+
+    // Expression converting the owned value `expr` to a
+    // `Result<Variant, glib::Error>`.
+    fn owned_to_variant(&self, expr: &str) -> String {
+        match self.conversion {
+            Conversion::Typed | Conversion::Boxed => {
+                format!("Ok(gio::glib::ToVariant::to_variant(&{}))", expr)
+            }
+            Conversion::ObjectPath => {
+                format!("gio::dbus_codegen::object_path_variant(&{})", expr)
+            }
+            Conversion::Signature => format!("gio::dbus_codegen::signature_variant(&{})", expr),
+            Conversion::Raw => format!("Ok({})", expr),
+        }
+    }
+
+    // Expression converting `v`, a `&Variant`, to `Option<owned>`.
+    fn variant_to_owned(&self) -> String {
+        match self.conversion {
+            Conversion::Typed => format!("v.get::<{}>()", self.owned),
+            Conversion::Boxed => "v.as_variant()".to_owned(),
+            Conversion::ObjectPath | Conversion::Signature => "v.get::<String>()".to_owned(),
+            Conversion::Raw => "Some(v.clone())".to_owned(),
+        }
+    }
+
+    // Expression converting `expr`, an `Option<Variant>`, to `Option<owned>`.
+    fn option_to_owned(&self, expr: &str) -> String {
+        match self.conversion {
+            Conversion::Raw => expr.to_owned(),
+            _ => format!(
+                "{}.as_ref().and_then(|v| {})",
+                expr,
+                self.variant_to_owned()
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Arg {
+    name: String,
+    ty: Type,
+}
+
+fn args(args: &[DBusArgInfo]) -> Vec<Arg> {
+    args.iter()
+        .map(|arg| Arg {
+            name: snake_case(arg.name()),
+            ty: Type::new(arg.signature()),
+        })
+        .collect()
+}
+
+// `()`, `T` or `(T0, T1, ...)` for a list of values.
+fn tuple_type(args: &[Arg]) -> String {
+    match args {
+        [] => "()".to_owned(),
+        [arg] => arg.ty.owned.clone(),
+        args => format!(
+            "({})",
+            args.iter()
+                .map(|arg| arg.ty.owned.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+// Expression converting the children of the tuple variant `tuple` to
+// `Option<tuple_type(args)>`.
+fn tuple_from_variant(args: &[Arg], tuple: &str) -> String {
+    let child = |i: usize, arg: &Arg| {
+        arg.ty
+            .option_to_owned(&format!("{}.try_child_value({})", tuple, i))
+    };
+    match args {
+        [] => "Some(())".to_owned(),
+        [arg] => child(0, arg),
+        args => format!(
+            "(|| Some(({})))()",
+            args.iter()
+                .enumerate()
+                .map(|(i, arg)| format!("{}?", child(i, arg)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn tuple_to_variant(values: &[String]) -> String {
+    format!("gio::glib::Variant::from_tuple(&[{}])", values.join(", "))
+}
+
+struct Method {
+    dbus_name: String,
+    name: String,
+    in_args: Vec<Arg>,
+    out_args: Vec<Arg>,
+}
+
+struct Signal {
+    dbus_name: String,
+    name: String,
+    args: Vec<Arg>,
+}
+
+struct Property {
+    dbus_name: String,
+    name: String,
+    ty: Type,
+    readable: bool,
+    writable: bool,
+}
+
+struct Interface {
+    dbus_name: String,
+    type_name: String,
+    xml: String,
+    methods: Vec<Method>,
+    signals: Vec<Signal>,
+    properties: Vec<Property>,
+}
+
+impl Interface {
+    fn new(info: &crate::DBusInterfaceInfo, type_name: &str) -> Result<Self, glib::Error> {
+        let mut xml = glib::String::new("<node>\n");
+        info.generate_xml(2, &mut xml);
+        xml.append("</node>\n");
+
+        let mut iface = Self {
+            dbus_name: info.name().to_owned(),
+            type_name: type_name.to_owned(),
+            xml: xml.to_string(),
+            methods: info
+                .methods()
+                .iter()
+                .map(|method| Method {
+                    dbus_name: method.name().to_owned(),
+                    name: snake_case(method.name()),
+                    in_args: args(&method.in_args()),
+                    out_args: args(&method.out_args()),
+                })
+                .collect(),
+            signals: info
+                .signals()
+                .iter()
+                .map(|signal| Signal {
+                    dbus_name: signal.name().to_owned(),
+                    name: snake_case(signal.name()),
+                    args: args(&signal.args()),
+                })
+                .collect(),
+            properties: info
+                .properties()
+                .iter()
+                .map(|property| Property {
+                    dbus_name: property.name().to_owned(),
+                    name: snake_case(property.name()),
+                    ty: Type::new(property.signature()),
+                    readable: property.is_readable(),
+                    writable: property.is_writable(),
+                })
+                .collect(),
+        };
+
+        let methods = iface.methods.iter_mut().map(|method| &mut method.name);
+        let properties = iface
+            .properties
+            .iter_mut()
+            .map(|property| &mut property.name);
+        for name in methods.chain(properties) {
+            if PROXY_MEMBERS.contains(&name.as_str()) {
+                name.push('_');
+            }
+        }
+        iface.check_names()?;
+        Ok(iface)
+    }
+
+    // Fails if two members of a generated type would get the same name.
+    fn check_names(&self) -> Result<(), glib::Error> {
+        let stem = |name: &str| name.trim_end_matches('_').to_owned();
+        let mut proxy: Vec<(String, &str)> = PROXY_MEMBERS
+            .iter()
+            .map(|name| (String::from(*name), "the proxy"))
+            .collect();
+        let mut server: Vec<(String, &str)> = Vec::new();
+        let mut skeleton = vec![
+            (String::from("register"), "the skeleton"),
+            (String::from("interface_info"), "the skeleton"),
+        ];
+        for method in &self.methods {
+            proxy.push((method.name.clone(), &method.dbus_name));
+            server.push((method.name.clone(), &method.dbus_name));
+        }
+        for property in &self.properties {
+            if property.readable {
+                proxy.push((property.name.clone(), &property.dbus_name));
+                proxy.push((
+                    format!("receive_{}_changed", stem(&property.name)),
+                    &property.dbus_name,
+                ));
+                server.push((property.name.clone(), &property.dbus_name));
+                skeleton.push((
+                    format!("emit_{}_changed", stem(&property.name)),
+                    &property.dbus_name,
+                ));
+            }
+            if property.writable {
+                let setter = format!("set_{}", stem(&property.name));
+                proxy.push((setter.clone(), &property.dbus_name));
+                server.push((setter, &property.dbus_name));
+            }
+        }
+        for signal in &self.signals {
+            proxy.push((format!("receive_{}", stem(&signal.name)), &signal.dbus_name));
+            skeleton.push((format!("emit_{}", stem(&signal.name)), &signal.dbus_name));
+        }
+
+        for names in &[proxy, server, skeleton] {
+            for (i, (name, member)) in names.iter().enumerate() {
+                if let Some((_, other)) = names[..i].iter().find(|(other, _)| other == name) {
+                    return Err(glib::Error::new(
+                        IOErrorEnum::InvalidData,
+                        &format!(
+                            "{} and {} of interface {} both generate {}",
+                            other, member, self.dbus_name, name
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_interface_info(&self, out: &mut String) {
+        writeln!(
+            out,
+            "    pub const INTERFACE_NAME: &'static str = {:?};
+
+    pub fn interface_info() -> gio::DBusInterfaceInfo {{
+        gio::DBusNodeInfo::for_xml({:?})
+            .expect(\"invalid introspection data\")
+            .lookup_interface(Self::INTERFACE_NAME)
+            .expect(\"interface missing from introspection data\")
+    }}",
+            self.dbus_name, self.xml
+        )
+        .unwrap();
+    }
+
+    fn write_proxy(&self, out: &mut String) {
+        let name = format!("{}Proxy", self.type_name);
+        writeln!(
+            out,
+            "
+/// Client proxy for the `{iface}` D-Bus interface.
+#[derive(Debug, Clone)]
+pub struct {name}(gio::DBusProxy);
+
+#[allow(
+    dead_code,
+    clippy::redundant_closure_call,
+    clippy::too_many_arguments,
+    clippy::type_complexity
+)]
+impl {name} {{",
+            iface = self.dbus_name,
+            name = name
+        )
+        .unwrap();
+        self.write_interface_info(out);
+        out.push_str(
+            "
+    pub async fn new(
+        connection: &gio::DBusConnection,
+        name: Option<&str>,
+        object_path: &str,
+    ) -> Result<Self, gio::glib::Error> {
+        let proxy = gio::DBusProxy::new_future(
+            connection,
+            gio::DBusProxyFlags::NONE,
+            Some(&Self::interface_info()),
+            name,
+            object_path,
+            Self::INTERFACE_NAME,
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    pub async fn for_bus(
+        bus_type: gio::BusType,
+        name: &str,
+        object_path: &str,
+    ) -> Result<Self, gio::glib::Error> {
+        let proxy = gio::DBusProxy::for_bus_future(
+            bus_type,
+            gio::DBusProxyFlags::NONE,
+            Some(&Self::interface_info()),
+            name,
+            object_path,
+            Self::INTERFACE_NAME,
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    /// Wraps an existing proxy for this interface.
+    pub fn from_proxy(proxy: gio::DBusProxy) -> Self {
+        Self(proxy)
+    }
+
+    pub fn proxy(&self) -> &gio::DBusProxy {
+        &self.0
+    }
+",
+        );
+
+        for method in &self.methods {
+            let params: String = method
+                .in_args
+                .iter()
+                .map(|arg| format!(", {}: {}", arg.name, arg.ty.param()))
+                .collect();
+            let parameters = if method.in_args.is_empty() {
+                "None".to_owned()
+            } else {
+                let values: Vec<String> = method
+                    .in_args
+                    .iter()
+                    .map(|arg| arg.ty.to_variant(&arg.name, true))
+                    .collect();
+                format!("Some(&{})", tuple_to_variant(&values))
+            };
+            writeln!(
+                out,
+                "
+    /// Calls `{dbus}`.
+    pub async fn {name}(&self{params}) -> Result<{ret}, gio::glib::Error> {{
+        let {reply} = gio::prelude::DBusProxyExt::call_future(
+            &self.0,
+            {dbus:?},
+            {parameters},
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await?;
+        gio::dbus_codegen::reply_value({convert}, {dbus:?})
+    }}",
+                dbus = method.dbus_name,
+                name = method.name,
+                params = params,
+                ret = tuple_type(&method.out_args),
+                parameters = parameters,
+                reply = if method.out_args.is_empty() {
+                    "_reply"
+                } else {
+                    "reply"
+                },
+                convert = tuple_from_variant(&method.out_args, "reply"),
+            )
+            .unwrap();
+        }
+
+        for property in self.properties.iter().filter(|p| p.readable) {
+            writeln!(
+                out,
+                "
+    /// Cached value of the `{dbus}` property.
+    pub fn {name}(&self) -> Option<{ty}> {{
+        {cached}
+    }}
+
+    /// Changes of the `{dbus}` property.
+    pub fn receive_{stem}_changed(&self) -> gio::dbus_codegen::PropertyStream<{ty}> {{
+        gio::dbus_codegen::PropertyStream::new(&self.0, {dbus:?}, |v| {convert})
+    }}",
+                dbus = property.dbus_name,
+                name = property.name,
+                stem = property.name.trim_end_matches('_'),
+                ty = property.ty.owned,
+                cached = property.ty.option_to_owned(&format!(
+                    "gio::prelude::DBusProxyExt::cached_property(&self.0, {:?})",
+                    property.dbus_name
+                )),
+                convert = property.ty.variant_to_owned(),
+            )
+            .unwrap();
+        }
+
+        for property in self.properties.iter().filter(|p| p.writable) {
+            writeln!(
+                out,
+                "
+    /// Sets the `{dbus}` property.
+    pub async fn set_{name}(&self, value: {param}) -> Result<(), gio::glib::Error> {{
+        gio::dbus_codegen::set_property(&self.0, {dbus:?}, {value}).await
+    }}",
+                dbus = property.dbus_name,
+                name = property.name.trim_end_matches('_'),
+                param = property.ty.param(),
+                value = property.ty.to_variant("value", true),
+            )
+            .unwrap();
+        }
+
+        for signal in &self.signals {
+            writeln!(
+                out,
+                "
+    /// Emissions of the `{dbus}` signal.
+    pub fn receive_{name}(&self) -> gio::dbus_codegen::SignalStream<{ty}> {{
+        gio::dbus_codegen::SignalStream::new(&self.0, {dbus:?}, |{binding}| {{
+            {convert}
+        }})
+    }}",
+                dbus = signal.dbus_name,
+                name = signal.name.trim_end_matches('_'),
+                ty = tuple_type(&signal.args),
+                binding = if signal.args.is_empty() {
+                    "_"
+                } else {
+                    "parameters"
+                },
+                convert = tuple_from_variant(&signal.args, "parameters"),
+            )
+            .unwrap();
+        }
+
+        out.push_str("}\n");
+    }
+
+    fn write_server(&self, out: &mut String) {
+        let trait_name = format!("{}Server", self.type_name);
+        let skeleton = format!("{}Skeleton", self.type_name);
+
+        writeln!(
+            out,
+            "
+/// Service side of the `{iface}` D-Bus interface.
+///
+/// Register an implementation with [`{skeleton}::register()`].
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub trait {name}: Send + Sync + 'static {{",
+            iface = self.dbus_name,
+            skeleton = skeleton,
+            name = trait_name
+        )
+        .unwrap();
+        for method in &self.methods {
+            let params: String = method
+                .in_args
+                .iter()
+                .map(|arg| format!(", {}: {}", arg.name, arg.ty.owned))
+                .collect();
+            writeln!(
+                out,
+                "    fn {}(&self{}) -> Result<{}, gio::glib::Error>;",
+                method.name,
+                params,
+                tuple_type(&method.out_args)
+            )
+            .unwrap();
+        }
+        for property in &self.properties {
+            if property.readable {
+                writeln!(
+                    out,
+                    "    fn {}(&self) -> {};",
+                    property.name, property.ty.owned
+                )
+                .unwrap();
+            }
+            if property.writable {
+                writeln!(
+                    out,
+                    "    fn set_{}(&self, value: {}) -> Result<(), gio::glib::Error>;",
+                    property.name.trim_end_matches('_'),
+                    property.ty.owned
+                )
+                .unwrap();
+            }
+        }
+        out.push_str("}\n");
+
+        writeln!(
+            out,
+            "
+/// Registration and signal emission for [`{name}`] implementations.
+#[derive(Debug, Clone, Copy)]
+pub struct {skeleton};
+
+#[allow(
+    dead_code,
+    clippy::match_single_binding,
+    clippy::redundant_closure_call,
+    clippy::too_many_arguments
+)]
+impl {skeleton} {{",
+            name = trait_name,
+            skeleton = skeleton
+        )
+        .unwrap();
+        self.write_interface_info(out);
+
+        // Method dispatch.
+        let mut method_arms = String::new();
+        for method in &self.methods {
+            let mut arm = format!("                {:?} => {{\n", method.dbus_name);
+            for (i, arg) in method.in_args.iter().enumerate() {
+                writeln!(
+                    arm,
+                    "                    let {name} = match parameters.try_child_value({i}).as_ref().and_then(|v| {convert}) {{
+                        Some(value) => value,
+                        None => return invocation.return_dbus_error(
+                            \"org.freedesktop.DBus.Error.InvalidArgs\",
+                            \"Invalid arguments for {dbus}\",
+                        ),
+                    }};",
+                    name = arg.name,
+                    i = i,
+                    convert = arg.ty.variant_to_owned(),
+                    dbus = method.dbus_name,
+                )
+                .unwrap();
+            }
+            let call_args = method
+                .in_args
+                .iter()
+                .map(|arg| arg.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let out_names: Vec<String> = (0..method.out_args.len())
+                .map(|i| format!("out{}", i))
+                .collect();
+            let (pattern, reply) = match method.out_args.len() {
+                0 => ("()".to_owned(), "None".to_owned()),
+                1 => (
+                    "out0".to_owned(),
+                    format!(
+                        "Some(&{})",
+                        tuple_to_variant(&[method.out_args[0].ty.to_variant("out0", false)])
+                    ),
+                ),
+                _ => (
+                    format!("({})", out_names.join(", ")),
+                    format!(
+                        "Some(&{})",
+                        tuple_to_variant(
+                            &method
+                                .out_args
+                                .iter()
+                                .zip(&out_names)
+                                .map(|(arg, name)| arg.ty.to_variant(name, false))
+                                .collect::<Vec<_>>()
+                        )
+                    ),
+                ),
+            };
+            writeln!(
+                arm,
+                "                    match server.{name}({call_args}) {{
+                        Ok({pattern}) => invocation.return_value({reply}),
+                        Err(err) => invocation.return_gerror(err),
+                    }}
+                }}",
+                name = method.name,
+                call_args = call_args,
+                pattern = pattern,
+                reply = reply,
+            )
+            .unwrap();
+            method_arms.push_str(&arm);
+        }
+
+        let mut get_arms = String::new();
+        let mut set_arms = String::new();
+        for property in &self.properties {
+            if property.readable {
+                writeln!(
+                    get_arms,
+                    "                {:?} => {},",
+                    property.dbus_name,
+                    property
+                        .ty
+                        .owned_to_variant(&format!("server.{}()", property.name))
+                )
+                .unwrap();
+            }
+            if property.writable {
+                writeln!(
+                    set_arms,
+                    "                {dbus:?} => {{
+                    let v = &value;
+                    match {convert} {{
+                        Some(value) => server.set_{name}(value),
+                        None => Err(gio::dbus_codegen::dbus_error(
+                            \"org.freedesktop.DBus.Error.InvalidArgs\",
+                            \"Invalid value for {dbus}\",
+                        )),
+                    }}
+                }}",
+                    dbus = property.dbus_name,
+                    name = property.name.trim_end_matches('_'),
+                    convert = property.ty.variant_to_owned(),
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(
+            out,
+            "
+    /// Exports `server` at `object_path` on `connection`.
+    pub fn register<T: {name}>(
+        connection: &gio::DBusConnection,
+        object_path: &str,
+        server: T,
+    ) -> Result<gio::RegistrationId, gio::glib::Error> {{
+        let server = std::sync::Arc::new(server);
+        let method_server = server.clone();
+        let get_server = server.clone();
+        let set_server = server;
+        gio::dbus_codegen::register_object(
+            connection,
+            object_path,
+            &Self::interface_info(),
+            move |method_name, parameters, invocation| {{
+                let server = &method_server;
+                let _ = (server, &parameters);
+                match method_name {{
+{method_arms}                _ => invocation.return_dbus_error(
+                    \"org.freedesktop.DBus.Error.UnknownMethod\",
+                    &format!(\"Unknown method {{}}\", method_name),
+                ),
+            }}
+            }},
+            move |property_name| {{
+                let server = &get_server;
+                let _ = server;
+                match property_name {{
+{get_arms}                _ => Err(gio::dbus_codegen::dbus_error(
+                    \"org.freedesktop.DBus.Error.UnknownProperty\",
+                    &format!(\"Unknown property {{}}\", property_name),
+                )),
+            }}
+            }},
+            move |property_name, value| {{
+                let server = &set_server;
+                let _ = (server, &value);
+                match property_name {{
+{set_arms}                _ => Err(gio::dbus_codegen::dbus_error(
+                    \"org.freedesktop.DBus.Error.PropertyReadOnly\",
+                    &format!(\"Property {{}} is not writable\", property_name),
+                )),
+            }}
+            }},
+        )
+    }}",
+            name = trait_name,
+            method_arms = method_arms,
+            get_arms = get_arms,
+            set_arms = set_arms,
+        )
+        .unwrap();
+
+        for signal in &self.signals {
+            let params: String = signal
+                .args
+                .iter()
+                .map(|arg| format!(", {}: {}", arg.name, arg.ty.param()))
+                .collect();
+            let values: Vec<String> = signal
+                .args
+                .iter()
+                .map(|arg| arg.ty.to_variant(&arg.name, true))
+                .collect();
+            writeln!(
+                out,
+                "
+    /// Emits the `{dbus}` signal.
+    pub fn emit_{name}(
+        connection: &gio::DBusConnection,
+        object_path: &str{params}
+    ) -> Result<(), gio::glib::Error> {{
+        connection.emit_signal(
+            None,
+            object_path,
+            Self::INTERFACE_NAME,
+            {dbus:?},
+            Some(&{parameters}),
+        )
+    }}",
+                dbus = signal.dbus_name,
+                name = signal.name.trim_end_matches('_'),
+                params = params,
+                parameters = tuple_to_variant(&values),
+            )
+            .unwrap();
+        }
+
+        for property in self.properties.iter().filter(|p| p.readable) {
+            writeln!(
+                out,
+                "
+    /// Notifies clients that the `{dbus}` property changed to `value`.
+    pub fn emit_{name}_changed(
+        connection: &gio::DBusConnection,
+        object_path: &str,
+        value: {param},
+    ) -> Result<(), gio::glib::Error> {{
+        gio::dbus_codegen::emit_properties_changed(
+            connection,
+            object_path,
+            Self::INTERFACE_NAME,
+            &[({dbus:?}, {value})],
+        )
+    }}",
+                dbus = property.dbus_name,
+                name = property.name.trim_end_matches('_'),
+                param = property.ty.param(),
+                value = property.ty.to_variant("value", true),
+            )
+            .unwrap();
+        }
+
+        out.push_str("}\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"
+<node>
+  <interface name="org.example.Media.Player">
+    <method name="OpenURI">
+      <arg name="uri" type="s" direction="in"/>
+    </method>
+    <method name="GetTracks">
+      <arg name="offset" type="u" direction="in"/>
+      <arg name="tracks" type="ao" direction="out"/>
+      <arg type="a{sv}" direction="out"/>
+    </method>
+    <signal name="Seeked">
+      <arg name="position" type="x"/>
+    </signal>
+    <property name="Volume" type="d" access="readwrite"/>
+    <property name="Type" type="o" access="read"/>
+  </interface>
+  <interface name="org.example.Other"/>
+</node>"#;
+
+    #[test]
+    fn names() {
+        assert_eq!(snake_case("GetDisplayDevice"), "get_display_device");
+        assert_eq!(snake_case("OpenURI"), "open_uri");
+        assert_eq!(snake_case("GetIPAddress4"), "get_ip_address4");
+        assert_eq!(snake_case("Type"), "type_");
+        assert_eq!(snake_case("can-go-next"), "can_go_next");
+        assert_eq!(camel_case("UPower"), "UPower");
+        assert_eq!(camel_case("media_player"), "MediaPlayer");
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            Type::new("a{sv}").owned,
+            "std::collections::HashMap<String, gio::glib::Variant>"
+        );
+        assert_eq!(Type::new("a(su)").owned, "Vec<(String, u32,)>");
+        assert_eq!(Type::new("v").conversion, Conversion::Boxed);
+        assert_eq!(Type::new("o").param(), "&str");
+        assert_eq!(Type::new("ao").conversion, Conversion::Raw);
+        assert_eq!(Type::new("as").param(), "&[String]");
+        assert_eq!(Type::new("t").param(), "u64");
+        assert_eq!(Type::new("a{dv}").conversion, Conversion::Raw);
+        assert_eq!(Type::new("a(da{ds})").conversion, Conversion::Raw);
+    }
+
+    #[test]
+    fn generate() {
+        let code = Generator::new(XML)
+            .strip_prefix("org.example.")
+            .interface("org.example.Media.Player")
+            .generate()
+            .unwrap();
+
+        assert!(code.contains("pub struct MediaPlayerProxy(gio::DBusProxy);"));
+        assert!(code
+            .contains("pub async fn open_uri(&self, uri: &str) -> Result<(), gio::glib::Error>"));
+        assert!(code.contains(
+            "pub async fn get_tracks(&self, offset: u32) -> Result<(gio::glib::Variant, std::collections::HashMap<String, gio::glib::Variant>), gio::glib::Error>"
+        ));
+        assert!(code.contains("pub fn volume(&self) -> Option<f64>"));
+        assert!(code.contains("pub async fn set_volume(&self, value: f64)"));
+        assert!(code.contains("pub fn type_(&self) -> Option<String>"));
+        assert!(
+            code.contains("pub fn receive_seeked(&self) -> gio::dbus_codegen::SignalStream<i64>")
+        );
+        assert!(code.contains("pub trait MediaPlayerServer: Send + Sync + 'static"));
+        assert!(code.contains("fn set_volume(&self, value: f64) -> Result<(), gio::glib::Error>;"));
+        assert!(code.contains("pub fn emit_seeked("));
+        assert!(!code.contains("OtherProxy"));
+
+        let client_only = Generator::new(XML).server(false).generate().unwrap();
+        assert!(client_only.contains("pub struct PlayerProxy"));
+        assert!(client_only.contains("pub struct OtherProxy"));
+        assert!(!client_only.contains("Server"));
+
+        assert!(Generator::new(XML)
+            .interface("org.example.Missing")
+            .generate()
+            .is_err());
+        assert!(Generator::new("<node").generate().is_err());
+    }
+
+    #[test]
+    fn name_collisions() {
+        let code = Generator::new(
+            r#"
+<node>
+  <interface name="org.example.Factory">
+    <method name="New">
+      <arg name="name" type="s" direction="in"/>
+    </method>
+    <property name="Proxy" type="s" access="readwrite"/>
+  </interface>
+</node>"#,
+        )
+        .generate()
+        .unwrap();
+        assert!(code.contains("pub async fn new_(&self, name: &str)"));
+        assert!(code.contains("pub fn proxy_(&self) -> Option<String>"));
+        assert!(code.contains("pub async fn set_proxy(&self, value: &str)"));
+        assert!(code.contains("fn new_(&self, name: String)"));
+
+        let err = Generator::new(
+            r#"
+<node>
+  <interface name="org.example.Mixer">
+    <method name="SetVolume">
+      <arg name="volume" type="d" direction="in"/>
+    </method>
+    <property name="Volume" type="d" access="readwrite"/>
+  </interface>
+</node>"#,
+        )
+        .generate()
+        .unwrap_err();
+        assert!(err.to_string().contains("SetVolume and Volume"));
+        assert!(err.to_string().contains("set_volume"));
+    }
+
+    #[test]
+    fn object_paths() {
+        assert_eq!(
+            object_path_variant("/org/example")
+                .unwrap()
+                .type_()
+                .to_str(),
+            "o"
+        );
+        assert!(object_path_variant("not a path").is_err());
+        assert_eq!(signature_variant("a{sv}").unwrap().type_().to_str(), "g");
+        assert!(signature_variant("a{").is_err());
+    }
+}
@@ -56,7 +56,8 @@ impl DBusConnection {
                 interface_info.to_glib_none().0,
                 glib::Closure::new(move |args| {
                     let conn = args[0].get::<DBusConnection>().unwrap();
-                    let sender = args[1].get::<&str>().unwrap();
+                    // There is no sender on peer-to-peer connections.
+                    let sender = args[1].get::<Option<&str>>().unwrap().unwrap_or("");
                     let object_path = args[2].get::<&str>().unwrap();
                     let interface_name = args[3].get::<&str>().unwrap();
                     let method_name = args[4].get::<&str>().unwrap();
@@ -77,7 +78,7 @@ impl DBusConnection {
                 .0,
                 glib::Closure::new(move |args| {
                     let conn = args[0].get::<DBusConnection>().unwrap();
-                    let sender = args[1].get::<&str>().unwrap();
+                    let sender = args[1].get::<Option<&str>>().unwrap().unwrap_or("");
                     let object_path = args[2].get::<&str>().unwrap();
                     let interface_name = args[3].get::<&str>().unwrap();
                     let property_name = args[4].get::<&str>().unwrap();
@@ -89,7 +90,7 @@ impl DBusConnection {
                 .0,
                 glib::Closure::new(move |args| {
                     let conn = args[0].get::<DBusConnection>().unwrap();
-                    let sender = args[1].get::<&str>().unwrap();
+                    let sender = args[1].get::<Option<&str>>().unwrap().unwrap_or("");
                     let object_path = args[2].get::<&str>().unwrap();
                     let interface_name = args[3].get::<&str>().unwrap();
                     let property_name = args[4].get::<&str>().unwrap();
@@ -116,6 +117,140 @@ impl DBusConnection {
         }
     }
 
+    // Like `register_object()`, but through a `GDBusInterfaceVTable` so that
+    // property accessors can fail with an error that is returned to the caller.
+    #[doc(alias = "g_dbus_connection_register_object")]
+    pub(crate) fn register_object_fallible<MethodCall, GetProperty, SetProperty>(
+        &self,
+        object_path: &str,
+        interface_info: &DBusInterfaceInfo,
+        method_call: MethodCall,
+        get_property: GetProperty,
+        set_property: SetProperty,
+    ) -> Result<RegistrationId, glib::Error>
+    where
+        MethodCall:
+            Fn(Option<&str>, &str, glib::Variant, DBusMethodInvocation) + Send + Sync + 'static,
+        GetProperty: Fn(&str) -> Result<glib::Variant, glib::Error> + Send + Sync + 'static,
+        SetProperty: Fn(&str, glib::Variant) -> Result<(), glib::Error> + Send + Sync + 'static,
+    {
+        struct Handlers<MethodCall, GetProperty, SetProperty> {
+            method_call: MethodCall,
+            get_property: GetProperty,
+            set_property: SetProperty,
+        }
+
+        unsafe extern "C" fn method_call_func<
+            MethodCall: Fn(Option<&str>, &str, glib::Variant, DBusMethodInvocation),
+            GetProperty,
+            SetProperty,
+        >(
+            _connection: *mut ffi::GDBusConnection,
+            sender: *const libc::c_char,
+            _object_path: *const libc::c_char,
+            _interface_name: *const libc::c_char,
+            method_name: *const libc::c_char,
+            parameters: *mut glib::ffi::GVariant,
+            invocation: *mut ffi::GDBusMethodInvocation,
+            user_data: glib::ffi::gpointer,
+        ) {
+            let handlers = &*(user_data as *const Handlers<MethodCall, GetProperty, SetProperty>);
+            let sender: Borrowed<Option<glib::GString>> = from_glib_borrow(sender);
+            let method_name: Borrowed<glib::GString> = from_glib_borrow(method_name);
+            (handlers.method_call)(
+                sender.as_ref().as_deref(),
+                method_name.as_str(),
+                from_glib_none(parameters),
+                from_glib_full(invocation),
+            );
+        }
+
+        unsafe extern "C" fn get_property_func<
+            MethodCall,
+            GetProperty: Fn(&str) -> Result<glib::Variant, glib::Error>,
+            SetProperty,
+        >(
+            _connection: *mut ffi::GDBusConnection,
+            _sender: *const libc::c_char,
+            _object_path: *const libc::c_char,
+            _interface_name: *const libc::c_char,
+            property_name: *const libc::c_char,
+            error: *mut *mut glib::ffi::GError,
+            user_data: glib::ffi::gpointer,
+        ) -> *mut glib::ffi::GVariant {
+            let handlers = &*(user_data as *const Handlers<MethodCall, GetProperty, SetProperty>);
+            let property_name: Borrowed<glib::GString> = from_glib_borrow(property_name);
+            match (handlers.get_property)(property_name.as_str()) {
+                Ok(value) => value.to_glib_full(),
+                Err(err) => {
+                    *error = err.into_raw();
+                    std::ptr::null_mut()
+                }
+            }
+        }
+
+        unsafe extern "C" fn set_property_func<
+            MethodCall,
+            GetProperty,
+            SetProperty: Fn(&str, glib::Variant) -> Result<(), glib::Error>,
+        >(
+            _connection: *mut ffi::GDBusConnection,
+            _sender: *const libc::c_char,
+            _object_path: *const libc::c_char,
+            _interface_name: *const libc::c_char,
+            property_name: *const libc::c_char,
+            value: *mut glib::ffi::GVariant,
+            error: *mut *mut glib::ffi::GError,
+            user_data: glib::ffi::gpointer,
+        ) -> glib::ffi::gboolean {
+            let handlers = &*(user_data as *const Handlers<MethodCall, GetProperty, SetProperty>);
+            let property_name: Borrowed<glib::GString> = from_glib_borrow(property_name);
+            match (handlers.set_property)(property_name.as_str(), from_glib_none(value)) {
+                Ok(()) => glib::ffi::GTRUE,
+                Err(err) => {
+                    *error = err.into_raw();
+                    glib::ffi::GFALSE
+                }
+            }
+        }
+
+        unsafe extern "C" fn free_func<MethodCall, GetProperty, SetProperty>(
+            user_data: glib::ffi::gpointer,
+        ) {
+            Box_::from_raw(user_data as *mut Handlers<MethodCall, GetProperty, SetProperty>);
+        }
+
+        let vtable = ffi::GDBusInterfaceVTable {
+            method_call: Some(method_call_func::<MethodCall, GetProperty, SetProperty>),
+            get_property: Some(get_property_func::<MethodCall, GetProperty, SetProperty>),
+            set_property: Some(set_property_func::<MethodCall, GetProperty, SetProperty>),
+            padding: [std::ptr::null_mut(); 8],
+        };
+        let handlers = Box_::new(Handlers {
+            method_call,
+            get_property,
+            set_property,
+        });
+        unsafe {
+            let mut error = std::ptr::null_mut();
+            // GDBus copies the vtable, so it can live on the stack.
+            let id = ffi::g_dbus_connection_register_object(
+                self.to_glib_none().0,
+                object_path.to_glib_none().0,
+                interface_info.to_glib_none().0,
+                &vtable,
+                Box_::into_raw(handlers) as glib::ffi::gpointer,
+                Some(free_func::<MethodCall, GetProperty, SetProperty>),
+                &mut error,
+            );
+            if error.is_null() {
+                Ok(RegistrationId(NonZeroU32::new_unchecked(id)))
+            } else {
+                Err(from_glib_full(error))
+            }
+        }
+    }
+
     #[doc(alias = "g_dbus_connection_unregister_object")]
     pub fn unregister_object(
         &self,
@@ -267,7 +402,7 @@ impl DBusConnection {
             user_data: glib::ffi::gpointer,
         ) {
             let connection = from_glib_borrow(connection);
-            let sender_name: Borrowed<glib::GString> = from_glib_borrow(sender_name);
+            let sender_name: Borrowed<Option<glib::GString>> = from_glib_borrow(sender_name);
             let object_path: Borrowed<glib::GString> = from_glib_borrow(object_path);
             let interface_name: Borrowed<glib::GString> = from_glib_borrow(interface_name);
             let signal_name: Borrowed<glib::GString> = from_glib_borrow(signal_name);
@@ -275,7 +410,8 @@ impl DBusConnection {
             let callback: &P = &*(user_data as *mut _);
             (*callback)(
                 &connection,
-                sender_name.as_str(),
+                // There is no sender on peer-to-peer connections.
+                sender_name.as_ref().as_deref().unwrap_or(""),
                 object_path.as_str(),
                 interface_name.as_str(),
                 signal_name.as_str(),
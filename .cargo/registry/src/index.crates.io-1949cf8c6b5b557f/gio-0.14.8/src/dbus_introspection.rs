@@ -0,0 +1,167 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::DBusArgInfo;
+use crate::DBusInterfaceInfo;
+use crate::DBusMethodInfo;
+use crate::DBusNodeInfo;
+use crate::DBusPropertyInfo;
+use crate::DBusSignalInfo;
+use glib::translate::*;
+use std::ffi::CStr;
+
+// Introspection data is immutable once parsed, so borrowing the strings for
+// the lifetime of the wrapper is fine.
+unsafe fn borrow_str<'a>(ptr: *const libc::c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(
+            CStr::from_ptr(ptr)
+                .to_str()
+                .expect("D-Bus introspection data is not valid UTF-8"),
+        )
+    }
+}
+
+impl DBusNodeInfo {
+    /// The path of the node, if any.
+    pub fn path(&self) -> Option<&str> {
+        unsafe { borrow_str((*self.to_glib_none().0).path) }
+    }
+
+    /// The interfaces declared directly on this node.
+    pub fn interfaces(&self) -> Vec<DBusInterfaceInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).interfaces) }
+    }
+
+    /// The child nodes of this node.
+    pub fn nodes(&self) -> Vec<DBusNodeInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).nodes) }
+    }
+}
+
+impl DBusInterfaceInfo {
+    /// The fully qualified name of the interface, e.g. `org.freedesktop.UPower`.
+    pub fn name(&self) -> &str {
+        unsafe { borrow_str((*self.to_glib_none().0).name).unwrap_or_default() }
+    }
+
+    pub fn methods(&self) -> Vec<DBusMethodInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).methods) }
+    }
+
+    pub fn signals(&self) -> Vec<DBusSignalInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).signals) }
+    }
+
+    pub fn properties(&self) -> Vec<DBusPropertyInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).properties) }
+    }
+}
+
+impl DBusMethodInfo {
+    pub fn name(&self) -> &str {
+        unsafe { borrow_str((*self.to_glib_none().0).name).unwrap_or_default() }
+    }
+
+    pub fn in_args(&self) -> Vec<DBusArgInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).in_args) }
+    }
+
+    pub fn out_args(&self) -> Vec<DBusArgInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).out_args) }
+    }
+}
+
+impl DBusSignalInfo {
+    pub fn name(&self) -> &str {
+        unsafe { borrow_str((*self.to_glib_none().0).name).unwrap_or_default() }
+    }
+
+    pub fn args(&self) -> Vec<DBusArgInfo> {
+        unsafe { FromGlibPtrContainer::from_glib_none((*self.to_glib_none().0).args) }
+    }
+}
+
+impl DBusPropertyInfo {
+    pub fn name(&self) -> &str {
+        unsafe { borrow_str((*self.to_glib_none().0).name).unwrap_or_default() }
+    }
+
+    /// The D-Bus type signature of the property, e.g. `a{sv}`.
+    pub fn signature(&self) -> &str {
+        unsafe { borrow_str((*self.to_glib_none().0).signature).unwrap_or_default() }
+    }
+
+    pub fn is_readable(&self) -> bool {
+        unsafe { (*self.to_glib_none().0).flags & ffi::G_DBUS_PROPERTY_INFO_FLAGS_READABLE != 0 }
+    }
+
+    pub fn is_writable(&self) -> bool {
+        unsafe { (*self.to_glib_none().0).flags & ffi::G_DBUS_PROPERTY_INFO_FLAGS_WRITABLE != 0 }
+    }
+}
+
+impl DBusArgInfo {
+    /// The name of the argument. GDBus names unnamed arguments `arg_N`.
+    pub fn name(&self) -> &str {
+        unsafe { borrow_str((*self.to_glib_none().0).name).unwrap_or_default() }
+    }
+
+    /// The D-Bus type signature of the argument.
+    pub fn signature(&self) -> &str {
+        unsafe { borrow_str((*self.to_glib_none().0).signature).unwrap_or_default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"
+<node name="/org/example/Thing">
+  <interface name="org.example.Thing">
+    <method name="Frobnicate">
+      <arg name="level" type="u" direction="in"/>
+      <arg type="a{sv}" direction="out"/>
+    </method>
+    <signal name="Changed">
+      <arg name="what" type="s"/>
+    </signal>
+    <property name="Enabled" type="b" access="readwrite"/>
+    <property name="Count" type="t" access="read"/>
+  </interface>
+  <node name="child"/>
+</node>"#;
+
+    #[test]
+    fn introspection_accessors() {
+        let node = DBusNodeInfo::for_xml(XML).unwrap();
+        assert_eq!(node.path(), Some("/org/example/Thing"));
+        assert_eq!(node.nodes().len(), 1);
+
+        let interfaces = node.interfaces();
+        assert_eq!(interfaces.len(), 1);
+        let iface = &interfaces[0];
+        assert_eq!(iface.name(), "org.example.Thing");
+
+        let methods = iface.methods();
+        assert_eq!(methods[0].name(), "Frobnicate");
+        let in_args = methods[0].in_args();
+        assert_eq!(in_args[0].name(), "level");
+        assert_eq!(in_args[0].signature(), "u");
+        let out_args = methods[0].out_args();
+        assert_eq!(out_args[0].name(), "arg_1");
+        assert_eq!(out_args[0].signature(), "a{sv}");
+
+        let signals = iface.signals();
+        assert_eq!(signals[0].name(), "Changed");
+        assert_eq!(signals[0].args()[0].signature(), "s");
+
+        let properties = iface.properties();
+        assert_eq!(properties.len(), 2);
+        assert!(properties[0].is_readable() && properties[0].is_writable());
+        assert!(properties[1].is_readable() && !properties[1].is_writable());
+        assert_eq!(properties[1].signature(), "t");
+    }
+}
@@ -0,0 +1,647 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! A private, in-process D-Bus peer for testing code that uses [`DBusConnection`].
+//!
+//! [`MockBus`] runs a [`DBusServer`] on its own thread and main context, so no
+//! `dbus-daemon` is needed. Tests export fake objects on it with scripted
+//! method replies, emit signals from them and inspect the calls they
+//! received, while the code under test talks to them through a regular
+//! client connection obtained from [`MockBus::connect()`].
+//!
+//! As this is a peer-to-peer connection there is no bus name: pass `None`
+//! wherever a bus name is expected.
+//!
+//! This module is only available with the `dbus-mock` feature.
+//!
+//! ```no_run
+//! use gio::dbus_mock::MockBus;
+//!
+//! let bus = MockBus::new().unwrap();
+//! let player = bus
+//!     .add_object(
+//!         "/org/example/Player",
+//!         r#"<node><interface name="org.example.Player">
+//!              <method name="Play"/>
+//!            </interface></node>"#,
+//!     )
+//!     .unwrap();
+//! player.reply("Play", None);
+//!
+//! let connection = bus.connect().unwrap();
+//! connection
+//!     .call_sync(
+//!         None,
+//!         "/org/example/Player",
+//!         "org.example.Player",
+//!         "Play",
+//!         None,
+//!         None,
+//!         gio::DBusCallFlags::NONE,
+//!         -1,
+//!         gio::NONE_CANCELLABLE,
+//!     )
+//!     .unwrap();
+//! assert_eq!(player.calls_to("Play").len(), 1);
+//! ```
+
+use crate::{
+    Cancellable, DBusCallFlags, DBusConnection, DBusConnectionFlags, DBusMethodInvocation,
+    DBusNodeInfo, DBusServer, DBusServerFlags, RegistrationId,
+};
+use glib::{MainContext, MainLoop, Variant};
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Handler = Arc<dyn Fn(&Variant) -> Result<Option<Variant>, glib::Error> + Send + Sync>;
+
+#[derive(Clone)]
+enum Reply {
+    Value(Option<Variant>),
+    Error(String, String),
+    Handler(Handler),
+}
+
+/// A method call received by a [`MockObject`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    /// Unique name of the caller. Always `None` on a peer-to-peer connection
+    /// unless the caller set one explicitly.
+    pub sender: Option<String>,
+    pub object_path: String,
+    pub interface_name: String,
+    pub method_name: String,
+    /// The call's arguments, always a tuple.
+    pub parameters: Variant,
+}
+
+struct ObjectState {
+    object_path: String,
+    interface_name: String,
+    xml: String,
+    replies: Mutex<HashMap<String, Reply>>,
+    properties: Mutex<HashMap<String, Variant>>,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+#[derive(Default)]
+struct BusState {
+    connections: Mutex<Vec<DBusConnection>>,
+    objects: Mutex<Vec<Arc<ObjectState>>>,
+}
+
+/// A private D-Bus peer running on a background thread.
+///
+/// The server and all fake objects are torn down when the `MockBus` is dropped.
+pub struct MockBus {
+    address: String,
+    context: MainContext,
+    main_loop: MainLoop,
+    thread: Option<thread::JoinHandle<()>>,
+    state: Arc<BusState>,
+}
+
+impl MockBus {
+    /// Starts a new server listening on a private socket.
+    pub fn new() -> Result<Self, glib::Error> {
+        let context = MainContext::new();
+        let main_loop = MainLoop::new(Some(&context), false);
+        let state = Arc::new(BusState::default());
+
+        let (sender, receiver) = channel();
+        let thread = {
+            let context = context.clone();
+            let main_loop = main_loop.clone();
+            let state = state.clone();
+            thread::Builder::new()
+                .name("gio-mock-bus".into())
+                .spawn(move || {
+                    context.with_thread_default(|| {
+                        let server = match start_server(&state) {
+                            Ok(server) => server,
+                            Err(err) => {
+                                let _ = sender.send(Err(err));
+                                return;
+                            }
+                        };
+                        let _ = sender.send(Ok(server.client_address().to_string()));
+                        main_loop.run();
+                        server.stop();
+                    });
+                })
+                .expect("Failed to spawn mock bus thread")
+        };
+
+        let address = receiver
+            .recv()
+            .expect("Mock bus thread exited before starting the server")?;
+        Ok(Self {
+            address,
+            context,
+            main_loop,
+            thread: Some(thread),
+            state,
+        })
+    }
+
+    /// The address clients connect to.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Opens a new client connection to this bus.
+    ///
+    /// Returns once the server side of the connection is set up, so all
+    /// objects are reachable and signals emitted afterwards are received.
+    pub fn connect(&self) -> Result<DBusConnection, glib::Error> {
+        let connection = DBusConnection::for_address_sync(
+            &self.address,
+            DBusConnectionFlags::AUTHENTICATION_CLIENT,
+            None,
+            None::<&Cancellable>,
+        )?;
+
+        // The server only starts processing messages once it has set up its
+        // side of the connection, so a reply to a ping means it is ready.
+        connection.call_sync(
+            None,
+            "/",
+            "org.freedesktop.DBus.Peer",
+            "Ping",
+            None,
+            None,
+            DBusCallFlags::NONE,
+            -1,
+            None::<&Cancellable>,
+        )?;
+        Ok(connection)
+    }
+
+    /// Exports a fake object at `object_path` implementing the single
+    /// interface described by the introspection document `xml`.
+    ///
+    /// The object is available on all current and future client connections.
+    /// Methods without a scripted reply fail with
+    /// `org.freedesktop.DBus.Error.Failed`, and reading a property that was
+    /// never set fails with `org.freedesktop.DBus.Error.InvalidArgs`.
+    pub fn add_object(&self, object_path: &str, xml: &str) -> Result<MockObject, glib::Error> {
+        let node = DBusNodeInfo::for_xml(xml)?;
+        let interface_name = match node.interfaces().as_slice() {
+            [interface] => interface.name().to_owned(),
+            _ => {
+                return Err(glib::Error::new(
+                    crate::IOErrorEnum::InvalidArgument,
+                    "Introspection data must describe exactly one interface",
+                ))
+            }
+        };
+
+        let object = Arc::new(ObjectState {
+            object_path: object_path.to_owned(),
+            interface_name,
+            xml: xml.to_owned(),
+            replies: Mutex::new(HashMap::new()),
+            properties: Mutex::new(HashMap::new()),
+            calls: Mutex::new(Vec::new()),
+        });
+
+        // Objects have to be registered from the bus thread so that their
+        // callbacks are dispatched there.
+        let (sender, receiver) = channel();
+        let state = self.state.clone();
+        let new_object = object.clone();
+        self.context.invoke(move || {
+            let mut result = Ok(());
+            for connection in state.connections.lock().unwrap().iter() {
+                if let Err(err) = register_object(connection, &new_object) {
+                    result = Err(err);
+                    break;
+                }
+            }
+            if result.is_ok() {
+                state.objects.lock().unwrap().push(new_object);
+            }
+            let _ = sender.send(result);
+        });
+        receiver
+            .recv()
+            .expect("Mock bus thread exited while registering an object")?;
+
+        Ok(MockObject {
+            object,
+            state: self.state.clone(),
+        })
+    }
+}
+
+impl Drop for MockBus {
+    fn drop(&mut self) {
+        self.main_loop.quit();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.state.connections.lock().unwrap().clear();
+    }
+}
+
+/// A fake object exported on a [`MockBus`].
+#[derive(Clone)]
+pub struct MockObject {
+    object: Arc<ObjectState>,
+    state: Arc<BusState>,
+}
+
+impl MockObject {
+    pub fn object_path(&self) -> &str {
+        &self.object.object_path
+    }
+
+    pub fn interface_name(&self) -> &str {
+        &self.object.interface_name
+    }
+
+    /// Answers every call of `method_name` with `parameters`, which must be a
+    /// tuple matching the method's out arguments, or `None` if it has none.
+    pub fn reply(&self, method_name: &str, parameters: Option<&Variant>) {
+        self.set_reply(method_name, Reply::Value(parameters.cloned()));
+    }
+
+    /// Answers every call of `method_name` with the D-Bus error `error_name`.
+    pub fn reply_error(&self, method_name: &str, error_name: &str, message: &str) {
+        self.set_reply(
+            method_name,
+            Reply::Error(error_name.to_owned(), message.to_owned()),
+        );
+    }
+
+    /// Answers calls of `method_name` with the result of `handler`, which is
+    /// called on the bus thread with the call's arguments.
+    pub fn reply_with<F>(&self, method_name: &str, handler: F)
+    where
+        F: Fn(&Variant) -> Result<Option<Variant>, glib::Error> + Send + Sync + 'static,
+    {
+        self.set_reply(method_name, Reply::Handler(Arc::new(handler)));
+    }
+
+    fn set_reply(&self, method_name: &str, reply: Reply) {
+        self.object
+            .replies
+            .lock()
+            .unwrap()
+            .insert(method_name.to_owned(), reply);
+    }
+
+    /// All calls received so far, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.object.calls.lock().unwrap().clone()
+    }
+
+    /// All calls of `method_name` received so far, oldest first.
+    pub fn calls_to(&self, method_name: &str) -> Vec<MockCall> {
+        self.object
+            .calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| call.method_name == method_name)
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets all recorded calls.
+    pub fn clear_calls(&self) {
+        self.object.calls.lock().unwrap().clear();
+    }
+
+    /// Current value of a property, as last set by the test or a client.
+    pub fn property(&self, name: &str) -> Option<Variant> {
+        self.object.properties.lock().unwrap().get(name).cloned()
+    }
+
+    /// Sets a property without notifying clients.
+    pub fn set_property(&self, name: &str, value: &Variant) {
+        self.object
+            .properties
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), value.clone());
+    }
+
+    /// Sets a property and emits `org.freedesktop.DBus.Properties.PropertiesChanged`.
+    pub fn update_property(&self, name: &str, value: &Variant) -> Result<(), glib::Error> {
+        self.set_property(name, value);
+        for connection in self.state.connections.lock().unwrap().iter() {
+            crate::dbus_codegen::emit_properties_changed(
+                connection,
+                &self.object.object_path,
+                &self.object.interface_name,
+                &[(name, value.clone())],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Emits `signal_name` to all connected clients.
+    pub fn emit_signal(
+        &self,
+        signal_name: &str,
+        parameters: Option<&Variant>,
+    ) -> Result<(), glib::Error> {
+        for connection in self.state.connections.lock().unwrap().iter() {
+            connection.emit_signal(
+                None,
+                &self.object.object_path,
+                &self.object.interface_name,
+                signal_name,
+                parameters,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn start_server(state: &Arc<BusState>) -> Result<DBusServer, glib::Error> {
+    let address = format!(
+        "unix:tmpdir={}",
+        crate::dbus_address_escape_value(&std::env::temp_dir().to_string_lossy())
+    );
+    let server = DBusServer::new_sync(
+        &address,
+        DBusServerFlags::AUTHENTICATION_ALLOW_ANONYMOUS,
+        &crate::dbus_generate_guid(),
+        None,
+        None::<&Cancellable>,
+    )?;
+
+    let state = state.clone();
+    server.connect_new_connection(move |_, connection| {
+        for object in state.objects.lock().unwrap().iter() {
+            if let Err(err) = register_object(connection, object) {
+                glib::g_critical!("gio-mock-bus", "Failed to register mock object: {}", err);
+            }
+        }
+        state.connections.lock().unwrap().push(connection.clone());
+        true
+    });
+    server.start();
+
+    Ok(server)
+}
+
+fn register_object(
+    connection: &DBusConnection,
+    object: &Arc<ObjectState>,
+) -> Result<RegistrationId, glib::Error> {
+    let info = DBusNodeInfo::for_xml(&object.xml)?
+        .lookup_interface(&object.interface_name)
+        .expect("interface disappeared from introspection data");
+
+    let method_object = object.clone();
+    let get_object = object.clone();
+    let set_object = object.clone();
+    connection.register_object_fallible(
+        &object.object_path,
+        &info,
+        move |sender, method_name, parameters, invocation| {
+            handle_method_call(
+                &method_object,
+                MockCall {
+                    sender: sender.map(ToOwned::to_owned),
+                    object_path: method_object.object_path.clone(),
+                    interface_name: method_object.interface_name.clone(),
+                    method_name: method_name.to_owned(),
+                    parameters,
+                },
+                invocation,
+            )
+        },
+        move |property_name| {
+            get_object
+                .properties
+                .lock()
+                .unwrap()
+                .get(property_name)
+                .cloned()
+                .ok_or_else(|| {
+                    crate::dbus_codegen::dbus_error(
+                        "org.freedesktop.DBus.Error.InvalidArgs",
+                        &format!("Property {} is not set", property_name),
+                    )
+                })
+        },
+        move |property_name, value| {
+            set_object
+                .properties
+                .lock()
+                .unwrap()
+                .insert(property_name.to_owned(), value);
+            Ok(())
+        },
+    )
+}
+
+fn handle_method_call(object: &ObjectState, call: MockCall, invocation: DBusMethodInvocation) {
+    let method_name = call.method_name.clone();
+    let parameters = call.parameters.clone();
+    object.calls.lock().unwrap().push(call);
+
+    // Cloned out of the lock so that handlers may script further replies.
+    let reply = object.replies.lock().unwrap().get(&method_name).cloned();
+    match reply {
+        Some(Reply::Value(value)) => invocation.return_value(value.as_ref()),
+        Some(Reply::Error(name, message)) => invocation.return_dbus_error(&name, &message),
+        Some(Reply::Handler(handler)) => match handler(&parameters) {
+            Ok(value) => invocation.return_value(value.as_ref()),
+            Err(err) => invocation.return_gerror(err),
+        },
+        None => invocation.return_dbus_error(
+            "org.freedesktop.DBus.Error.Failed",
+            &format!("No reply scripted for {}", method_name),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run_async_local;
+    use crate::DBusSignalFlags;
+    use glib::ToVariant;
+
+    const XML: &str = r#"
+<node>
+  <interface name="org.example.Player">
+    <method name="Play"/>
+    <method name="Seek">
+      <arg name="offset" type="x" direction="in"/>
+      <arg name="position" type="x" direction="out"/>
+    </method>
+    <signal name="Seeked">
+      <arg name="position" type="x"/>
+    </signal>
+    <property name="Volume" type="d" access="readwrite"/>
+  </interface>
+</node>"#;
+
+    fn call(
+        connection: &DBusConnection,
+        method: &str,
+        parameters: Option<&Variant>,
+    ) -> Result<Variant, glib::Error> {
+        connection.call_sync(
+            None,
+            "/org/example/Player",
+            "org.example.Player",
+            method,
+            parameters,
+            None,
+            DBusCallFlags::NONE,
+            -1,
+            None::<&Cancellable>,
+        )
+    }
+
+    #[test]
+    fn scripted_replies_and_recorded_calls() {
+        let bus = MockBus::new().unwrap();
+        let player = bus.add_object("/org/example/Player", XML).unwrap();
+        let connection = bus.connect().unwrap();
+
+        assert!(call(&connection, "Play", None).is_err());
+        player.reply("Play", None);
+        call(&connection, "Play", None).unwrap();
+
+        player.reply_with("Seek", |parameters| {
+            let (offset,) = parameters.get::<(i64,)>().unwrap();
+            Ok(Some((100 + offset,).to_variant()))
+        });
+        let reply = call(&connection, "Seek", Some(&(20i64,).to_variant())).unwrap();
+        assert_eq!(reply.get::<(i64,)>(), Some((120,)));
+
+        player.reply_error("Seek", "org.example.Error.Busy", "busy");
+        assert!(call(&connection, "Seek", Some(&(1i64,).to_variant())).is_err());
+
+        assert_eq!(player.calls().len(), 4);
+        let seeks = player.calls_to("Seek");
+        assert_eq!(seeks.len(), 2);
+        assert_eq!(seeks[0].parameters.get::<(i64,)>(), Some((20,)));
+        assert_eq!(seeks[0].object_path, "/org/example/Player");
+
+        player.clear_calls();
+        assert!(player.calls().is_empty());
+    }
+
+    #[test]
+    fn properties() {
+        let bus = MockBus::new().unwrap();
+        let player = bus.add_object("/org/example/Player", XML).unwrap();
+        player.set_property("Volume", &0.5.to_variant());
+
+        let connection = bus.connect().unwrap();
+        let get = |connection: &DBusConnection| {
+            connection
+                .call_sync(
+                    None,
+                    "/org/example/Player",
+                    "org.freedesktop.DBus.Properties",
+                    "Get",
+                    Some(&("org.example.Player", "Volume").to_variant()),
+                    None,
+                    DBusCallFlags::NONE,
+                    -1,
+                    None::<&Cancellable>,
+                )
+                .unwrap()
+                .child_value(0)
+                .as_variant()
+                .unwrap()
+                .get::<f64>()
+        };
+        assert_eq!(get(&connection), Some(0.5));
+
+        connection
+            .call_sync(
+                None,
+                "/org/example/Player",
+                "org.freedesktop.DBus.Properties",
+                "Set",
+                Some(&("org.example.Player", "Volume", 0.8.to_variant()).to_variant()),
+                None,
+                DBusCallFlags::NONE,
+                -1,
+                None::<&Cancellable>,
+            )
+            .unwrap();
+        assert_eq!(player.property("Volume").unwrap().get::<f64>(), Some(0.8));
+    }
+
+    #[test]
+    fn unset_property() {
+        let bus = MockBus::new().unwrap();
+        bus.add_object("/org/example/Player", XML).unwrap();
+        let connection = bus.connect().unwrap();
+
+        let err = connection
+            .call_sync(
+                None,
+                "/org/example/Player",
+                "org.freedesktop.DBus.Properties",
+                "Get",
+                Some(&("org.example.Player", "Volume").to_variant()),
+                None,
+                DBusCallFlags::NONE,
+                -1,
+                None::<&Cancellable>,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("org.freedesktop.DBus.Error.InvalidArgs"));
+    }
+
+    #[test]
+    fn signals() {
+        let bus = MockBus::new().unwrap();
+        let player = bus.add_object("/org/example/Player", XML).unwrap();
+        let connection = bus.connect().unwrap();
+
+        // The subscription doesn't keep the connection alive.
+        let subscriber = connection.clone();
+        let position = run_async_local(move |tx, l| {
+            let tx = Mutex::new(Some(tx));
+            subscriber.signal_subscribe(
+                None,
+                Some("org.example.Player"),
+                Some("Seeked"),
+                None,
+                None,
+                DBusSignalFlags::NONE,
+                move |_, _, _, _, _, parameters| {
+                    if let Some(tx) = tx.lock().unwrap().take() {
+                        tx.send(parameters.get::<(i64,)>().unwrap().0).unwrap();
+                        l.quit();
+                    }
+                },
+            );
+            player
+                .emit_signal("Seeked", Some(&(42i64,).to_variant()))
+                .unwrap();
+        });
+        assert_eq!(position, 42);
+    }
+
+    #[test]
+    fn objects_added_after_connecting() {
+        let bus = MockBus::new().unwrap();
+        let connection = bus.connect().unwrap();
+        assert!(call(&connection, "Play", None).is_err());
+
+        let player = bus.add_object("/org/example/Player", XML).unwrap();
+        player.reply("Play", None);
+        call(&connection, "Play", None).unwrap();
+
+        assert!(bus.add_object("/org/example/Player", XML).is_err());
+        assert!(bus.add_object("/org/example/Other", "<node/>").is_err());
+    }
+}
@@ -24,13 +24,17 @@ mod converter;
 mod data_input_stream;
 mod dbus;
 pub use self::dbus::*;
+pub mod dbus_codegen;
 mod dbus_connection;
 pub use self::dbus_connection::{
     ActionGroupExportId, FilterId, MenuModelExportId, RegistrationId, SignalSubscriptionId,
     WatcherId,
 };
+mod dbus_introspection;
 mod dbus_message;
 mod dbus_method_invocation;
+#[cfg(any(feature = "dbus-mock", feature = "dox", test))]
+pub mod dbus_mock;
 #[cfg(any(all(not(windows), not(target_os = "macos")), feature = "dox"))]
 mod desktop_app_info;
 mod error;
@@ -63,10 +67,20 @@ mod resource;
 pub use crate::resource::{compile_resources, resources_register_include_impl};
 mod settings;
 pub use crate::settings::BindingBuilder;
+mod settings_backend;
+pub use crate::settings_backend::SettingsBackendOriginTag;
 mod socket;
 mod subprocess;
 mod subprocess_launcher;
+mod subprocess_pipeline;
+pub use crate::subprocess_pipeline::{
+    PipelineLines, PipelineStage, RunningPipeline, StageStatus, SubprocessPipeline,
+};
 mod threaded_socket_service;
+#[cfg(any(feature = "toml", feature = "dox"))]
+mod toml_settings_backend;
+#[cfg(any(feature = "toml", feature = "dox"))]
+pub use crate::toml_settings_backend::TomlSettingsBackend;
 #[cfg(any(unix, feature = "dox"))]
 mod unix_fd_list;
 #[cfg(any(unix, feature = "dox"))]
@@ -168,7 +168,7 @@ impl<O: IsA<Settings>> SettingsExtManual for O {
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
     use std::env::set_var;
     use std::process::Command;
@@ -177,7 +177,7 @@ mod test {
 
     static INIT: Once = Once::new();
 
-    fn set_env() {
+    pub(crate) fn set_env() {
         INIT.call_once(|| {
             let output = Command::new("glib-compile-schemas")
                 .args(&[
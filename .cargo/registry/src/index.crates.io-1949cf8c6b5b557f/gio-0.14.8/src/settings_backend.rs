@@ -0,0 +1,93 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::SettingsBackend;
+use glib::object::IsA;
+use glib::translate::*;
+use std::ptr;
+
+/// Opaque token identifying the [`Settings`](crate::Settings) instance that
+/// caused a write.
+///
+/// Backends receive it in their write and reset implementations and should
+/// pass it back when emitting the resulting change notification, so that the
+/// originating `Settings` can tell its own changes apart from external ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettingsBackendOriginTag(glib::ffi::gpointer);
+
+// The tag is only ever compared, never dereferenced.
+unsafe impl Send for SettingsBackendOriginTag {}
+unsafe impl Sync for SettingsBackendOriginTag {}
+
+impl SettingsBackendOriginTag {
+    #[doc(hidden)]
+    pub unsafe fn from_raw(ptr: glib::ffi::gpointer) -> Self {
+        Self(ptr)
+    }
+
+    #[doc(hidden)]
+    pub fn as_raw(&self) -> glib::ffi::gpointer {
+        self.0
+    }
+}
+
+fn origin_tag_ptr(origin_tag: Option<SettingsBackendOriginTag>) -> glib::ffi::gpointer {
+    origin_tag.map_or(ptr::null_mut(), |tag| tag.0)
+}
+
+pub trait SettingsBackendExtManual: 'static {
+    /// Signals that the value of `key` changed.
+    #[doc(alias = "g_settings_backend_changed")]
+    fn changed(&self, key: &str, origin_tag: Option<SettingsBackendOriginTag>);
+
+    /// Signals that the given keys below `path` changed. `path` must end with
+    /// a slash and `items` are relative to it.
+    #[doc(alias = "g_settings_backend_keys_changed")]
+    fn keys_changed(
+        &self,
+        path: &str,
+        items: &[&str],
+        origin_tag: Option<SettingsBackendOriginTag>,
+    );
+
+    /// Signals that any key below `path` may have changed.
+    #[doc(alias = "g_settings_backend_path_changed")]
+    fn path_changed(&self, path: &str, origin_tag: Option<SettingsBackendOriginTag>);
+}
+
+impl<O: IsA<SettingsBackend>> SettingsBackendExtManual for O {
+    fn changed(&self, key: &str, origin_tag: Option<SettingsBackendOriginTag>) {
+        unsafe {
+            ffi::g_settings_backend_changed(
+                self.as_ref().to_glib_none().0,
+                key.to_glib_none().0,
+                origin_tag_ptr(origin_tag),
+            );
+        }
+    }
+
+    fn keys_changed(
+        &self,
+        path: &str,
+        items: &[&str],
+        origin_tag: Option<SettingsBackendOriginTag>,
+    ) {
+        unsafe {
+            ffi::g_settings_backend_keys_changed(
+                self.as_ref().to_glib_none().0,
+                path.to_glib_none().0,
+                items.to_glib_none().0,
+                origin_tag_ptr(origin_tag),
+            );
+        }
+    }
+
+    fn path_changed(&self, path: &str, origin_tag: Option<SettingsBackendOriginTag>) {
+        unsafe {
+            ffi::g_settings_backend_path_changed(
+                self.as_ref().to_glib_none().0,
+                path.to_glib_none().0,
+                origin_tag_ptr(origin_tag),
+            );
+        }
+    }
+}
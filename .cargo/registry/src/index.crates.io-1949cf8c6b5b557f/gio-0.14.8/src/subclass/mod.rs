@@ -9,6 +9,7 @@ mod io_stream;
 mod list_model;
 mod output_stream;
 mod seekable;
+mod settings_backend;
 
 pub use self::application::ArgumentList;
 
@@ -25,4 +26,5 @@ pub mod prelude {
     pub use super::list_model::{ListModelImpl, ListModelImplExt};
     pub use super::output_stream::{OutputStreamImpl, OutputStreamImplExt};
     pub use super::seekable::{SeekableImpl, SeekableImplExt};
+    pub use super::settings_backend::{SettingsBackendImpl, SettingsBackendImplExt};
 }
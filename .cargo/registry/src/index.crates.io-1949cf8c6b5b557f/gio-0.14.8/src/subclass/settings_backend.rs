@@ -0,0 +1,302 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::subclass::prelude::*;
+use glib::translate::*;
+
+use glib::{Cast, Variant, VariantTy};
+
+use crate::SettingsBackend;
+use crate::SettingsBackendOriginTag;
+
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::ptr;
+
+pub trait SettingsBackendImpl: ObjectImpl + SettingsBackendImplExt + Send + Sync {
+    /// Reads the value of `key`, or `None` if the backend has no value for it
+    /// and the schema default should be used.
+    ///
+    /// The returned value must be of `expected_type`. If `default_value` is
+    /// `true`, only a system-wide default value should be returned.
+    fn read(
+        &self,
+        backend: &Self::Type,
+        key: &str,
+        expected_type: &VariantTy,
+        default_value: bool,
+    ) -> Option<Variant>;
+
+    /// Whether `key` can be written.
+    fn writable(&self, backend: &Self::Type, key: &str) -> bool;
+
+    /// Writes `value` to `key` and emits a change notification for it with
+    /// `origin_tag`. Returns `false` if the key could not be written.
+    fn write(
+        &self,
+        backend: &Self::Type,
+        key: &str,
+        value: &Variant,
+        origin_tag: SettingsBackendOriginTag,
+    ) -> bool;
+
+    /// Writes a set of changes at once. Keys are absolute and a value of
+    /// `None` resets the key.
+    ///
+    /// The default implementation applies the changes one by one with
+    /// [`write`](Self::write) and [`reset`](Self::reset), so it is not atomic.
+    fn write_tree(
+        &self,
+        backend: &Self::Type,
+        tree: &BTreeMap<String, Option<Variant>>,
+        origin_tag: SettingsBackendOriginTag,
+    ) -> bool {
+        let mut success = true;
+        for (key, value) in tree {
+            match value {
+                Some(value) => success &= self.write(backend, key, value, origin_tag),
+                None => self.reset(backend, key, origin_tag),
+            }
+        }
+        success
+    }
+
+    /// Removes the value of `key` so that the default value applies again.
+    fn reset(&self, backend: &Self::Type, key: &str, origin_tag: SettingsBackendOriginTag);
+
+    /// Called when a [`Settings`](crate::Settings) starts watching `name`,
+    /// which is a key or a path ending in a slash.
+    fn subscribe(&self, backend: &Self::Type, name: &str) {
+        self.parent_subscribe(backend, name)
+    }
+
+    fn unsubscribe(&self, backend: &Self::Type, name: &str) {
+        self.parent_unsubscribe(backend, name)
+    }
+
+    /// Flushes any pending writes.
+    fn sync(&self, backend: &Self::Type) {
+        self.parent_sync(backend)
+    }
+}
+
+pub trait SettingsBackendImplExt: ObjectSubclass {
+    fn parent_subscribe(&self, backend: &Self::Type, name: &str);
+    fn parent_unsubscribe(&self, backend: &Self::Type, name: &str);
+    fn parent_sync(&self, backend: &Self::Type);
+}
+
+impl<T: SettingsBackendImpl> SettingsBackendImplExt for T {
+    fn parent_subscribe(&self, backend: &Self::Type, name: &str) {
+        unsafe {
+            let data = T::type_data();
+            let parent_class = data.as_ref().parent_class() as *mut ffi::GSettingsBackendClass;
+            if let Some(f) = (*parent_class).subscribe {
+                f(
+                    backend
+                        .unsafe_cast_ref::<SettingsBackend>()
+                        .to_glib_none()
+                        .0,
+                    name.to_glib_none().0,
+                )
+            }
+        }
+    }
+
+    fn parent_unsubscribe(&self, backend: &Self::Type, name: &str) {
+        unsafe {
+            let data = T::type_data();
+            let parent_class = data.as_ref().parent_class() as *mut ffi::GSettingsBackendClass;
+            if let Some(f) = (*parent_class).unsubscribe {
+                f(
+                    backend
+                        .unsafe_cast_ref::<SettingsBackend>()
+                        .to_glib_none()
+                        .0,
+                    name.to_glib_none().0,
+                )
+            }
+        }
+    }
+
+    fn parent_sync(&self, backend: &Self::Type) {
+        unsafe {
+            let data = T::type_data();
+            let parent_class = data.as_ref().parent_class() as *mut ffi::GSettingsBackendClass;
+            if let Some(f) = (*parent_class).sync {
+                f(backend
+                    .unsafe_cast_ref::<SettingsBackend>()
+                    .to_glib_none()
+                    .0)
+            }
+        }
+    }
+}
+
+unsafe impl<T: SettingsBackendImpl> IsSubclassable<T> for SettingsBackend {
+    fn class_init(class: &mut ::glib::Class<Self>) {
+        <glib::Object as IsSubclassable<T>>::class_init(class);
+
+        let klass = class.as_mut();
+        klass.read = Some(backend_read::<T>);
+        klass.get_writable = Some(backend_get_writable::<T>);
+        klass.write = Some(backend_write::<T>);
+        klass.write_tree = Some(backend_write_tree::<T>);
+        klass.reset = Some(backend_reset::<T>);
+        klass.subscribe = Some(backend_subscribe::<T>);
+        klass.unsubscribe = Some(backend_unsubscribe::<T>);
+        klass.sync = Some(backend_sync::<T>);
+    }
+
+    fn instance_init(instance: &mut glib::subclass::InitializingObject<T>) {
+        <glib::Object as IsSubclassable<T>>::instance_init(instance);
+    }
+}
+
+unsafe extern "C" fn backend_read<T: SettingsBackendImpl>(
+    ptr: *mut ffi::GSettingsBackend,
+    key: *const libc::c_char,
+    expected_type: *const glib::ffi::GVariantType,
+    default_value: glib::ffi::gboolean,
+) -> *mut glib::ffi::GVariant {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    imp.read(
+        wrap.unsafe_cast_ref(),
+        &glib::GString::from_glib_borrow(key),
+        VariantTy::from_ptr(expected_type),
+        from_glib(default_value),
+    )
+    .map_or(ptr::null_mut(), |value| value.to_glib_full())
+}
+
+unsafe extern "C" fn backend_get_writable<T: SettingsBackendImpl>(
+    ptr: *mut ffi::GSettingsBackend,
+    key: *const libc::c_char,
+) -> glib::ffi::gboolean {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    imp.writable(
+        wrap.unsafe_cast_ref(),
+        &glib::GString::from_glib_borrow(key),
+    )
+    .into_glib()
+}
+
+unsafe extern "C" fn backend_write<T: SettingsBackendImpl>(
+    ptr: *mut ffi::GSettingsBackend,
+    key: *const libc::c_char,
+    value: *mut glib::ffi::GVariant,
+    origin_tag: glib::ffi::gpointer,
+) -> glib::ffi::gboolean {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    imp.write(
+        wrap.unsafe_cast_ref(),
+        &glib::GString::from_glib_borrow(key),
+        &Variant::from_glib_borrow(value),
+        SettingsBackendOriginTag::from_raw(origin_tag),
+    )
+    .into_glib()
+}
+
+unsafe extern "C" fn backend_write_tree<T: SettingsBackendImpl>(
+    ptr: *mut ffi::GSettingsBackend,
+    tree: *mut glib::ffi::GTree,
+    origin_tag: glib::ffi::gpointer,
+) -> glib::ffi::gboolean {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    let mut path = ptr::null_mut();
+    let mut keys = ptr::null_mut();
+    let mut values = ptr::null_mut();
+    ffi::g_settings_backend_flatten_tree(tree, &mut path, &mut keys, &mut values);
+    let path: glib::GString = from_glib_full(path);
+
+    // The key strings and values are owned by the tree, only the arrays
+    // themselves have to be freed.
+    let mut changes = BTreeMap::new();
+    for i in 0.. {
+        let key = *keys.add(i);
+        if key.is_null() {
+            break;
+        }
+        let value = *values.add(i);
+        changes.insert(
+            format!("{}{}", path, CStr::from_ptr(key).to_string_lossy()),
+            if value.is_null() {
+                None
+            } else {
+                Some(from_glib_none(value))
+            },
+        );
+    }
+    glib::ffi::g_free(keys as glib::ffi::gpointer);
+    glib::ffi::g_free(values as glib::ffi::gpointer);
+
+    imp.write_tree(
+        wrap.unsafe_cast_ref(),
+        &changes,
+        SettingsBackendOriginTag::from_raw(origin_tag),
+    )
+    .into_glib()
+}
+
+unsafe extern "C" fn backend_reset<T: SettingsBackendImpl>(
+    ptr: *mut ffi::GSettingsBackend,
+    key: *const libc::c_char,
+    origin_tag: glib::ffi::gpointer,
+) {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    imp.reset(
+        wrap.unsafe_cast_ref(),
+        &glib::GString::from_glib_borrow(key),
+        SettingsBackendOriginTag::from_raw(origin_tag),
+    )
+}
+
+unsafe extern "C" fn backend_subscribe<T: SettingsBackendImpl>(
+    ptr: *mut ffi::GSettingsBackend,
+    name: *const libc::c_char,
+) {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    imp.subscribe(
+        wrap.unsafe_cast_ref(),
+        &glib::GString::from_glib_borrow(name),
+    )
+}
+
+unsafe extern "C" fn backend_unsubscribe<T: SettingsBackendImpl>(
+    ptr: *mut ffi::GSettingsBackend,
+    name: *const libc::c_char,
+) {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    imp.unsubscribe(
+        wrap.unsafe_cast_ref(),
+        &glib::GString::from_glib_borrow(name),
+    )
+}
+
+unsafe extern "C" fn backend_sync<T: SettingsBackendImpl>(ptr: *mut ffi::GSettingsBackend) {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.impl_();
+    let wrap: Borrowed<SettingsBackend> = from_glib_borrow(ptr);
+
+    imp.sync(wrap.unsafe_cast_ref())
+}
@@ -0,0 +1,621 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::prelude::*;
+use crate::{
+    Cancellable, DataInputStream, IOErrorEnum, InputStream, OutputStreamSpliceFlags, Subprocess,
+    SubprocessFlags, SubprocessLauncher,
+};
+use futures_core::stream::Stream;
+use glib::translate::*;
+use std::boxed::Box as Box_;
+use std::cell::Cell;
+use std::ffi::{OsStr, OsString};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::ptr;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// A single command of a [`SubprocessPipeline`].
+#[derive(Debug, Clone)]
+pub struct PipelineStage {
+    argv: Vec<OsString>,
+    env: Vec<(OsString, Option<OsString>)>,
+    cwd: Option<PathBuf>,
+    flags: SubprocessFlags,
+}
+
+impl PipelineStage {
+    pub fn new<S: AsRef<OsStr>>(argv: &[S]) -> Self {
+        assert!(!argv.is_empty(), "Pipeline stage needs a command");
+        Self {
+            argv: argv.iter().map(|arg| arg.as_ref().to_owned()).collect(),
+            env: Vec::new(),
+            cwd: None,
+            flags: SubprocessFlags::NONE,
+        }
+    }
+
+    /// Sets an environment variable for this stage only.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.env
+            .push((key.as_ref().to_owned(), Some(value.as_ref().to_owned())));
+        self
+    }
+
+    /// Removes an inherited environment variable for this stage only.
+    pub fn unsetenv<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.env.push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    /// Sets the working directory of this stage.
+    pub fn cwd<P: Into<PathBuf>>(mut self, cwd: P) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Sets additional flags for this stage.
+    ///
+    /// Only the `STDERR_*` flags and `INHERIT_FDS` are used, standard input
+    /// and output are managed by the pipeline. At most one of the `STDERR_*`
+    /// flags may be set.
+    pub fn flags(mut self, flags: SubprocessFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn argv(&self) -> &[OsString] {
+        &self.argv
+    }
+}
+
+impl<S: AsRef<OsStr>> From<&[S]> for PipelineStage {
+    fn from(argv: &[S]) -> Self {
+        Self::new(argv)
+    }
+}
+
+/// How a stage of a [`RunningPipeline`] terminated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageStatus {
+    /// The process exited normally with the given exit code.
+    Exited(i32),
+    /// The process was terminated by the given signal.
+    Signaled(i32),
+}
+
+impl StageStatus {
+    pub fn success(&self) -> bool {
+        *self == StageStatus::Exited(0)
+    }
+
+    fn from_subprocess(subprocess: &Subprocess) -> Self {
+        if subprocess.has_signaled() {
+            StageStatus::Signaled(subprocess.term_sig())
+        } else {
+            StageStatus::Exited(subprocess.exit_status())
+        }
+    }
+}
+
+/// Builder for a chain of subprocesses where each stage's standard output is
+/// connected to the next stage's standard input, like a shell pipeline.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), glib::Error> {
+/// use futures_util::StreamExt;
+/// use gio::{PipelineStage, SubprocessPipeline};
+/// use std::time::Duration;
+///
+/// let pipeline = SubprocessPipeline::new()
+///     .command(&["pactl", "subscribe"])
+///     .stage(PipelineStage::new(&["grep", "--line-buffered", "sink"]).env("LC_ALL", "C"))
+///     .timeout(Duration::from_secs(60))
+///     .spawn()?;
+///
+/// let mut lines = pipeline.lines();
+/// while let Some(line) = lines.next().await {
+///     println!("{}", line?);
+/// }
+/// let statuses = pipeline.wait_future().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SubprocessPipeline {
+    stages: Vec<PipelineStage>,
+    timeout: Option<Duration>,
+    kill_on_drop: bool,
+}
+
+impl Default for SubprocessPipeline {
+    fn default() -> Self {
+        Self {
+            stages: Vec::new(),
+            timeout: None,
+            kill_on_drop: true,
+        }
+    }
+}
+
+impl SubprocessPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a stage running `argv` with the inherited environment.
+    pub fn command<S: AsRef<OsStr>>(self, argv: &[S]) -> Self {
+        self.stage(PipelineStage::new(argv))
+    }
+
+    /// Appends a stage.
+    pub fn stage(mut self, stage: PipelineStage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Kills all stages if the pipeline is still running `timeout` after it
+    /// was spawned.
+    ///
+    /// The timeout is enforced while the pipeline's output or exit status is
+    /// being awaited, which then fails with [`IOErrorEnum::TimedOut`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Whether to kill all stages that are still running once the
+    /// [`RunningPipeline`] and all streams and futures created from it are
+    /// dropped. Defaults to `true`.
+    pub fn kill_on_drop(mut self, kill_on_drop: bool) -> Self {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+
+    /// Spawns all stages.
+    ///
+    /// The first stage reads its standard input from `/dev/null` and the
+    /// last stage's standard output is available from the returned
+    /// [`RunningPipeline`]. It has to be consumed, otherwise the pipeline
+    /// stalls once the pipe buffer is full.
+    ///
+    /// Data is copied between stages on the thread-default main context of
+    /// the calling thread, which has to be running for the pipeline to make
+    /// progress.
+    pub fn spawn(&self) -> Result<RunningPipeline, glib::Error> {
+        if self.stages.is_empty() {
+            return Err(glib::Error::new(
+                IOErrorEnum::InvalidArgument,
+                "Pipeline has no stages",
+            ));
+        }
+
+        let stderr_flags = SubprocessFlags::STDERR_PIPE
+            | SubprocessFlags::STDERR_SILENCE
+            | SubprocessFlags::STDERR_MERGE;
+        if let Some(stage) = self
+            .stages
+            .iter()
+            .find(|stage| (stage.flags & stderr_flags).bits().count_ones() > 1)
+        {
+            return Err(glib::Error::new(
+                IOErrorEnum::InvalidArgument,
+                &format!(
+                    "Stage {:?} has conflicting standard error flags",
+                    stage.argv[0]
+                ),
+            ));
+        }
+
+        let stage_flags = stderr_flags | SubprocessFlags::INHERIT_FDS;
+        let cancellable = Cancellable::new();
+        let mut subprocesses: Vec<Subprocess> = Vec::with_capacity(self.stages.len());
+
+        for stage in &self.stages {
+            let mut flags = (stage.flags & stage_flags) | SubprocessFlags::STDOUT_PIPE;
+            if !subprocesses.is_empty() {
+                flags |= SubprocessFlags::STDIN_PIPE;
+            }
+
+            let launcher = SubprocessLauncher::new(flags);
+            if let Some(ref cwd) = stage.cwd {
+                launcher.set_cwd(cwd);
+            }
+            for (key, value) in &stage.env {
+                match value {
+                    Some(value) => launcher.setenv(key, value, true),
+                    None => launcher.unsetenv(key),
+                }
+            }
+
+            let argv = stage
+                .argv
+                .iter()
+                .map(|arg| arg.as_os_str())
+                .collect::<Vec<_>>();
+            let subprocess = match launcher.spawn(&argv) {
+                Ok(subprocess) => subprocess,
+                Err(err) => {
+                    cancellable.cancel();
+                    for subprocess in &subprocesses {
+                        subprocess.force_exit();
+                    }
+                    return Err(err);
+                }
+            };
+
+            if let Some(previous) = subprocesses.last() {
+                // Errors here mean that one side exited early, e.g. `head`,
+                // which the stages' exit status reports already.
+                subprocess.stdin_pipe().unwrap().splice_async(
+                    &previous.stdout_pipe().unwrap(),
+                    OutputStreamSpliceFlags::CLOSE_SOURCE | OutputStreamSpliceFlags::CLOSE_TARGET,
+                    glib::PRIORITY_DEFAULT,
+                    Some(&cancellable),
+                    |_| (),
+                );
+            }
+            subprocesses.push(subprocess);
+        }
+
+        let stdout = subprocesses.last().unwrap().stdout_pipe().unwrap();
+        Ok(RunningPipeline {
+            inner: Rc::new(Inner {
+                subprocesses,
+                stdout: DataInputStream::new(&stdout),
+                cancellable,
+                deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+                timed_out: Cell::new(false),
+                kill_on_drop: self.kill_on_drop,
+            }),
+        })
+    }
+}
+
+struct Inner {
+    subprocesses: Vec<Subprocess>,
+    stdout: DataInputStream,
+    cancellable: Cancellable,
+    deadline: Option<Instant>,
+    timed_out: Cell<bool>,
+    kill_on_drop: bool,
+}
+
+impl Inner {
+    fn force_exit(&self) {
+        self.cancellable.cancel();
+        for subprocess in &self.subprocesses {
+            subprocess.force_exit();
+        }
+    }
+
+    fn timeout_future(&self) -> Option<Pin<Box_<dyn Future<Output = ()> + Send + 'static>>> {
+        self.deadline.map(|deadline| {
+            glib::timeout_future(deadline.saturating_duration_since(Instant::now()))
+        })
+    }
+
+    // Polls the timeout, killing the pipeline once it fires.
+    fn poll_timeout(
+        &self,
+        timeout: &mut Option<Pin<Box_<dyn Future<Output = ()> + Send + 'static>>>,
+        cx: &mut Context,
+    ) -> Result<(), glib::Error> {
+        let fired = self.timed_out.get()
+            || match timeout {
+                Some(timeout) => timeout.as_mut().poll(cx).is_ready(),
+                None => false,
+            };
+        if fired {
+            if !self.timed_out.replace(true) {
+                self.force_exit();
+            }
+            Err(glib::Error::new(
+                IOErrorEnum::TimedOut,
+                "Pipeline did not finish in time",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if self.kill_on_drop {
+            self.force_exit();
+        }
+    }
+}
+
+/// A spawned [`SubprocessPipeline`].
+pub struct RunningPipeline {
+    inner: Rc<Inner>,
+}
+
+impl RunningPipeline {
+    /// The subprocesses of all stages, in order.
+    pub fn subprocesses(&self) -> &[Subprocess] {
+        &self.inner.subprocesses
+    }
+
+    /// The standard output of the last stage.
+    pub fn stdout(&self) -> InputStream {
+        self.inner.stdout.clone().upcast()
+    }
+
+    /// Streams the standard output of the last stage line by line, without
+    /// the trailing newline.
+    ///
+    /// Invalid UTF-8 is replaced with U+FFFD REPLACEMENT CHARACTER.
+    ///
+    /// All streams created by this function read from the same output, so
+    /// each line is only returned once.
+    pub fn lines(&self) -> PipelineLines {
+        PipelineLines {
+            inner: self.inner.clone(),
+            timeout: self.inner.timeout_future(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Waits for all stages to terminate and returns their status, in order.
+    pub fn wait_future(
+        &self,
+    ) -> Pin<Box_<dyn Future<Output = Result<Vec<StageStatus>, glib::Error>> + 'static>> {
+        let inner = self.inner.clone();
+        let wait = Box_::pin(async move {
+            let mut statuses = Vec::with_capacity(inner.subprocesses.len());
+            for subprocess in &inner.subprocesses {
+                subprocess.wait_async_future().await?;
+                statuses.push(StageStatus::from_subprocess(subprocess));
+            }
+            Ok(statuses)
+        });
+
+        Box_::pin(WithTimeout {
+            timeout: self.inner.timeout_future(),
+            inner: self.inner.clone(),
+            future: wait,
+        })
+    }
+
+    /// Kills all stages.
+    pub fn force_exit(&self) {
+        self.inner.force_exit();
+    }
+}
+
+struct WithTimeout<T> {
+    inner: Rc<Inner>,
+    timeout: Option<Pin<Box_<dyn Future<Output = ()> + Send + 'static>>>,
+    future: Pin<Box_<dyn Future<Output = Result<T, glib::Error>> + 'static>>,
+}
+
+impl<T> Future for WithTimeout<T> {
+    type Output = Result<T, glib::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Poll::Ready(res) = this.future.as_mut().poll(cx) {
+            return Poll::Ready(res);
+        }
+        this.inner.poll_timeout(&mut this.timeout, cx)?;
+        Poll::Pending
+    }
+}
+
+/// Stream of the output lines of a [`RunningPipeline`].
+pub struct PipelineLines {
+    inner: Rc<Inner>,
+    timeout: Option<Pin<Box_<dyn Future<Output = ()> + Send + 'static>>>,
+    pending: Option<Pin<Box_<dyn Future<Output = Result<Option<Vec<u8>>, glib::Error>> + 'static>>>,
+    done: bool,
+}
+
+impl Stream for PipelineLines {
+    type Item = Result<String, glib::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.done {
+            return Poll::Ready(None);
+        }
+        if let Err(err) = this.inner.poll_timeout(&mut this.timeout, cx) {
+            this.done = true;
+            return Poll::Ready(Some(Err(err)));
+        }
+
+        let stdout = &this.inner.stdout;
+        let pending = this.pending.get_or_insert_with(|| read_line_future(stdout));
+        let res = match pending.as_mut().poll(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(res) => res,
+        };
+        this.pending = None;
+
+        match res {
+            Ok(Some(line)) => Poll::Ready(Some(Ok(String::from_utf8_lossy(&line).into_owned()))),
+            Ok(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Err(err) => {
+                this.done = true;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+// Like `read_line_async_future()`, but tells the end of the stream apart from
+// an empty line.
+fn read_line_future(
+    stream: &DataInputStream,
+) -> Pin<Box_<dyn Future<Output = Result<Option<Vec<u8>>, glib::Error>> + 'static>> {
+    unsafe extern "C" fn read_line_trampoline(
+        source_object: *mut glib::gobject_ffi::GObject,
+        res: *mut ffi::GAsyncResult,
+        user_data: glib::ffi::gpointer,
+    ) {
+        let mut error = ptr::null_mut();
+        let mut length = 0;
+        let ret = ffi::g_data_input_stream_read_line_finish(
+            source_object as *mut _,
+            res,
+            &mut length,
+            &mut error,
+        );
+        let result = if !error.is_null() {
+            Err(from_glib_full(error))
+        } else if ret.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(FromGlibContainer::from_glib_full_num(ret, length)))
+        };
+        let send: Box_<crate::GioFutureResult<Option<Vec<u8>>, glib::Error>> =
+            Box_::from_raw(user_data as *mut _);
+        send.resolve(result);
+    }
+
+    Box_::pin(crate::GioFuture::new(
+        stream,
+        move |stream, cancellable, send| unsafe {
+            ffi::g_data_input_stream_read_line_async(
+                stream.to_glib_none().0,
+                glib::PRIORITY_DEFAULT.into_glib(),
+                cancellable.to_glib_none().0,
+                Some(read_line_trampoline),
+                Box_::into_raw(Box_::new(send)) as *mut _,
+            );
+        },
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_util::run_async_local;
+    use futures_util::StreamExt;
+
+    fn run<T: Send + 'static, F: Future<Output = T> + 'static>(
+        f: impl FnOnce() -> F + Send + 'static,
+    ) -> T {
+        run_async_local(move |tx, l| {
+            glib::MainContext::ref_thread_default().spawn_local(async move {
+                tx.send(f().await).unwrap();
+                l.quit();
+            });
+        })
+    }
+
+    #[test]
+    fn lines_and_statuses() {
+        let (lines, statuses) = run(|| async {
+            let pipeline = SubprocessPipeline::new()
+                .command(&["printf", "one\\ntwo\\nthree\\n"])
+                .command(&["grep", "-v", "two"])
+                .spawn()
+                .unwrap();
+            let lines = pipeline
+                .lines()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+                .await;
+            (lines, pipeline.wait_future().await.unwrap())
+        });
+        assert_eq!(lines, vec!["one", "three"]);
+        assert_eq!(
+            statuses,
+            vec![StageStatus::Exited(0), StageStatus::Exited(0)]
+        );
+    }
+
+    #[test]
+    fn stage_environment() {
+        let (lines, statuses) = run(|| async {
+            let pipeline = SubprocessPipeline::new()
+                .stage(
+                    PipelineStage::new(&["sh", "-c", "echo $GIO_PIPELINE_TEST; pwd; exit 3"])
+                        .env("GIO_PIPELINE_TEST", "hello")
+                        .cwd("/"),
+                )
+                .command(&["cat"])
+                .spawn()
+                .unwrap();
+            let lines = pipeline
+                .lines()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+                .await;
+            (lines, pipeline.wait_future().await.unwrap())
+        });
+        assert_eq!(lines, vec!["hello", "/"]);
+        assert_eq!(
+            statuses,
+            vec![StageStatus::Exited(3), StageStatus::Exited(0)]
+        );
+        assert!(!statuses[0].success());
+    }
+
+    #[test]
+    fn timeout() {
+        let err = run(|| async {
+            let pipeline = SubprocessPipeline::new()
+                .command(&["sleep", "10"])
+                .command(&["cat"])
+                .timeout(Duration::from_millis(50))
+                .spawn()
+                .unwrap();
+            pipeline.wait_future().await.unwrap_err()
+        });
+        assert_eq!(err.kind::<IOErrorEnum>(), Some(IOErrorEnum::TimedOut));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let lines = run(|| async {
+            let pipeline = SubprocessPipeline::new()
+                .command(&["printf", "a\\377b\\n\\nc"])
+                .spawn()
+                .unwrap();
+            pipeline
+                .lines()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+                .await
+        });
+        assert_eq!(lines, vec!["a\u{fffd}b", "", "c"]);
+    }
+
+    #[test]
+    fn stderr_flags() {
+        let err = SubprocessPipeline::new()
+            .stage(
+                PipelineStage::new(&["cat"])
+                    .flags(SubprocessFlags::STDERR_SILENCE | SubprocessFlags::STDERR_MERGE),
+            )
+            .spawn()
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.kind::<IOErrorEnum>(),
+            Some(IOErrorEnum::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn spawn_failure() {
+        assert!(SubprocessPipeline::new()
+            .command(&["cat"])
+            .command(&["/nonexistent/gio-pipeline-test"])
+            .spawn()
+            .is_err());
+        assert!(SubprocessPipeline::new().spawn().is_err());
+    }
+}
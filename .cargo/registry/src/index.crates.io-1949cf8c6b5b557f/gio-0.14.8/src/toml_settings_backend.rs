@@ -0,0 +1,804 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! A [`SettingsBackend`] storing keys in a human-editable TOML file.
+//!
+//! Keys below the backend's root path map to TOML keys, with every further
+//! path component becoming a table:
+//!
+//! ```toml
+//! dark-mode = true
+//! accent = "teal"
+//!
+//! [window]
+//! size = [1280, 800]
+//! ```
+//!
+//! holds `/org/example/App/dark-mode`, `/org/example/App/accent` and
+//! `/org/example/App/window/size` for a backend rooted at `/org/example/App/`.
+//! Booleans, integers, doubles, strings and arrays or string-keyed
+//! dictionaries of those are stored as native TOML values. Any other value,
+//! and any value whose type could not be told from the TOML value when reading
+//! it back such as an `int32` inside an `a{sv}` dictionary, is stored as a
+//! string in GVariant text format with type annotations. That format is also
+//! accepted when reading any key.
+//!
+//! Comments and formatting in the file are not preserved when the backend
+//! writes to it.
+//!
+//! For INI files, use the GKeyfile backend from
+//! [`keyfile_settings_backend_new`](crate::keyfile_settings_backend_new).
+
+use crate::prelude::*;
+use crate::subclass::prelude::*;
+use crate::{
+    File, FileMonitor, FileMonitorEvent, FileMonitorFlags, IOErrorEnum, SettingsBackend,
+    SettingsBackendOriginTag,
+};
+use glib::translate::*;
+use glib::{ToVariant, Variant, VariantTy};
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::{fs, io, ptr};
+
+type Table = toml::value::Table;
+
+mod imp {
+    use super::*;
+    use once_cell::sync::OnceCell;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    pub struct TomlSettingsBackend {
+        pub(super) path: OnceCell<PathBuf>,
+        pub(super) root_path: OnceCell<String>,
+        pub(super) table: Mutex<Table>,
+        // Keys read or written as dictionaries, which are stored as tables
+        // just like path components.
+        pub(super) dictionaries: Mutex<HashSet<String>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TomlSettingsBackend {
+        const NAME: &'static str = "TomlSettingsBackend";
+        type Type = super::TomlSettingsBackend;
+        type ParentType = SettingsBackend;
+    }
+
+    impl ObjectImpl for TomlSettingsBackend {}
+
+    impl SettingsBackendImpl for TomlSettingsBackend {
+        fn read(
+            &self,
+            _backend: &Self::Type,
+            key: &str,
+            expected_type: &VariantTy,
+            default_value: bool,
+        ) -> Option<Variant> {
+            if default_value {
+                return None;
+            }
+            let relative = self.relative_key(key)?;
+            self.note_type(relative, expected_type);
+            let table = self.table.lock().unwrap();
+            lookup(&table, relative).and_then(|value| toml_to_variant(value, expected_type))
+        }
+
+        fn writable(&self, _backend: &Self::Type, key: &str) -> bool {
+            self.relative_key(key).is_some()
+        }
+
+        fn write(
+            &self,
+            backend: &Self::Type,
+            key: &str,
+            value: &Variant,
+            origin_tag: SettingsBackendOriginTag,
+        ) -> bool {
+            let mut changes = BTreeMap::new();
+            changes.insert(key.to_owned(), Some(value.clone()));
+            self.write_tree(backend, &changes, origin_tag)
+        }
+
+        fn write_tree(
+            &self,
+            backend: &Self::Type,
+            tree: &BTreeMap<String, Option<Variant>>,
+            origin_tag: SettingsBackendOriginTag,
+        ) -> bool {
+            if tree.keys().any(|key| self.relative_key(key).is_none()) {
+                return false;
+            }
+
+            {
+                let mut table = self.table.lock().unwrap();
+                let mut new_table = table.clone();
+                for (key, value) in tree {
+                    let relative = self.relative_key(key).unwrap();
+                    match value {
+                        Some(value) => {
+                            self.note_type(relative, value.type_());
+                            insert(&mut new_table, relative, variant_to_toml(value))
+                        }
+                        None => remove(&mut new_table, relative),
+                    }
+                }
+                if let Err(err) = save(self.path.get().unwrap(), &new_table) {
+                    glib::g_warning!(
+                        "TomlSettingsBackend",
+                        "Failed to write {}: {}",
+                        self.path.get().unwrap().display(),
+                        err
+                    );
+                    return false;
+                }
+                *table = new_table;
+            }
+
+            // Notify without holding the lock, as listeners read back the values.
+            for key in tree.keys() {
+                backend.changed(key, Some(origin_tag));
+            }
+            true
+        }
+
+        fn reset(&self, backend: &Self::Type, key: &str, origin_tag: SettingsBackendOriginTag) {
+            let mut changes = BTreeMap::new();
+            changes.insert(key.to_owned(), None);
+            self.write_tree(backend, &changes, origin_tag);
+        }
+    }
+
+    impl TomlSettingsBackend {
+        fn relative_key<'a>(&self, key: &'a str) -> Option<&'a str> {
+            key.strip_prefix(self.root_path.get().unwrap().as_str())
+                .filter(|relative| !relative.is_empty() && !relative.ends_with('/'))
+        }
+
+        fn note_type(&self, relative_key: &str, type_: &VariantTy) {
+            if type_.to_str().starts_with("a{") {
+                self.dictionaries
+                    .lock()
+                    .unwrap()
+                    .insert(relative_key.to_owned());
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct TomlSettingsBackend(ObjectSubclass<imp::TomlSettingsBackend>) @extends SettingsBackend;
+}
+
+impl TomlSettingsBackend {
+    /// Creates a backend storing all keys below `root_path` in the TOML file
+    /// at `path`.
+    ///
+    /// `root_path` must start and end with a slash, usually it is the path of
+    /// the schema the backend is used with. Keys outside of it are not
+    /// writable. The file does not have to exist yet and is created on the
+    /// first write, including missing parent directories.
+    pub fn new<P: AsRef<Path>>(path: P, root_path: &str) -> Result<Self, glib::Error> {
+        if !root_path.starts_with('/') || !root_path.ends_with('/') {
+            return Err(glib::Error::new(
+                IOErrorEnum::InvalidArgument,
+                "Root path must start and end with a slash",
+            ));
+        }
+
+        let backend = glib::Object::new::<Self>(&[]).expect("Failed to create TomlSettingsBackend");
+        let imp = imp::TomlSettingsBackend::from_instance(&backend);
+        imp.path.set(path.as_ref().to_owned()).unwrap();
+        imp.root_path.set(root_path.to_owned()).unwrap();
+        *imp.table.lock().unwrap() = load(path.as_ref())?;
+
+        Ok(backend)
+    }
+
+    /// The file the settings are stored in.
+    pub fn path(&self) -> &Path {
+        imp::TomlSettingsBackend::from_instance(self)
+            .path
+            .get()
+            .unwrap()
+    }
+
+    pub fn root_path(&self) -> &str {
+        imp::TomlSettingsBackend::from_instance(self)
+            .root_path
+            .get()
+            .unwrap()
+    }
+
+    /// Re-reads the file and emits change notifications for all keys whose
+    /// value differs from before.
+    ///
+    /// Only keys holding a value are notified, not the tables grouping them.
+    /// Tables are taken as dictionary values once the key was read or written
+    /// with a dictionary type.
+    ///
+    /// If the file cannot be parsed the previous values are kept.
+    pub fn reload(&self) -> Result<(), glib::Error> {
+        let imp = imp::TomlSettingsBackend::from_instance(self);
+        let new_table = load(self.path())?;
+
+        let changed = {
+            let mut table = imp.table.lock().unwrap();
+            let dictionaries = imp.dictionaries.lock().unwrap();
+            let mut old = BTreeMap::new();
+            leaves(&table, "", &dictionaries, &mut old);
+            let mut new = BTreeMap::new();
+            leaves(&new_table, "", &dictionaries, &mut new);
+            *table = new_table;
+
+            let mut changed = old
+                .iter()
+                .filter(|(key, value)| new.get(*key) != Some(*value))
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            changed.extend(new.keys().filter(|key| !old.contains_key(*key)).cloned());
+            changed
+        };
+
+        for key in changed {
+            self.changed(&format!("{}{}", self.root_path(), key), None);
+        }
+        Ok(())
+    }
+
+    /// Watches the file and reloads it whenever it is modified, so that edits
+    /// made outside of the application are picked up.
+    ///
+    /// Changes are detected for as long as the returned monitor is alive.
+    /// Reloading happens on the thread-default main context of the calling
+    /// thread, and files that fail to parse are ignored until fixed.
+    pub fn monitor(&self) -> Result<FileMonitor, glib::Error> {
+        let monitor = File::for_path(self.path())
+            .monitor_file(FileMonitorFlags::WATCH_MOVES, crate::NONE_CANCELLABLE)?;
+
+        let backend = self.downgrade();
+        monitor.connect_changed(move |_, _, _, event| {
+            let backend = match backend.upgrade() {
+                Some(backend) => backend,
+                None => return,
+            };
+            match event {
+                FileMonitorEvent::ChangesDoneHint
+                | FileMonitorEvent::Created
+                | FileMonitorEvent::Deleted
+                | FileMonitorEvent::MovedIn
+                | FileMonitorEvent::Renamed => {
+                    if let Err(err) = backend.reload() {
+                        glib::g_warning!(
+                            "TomlSettingsBackend",
+                            "Failed to reload {}: {}",
+                            backend.path().display(),
+                            err
+                        );
+                    }
+                }
+                _ => (),
+            }
+        });
+
+        Ok(monitor)
+    }
+}
+
+fn load(path: &Path) -> Result<Table, glib::Error> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Table::new()),
+        Err(err) => {
+            return Err(glib::Error::new(
+                IOErrorEnum::Failed,
+                &format!("Failed to read {}: {}", path.display(), err),
+            ))
+        }
+    };
+    toml::from_str(&contents).map_err(|err| {
+        glib::Error::new(
+            IOErrorEnum::InvalidData,
+            &format!("Failed to parse {}: {}", path.display(), err),
+        )
+    })
+}
+
+// Writes to a temporary file first so that readers never see a partial file.
+// Serializing a `Value` rather than the bare table puts plain values before
+// tables, as TOML requires.
+fn save(path: &Path, table: &Table) -> io::Result<()> {
+    let contents = toml::to_string_pretty(&toml::Value::Table(table.clone()))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+// Splits off the first path component.
+fn split_key(key: &str) -> Option<(&str, &str)> {
+    let slash = key.find('/')?;
+    Some((&key[..slash], &key[slash + 1..]))
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a toml::Value> {
+    match split_key(key) {
+        Some((head, rest)) => lookup(table.get(head)?.as_table()?, rest),
+        None => table.get(key),
+    }
+}
+
+fn insert(table: &mut Table, key: &str, value: toml::Value) {
+    match split_key(key) {
+        Some((head, rest)) => {
+            let entry = table
+                .entry(head.to_owned())
+                .or_insert_with(|| toml::Value::Table(Table::new()));
+            if !entry.is_table() {
+                *entry = toml::Value::Table(Table::new());
+            }
+            insert(entry.as_table_mut().unwrap(), rest, value)
+        }
+        None => {
+            table.insert(key.to_owned(), value);
+        }
+    }
+}
+
+fn remove(table: &mut Table, key: &str) {
+    match split_key(key) {
+        Some((head, rest)) => {
+            if let Some(child) = table.get_mut(head).and_then(toml::Value::as_table_mut) {
+                remove(child, rest);
+                if child.is_empty() {
+                    table.remove(head);
+                }
+            }
+        }
+        None => {
+            table.remove(key);
+        }
+    }
+}
+
+// Collects all values by their key relative to the root. Tables are path
+// components unless they are known to be dictionary values.
+fn leaves(
+    table: &Table,
+    prefix: &str,
+    dictionaries: &HashSet<String>,
+    out: &mut BTreeMap<String, toml::Value>,
+) {
+    for (key, value) in table {
+        let key = format!("{}{}", prefix, key);
+        match value {
+            toml::Value::Table(child) if !dictionaries.contains(&key) => {
+                leaves(child, &format!("{}/", key), dictionaries, out)
+            }
+            _ => {
+                out.insert(key, value.clone());
+            }
+        }
+    }
+}
+
+// Values that would not read back as they are, e.g. because the TOML value
+// loses their type, are written in GVariant text format. Dictionaries are
+// checked per entry as tables do not keep the order of their entries.
+fn variant_to_toml(value: &Variant) -> toml::Value {
+    match native_toml(value) {
+        Some(native) if value.type_().to_str().starts_with("a{s") => native,
+        Some(native) if toml_to_variant(&native, value.type_()).as_ref() == Some(value) => native,
+        _ => toml::Value::String(print_variant(value)),
+    }
+}
+
+fn native_toml(value: &Variant) -> Option<toml::Value> {
+    let type_ = value.type_().to_str();
+    let native = match type_ {
+        "b" => toml::Value::Boolean(value.get::<bool>().unwrap()),
+        "y" => toml::Value::Integer(value.get::<u8>().unwrap().into()),
+        "n" => toml::Value::Integer(value.get::<i16>().unwrap().into()),
+        "q" => toml::Value::Integer(value.get::<u16>().unwrap().into()),
+        "i" => toml::Value::Integer(value.get::<i32>().unwrap().into()),
+        "u" => toml::Value::Integer(value.get::<u32>().unwrap().into()),
+        "x" => toml::Value::Integer(value.get::<i64>().unwrap()),
+        "t" => toml::Value::Integer(i64::try_from(value.get::<u64>().unwrap()).ok()?),
+        "d" => toml::Value::Float(value.get::<f64>().unwrap()),
+        "s" | "o" | "g" => toml::Value::String(value.str().unwrap().to_owned()),
+        "v" => native_toml(&value.as_variant().unwrap())?,
+        _ if type_.starts_with("a{s") => toml::Value::Table(
+            value
+                .iter()
+                .map(|entry| {
+                    let key = entry.child_value(0).str().unwrap().to_owned();
+                    (key, variant_to_toml(&entry.child_value(1)))
+                })
+                .collect(),
+        ),
+        _ if type_.starts_with('a') && !type_.starts_with("a{") => {
+            toml::Value::Array(value.iter().map(|child| variant_to_toml(&child)).collect())
+        }
+        _ => return None,
+    };
+    Some(native)
+}
+
+fn print_variant(value: &Variant) -> String {
+    unsafe {
+        from_glib_full(glib::ffi::g_variant_print(
+            value.to_glib_none().0,
+            true.into_glib(),
+        ))
+    }
+}
+
+fn toml_to_variant(value: &toml::Value, type_: &VariantTy) -> Option<Variant> {
+    use toml::Value::*;
+
+    let type_str = type_.to_str();
+    match (type_str, value) {
+        ("b", Boolean(b)) => Some(b.to_variant()),
+        ("y", Integer(i)) => u8::try_from(*i).ok().map(|i| i.to_variant()),
+        ("n", Integer(i)) => i16::try_from(*i).ok().map(|i| i.to_variant()),
+        ("q", Integer(i)) => u16::try_from(*i).ok().map(|i| i.to_variant()),
+        ("i", Integer(i)) => i32::try_from(*i).ok().map(|i| i.to_variant()),
+        ("u", Integer(i)) => u32::try_from(*i).ok().map(|i| i.to_variant()),
+        ("x", Integer(i)) => Some(i.to_variant()),
+        ("t", Integer(i)) => u64::try_from(*i).ok().map(|i| i.to_variant()),
+        ("d", Float(f)) => Some(f.to_variant()),
+        ("d", Integer(i)) => Some((*i as f64).to_variant()),
+        ("s", String(s)) => Some(s.to_variant()),
+        ("o", String(s)) => unsafe {
+            if from_glib(glib::ffi::g_variant_is_object_path(s.to_glib_none().0)) {
+                Some(from_glib_none(glib::ffi::g_variant_new_object_path(
+                    s.to_glib_none().0,
+                )))
+            } else {
+                None
+            }
+        },
+        ("g", String(s)) => unsafe {
+            if from_glib(glib::ffi::g_variant_is_signature(s.to_glib_none().0)) {
+                Some(from_glib_none(glib::ffi::g_variant_new_signature(
+                    s.to_glib_none().0,
+                )))
+            } else {
+                None
+            }
+        },
+        ("v", String(text)) => {
+            parse_variant(text, type_).or_else(|| Some(Variant::from_variant(&text.to_variant())))
+        }
+        ("v", _) => infer_variant(value).map(|value| Variant::from_variant(&value)),
+        (_, Array(items)) if type_str.starts_with('a') && !type_str.starts_with("a{") => {
+            let child_type = VariantTy::new(&type_str[1..]).ok()?;
+            let children = items
+                .iter()
+                .map(|item| toml_to_variant(item, child_type))
+                .collect::<Option<Vec<_>>>()?;
+            Some(new_array(child_type, &children))
+        }
+        (_, Table(table)) if type_str.starts_with("a{s") => {
+            let entry_type = VariantTy::new(&type_str[1..]).ok()?;
+            let value_type = VariantTy::new(&type_str[3..type_str.len() - 1]).ok()?;
+            let entries = table
+                .iter()
+                .map(|(key, value)| {
+                    let value = toml_to_variant(value, value_type)?;
+                    Some(unsafe {
+                        from_glib_none(glib::ffi::g_variant_new_dict_entry(
+                            key.to_variant().to_glib_none().0,
+                            value.to_glib_none().0,
+                        ))
+                    })
+                })
+                .collect::<Option<Vec<Variant>>>()?;
+            Some(new_array(entry_type, &entries))
+        }
+        (_, String(text)) => parse_variant(text, type_),
+        _ => None,
+    }
+}
+
+// Picks a type for values stored in variants, e.g. in `a{sv}` dictionaries.
+fn infer_variant(value: &toml::Value) -> Option<Variant> {
+    use toml::Value::*;
+
+    match value {
+        Boolean(b) => Some(b.to_variant()),
+        Integer(i) => Some(i.to_variant()),
+        Float(f) => Some(f.to_variant()),
+        String(s) => Some(s.to_variant()),
+        Datetime(d) => Some(d.to_string().to_variant()),
+        Array(items) => {
+            let children = items
+                .iter()
+                .map(infer_variant)
+                .collect::<Option<Vec<_>>>()?;
+            match children.first() {
+                Some(first) if children.iter().all(|child| child.type_() == first.type_()) => {
+                    Some(new_array(first.type_(), &children))
+                }
+                _ => {
+                    let children = children
+                        .iter()
+                        .map(Variant::from_variant)
+                        .collect::<Vec<_>>();
+                    Some(new_array(VariantTy::new("v").unwrap(), &children))
+                }
+            }
+        }
+        Table(_) => toml_to_variant(value, VariantTy::new("a{sv}").unwrap()),
+    }
+}
+
+fn new_array(child_type: &VariantTy, children: &[Variant]) -> Variant {
+    unsafe {
+        from_glib_none(glib::ffi::g_variant_new_array(
+            child_type.to_glib_none().0,
+            children.to_glib_none().0,
+            children.len(),
+        ))
+    }
+}
+
+fn parse_variant(text: &str, type_: &VariantTy) -> Option<Variant> {
+    unsafe {
+        let mut error = ptr::null_mut();
+        let value = glib::ffi::g_variant_parse(
+            type_.to_glib_none().0,
+            text.to_glib_none().0,
+            ptr::null(),
+            ptr::null_mut(),
+            &mut error,
+        );
+        if value.is_null() {
+            let _: glib::Error = from_glib_full(error);
+            None
+        } else {
+            Some(from_glib_full(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn roundtrip(value: Variant) {
+        let toml = variant_to_toml(&value);
+        assert_eq!(
+            toml_to_variant(&toml, value.type_()),
+            Some(value.clone()),
+            "{} via {}",
+            value,
+            toml
+        );
+    }
+
+    #[test]
+    fn value_conversions() {
+        roundtrip(true.to_variant());
+        roundtrip(7u8.to_variant());
+        roundtrip((-3i32).to_variant());
+        roundtrip(u64::MAX.to_variant());
+        roundtrip(0.25f64.to_variant());
+        roundtrip("hello".to_variant());
+        roundtrip(vec!["a", "b"].to_variant());
+        roundtrip(vec![1i32, 2, 3].to_variant());
+        roundtrip((1i32, "tuple").to_variant());
+
+        let mut dict = std::collections::HashMap::new();
+        dict.insert("answer".to_owned(), 42i64.to_variant());
+        roundtrip(dict.to_variant());
+
+        assert_eq!(
+            variant_to_toml(&vec![1i32, 2].to_variant()),
+            toml::Value::Array(vec![1.into(), 2.into()])
+        );
+        assert_eq!(
+            toml_to_variant(&toml::Value::Integer(300), VariantTy::new("y").unwrap()),
+            None
+        );
+        assert_eq!(
+            toml_to_variant(&toml::Value::Integer(3), VariantTy::new("d").unwrap()),
+            Some(3.0f64.to_variant())
+        );
+        assert_eq!(
+            toml_to_variant(&"(1, 'x')".into(), VariantTy::new("(is)").unwrap()),
+            Some((1i32, "x").to_variant())
+        );
+    }
+
+    #[test]
+    fn nested_keys() {
+        let mut table = Table::new();
+        insert(&mut table, "dark-mode", true.into());
+        insert(&mut table, "window/size", 3.into());
+        assert_eq!(lookup(&table, "window/size"), Some(&3.into()));
+        assert_eq!(lookup(&table, "dark-mode/size"), None);
+
+        remove(&mut table, "window/size");
+        assert!(!table.contains_key("window"));
+        assert_eq!(lookup(&table, "dark-mode"), Some(&true.into()));
+    }
+
+    #[test]
+    fn typed_values() {
+        let dict = parse_variant(
+            "{'name': <'main'>, 'width': <640>}",
+            VariantTy::new("a{sv}").unwrap(),
+        )
+        .unwrap();
+        roundtrip(dict.clone());
+        roundtrip(Variant::from_variant(&7u16.to_variant()));
+        roundtrip(Variant::from_variant(&"<5>".to_variant()));
+        roundtrip(Variant::from_variant(&(1i32, "x").to_variant()));
+
+        let toml = variant_to_toml(&dict);
+        let table = toml.as_table().unwrap();
+        assert_eq!(table["name"], toml::Value::from("main"));
+        assert!(table["width"].is_str(), "{}", toml);
+        assert_eq!(
+            variant_to_toml(&Variant::from_variant(&7i64.to_variant())),
+            toml::Value::Integer(7)
+        );
+    }
+
+    #[test]
+    fn leaf_keys() {
+        let table: Table = toml::from_str(
+            "count = 1\n[window]\nwidth = 2\n[extra]\nname = 'x'\n[window.tabs]\nopen = []\n",
+        )
+        .unwrap();
+        let mut dictionaries = HashSet::new();
+        dictionaries.insert("extra".to_owned());
+        let mut out = BTreeMap::new();
+        leaves(&table, "", &dictionaries, &mut out);
+        assert_eq!(
+            out.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["count", "extra", "window/tabs/open", "window/width"]
+        );
+    }
+
+    #[test]
+    fn write_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.toml");
+
+        let backend = TomlSettingsBackend::new(&path, "/org/example/App/").unwrap();
+        let imp = imp::TomlSettingsBackend::from_instance(&backend);
+        let tag = unsafe { SettingsBackendOriginTag::from_raw(ptr::null_mut()) };
+        assert!(imp.write(
+            &backend,
+            "/org/example/App/window/width",
+            &1280i32.to_variant(),
+            tag
+        ));
+        assert!(!imp.write(&backend, "/org/other/key", &true.to_variant(), tag));
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("[window]"), "{}", contents);
+        assert!(contents.contains("width = 1280"), "{}", contents);
+
+        fs::write(&path, "[window]\nwidth = 640\n").unwrap();
+        backend.reload().unwrap();
+        assert_eq!(
+            imp.read(
+                &backend,
+                "/org/example/App/window/width",
+                VariantTy::new("i").unwrap(),
+                false
+            ),
+            Some(640i32.to_variant())
+        );
+
+        fs::write(&path, "not toml [").unwrap();
+        assert!(backend.reload().is_err());
+        assert_eq!(
+            imp.read(
+                &backend,
+                "/org/example/App/window/width",
+                VariantTy::new("i").unwrap(),
+                false
+            ),
+            Some(640i32.to_variant())
+        );
+    }
+
+    fn new_settings(path: &Path) -> crate::Settings {
+        crate::settings::test::set_env();
+        let backend = TomlSettingsBackend::new(path, "/com/github/gtk-rs/toml/").unwrap();
+        crate::Settings::with_backend("com.github.gtk-rs.toml-test", &backend)
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.toml");
+        let settings = new_settings(&path);
+
+        let mut extra = HashMap::new();
+        extra.insert("width".to_owned(), 640i32.to_variant());
+        extra.insert("title".to_owned(), "main".to_variant());
+        settings.set_int("count", 5).unwrap();
+        settings.set_value("extra", &extra.to_variant()).unwrap();
+        settings.set("position", &(3i32, 4i32)).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("count = 5"), "{}", contents);
+        assert!(contents.contains("title = 'main'"), "{}", contents);
+
+        let settings = new_settings(&path);
+        assert_eq!(settings.int("count"), 5);
+        assert_eq!(settings.get::<HashMap<String, Variant>>("extra"), extra);
+        assert_eq!(settings.get::<(i32, i32)>("position"), (3, 4));
+
+        let changed = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let changed_clone = changed.clone();
+        settings.connect_changed(None, move |_, key| {
+            changed_clone.borrow_mut().push(key.to_owned())
+        });
+        let contents = contents
+            .replace("count = 5", "count = 6")
+            .replace("title = 'main'", "title = 'other'");
+        fs::write(&path, contents).unwrap();
+        settings
+            .backend()
+            .unwrap()
+            .downcast::<TomlSettingsBackend>()
+            .unwrap()
+            .reload()
+            .unwrap();
+
+        assert_eq!(*changed.borrow(), ["count", "extra"]);
+        assert_eq!(settings.int("count"), 6);
+        extra.insert("title".to_owned(), "other".to_variant());
+        assert_eq!(settings.get::<HashMap<String, Variant>>("extra"), extra);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn monitor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.toml");
+        fs::write(&path, "count = 1\n").unwrap();
+
+        let context = glib::MainContext::new();
+        context.with_thread_default(|| {
+            let settings = new_settings(&path);
+            let backend = settings
+                .backend()
+                .unwrap()
+                .downcast::<TomlSettingsBackend>()
+                .unwrap();
+            let _monitor = backend.monitor().unwrap();
+            assert_eq!(settings.int("count"), 1);
+
+            let changed = std::rc::Rc::new(std::cell::Cell::new(false));
+            let changed_clone = changed.clone();
+            settings.connect_changed(Some("count"), move |_, _| changed_clone.set(true));
+            fs::write(&path, "count = 2\n").unwrap();
+
+            let timeout = glib::timeout_source_new(
+                std::time::Duration::from_secs(10),
+                None,
+                glib::PRIORITY_DEFAULT,
+                || glib::Continue(false),
+            );
+            timeout.attach(Some(&context));
+            while !changed.get() && !timeout.is_destroyed() {
+                context.iteration(true);
+            }
+            assert!(changed.get(), "no change notification");
+            assert_eq!(settings.int("count"), 2);
+        });
+    }
+}
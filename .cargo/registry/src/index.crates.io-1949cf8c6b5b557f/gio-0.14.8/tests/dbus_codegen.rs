@@ -0,0 +1,183 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gio::dbus_codegen::Generator;
+use glib::ToVariant;
+use std::collections::HashMap;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+// Compiling this module checks that the generated code builds.
+#[allow(dead_code)]
+mod media_player {
+    include!("dbus_codegen/media_player.rs");
+}
+
+const XML: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/dbus_codegen/media_player.xml"
+);
+const GENERATED: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/dbus_codegen/media_player.rs"
+);
+
+fn generate() -> String {
+    Generator::from_file(XML)
+        .unwrap()
+        .strip_prefix("org.example.")
+        .generate()
+        .unwrap()
+}
+
+// Run with `GIO_UPDATE_GOLDEN=1` to regenerate the checked-in file.
+#[test]
+fn generated_code_matches_golden_file() {
+    let code = generate();
+    if std::env::var_os("GIO_UPDATE_GOLDEN").is_some() {
+        std::fs::write(GENERATED, &code).unwrap();
+    }
+    assert_eq!(code, std::fs::read_to_string(GENERATED).unwrap());
+}
+
+#[test]
+fn generated_interface_info() {
+    let info = media_player::MediaPlayerProxy::interface_info();
+    assert_eq!(info.name(), "org.example.Media.Player");
+    assert_eq!(info.methods().len(), 3);
+    assert_eq!(info.properties().len(), 3);
+}
+
+struct Player;
+
+impl media_player::MediaPlayerServer for Player {
+    fn open_uri(&self, _uri: String) -> Result<(), glib::Error> {
+        Ok(())
+    }
+
+    fn get_tracks(
+        &self,
+        _offset: u32,
+    ) -> Result<(glib::Variant, HashMap<String, glib::Variant>), glib::Error> {
+        Ok((Vec::<String>::new().to_variant(), HashMap::new()))
+    }
+
+    fn seek(&self, offset: i64) -> Result<i64, glib::Error> {
+        Ok(offset)
+    }
+
+    fn volume(&self) -> f64 {
+        0.5
+    }
+
+    fn set_volume(&self, value: f64) -> Result<(), glib::Error> {
+        if (0.0..=1.0).contains(&value) {
+            Ok(())
+        } else {
+            Err(gio::dbus_codegen::dbus_error(
+                "org.example.Error.OutOfRange",
+                "Volume must be between 0 and 1",
+            ))
+        }
+    }
+
+    fn type_(&self) -> String {
+        "not an object path".to_owned()
+    }
+
+    fn metadata(&self) -> HashMap<String, glib::Variant> {
+        HashMap::new()
+    }
+}
+
+// Exports `Player` on the server side of a peer-to-peer connection served
+// from its own thread and returns the client side.
+fn export_player() -> (gio::DBusConnection, glib::MainLoop) {
+    let context = glib::MainContext::new();
+    let main_loop = glib::MainLoop::new(Some(&context), false);
+    let (sender, receiver) = mpsc::channel();
+    let thread_loop = main_loop.clone();
+    thread::spawn(move || {
+        context.with_thread_default(|| {
+            let address = format!(
+                "unix:tmpdir={}",
+                gio::dbus_address_escape_value(&std::env::temp_dir().to_string_lossy())
+            );
+            let server = gio::DBusServer::new_sync(
+                &address,
+                gio::DBusServerFlags::AUTHENTICATION_ALLOW_ANONYMOUS,
+                &gio::dbus_generate_guid(),
+                None,
+                None::<&gio::Cancellable>,
+            )
+            .unwrap();
+            // The server only keeps connections alive that are referenced.
+            let connections = Mutex::new(Vec::new());
+            server.connect_new_connection(move |_, connection| {
+                media_player::MediaPlayerSkeleton::register(connection, PATH, Player).unwrap();
+                connections.lock().unwrap().push(connection.clone());
+                true
+            });
+            server.start();
+            sender.send(server.client_address().to_string()).unwrap();
+            thread_loop.run();
+            server.stop();
+        });
+    });
+
+    let connection = gio::DBusConnection::for_address_sync(
+        &receiver.recv().unwrap(),
+        gio::DBusConnectionFlags::AUTHENTICATION_CLIENT,
+        None,
+        None::<&gio::Cancellable>,
+    )
+    .unwrap();
+    (connection, main_loop)
+}
+
+const PATH: &str = "/org/example/Player";
+
+fn properties_call(
+    connection: &gio::DBusConnection,
+    method: &str,
+    parameters: glib::Variant,
+) -> Result<glib::Variant, glib::Error> {
+    connection.call_sync(
+        None,
+        PATH,
+        "org.freedesktop.DBus.Properties",
+        method,
+        Some(&parameters),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+    )
+}
+
+#[test]
+fn skeleton_property_errors() {
+    let (connection, main_loop) = export_player();
+    let iface = "org.example.Media.Player";
+
+    let volume = properties_call(&connection, "Get", (iface, "Volume").to_variant()).unwrap();
+    assert_eq!(volume.child_value(0).as_variant().unwrap().get(), Some(0.5));
+
+    // An invalid object path fails the call instead of panicking the service.
+    assert!(properties_call(&connection, "Get", (iface, "Type").to_variant()).is_err());
+
+    properties_call(
+        &connection,
+        "Set",
+        (iface, "Volume", 0.25.to_variant()).to_variant(),
+    )
+    .unwrap();
+    let err = properties_call(
+        &connection,
+        "Set",
+        (iface, "Volume", 2.0.to_variant()).to_variant(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("org.example.Error.OutOfRange"));
+
+    main_loop.quit();
+}
@@ -0,0 +1,337 @@
+// This file was generated by gio::dbus_codegen
+// DO NOT EDIT
+
+/// Client proxy for the `org.example.Media.Player` D-Bus interface.
+#[derive(Debug, Clone)]
+pub struct MediaPlayerProxy(gio::DBusProxy);
+
+#[allow(
+    dead_code,
+    clippy::redundant_closure_call,
+    clippy::too_many_arguments,
+    clippy::type_complexity
+)]
+impl MediaPlayerProxy {
+    pub const INTERFACE_NAME: &'static str = "org.example.Media.Player";
+
+    pub fn interface_info() -> gio::DBusInterfaceInfo {
+        gio::DBusNodeInfo::for_xml("<node>\n  <interface name=\"org.example.Media.Player\">\n    <method name=\"OpenURI\">\n      <arg type=\"s\" name=\"uri\" direction=\"in\">\n      </arg>\n    </method>\n    <method name=\"GetTracks\">\n      <arg type=\"u\" name=\"offset\" direction=\"in\">\n      </arg>\n      <arg type=\"ao\" name=\"tracks\" direction=\"out\">\n      </arg>\n      <arg type=\"a{sv}\" name=\"arg_2\" direction=\"out\">\n      </arg>\n    </method>\n    <method name=\"Seek\">\n      <arg type=\"x\" name=\"offset\" direction=\"in\">\n      </arg>\n      <arg type=\"x\" name=\"position\" direction=\"out\">\n      </arg>\n    </method>\n    <signal name=\"Seeked\">\n      <arg type=\"x\" name=\"position\">\n      </arg>\n    </signal>\n    <property type=\"d\" name=\"Volume\" access=\"readwrite\">\n    </property>\n    <property type=\"o\" name=\"Type\" access=\"read\">\n    </property>\n    <property type=\"a{sv}\" name=\"Metadata\" access=\"read\">\n    </property>\n  </interface>\n</node>\n")
+            .expect("invalid introspection data")
+            .lookup_interface(Self::INTERFACE_NAME)
+            .expect("interface missing from introspection data")
+    }
+
+    pub async fn new(
+        connection: &gio::DBusConnection,
+        name: Option<&str>,
+        object_path: &str,
+    ) -> Result<Self, gio::glib::Error> {
+        let proxy = gio::DBusProxy::new_future(
+            connection,
+            gio::DBusProxyFlags::NONE,
+            Some(&Self::interface_info()),
+            name,
+            object_path,
+            Self::INTERFACE_NAME,
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    pub async fn for_bus(
+        bus_type: gio::BusType,
+        name: &str,
+        object_path: &str,
+    ) -> Result<Self, gio::glib::Error> {
+        let proxy = gio::DBusProxy::for_bus_future(
+            bus_type,
+            gio::DBusProxyFlags::NONE,
+            Some(&Self::interface_info()),
+            name,
+            object_path,
+            Self::INTERFACE_NAME,
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    /// Wraps an existing proxy for this interface.
+    pub fn from_proxy(proxy: gio::DBusProxy) -> Self {
+        Self(proxy)
+    }
+
+    pub fn proxy(&self) -> &gio::DBusProxy {
+        &self.0
+    }
+
+    /// Calls `OpenURI`.
+    pub async fn open_uri(&self, uri: &str) -> Result<(), gio::glib::Error> {
+        let _reply = gio::prelude::DBusProxyExt::call_future(
+            &self.0,
+            "OpenURI",
+            Some(&gio::glib::Variant::from_tuple(&[gio::glib::ToVariant::to_variant(&uri)])),
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await?;
+        gio::dbus_codegen::reply_value(Some(()), "OpenURI")
+    }
+
+    /// Calls `GetTracks`.
+    pub async fn get_tracks(&self, offset: u32) -> Result<(gio::glib::Variant, std::collections::HashMap<String, gio::glib::Variant>), gio::glib::Error> {
+        let reply = gio::prelude::DBusProxyExt::call_future(
+            &self.0,
+            "GetTracks",
+            Some(&gio::glib::Variant::from_tuple(&[gio::glib::ToVariant::to_variant(&offset)])),
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await?;
+        gio::dbus_codegen::reply_value((|| Some((reply.try_child_value(0)?, reply.try_child_value(1).as_ref().and_then(|v| v.get::<std::collections::HashMap<String, gio::glib::Variant>>())?)))(), "GetTracks")
+    }
+
+    /// Calls `Seek`.
+    pub async fn seek(&self, offset: i64) -> Result<i64, gio::glib::Error> {
+        let reply = gio::prelude::DBusProxyExt::call_future(
+            &self.0,
+            "Seek",
+            Some(&gio::glib::Variant::from_tuple(&[gio::glib::ToVariant::to_variant(&offset)])),
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await?;
+        gio::dbus_codegen::reply_value(reply.try_child_value(0).as_ref().and_then(|v| v.get::<i64>()), "Seek")
+    }
+
+    /// Cached value of the `Volume` property.
+    pub fn volume(&self) -> Option<f64> {
+        gio::prelude::DBusProxyExt::cached_property(&self.0, "Volume").as_ref().and_then(|v| v.get::<f64>())
+    }
+
+    /// Changes of the `Volume` property.
+    pub fn receive_volume_changed(&self) -> gio::dbus_codegen::PropertyStream<f64> {
+        gio::dbus_codegen::PropertyStream::new(&self.0, "Volume", |v| v.get::<f64>())
+    }
+
+    /// Cached value of the `Type` property.
+    pub fn type_(&self) -> Option<String> {
+        gio::prelude::DBusProxyExt::cached_property(&self.0, "Type").as_ref().and_then(|v| v.get::<String>())
+    }
+
+    /// Changes of the `Type` property.
+    pub fn receive_type_changed(&self) -> gio::dbus_codegen::PropertyStream<String> {
+        gio::dbus_codegen::PropertyStream::new(&self.0, "Type", |v| v.get::<String>())
+    }
+
+    /// Cached value of the `Metadata` property.
+    pub fn metadata(&self) -> Option<std::collections::HashMap<String, gio::glib::Variant>> {
+        gio::prelude::DBusProxyExt::cached_property(&self.0, "Metadata").as_ref().and_then(|v| v.get::<std::collections::HashMap<String, gio::glib::Variant>>())
+    }
+
+    /// Changes of the `Metadata` property.
+    pub fn receive_metadata_changed(&self) -> gio::dbus_codegen::PropertyStream<std::collections::HashMap<String, gio::glib::Variant>> {
+        gio::dbus_codegen::PropertyStream::new(&self.0, "Metadata", |v| v.get::<std::collections::HashMap<String, gio::glib::Variant>>())
+    }
+
+    /// Sets the `Volume` property.
+    pub async fn set_volume(&self, value: f64) -> Result<(), gio::glib::Error> {
+        gio::dbus_codegen::set_property(&self.0, "Volume", gio::glib::ToVariant::to_variant(&value)).await
+    }
+
+    /// Emissions of the `Seeked` signal.
+    pub fn receive_seeked(&self) -> gio::dbus_codegen::SignalStream<i64> {
+        gio::dbus_codegen::SignalStream::new(&self.0, "Seeked", |parameters| {
+            parameters.try_child_value(0).as_ref().and_then(|v| v.get::<i64>())
+        })
+    }
+}
+
+/// Service side of the `org.example.Media.Player` D-Bus interface.
+///
+/// Register an implementation with [`MediaPlayerSkeleton::register()`].
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub trait MediaPlayerServer: Send + Sync + 'static {
+    fn open_uri(&self, uri: String) -> Result<(), gio::glib::Error>;
+    fn get_tracks(&self, offset: u32) -> Result<(gio::glib::Variant, std::collections::HashMap<String, gio::glib::Variant>), gio::glib::Error>;
+    fn seek(&self, offset: i64) -> Result<i64, gio::glib::Error>;
+    fn volume(&self) -> f64;
+    fn set_volume(&self, value: f64) -> Result<(), gio::glib::Error>;
+    fn type_(&self) -> String;
+    fn metadata(&self) -> std::collections::HashMap<String, gio::glib::Variant>;
+}
+
+/// Registration and signal emission for [`MediaPlayerServer`] implementations.
+#[derive(Debug, Clone, Copy)]
+pub struct MediaPlayerSkeleton;
+
+#[allow(
+    dead_code,
+    clippy::match_single_binding,
+    clippy::redundant_closure_call,
+    clippy::too_many_arguments
+)]
+impl MediaPlayerSkeleton {
+    pub const INTERFACE_NAME: &'static str = "org.example.Media.Player";
+
+    pub fn interface_info() -> gio::DBusInterfaceInfo {
+        gio::DBusNodeInfo::for_xml("<node>\n  <interface name=\"org.example.Media.Player\">\n    <method name=\"OpenURI\">\n      <arg type=\"s\" name=\"uri\" direction=\"in\">\n      </arg>\n    </method>\n    <method name=\"GetTracks\">\n      <arg type=\"u\" name=\"offset\" direction=\"in\">\n      </arg>\n      <arg type=\"ao\" name=\"tracks\" direction=\"out\">\n      </arg>\n      <arg type=\"a{sv}\" name=\"arg_2\" direction=\"out\">\n      </arg>\n    </method>\n    <method name=\"Seek\">\n      <arg type=\"x\" name=\"offset\" direction=\"in\">\n      </arg>\n      <arg type=\"x\" name=\"position\" direction=\"out\">\n      </arg>\n    </method>\n    <signal name=\"Seeked\">\n      <arg type=\"x\" name=\"position\">\n      </arg>\n    </signal>\n    <property type=\"d\" name=\"Volume\" access=\"readwrite\">\n    </property>\n    <property type=\"o\" name=\"Type\" access=\"read\">\n    </property>\n    <property type=\"a{sv}\" name=\"Metadata\" access=\"read\">\n    </property>\n  </interface>\n</node>\n")
+            .expect("invalid introspection data")
+            .lookup_interface(Self::INTERFACE_NAME)
+            .expect("interface missing from introspection data")
+    }
+
+    /// Exports `server` at `object_path` on `connection`.
+    pub fn register<T: MediaPlayerServer>(
+        connection: &gio::DBusConnection,
+        object_path: &str,
+        server: T,
+    ) -> Result<gio::RegistrationId, gio::glib::Error> {
+        let server = std::sync::Arc::new(server);
+        let method_server = server.clone();
+        let get_server = server.clone();
+        let set_server = server;
+        gio::dbus_codegen::register_object(
+            connection,
+            object_path,
+            &Self::interface_info(),
+            move |method_name, parameters, invocation| {
+                let server = &method_server;
+                let _ = (server, &parameters);
+                match method_name {
+                "OpenURI" => {
+                    let uri = match parameters.try_child_value(0).as_ref().and_then(|v| v.get::<String>()) {
+                        Some(value) => value,
+                        None => return invocation.return_dbus_error(
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            "Invalid arguments for OpenURI",
+                        ),
+                    };
+                    match server.open_uri(uri) {
+                        Ok(()) => invocation.return_value(None),
+                        Err(err) => invocation.return_gerror(err),
+                    }
+                }
+                "GetTracks" => {
+                    let offset = match parameters.try_child_value(0).as_ref().and_then(|v| v.get::<u32>()) {
+                        Some(value) => value,
+                        None => return invocation.return_dbus_error(
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            "Invalid arguments for GetTracks",
+                        ),
+                    };
+                    match server.get_tracks(offset) {
+                        Ok((out0, out1)) => invocation.return_value(Some(&gio::glib::Variant::from_tuple(&[out0.clone(), gio::glib::ToVariant::to_variant(&out1)]))),
+                        Err(err) => invocation.return_gerror(err),
+                    }
+                }
+                "Seek" => {
+                    let offset = match parameters.try_child_value(0).as_ref().and_then(|v| v.get::<i64>()) {
+                        Some(value) => value,
+                        None => return invocation.return_dbus_error(
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            "Invalid arguments for Seek",
+                        ),
+                    };
+                    match server.seek(offset) {
+                        Ok(out0) => invocation.return_value(Some(&gio::glib::Variant::from_tuple(&[gio::glib::ToVariant::to_variant(&out0)]))),
+                        Err(err) => invocation.return_gerror(err),
+                    }
+                }
+                _ => invocation.return_dbus_error(
+                    "org.freedesktop.DBus.Error.UnknownMethod",
+                    &format!("Unknown method {}", method_name),
+                ),
+            }
+            },
+            move |property_name| {
+                let server = &get_server;
+                let _ = server;
+                match property_name {
+                "Volume" => Ok(gio::glib::ToVariant::to_variant(&server.volume())),
+                "Type" => gio::dbus_codegen::object_path_variant(&server.type_()),
+                "Metadata" => Ok(gio::glib::ToVariant::to_variant(&server.metadata())),
+                _ => Err(gio::dbus_codegen::dbus_error(
+                    "org.freedesktop.DBus.Error.UnknownProperty",
+                    &format!("Unknown property {}", property_name),
+                )),
+            }
+            },
+            move |property_name, value| {
+                let server = &set_server;
+                let _ = (server, &value);
+                match property_name {
+                "Volume" => {
+                    let v = &value;
+                    match v.get::<f64>() {
+                        Some(value) => server.set_volume(value),
+                        None => Err(gio::dbus_codegen::dbus_error(
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            "Invalid value for Volume",
+                        )),
+                    }
+                }
+                _ => Err(gio::dbus_codegen::dbus_error(
+                    "org.freedesktop.DBus.Error.PropertyReadOnly",
+                    &format!("Property {} is not writable", property_name),
+                )),
+            }
+            },
+        )
+    }
+
+    /// Emits the `Seeked` signal.
+    pub fn emit_seeked(
+        connection: &gio::DBusConnection,
+        object_path: &str, position: i64
+    ) -> Result<(), gio::glib::Error> {
+        connection.emit_signal(
+            None,
+            object_path,
+            Self::INTERFACE_NAME,
+            "Seeked",
+            Some(&gio::glib::Variant::from_tuple(&[gio::glib::ToVariant::to_variant(&position)])),
+        )
+    }
+
+    /// Notifies clients that the `Volume` property changed to `value`.
+    pub fn emit_volume_changed(
+        connection: &gio::DBusConnection,
+        object_path: &str,
+        value: f64,
+    ) -> Result<(), gio::glib::Error> {
+        gio::dbus_codegen::emit_properties_changed(
+            connection,
+            object_path,
+            Self::INTERFACE_NAME,
+            &[("Volume", gio::glib::ToVariant::to_variant(&value))],
+        )
+    }
+
+    /// Notifies clients that the `Type` property changed to `value`.
+    pub fn emit_type_changed(
+        connection: &gio::DBusConnection,
+        object_path: &str,
+        value: &str,
+    ) -> Result<(), gio::glib::Error> {
+        gio::dbus_codegen::emit_properties_changed(
+            connection,
+            object_path,
+            Self::INTERFACE_NAME,
+            &[("Type", gio::dbus_codegen::object_path_variant(value)?)],
+        )
+    }
+
+    /// Notifies clients that the `Metadata` property changed to `value`.
+    pub fn emit_metadata_changed(
+        connection: &gio::DBusConnection,
+        object_path: &str,
+        value: &std::collections::HashMap<String, gio::glib::Variant>,
+    ) -> Result<(), gio::glib::Error> {
+        gio::dbus_codegen::emit_properties_changed(
+            connection,
+            object_path,
+            Self::INTERFACE_NAME,
+            &[("Metadata", gio::glib::ToVariant::to_variant(&value))],
+        )
+    }
+}
@@ -147,6 +147,7 @@ pub mod prelude;
 pub mod signal;
 pub mod source;
 pub use self::source::*;
+pub mod virtual_clock;
 #[macro_use]
 pub mod translate;
 mod gstring;
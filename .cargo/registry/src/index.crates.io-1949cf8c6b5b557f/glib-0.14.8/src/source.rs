@@ -287,6 +287,10 @@ where
     F: FnMut() -> Continue + Send + 'static,
 {
     unsafe {
+        if crate::virtual_clock::is_active() {
+            return timeout_source_new_unsafe(interval, None, crate::PRIORITY_DEFAULT, func)
+                .attach(None);
+        }
         from_glib(ffi::g_timeout_add_full(
             ffi::G_PRIORITY_DEFAULT,
             interval.as_millis() as _,
@@ -341,6 +345,10 @@ where
 {
     unsafe {
         assert!(MainContext::default().is_owner());
+        if crate::virtual_clock::is_active() {
+            return timeout_source_new_unsafe(interval, None, crate::PRIORITY_DEFAULT, func)
+                .attach(None);
+        }
         from_glib(ffi::g_timeout_add_full(
             ffi::G_PRIORITY_DEFAULT,
             interval.as_millis() as _,
@@ -393,6 +401,15 @@ where
     F: FnMut() -> Continue + Send + 'static,
 {
     unsafe {
+        if crate::virtual_clock::is_active() {
+            return timeout_source_new_unsafe(
+                Duration::from_secs(interval.into()),
+                None,
+                crate::PRIORITY_DEFAULT,
+                func,
+            )
+            .attach(None);
+        }
         from_glib(ffi::g_timeout_add_seconds_full(
             ffi::G_PRIORITY_DEFAULT,
             interval,
@@ -445,6 +462,15 @@ where
 {
     unsafe {
         assert!(MainContext::default().is_owner());
+        if crate::virtual_clock::is_active() {
+            return timeout_source_new_unsafe(
+                Duration::from_secs(interval.into()),
+                None,
+                crate::PRIORITY_DEFAULT,
+                func,
+            )
+            .attach(None);
+        }
         from_glib(ffi::g_timeout_add_seconds_full(
             ffi::G_PRIORITY_DEFAULT,
             interval,
@@ -750,22 +776,33 @@ pub fn idle_source_new<F>(name: Option<&str>, priority: Priority, func: F) -> So
 where
     F: FnMut() -> Continue + Send + 'static,
 {
-    unsafe {
-        let source = ffi::g_idle_source_new();
-        ffi::g_source_set_callback(
-            source,
-            Some(trampoline::<F>),
-            into_raw(func),
-            Some(destroy_closure::<F>),
-        );
-        ffi::g_source_set_priority(source, priority.into_glib());
-
-        if let Some(name) = name {
-            ffi::g_source_set_name(source, name.to_glib_none().0);
-        }
+    unsafe { idle_source_new_unsafe(name, priority, func) }
+}
 
-        from_glib_full(source)
+// The caller has to make sure the returned source is only ever attached to a
+// main context owned by the current thread unless `F` is `Send`.
+pub(crate) unsafe fn idle_source_new_unsafe<F>(
+    name: Option<&str>,
+    priority: Priority,
+    func: F,
+) -> Source
+where
+    F: FnMut() -> Continue + 'static,
+{
+    let source = ffi::g_idle_source_new();
+    ffi::g_source_set_callback(
+        source,
+        Some(trampoline::<F>),
+        into_raw(func),
+        Some(destroy_closure::<F>),
+    );
+    ffi::g_source_set_priority(source, priority.into_glib());
+
+    if let Some(name) = name {
+        ffi::g_source_set_name(source, name.to_glib_none().0);
     }
+
+    from_glib_full(source)
 }
 
 /// Adds a closure to be called by the main loop the returned `Source` is attached to at regular
@@ -785,22 +822,38 @@ pub fn timeout_source_new<F>(
 where
     F: FnMut() -> Continue + Send + 'static,
 {
-    unsafe {
-        let source = ffi::g_timeout_source_new(interval.as_millis() as _);
-        ffi::g_source_set_callback(
-            source,
-            Some(trampoline::<F>),
-            into_raw(func),
-            Some(destroy_closure::<F>),
-        );
-        ffi::g_source_set_priority(source, priority.into_glib());
-
-        if let Some(name) = name {
-            ffi::g_source_set_name(source, name.to_glib_none().0);
-        }
+    unsafe { timeout_source_new_unsafe(interval, name, priority, func) }
+}
 
-        from_glib_full(source)
+// The caller has to make sure the returned source is only ever attached to a
+// main context owned by the current thread unless `F` is `Send`.
+pub(crate) unsafe fn timeout_source_new_unsafe<F>(
+    interval: Duration,
+    name: Option<&str>,
+    priority: Priority,
+    func: F,
+) -> Source
+where
+    F: FnMut() -> Continue + 'static,
+{
+    let source = if crate::virtual_clock::is_active() {
+        crate::virtual_clock::timeout_source_new(interval)
+    } else {
+        ffi::g_timeout_source_new(interval.as_millis() as _)
+    };
+    ffi::g_source_set_callback(
+        source,
+        Some(trampoline::<F>),
+        into_raw(func),
+        Some(destroy_closure::<F>),
+    );
+    ffi::g_source_set_priority(source, priority.into_glib());
+
+    if let Some(name) = name {
+        ffi::g_source_set_name(source, name.to_glib_none().0);
     }
+
+    from_glib_full(source)
 }
 
 /// Adds a closure to be called by the main loop the returned `Source` is attached to at regular
@@ -820,7 +873,11 @@ where
     F: FnMut() -> Continue + Send + 'static,
 {
     unsafe {
-        let source = ffi::g_timeout_source_new_seconds(interval);
+        let source = if crate::virtual_clock::is_active() {
+            crate::virtual_clock::timeout_source_new(Duration::from_secs(interval.into()))
+        } else {
+            ffi::g_timeout_source_new_seconds(interval)
+        };
         ffi::g_source_set_callback(
             source,
             Some(trampoline::<F>),
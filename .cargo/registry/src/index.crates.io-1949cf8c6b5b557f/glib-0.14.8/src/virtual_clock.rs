@@ -0,0 +1,815 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Virtual-time mode for [`MainContext`], for deterministic tests of code
+//! that schedules idle and timeout callbacks.
+//!
+//! Code that schedules work with [`timeout_add_local()`] or [`idle_add_local()`]
+//! is hard to test: the test has to actually sleep until the timeouts expire,
+//! and the order in which sources fire depends on the machine's load.
+//!
+//! While a [`VirtualClock`] is bound to a [`MainContext`], timeouts attached to
+//! that context expire in virtual time instead. Time only moves when the test
+//! calls [`VirtualClock::advance()`], which iterates the context and dispatches
+//! every timeout at its exact deadline. This covers timeouts added with
+//! [`timeout_add_local()`] and friends, [`timeout_source_new()`] and the
+//! futures returned by [`timeout_future()`], as long as they are created on the
+//! thread that created the clock and after the clock was created. Every
+//! dispatched timeout is recorded and can be inspected with
+//! [`VirtualClock::fired()`].
+//!
+//! Code under test can also be written against the [`Clock`] trait, which is
+//! implemented by [`VirtualClock`] and by [`SystemClock`], the latter simply
+//! forwarding to the functions of the [`source`](crate::source) module.
+//!
+//! ```
+//! use glib::virtual_clock::VirtualClock;
+//! use std::time::Duration;
+//!
+//! let clock = VirtualClock::new();
+//! let source = glib::timeout_source_new(
+//!     Duration::from_millis(300),
+//!     Some("debounce"),
+//!     glib::PRIORITY_DEFAULT,
+//!     || glib::Continue(false),
+//! );
+//! source.attach(Some(clock.context()));
+//!
+//! clock.advance(Duration::from_millis(299));
+//! assert!(clock.fired().is_empty());
+//!
+//! clock.advance(Duration::from_millis(1));
+//! assert_eq!(clock.fired_names(), ["debounce"]);
+//! ```
+//!
+//! [`timeout_add_local()`]: crate::timeout_add_local
+//! [`idle_add_local()`]: crate::idle_add_local
+//! [`timeout_source_new()`]: crate::timeout_source_new
+//! [`timeout_future()`]: crate::timeout_future
+
+use crate::source::{Continue, Priority, SourceId};
+use crate::translate::{from_glib, from_glib_none, mut_override, IntoGlib, ToGlibPtr};
+use crate::{MainContext, Source};
+use ffi::{gboolean, gpointer};
+use libc::c_int;
+use std::cell::{Cell, RefCell};
+use std::ffi::CStr;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
+
+// Upper bound on the number of main context iterations `run_until_idle()`
+// performs before it assumes a source keeps rescheduling itself forever.
+const MAX_ITERATIONS: usize = 10_000;
+
+thread_local! {
+    static CLOCKS: RefCell<Vec<Weak<Inner>>> = RefCell::new(Vec::new());
+}
+
+/// Scheduling operations shared by the real main loop and [`VirtualClock`].
+///
+/// Widgets and helpers that want to be testable headlessly should take a
+/// `Clock` instead of calling the free functions of the
+/// [`source`](crate::source) module directly.
+pub trait Clock {
+    /// Handle returned when scheduling a callback.
+    type Id;
+
+    /// Time elapsed since the clock's origin.
+    fn now(&self) -> Duration;
+
+    /// Adds a closure to be called when the clock's main context is idle.
+    ///
+    /// `func` will be called repeatedly until it returns `Continue(false)`.
+    fn idle_add_local_full<F>(&self, name: Option<&str>, priority: Priority, func: F) -> Self::Id
+    where
+        F: FnMut() -> Continue + 'static;
+
+    /// Adds a closure to be called every `interval` until it returns `Continue(false)`.
+    fn timeout_add_local_full<F>(
+        &self,
+        interval: Duration,
+        name: Option<&str>,
+        func: F,
+    ) -> Self::Id
+    where
+        F: FnMut() -> Continue + 'static;
+
+    /// Removes a source previously added with this clock.
+    fn source_remove(&self, id: Self::Id);
+
+    /// Adds a closure to be called when the clock's main context is idle.
+    ///
+    /// `func` will be called repeatedly until it returns `Continue(false)`.
+    fn idle_add_local<F>(&self, func: F) -> Self::Id
+    where
+        F: FnMut() -> Continue + 'static,
+    {
+        self.idle_add_local_full(None, crate::PRIORITY_DEFAULT_IDLE, func)
+    }
+
+    /// Adds a closure to be called every `interval` until it returns `Continue(false)`.
+    fn timeout_add_local<F>(&self, interval: Duration, func: F) -> Self::Id
+    where
+        F: FnMut() -> Continue + 'static,
+    {
+        self.timeout_add_local_full(interval, None, func)
+    }
+
+    /// Adds a closure to be called once after `interval`.
+    fn timeout_add_local_once<F>(&self, interval: Duration, func: F) -> Self::Id
+    where
+        F: FnOnce() + 'static,
+    {
+        let mut func = Some(func);
+        self.timeout_add_local(interval, move || {
+            let func = func
+                .take()
+                .expect("Clock closure called after returning glib::Continue(false)");
+            func();
+            Continue(false)
+        })
+    }
+}
+
+/// [`Clock`] backed by the default main context and the monotonic system clock.
+///
+/// Sources are added exactly like [`idle_add_local()`](crate::idle_add_local) and
+/// [`timeout_add_local()`](crate::timeout_add_local) would add them.
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    type Id = SourceId;
+
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn idle_add_local_full<F>(&self, name: Option<&str>, priority: Priority, func: F) -> SourceId
+    where
+        F: FnMut() -> Continue + 'static,
+    {
+        let context = MainContext::default();
+        assert!(context.is_owner());
+        let source = unsafe { crate::source::idle_source_new_unsafe(name, priority, func) };
+        source.attach(Some(&context))
+    }
+
+    fn timeout_add_local_full<F>(&self, interval: Duration, name: Option<&str>, func: F) -> SourceId
+    where
+        F: FnMut() -> Continue + 'static,
+    {
+        let context = MainContext::default();
+        assert!(context.is_owner());
+        let source = unsafe {
+            crate::source::timeout_source_new_unsafe(interval, name, crate::PRIORITY_DEFAULT, func)
+        };
+        source.attach(Some(&context))
+    }
+
+    fn source_remove(&self, id: SourceId) {
+        crate::source_remove(id);
+    }
+}
+
+/// A dispatch recorded by a [`VirtualClock`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FiredSource {
+    /// Id of the dispatched source in its main context.
+    pub id: u32,
+    /// Name of the source, if it has one.
+    pub name: Option<String>,
+    /// Virtual time at which the source was dispatched.
+    pub at: Duration,
+}
+
+struct Inner {
+    context: MainContext,
+    now: Cell<Duration>,
+    // Virtual timeouts that were prepared on the context and not destroyed yet.
+    timeouts: RefCell<Vec<Source>>,
+    fired: RefCell<Vec<FiredSource>>,
+    advancing: Cell<bool>,
+    queued: Cell<Duration>,
+}
+
+impl Inner {
+    fn track(&self, source: *mut ffi::GSource) {
+        let mut timeouts = self.timeouts.borrow_mut();
+        if !timeouts.iter().any(|s| s.to_glib_none().0 == source) {
+            timeouts.push(unsafe { from_glib_none(source) });
+        }
+    }
+
+    // Earliest virtual deadline of all scheduled timeouts.
+    fn next_deadline(&self) -> Option<Duration> {
+        let mut timeouts = self.timeouts.borrow_mut();
+        timeouts.retain(|source| !source.is_destroyed());
+        timeouts
+            .iter()
+            .filter_map(|source| unsafe {
+                let source = &*(source.to_glib_none().0 as *const TimeoutSource);
+                match source.deadline.get() {
+                    Some(Deadline::Virtual(deadline)) => Some(deadline),
+                    _ => None,
+                }
+            })
+            .min()
+    }
+
+    unsafe fn record(&self, source: *mut ffi::GSource) {
+        let name = ffi::g_source_get_name(source);
+        let name = if name.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+        };
+        self.fired.borrow_mut().push(FiredSource {
+            id: ffi::g_source_get_id(source),
+            name,
+            at: self.now.get(),
+        });
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        unsafe { ffi::g_main_context_release(self.context.to_glib_none().0) }
+    }
+}
+
+fn clock_for(context: *mut ffi::GMainContext) -> Option<Rc<Inner>> {
+    // `try_with` as sources may be finalized while the thread shuts down.
+    CLOCKS
+        .try_with(|clocks| {
+            let mut clocks = clocks.borrow_mut();
+            clocks.retain(|clock| clock.strong_count() > 0);
+            clocks
+                .iter()
+                .filter_map(Weak::upgrade)
+                .find(|clock| clock.context.to_glib_none().0 == context)
+        })
+        .ok()
+        .flatten()
+}
+
+// Whether a `VirtualClock` exists on the current thread, in which case new
+// timeouts are created as virtual-time aware sources.
+pub(crate) fn is_active() -> bool {
+    CLOCKS
+        .try_with(|clocks| clocks.borrow().iter().any(|clock| clock.strong_count() > 0))
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Deadline {
+    Virtual(Duration),
+    // Monotonic time in microseconds, used when no clock drives the context.
+    Real(i64),
+}
+
+#[repr(C)]
+struct TimeoutSource {
+    source: ffi::GSource,
+    interval: Duration,
+    deadline: Cell<Option<Deadline>>,
+}
+
+impl TimeoutSource {
+    // The first expiration is counted from the first time the source is
+    // prepared, i.e. from the iteration after it was attached.
+    unsafe fn ready(source: *mut ffi::GSource) -> (bool, c_int) {
+        let this = &*(source as *const TimeoutSource);
+        match clock_for(ffi::g_source_get_context(source)) {
+            Some(clock) => {
+                clock.track(source);
+                let now = clock.now.get();
+                let deadline = match this.deadline.get() {
+                    Some(Deadline::Virtual(deadline)) => deadline,
+                    _ => {
+                        let deadline = now + this.interval;
+                        this.deadline.set(Some(Deadline::Virtual(deadline)));
+                        deadline
+                    }
+                };
+                // Never wake up for real, only `advance()` moves virtual time.
+                (now >= deadline, -1)
+            }
+            None => {
+                let now = ffi::g_source_get_time(source);
+                let deadline = match this.deadline.get() {
+                    Some(Deadline::Real(deadline)) => deadline,
+                    _ => {
+                        let deadline = now + this.interval.as_micros() as i64;
+                        this.deadline.set(Some(Deadline::Real(deadline)));
+                        deadline
+                    }
+                };
+                if now >= deadline {
+                    (true, 0)
+                } else {
+                    let remaining = (deadline - now + 999) / 1000;
+                    (false, std::cmp::min(remaining, c_int::MAX as i64) as c_int)
+                }
+            }
+        }
+    }
+
+    unsafe extern "C" fn prepare(source: *mut ffi::GSource, timeout: *mut c_int) -> gboolean {
+        let (ready, remaining) = Self::ready(source);
+        *timeout = remaining;
+        ready.into_glib()
+    }
+
+    unsafe extern "C" fn check(source: *mut ffi::GSource) -> gboolean {
+        Self::ready(source).0.into_glib()
+    }
+
+    unsafe extern "C" fn dispatch(
+        source: *mut ffi::GSource,
+        callback: ffi::GSourceFunc,
+        user_data: gpointer,
+    ) -> gboolean {
+        let this = &*(source as *const TimeoutSource);
+        let callback = match callback {
+            Some(callback) => callback,
+            None => return ffi::G_SOURCE_REMOVE,
+        };
+
+        let clock = clock_for(ffi::g_source_get_context(source));
+        if let Some(ref clock) = clock {
+            clock.record(source);
+        }
+
+        let result = callback(user_data);
+        if result != ffi::G_SOURCE_REMOVE {
+            // Like GLib's timeouts the next expiration is counted from the
+            // dispatch. Zero intervals would otherwise make `advance()` spin.
+            this.deadline.set(Some(match clock {
+                Some(clock) => Deadline::Virtual(
+                    clock.now.get() + std::cmp::max(this.interval, Duration::from_millis(1)),
+                ),
+                None => Deadline::Real(
+                    ffi::g_source_get_time(source) + this.interval.as_micros() as i64,
+                ),
+            }));
+        }
+        result
+    }
+}
+
+// Creates a timeout that expires in virtual time while the context it is
+// attached to is driven by a `VirtualClock`, and in real time otherwise.
+pub(crate) unsafe fn timeout_source_new(interval: Duration) -> *mut ffi::GSource {
+    static TIMEOUT_SOURCE_FUNCS: ffi::GSourceFuncs = ffi::GSourceFuncs {
+        prepare: Some(TimeoutSource::prepare),
+        check: Some(TimeoutSource::check),
+        dispatch: Some(TimeoutSource::dispatch),
+        finalize: None,
+        closure_callback: None,
+        closure_marshal: None,
+    };
+
+    let source = ffi::g_source_new(
+        mut_override(&TIMEOUT_SOURCE_FUNCS),
+        mem::size_of::<TimeoutSource>() as u32,
+    );
+    {
+        let source = &mut *(source as *mut TimeoutSource);
+        ptr::write(&mut source.interval, interval);
+        ptr::write(&mut source.deadline, Cell::new(None));
+    }
+    source
+}
+
+/// Drives a [`MainContext`] in virtual time.
+///
+/// Time only moves when told to with [`advance()`](Self::advance). Timeouts
+/// attached to the context then expire in deadline order; timeouts with the
+/// same deadline are dispatched by priority and then in the order they were
+/// attached, just like a real main loop without any scheduling jitter.
+///
+/// The clock is bound to a [`MainContext`] (the thread-default one unless
+/// created with [`with_context()`](Self::with_context)) and acquires it for as
+/// long as the clock exists, so no other thread can dispatch its sources.
+/// Only one clock should be bound to a context at a time.
+///
+/// A clock and its clones share the same time. Timeouts created before the
+/// clock, or on another thread, keep running in real time.
+#[derive(Clone)]
+pub struct VirtualClock {
+    inner: Rc<Inner>,
+}
+
+impl fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("now", &self.now())
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+// Clears the re-entrancy flag of `advance()` even if a callback panics.
+struct AdvanceGuard<'a>(&'a Cell<bool>);
+
+impl Drop for AdvanceGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+impl VirtualClock {
+    /// Creates a clock at time zero bound to the thread-default main context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is owned by another thread.
+    pub fn new() -> Self {
+        Self::with_context(&MainContext::ref_thread_default())
+    }
+
+    /// Creates a clock at time zero bound to `context`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `context` is owned by another thread.
+    pub fn with_context(context: &MainContext) -> Self {
+        let acquired: bool =
+            unsafe { from_glib(ffi::g_main_context_acquire(context.to_glib_none().0)) };
+        assert!(
+            acquired,
+            "VirtualClock can't be bound to a main context owned by another thread"
+        );
+
+        let inner = Rc::new(Inner {
+            context: context.clone(),
+            now: Cell::new(Duration::from_millis(0)),
+            timeouts: RefCell::new(Vec::new()),
+            fired: RefCell::new(Vec::new()),
+            advancing: Cell::new(false),
+            queued: Cell::new(Duration::from_millis(0)),
+        });
+        CLOCKS.with(|clocks| clocks.borrow_mut().push(Rc::downgrade(&inner)));
+
+        Self { inner }
+    }
+
+    /// The main context driven by this clock.
+    pub fn context(&self) -> &MainContext {
+        &self.inner.context
+    }
+
+    /// Number of timeouts that are scheduled in virtual time.
+    pub fn pending(&self) -> usize {
+        self.prepare();
+        self.inner.next_deadline();
+        self.inner.timeouts.borrow().len()
+    }
+
+    /// Virtual time until the next timeout expires, if any timeout is scheduled.
+    ///
+    /// Returns zero if the context has a source that is ready to be dispatched.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        if self.prepare() {
+            return Some(Duration::from_millis(0));
+        }
+        let now = self.now();
+        self.inner
+            .next_deadline()
+            .map(|deadline| deadline.checked_sub(now).unwrap_or_default())
+    }
+
+    /// Moves virtual time forward by `duration`, dispatching every timeout
+    /// that becomes due on the way at its exact deadline.
+    ///
+    /// The context is run until idle before each deadline and once more after
+    /// the target time was reached, so idle sources, futures and channels
+    /// attached to it make progress too.
+    ///
+    /// Calls from a callback dispatched by `advance()` don't recurse but are
+    /// queued: time moves on by the queued duration once the outer call
+    /// reached its own target.
+    pub fn advance(&self, duration: Duration) {
+        let inner = &self.inner;
+        if inner.advancing.get() {
+            inner.queued.set(inner.queued.get() + duration);
+            return;
+        }
+        inner.advancing.set(true);
+        let _guard = AdvanceGuard(&inner.advancing);
+
+        let mut target = self.now() + duration;
+        loop {
+            self.run_until_idle();
+
+            let now = self.now();
+            match inner.next_deadline() {
+                Some(deadline) if deadline > now && deadline <= target => {
+                    inner.now.set(deadline);
+                }
+                _ => {
+                    let queued = inner.queued.replace(Duration::from_millis(0));
+                    if queued == Duration::from_millis(0) {
+                        break;
+                    }
+                    target += queued;
+                }
+            }
+        }
+        inner.now.set(target);
+        self.run_until_idle();
+    }
+
+    /// Advances virtual time to the next deadline and dispatches it.
+    ///
+    /// Returns `false` if no timeout is scheduled.
+    pub fn advance_to_next(&self) -> bool {
+        self.run_until_idle();
+        match self.next_deadline() {
+            Some(remaining) => {
+                self.advance(remaining);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates the main context until it has nothing left to dispatch.
+    /// Virtual time does not move.
+    ///
+    /// # Panics
+    ///
+    /// Panics if sources keep rescheduling themselves, as the real main loop
+    /// would never become idle in that case either.
+    pub fn run_until_idle(&self) {
+        for _ in 0..MAX_ITERATIONS {
+            if !self.inner.context.iteration(false) {
+                return;
+            }
+        }
+        panic!(
+            "VirtualClock did not become idle after {} iterations, a source keeps returning glib::Continue(true)",
+            MAX_ITERATIONS
+        );
+    }
+
+    /// All dispatches recorded so far, oldest first.
+    ///
+    /// Every timeout dispatched in virtual time is recorded, as well as idle
+    /// callbacks added through the [`Clock`] trait.
+    pub fn fired(&self) -> Vec<FiredSource> {
+        self.inner.fired.borrow().clone()
+    }
+
+    /// Names of all dispatched sources, oldest first. Unnamed sources are skipped.
+    pub fn fired_names(&self) -> Vec<String> {
+        self.inner
+            .fired
+            .borrow()
+            .iter()
+            .filter_map(|fired| fired.name.clone())
+            .collect()
+    }
+
+    /// Returns the recorded dispatches and clears the record.
+    pub fn take_fired(&self) -> Vec<FiredSource> {
+        self.inner.fired.replace(Vec::new())
+    }
+
+    // Prepares the sources of the context without dispatching them, which
+    // gives newly attached timeouts their virtual deadline. Returns whether a
+    // source is ready.
+    fn prepare(&self) -> bool {
+        self.inner.context.pending()
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    type Id = SourceId;
+
+    fn now(&self) -> Duration {
+        self.inner.now.get()
+    }
+
+    fn idle_add_local_full<F>(
+        &self,
+        name: Option<&str>,
+        priority: Priority,
+        mut func: F,
+    ) -> SourceId
+    where
+        F: FnMut() -> Continue + 'static,
+    {
+        let inner = Rc::downgrade(&self.inner);
+        // The context is owned by this thread as long as the clock exists.
+        let source = unsafe {
+            crate::source::idle_source_new_unsafe(name, priority, move || {
+                if let Some(inner) = inner.upgrade() {
+                    inner.record(ffi::g_main_current_source());
+                }
+                func()
+            })
+        };
+        source.attach(Some(&self.inner.context))
+    }
+
+    fn timeout_add_local_full<F>(&self, interval: Duration, name: Option<&str>, func: F) -> SourceId
+    where
+        F: FnMut() -> Continue + 'static,
+    {
+        let source = unsafe {
+            crate::source::timeout_source_new_unsafe(interval, name, crate::PRIORITY_DEFAULT, func)
+        };
+        source.attach(Some(&self.inner.context))
+    }
+
+    fn source_remove(&self, id: SourceId) {
+        if let Some(source) = self.inner.context.find_source_by_id(&id) {
+            source.destroy();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_timeouts_fire_in_deadline_order() {
+        let clock = VirtualClock::with_context(&MainContext::new());
+        clock.timeout_add_local_full(ms(30), Some("c"), || Continue(false));
+        clock.timeout_add_local_full(ms(10), Some("a"), || Continue(false));
+        clock.timeout_add_local_full(ms(20), Some("b"), || Continue(false));
+
+        clock.advance(ms(15));
+        assert_eq!(clock.fired_names(), ["a"]);
+        assert_eq!(clock.now(), ms(15));
+
+        clock.advance(ms(100));
+        assert_eq!(clock.fired_names(), ["a", "b", "c"]);
+        let at: Vec<_> = clock.fired().iter().map(|f| f.at).collect();
+        assert_eq!(at, [ms(10), ms(20), ms(30)]);
+        assert_eq!(clock.pending(), 0);
+    }
+
+    #[test]
+    fn test_repeating_timeout_and_remove() {
+        let clock = VirtualClock::with_context(&MainContext::new());
+        let count = Rc::new(Cell::new(0));
+        let count_clone = count.clone();
+        let id = clock.timeout_add_local(ms(100), move || {
+            count_clone.set(count_clone.get() + 1);
+            Continue(true)
+        });
+
+        clock.advance(ms(350));
+        assert_eq!(count.get(), 3);
+        assert_eq!(clock.next_deadline(), Some(ms(50)));
+
+        clock.source_remove(id);
+        clock.advance(ms(1000));
+        assert_eq!(count.get(), 3);
+        assert_eq!(clock.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_debounce_restarts() {
+        let clock = VirtualClock::with_context(&MainContext::new());
+        let pending = RefCell::new(None);
+        let trigger = || {
+            if let Some(id) = pending.borrow_mut().take() {
+                clock.source_remove(id);
+            }
+            *pending.borrow_mut() =
+                Some(clock.timeout_add_local_full(ms(200), Some("save"), || Continue(false)));
+        };
+
+        trigger();
+        clock.advance(ms(150));
+        trigger();
+        clock.advance(ms(150));
+        assert!(clock.fired().is_empty());
+        clock.advance(ms(50));
+        assert_eq!(clock.fired_names(), ["save"]);
+        assert_eq!(clock.fired()[0].at, ms(350));
+    }
+
+    #[test]
+    fn test_idle_sources_and_context() {
+        let context = MainContext::new();
+        let clock = VirtualClock::with_context(&context);
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let order_clone = order.clone();
+        let clock_clone = clock.clone();
+        clock.idle_add_local_full(Some("low"), crate::PRIORITY_LOW, move || {
+            order_clone.borrow_mut().push("low");
+            let order_clone = order_clone.clone();
+            clock_clone.timeout_add_local_once(ms(5), move || {
+                order_clone.borrow_mut().push("nested");
+            });
+            Continue(false)
+        });
+        let order_clone = order.clone();
+        clock.idle_add_local_full(Some("high"), crate::PRIORITY_HIGH_IDLE, move || {
+            order_clone.borrow_mut().push("high");
+            Continue(false)
+        });
+        let order_clone = order.clone();
+        let _guard = context.acquire().unwrap();
+        context.spawn_local(async move { order_clone.borrow_mut().push("future") });
+
+        clock.run_until_idle();
+        assert_eq!(*order.borrow(), ["future", "high", "low"]);
+        assert_eq!(clock.now(), ms(0));
+
+        assert!(clock.advance_to_next());
+        assert_eq!(*order.borrow(), ["future", "high", "low", "nested"]);
+        assert_eq!(clock.now(), ms(5));
+        assert!(!clock.advance_to_next());
+    }
+
+    #[test]
+    fn test_context_timeouts_use_virtual_time() {
+        let context = MainContext::new();
+        context.with_thread_default(|| {
+            let clock = VirtualClock::new();
+            let done = Rc::new(Cell::new(false));
+            let done_clone = done.clone();
+            context.spawn_local(async move {
+                crate::timeout_future(ms(500)).await;
+                done_clone.set(true);
+            });
+            crate::timeout_source_new_seconds(2, Some("tick"), crate::PRIORITY_DEFAULT, || {
+                Continue(true)
+            })
+            .attach(Some(&context));
+
+            clock.advance(ms(499));
+            assert!(!done.get());
+            clock.advance(ms(1));
+            assert!(done.get());
+            assert_eq!(clock.fired()[0].at, ms(500));
+
+            clock.advance(ms(4000));
+            let at: Vec<_> = clock
+                .fired()
+                .iter()
+                .filter(|fired| fired.name.as_deref() == Some("tick"))
+                .map(|fired| fired.at)
+                .collect();
+            assert_eq!(at, [ms(2000), ms(4000)]);
+            assert_eq!(clock.next_deadline(), Some(ms(1500)));
+        });
+    }
+
+    #[test]
+    fn test_reentrant_advance_is_queued() {
+        let clock = VirtualClock::with_context(&MainContext::new());
+        let clock_clone = clock.clone();
+        clock.timeout_add_local_once(ms(10), move || clock_clone.advance(ms(100)));
+        clock.timeout_add_local_full(ms(50), Some("late"), || Continue(false));
+
+        clock.advance(ms(20));
+        assert_eq!(clock.now(), ms(120));
+        assert_eq!(clock.fired_names(), ["late"]);
+        assert_eq!(clock.fired()[1].at, ms(50));
+    }
+
+    #[test]
+    #[should_panic(expected = "did not become idle")]
+    fn test_endless_idle_panics() {
+        let clock = VirtualClock::with_context(&MainContext::new());
+        clock.idle_add_local(|| Continue(true));
+        clock.run_until_idle();
+    }
+}
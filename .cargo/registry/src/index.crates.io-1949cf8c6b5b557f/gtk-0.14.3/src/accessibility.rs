@@ -0,0 +1,412 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Inspection of what assistive technologies see.
+//!
+//! [`AccessibilityTree`] is a snapshot of the [`atk::Object`] hierarchy of a
+//! widget, and [`AccessibilityTree::audit()`] checks it for common problems.
+//! Its [`Display`](std::fmt::Display) output lists one node per line, which
+//! works well for snapshot tests.
+//!
+//! ```no_run
+//! use gtk::accessibility;
+//!
+//! fn check_window(window: &gtk::Window) {
+//!     let tree = accessibility::AccessibilityTree::for_widget(window).unwrap();
+//!     println!("{}", tree);
+//!     for issue in tree.audit() {
+//!         eprintln!("{}", issue);
+//!     }
+//! }
+//! ```
+
+use crate::prelude::*;
+use crate::Widget;
+use glib::translate::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// An action of an accessible object, e.g. `click`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessibleAction {
+    pub name: String,
+    pub keybinding: Option<String>,
+}
+
+/// A relation between accessible objects, e.g. `labelled-by`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessibleRelation {
+    pub relation_type: atk::RelationType,
+    /// Indices of the targets in the tree. Targets outside of the tree are
+    /// left out.
+    pub targets: Vec<usize>,
+}
+
+/// A node of an [`AccessibilityTree`].
+#[derive(Debug, Clone)]
+pub struct AccessibleNode {
+    pub object: atk::Object,
+    /// The widget the object belongs to, if any.
+    pub widget: Option<Widget>,
+    pub role: atk::Role,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub states: Vec<atk::StateType>,
+    pub relations: Vec<AccessibleRelation>,
+    pub actions: Vec<AccessibleAction>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub depth: usize,
+}
+
+impl AccessibleNode {
+    pub fn has_state(&self, state: atk::StateType) -> bool {
+        self.states.contains(&state)
+    }
+
+    pub fn relation(&self, relation_type: atk::RelationType) -> Option<&AccessibleRelation> {
+        self.relations
+            .iter()
+            .find(|relation| relation.relation_type == relation_type)
+    }
+
+    /// Whether the node has a name or is labelled by another object.
+    pub fn is_labelled(&self) -> bool {
+        self.name
+            .as_deref()
+            .map_or(false, |name| !name.trim().is_empty())
+            || self.relation(atk::RelationType::LabelledBy).is_some()
+    }
+
+    /// Whether the node has a description or is described by another object.
+    pub fn is_described(&self) -> bool {
+        self.description
+            .as_deref()
+            .map_or(false, |description| !description.trim().is_empty())
+            || self.relation(atk::RelationType::DescribedBy).is_some()
+    }
+
+    fn role_name(&self) -> String {
+        self.role
+            .name()
+            .map_or_else(|| format!("{:?}", self.role), |name| name.to_string())
+    }
+}
+
+impl fmt::Display for AccessibleNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.role_name())?;
+        if let Some(ref name) = self.name {
+            write!(f, " {:?}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// A snapshot of an accessible object and its descendants, in depth-first
+/// order.
+#[derive(Debug, Clone)]
+pub struct AccessibilityTree {
+    nodes: Vec<AccessibleNode>,
+}
+
+impl AccessibilityTree {
+    pub fn for_widget<W: IsA<Widget>>(widget: &W) -> Option<Self> {
+        widget.accessible().map(|object| Self::for_object(&object))
+    }
+
+    pub fn for_object(root: &atk::Object) -> Self {
+        let mut nodes: Vec<AccessibleNode> = Vec::new();
+        let mut indices = HashMap::new();
+        let mut pending: Vec<(atk::Object, Option<usize>, usize)> = vec![(root.clone(), None, 0)];
+        while let Some((object, parent, depth)) = pending.pop() {
+            // Broken implementations can contain cycles.
+            if indices.contains_key(&object.as_ptr()) {
+                continue;
+            }
+            let index = nodes.len();
+            indices.insert(object.as_ptr(), index);
+            if let Some(parent) = parent {
+                nodes[parent].children.push(index);
+            }
+
+            let children = (0..object.n_accessible_children())
+                .filter_map(|i| object.ref_accessible_child(i))
+                .collect::<Vec<_>>();
+            for child in children.into_iter().rev() {
+                pending.push((child, Some(index), depth + 1));
+            }
+            nodes.push(node(object, parent, depth));
+        }
+
+        // Resolve the relation targets now that all nodes are known.
+        for node in &mut nodes {
+            let relation_set = match node.object.ref_relation_set() {
+                Some(relation_set) => relation_set,
+                None => continue,
+            };
+            for i in 0..relation_set.n_relations() {
+                if let Some(relation) = relation_set.relation(i) {
+                    node.relations.push(AccessibleRelation {
+                        relation_type: relation.relation_type(),
+                        targets: relation
+                            .target()
+                            .iter()
+                            .filter_map(|target| indices.get(&target.as_ptr()).copied())
+                            .collect(),
+                    });
+                }
+            }
+        }
+        Self { nodes }
+    }
+
+    pub fn root(&self) -> &AccessibleNode {
+        &self.nodes[0]
+    }
+
+    pub fn nodes(&self) -> &[AccessibleNode] {
+        &self.nodes
+    }
+
+    /// Finds the first node with the accessible name `name`.
+    pub fn find_by_name(&self, name: &str) -> Option<&AccessibleNode> {
+        self.nodes
+            .iter()
+            .find(|node| node.name.as_deref() == Some(name))
+    }
+
+    pub fn find_by_role(&self, role: atk::Role) -> Vec<&AccessibleNode> {
+        self.nodes.iter().filter(|node| node.role == role).collect()
+    }
+
+    /// Describes the position of the node at `index`, e.g.
+    /// `frame "Settings" > filler > push button "OK"`.
+    pub fn path(&self, index: usize) -> String {
+        let mut path = vec![self.nodes[index].to_string()];
+        let mut parent = self.nodes[index].parent;
+        while let Some(index) = parent {
+            path.push(self.nodes[index].to_string());
+            parent = self.nodes[index].parent;
+        }
+        path.reverse();
+        path.join(" > ")
+    }
+
+    /// Checks all visible nodes for the problems described by [`AuditRule`].
+    pub fn audit(&self) -> Vec<AuditIssue> {
+        let mut issues = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if !node.has_state(atk::StateType::Visible) {
+                continue;
+            }
+            let mut report = |rule| {
+                issues.push(AuditIssue {
+                    rule,
+                    node: index,
+                    path: self.path(index),
+                    widget: node.widget.clone(),
+                })
+            };
+
+            if is_button(node.role) && !node.is_labelled() {
+                report(AuditRule::UnlabeledButton);
+            }
+            if matches!(node.role, atk::Role::Image | atk::Role::Icon)
+                && !node.is_labelled()
+                && !node.is_described()
+                && !self.is_decorative(index)
+            {
+                report(AuditRule::ImageWithoutDescription);
+            }
+            if is_interactive(node)
+                && node.has_state(atk::StateType::Sensitive)
+                && !node.has_state(atk::StateType::Focusable)
+            {
+                report(AuditRule::NotKeyboardAccessible);
+            }
+        }
+        issues
+    }
+
+    // Images inside labelled buttons only decorate the label.
+    fn is_decorative(&self, index: usize) -> bool {
+        let mut parent = self.nodes[index].parent;
+        while let Some(index) = parent {
+            let node = &self.nodes[index];
+            if is_button(node.role) && node.is_labelled() {
+                return true;
+            }
+            parent = node.parent;
+        }
+        false
+    }
+}
+
+impl fmt::Display for AccessibilityTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{:indent$}{}", "", node, indent = node.depth * 2)?;
+            if let Some(ref description) = node.description {
+                write!(f, " ({:?})", description)?;
+            }
+            let states = node
+                .states
+                .iter()
+                .map(|state| {
+                    state
+                        .name()
+                        .map_or_else(String::new, |name| name.to_string())
+                })
+                .collect::<Vec<_>>();
+            if !states.is_empty() {
+                write!(f, " [{}]", states.join(", "))?;
+            }
+            for relation in &node.relations {
+                let targets = relation
+                    .targets
+                    .iter()
+                    .map(|&target| self.nodes[target].to_string())
+                    .collect::<Vec<_>>();
+                let relation_name = relation
+                    .relation_type
+                    .name()
+                    .map_or_else(String::new, |name| name.to_string());
+                write!(f, " {} -> {}", relation_name, targets.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// A check of [`AccessibilityTree::audit()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditRule {
+    /// A button, check box, radio button or link has no accessible name.
+    UnlabeledButton,
+    /// An image has neither a name nor a description and is not part of a
+    /// labelled button.
+    ImageWithoutDescription,
+    /// A sensitive control can't receive the keyboard focus.
+    NotKeyboardAccessible,
+}
+
+impl fmt::Display for AuditRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Self::UnlabeledButton => "button without an accessible name",
+            Self::ImageWithoutDescription => "image without a name or description",
+            Self::NotKeyboardAccessible => "control can't be focused with the keyboard",
+        })
+    }
+}
+
+/// A problem found by [`AccessibilityTree::audit()`].
+#[derive(Debug, Clone)]
+pub struct AuditIssue {
+    pub rule: AuditRule,
+    /// The index of the node in the tree.
+    pub node: usize,
+    /// The position of the node, see [`AccessibilityTree::path()`].
+    pub path: String,
+    pub widget: Option<Widget>,
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.rule)
+    }
+}
+
+/// Audits `widget` and panics with a list of all issues if there are any.
+pub fn assert_accessible<W: IsA<Widget>>(widget: &W) {
+    let tree = AccessibilityTree::for_widget(widget).expect("Widget has no accessible object");
+    let issues = tree.audit();
+    if !issues.is_empty() {
+        let issues = issues
+            .iter()
+            .map(|issue| format!("  {}", issue))
+            .collect::<Vec<_>>();
+        panic!("Accessibility issues found:\n{}", issues.join("\n"));
+    }
+}
+
+fn node(object: atk::Object, parent: Option<usize>, depth: usize) -> AccessibleNode {
+    let states = object
+        .ref_state_set()
+        .map(|state_set| {
+            (1..atk::ffi::ATK_STATE_LAST_DEFINED)
+                .map(|state| unsafe { atk::StateType::from_glib(state) })
+                .filter(|&state| state_set.contains_state(state))
+                .collect()
+        })
+        .unwrap_or_default();
+    let actions = object
+        .dynamic_cast_ref::<atk::Action>()
+        .map(|action| {
+            (0..action.n_actions())
+                .filter_map(|i| {
+                    Some(AccessibleAction {
+                        name: action.name(i)?.to_string(),
+                        keybinding: action
+                            .keybinding(i)
+                            .map(|keybinding| keybinding.to_string())
+                            .filter(|keybinding| !keybinding.is_empty()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    AccessibleNode {
+        widget: widget(&object),
+        role: object.role(),
+        name: object.name().map(|name| name.to_string()),
+        description: object
+            .description()
+            .map(|description| description.to_string()),
+        states,
+        relations: Vec::new(),
+        actions,
+        parent,
+        children: Vec::new(),
+        depth,
+        object,
+    }
+}
+
+fn widget(object: &atk::Object) -> Option<Widget> {
+    unsafe {
+        let accessible_type: glib::Type = from_glib(ffi::gtk_accessible_get_type());
+        if !object.type_().is_a(accessible_type) {
+            return None;
+        }
+        from_glib_none(ffi::gtk_accessible_get_widget(
+            object.as_ptr() as *mut ffi::GtkAccessible
+        ))
+    }
+}
+
+fn is_button(role: atk::Role) -> bool {
+    matches!(
+        role,
+        atk::Role::PushButton
+            | atk::Role::ToggleButton
+            | atk::Role::CheckBox
+            | atk::Role::RadioButton
+            | atk::Role::Link
+    )
+}
+
+// Controls that users operate directly. Menu items, cells and tabs are
+// reached through the focus of their parent instead.
+fn is_interactive(node: &AccessibleNode) -> bool {
+    match node.role {
+        atk::Role::Text | atk::Role::Entry => node.has_state(atk::StateType::Editable),
+        atk::Role::Slider
+        | atk::Role::SpinButton
+        | atk::Role::ComboBox
+        | atk::Role::PasswordText => true,
+        role => is_button(role),
+    }
+}
@@ -7,6 +7,7 @@ use crate::ResponseType;
 use crate::Widget;
 use crate::Window;
 use glib::translate::*;
+use glib::SignalHandlerId;
 use std::cell::Cell;
 use std::future::Future;
 use std::pin::Pin;
@@ -44,6 +45,10 @@ pub trait DialogExtManual: 'static {
     /// Shows the dialog and returns a `Future` that resolves to the
     /// `ResponseType` on response.
     ///
+    /// The future keeps its own reference to the dialog, so it can be
+    /// spawned with [`glib::MainContext::spawn_local`]. Dropping it before
+    /// the response hides the dialog.
+    ///
     /// ```no_run
     /// use gtk::prelude::*;
     ///
@@ -58,7 +63,7 @@ pub trait DialogExtManual: 'static {
     /// println!("Answer: {:?}", answer);
     /// # }
     /// ```
-    fn run_future<'a>(&'a self) -> Pin<Box<dyn Future<Output = ResponseType> + 'a>>;
+    fn run_future(&self) -> Pin<Box<dyn Future<Output = ResponseType>>>;
 }
 
 impl<O: IsA<Dialog> + IsA<Widget>> DialogExtManual for O {
@@ -69,28 +74,86 @@ impl<O: IsA<Dialog> + IsA<Widget>> DialogExtManual for O {
         }
     }
 
-    fn run_future<'a>(&'a self) -> Pin<Box<dyn Future<Output = ResponseType> + 'a>> {
-        Box::pin(async move {
-            let (sender, receiver) = futures_channel::oneshot::channel();
+    fn run_future(&self) -> Pin<Box<dyn Future<Output = ResponseType>>> {
+        Box::pin(wait_for_response(
+            self.clone(),
+            |dialog, callback| dialog.connect_response(move |_, response| callback(response)),
+            |dialog| dialog.show(),
+            |dialog| dialog.hide(),
+        ))
+    }
+}
+
+// Whether `response` accepts the contents of a dialog, e.g. the selected
+// files of a file chooser.
+pub(crate) fn is_accept(response: ResponseType) -> bool {
+    matches!(
+        response,
+        ResponseType::Accept | ResponseType::Ok | ResponseType::Yes | ResponseType::Apply
+    )
+}
+
+// Shows `object` and waits for its response. If the future is dropped
+// before the response, the handler is disconnected and `object` is hidden.
+pub(crate) async fn wait_for_response<O, C>(
+    object: O,
+    connect_response: C,
+    show: fn(&O),
+    hide: fn(&O),
+) -> ResponseType
+where
+    O: ObjectType,
+    C: FnOnce(&O, Box<dyn Fn(ResponseType)>) -> SignalHandlerId,
+{
+    let (sender, receiver) = futures_channel::oneshot::channel();
+    let sender = Cell::new(Some(sender));
+    let handler = connect_response(
+        &object,
+        Box::new(move |response| {
+            if let Some(sender) = sender.take() {
+                let _result = sender.send(response);
+            }
+        }),
+    );
+    let mut guard = ResponseGuard {
+        object,
+        handler: Some(handler),
+        hide,
+    };
 
-            let sender = Cell::new(Some(sender));
+    show(&guard.object);
+    let response = receiver.await.unwrap_or(ResponseType::None);
+    if let Some(handler) = guard.handler.take() {
+        disconnect_if_connected(&guard.object, handler);
+    }
+    response
+}
 
-            let response_handler = self.connect_response(move |_, response_type| {
-                if let Some(m) = sender.replace(None) {
-                    let _result = m.send(response_type);
-                }
-            });
+struct ResponseGuard<O: ObjectType> {
+    object: O,
+    handler: Option<SignalHandlerId>,
+    hide: fn(&O),
+}
 
-            self.show();
+impl<O: ObjectType> Drop for ResponseGuard<O> {
+    fn drop(&mut self) {
+        if let Some(handler) = self.handler.take() {
+            disconnect_if_connected(&self.object, handler);
+            (self.hide)(&self.object);
+        }
+    }
+}
 
-            if let Ok(response) = receiver.await {
-                if response != ResponseType::DeleteEvent {
-                    self.disconnect(response_handler);
-                }
-                response
-            } else {
-                ResponseType::None
-            }
-        })
+// Destroying a dialog, e.g. on `DeleteEvent`, already disconnects its
+// handlers.
+fn disconnect_if_connected<O: ObjectType>(object: &O, handler: SignalHandlerId) {
+    let connected = unsafe {
+        glib::gobject_ffi::g_signal_handler_is_connected(
+            object.as_ptr() as *mut glib::gobject_ffi::GObject,
+            handler.as_raw(),
+        ) != glib::ffi::GFALSE
+    };
+    if connected {
+        object.disconnect(handler);
     }
 }
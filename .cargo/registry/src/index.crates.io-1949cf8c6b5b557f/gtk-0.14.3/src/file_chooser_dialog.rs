@@ -1,13 +1,16 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
+use crate::dialog::is_accept;
+use crate::prelude::*;
 use crate::FileChooserAction;
 use crate::FileChooserDialog;
 use crate::ResponseType;
 use crate::Widget;
 use crate::Window;
-use glib::object::{Cast, IsA};
 use glib::translate::*;
 use libc::c_char;
+use std::future::Future;
+use std::pin::Pin;
 use std::ptr;
 
 impl FileChooserDialog {
@@ -93,4 +96,40 @@ impl FileChooserDialog {
             }).unsafe_cast()
         }
     }
+
+    // rustdoc-stripper-ignore-next
+    /// Shows the dialog and returns a `Future` that resolves to the selected
+    /// files once the dialog is accepted, or `None` if it is cancelled.
+    ///
+    /// The dialog is hidden afterwards, and when the future is dropped.
+    ///
+    /// ```no_run
+    /// use gtk::prelude::*;
+    ///
+    /// # async fn run(window: &gtk::ApplicationWindow) {
+    /// let dialog = gtk::FileChooserDialog::with_buttons(
+    ///     Some("Open Files"),
+    ///     Some(window),
+    ///     gtk::FileChooserAction::Open,
+    ///     &[("_Cancel", gtk::ResponseType::Cancel), ("_Open", gtk::ResponseType::Accept)],
+    /// );
+    /// dialog.set_select_multiple(true);
+    /// let files = dialog.run_files_future().await;
+    /// dialog.close();
+    /// println!("Selected files: {:?}", files);
+    /// # }
+    /// ```
+    pub fn run_files_future(&self) -> Pin<Box<dyn Future<Output = Option<Vec<gio::File>>>>> {
+        let dialog = self.clone();
+        Box::pin(async move {
+            let response = dialog.run_future().await;
+            let files = if is_accept(response) {
+                Some(dialog.files())
+            } else {
+                None
+            };
+            dialog.hide();
+            files
+        })
+    }
 }
@@ -238,9 +238,11 @@ mod recent_data;
 mod requisition;
 mod response_type;
 mod selection_data;
+mod shortcut_manager;
 mod signal;
 mod stack_switcher;
 mod style_context;
+mod stylesheet;
 mod switch;
 mod target_entry;
 mod target_list;
@@ -251,12 +253,17 @@ mod tree_path;
 mod tree_row_reference;
 mod tree_sortable;
 mod tree_store;
+mod typed_store;
 mod widget;
 
 #[macro_use]
 pub mod subclass;
 
+pub mod accessibility;
 pub mod prelude;
+pub mod reactive;
+pub mod testing;
+pub mod view;
 
 pub use crate::auto::functions::*;
 pub use crate::auto::*;
@@ -274,9 +281,17 @@ pub use crate::page_range::PageRange;
 pub use crate::recent_data::RecentData;
 pub use crate::requisition::Requisition;
 pub use crate::response_type::ResponseType;
+pub use crate::shortcut_manager::{
+    KeySequence, KeyStroke, Shortcut, ShortcutConflict, ShortcutError, ShortcutManager,
+};
 pub use crate::stack_switcher::StackSwitcherBuilder;
+pub use crate::stylesheet::{CssError, Stylesheet};
 pub use crate::target_entry::TargetEntry;
 pub use crate::tree_sortable::SortColumn;
+pub use crate::typed_store::{
+    append_columns, tree_view_columns, CellKind, ModelColumn, ModelRow, TypedListStore,
+    TypedTreeStore,
+};
 pub use crate::widget::TickCallbackId;
 #[cfg(any(feature = "v3_22", feature = "dox"))]
 pub use pad_action_entry::PadActionEntry;
@@ -1,14 +1,17 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
+use crate::prelude::*;
 use crate::ButtonsType;
 use crate::DialogFlags;
 use crate::MessageDialog;
 use crate::MessageType;
+use crate::ResponseType;
 use crate::Widget;
 use crate::Window;
-use glib::object::{Cast, IsA};
 use glib::translate::*;
 use libc::c_char;
+use std::future::Future;
+use std::pin::Pin;
 use std::ptr;
 
 impl MessageDialog {
@@ -35,6 +38,59 @@ impl MessageDialog {
             .unsafe_cast()
         }
     }
+
+    // rustdoc-stripper-ignore-next
+    /// Shows a modal message dialog for `parent` and returns a `Future` that
+    /// resolves to the response.
+    ///
+    /// The dialog is destroyed afterwards, and when the future is dropped.
+    ///
+    /// ```no_run
+    /// use gtk::prelude::*;
+    ///
+    /// fn confirm_close(window: &gtk::ApplicationWindow) {
+    ///     let window = window.clone();
+    ///     gtk::glib::MainContext::default().spawn_local(async move {
+    ///         let response = gtk::MessageDialog::run_message_future(
+    ///             Some(&window),
+    ///             gtk::MessageType::Question,
+    ///             gtk::ButtonsType::YesNo,
+    ///             "Discard unsaved changes?",
+    ///         )
+    ///         .await;
+    ///         if response == gtk::ResponseType::Yes {
+    ///             window.close();
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn run_message_future<T: IsA<Window>>(
+        parent: Option<&T>,
+        type_: MessageType,
+        buttons: ButtonsType,
+        message: &str,
+    ) -> Pin<Box<dyn Future<Output = ResponseType>>> {
+        assert_initialized_main_thread!();
+        let dialog = MessageDialog::new(
+            parent,
+            DialogFlags::MODAL | DialogFlags::DESTROY_WITH_PARENT,
+            type_,
+            buttons,
+            message,
+        );
+        let dialog = DestroyOnDrop(dialog);
+        Box::pin(async move { dialog.0.run_future().await })
+    }
+}
+
+// Nothing else has a reference to the dialog, so it can't be used after
+// being destroyed.
+struct DestroyOnDrop(MessageDialog);
+
+impl Drop for DestroyOnDrop {
+    fn drop(&mut self) {
+        unsafe { self.0.destroy() }
+    }
 }
 
 pub trait MessageDialogExt: 'static {
@@ -1,9 +1,13 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
+#[cfg(any(feature = "v3_20", feature = "dox"))]
+use crate::dialog::is_accept;
+use crate::dialog::wait_for_response;
 use crate::prelude::*;
+#[cfg(any(feature = "v3_20", feature = "dox"))]
+use crate::FileChooserNative;
 use crate::NativeDialog;
 use crate::ResponseType;
-use std::cell::Cell;
 use std::future::Future;
 use std::pin::Pin;
 
@@ -12,6 +16,10 @@ pub trait NativeDialogExtManual {
     /// Shows the dialog and returns a `Future` that resolves to the
     /// `ResponseType` on response.
     ///
+    /// The future keeps its own reference to the dialog, so it can be
+    /// spawned with [`glib::MainContext::spawn_local`]. Dropping it before
+    /// the response hides the dialog.
+    ///
     /// ```no_run
     /// use gtk::prelude::*;
     ///
@@ -27,34 +35,63 @@ pub trait NativeDialogExtManual {
     /// ```
     #[cfg(any(feature = "v3_20", feature = "dox"))]
     #[cfg_attr(feature = "dox", doc(cfg(feature = "v3_20")))]
-    fn run_future<'a>(&'a self) -> Pin<Box<dyn Future<Output = ResponseType> + 'a>>;
+    fn run_future(&self) -> Pin<Box<dyn Future<Output = ResponseType>>>;
 }
 
 impl<O: IsA<NativeDialog>> NativeDialogExtManual for O {
     #[cfg(any(feature = "v3_20", feature = "dox"))]
     #[cfg_attr(feature = "dox", doc(cfg(feature = "v3_20")))]
-    fn run_future<'a>(&'a self) -> Pin<Box<dyn Future<Output = ResponseType> + 'a>> {
-        Box::pin(async move {
-            let (sender, receiver) = futures_channel::oneshot::channel();
-
-            let sender = Cell::new(Some(sender));
-
-            let response_handler = self.connect_response(move |_, response_type| {
-                if let Some(m) = sender.replace(None) {
-                    let _result = m.send(response_type);
-                }
-            });
-
-            self.show();
+    fn run_future(&self) -> Pin<Box<dyn Future<Output = ResponseType>>> {
+        Box::pin(wait_for_response(
+            self.clone(),
+            |dialog, callback| dialog.connect_response(move |_, response| callback(response)),
+            |dialog| dialog.show(),
+            |dialog| dialog.hide(),
+        ))
+    }
+}
 
-            if let Ok(response) = receiver.await {
-                if response != ResponseType::DeleteEvent {
-                    self.disconnect(response_handler);
-                }
-                response
+#[cfg(any(feature = "v3_20", feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(feature = "v3_20")))]
+impl FileChooserNative {
+    // rustdoc-stripper-ignore-next
+    /// Shows the dialog and returns a `Future` that resolves to the selected
+    /// files once the dialog is accepted, or `None` if it is cancelled.
+    ///
+    /// The dialog is hidden afterwards, and when the future is dropped.
+    ///
+    /// ```no_run
+    /// use gtk::prelude::*;
+    ///
+    /// fn open_files(window: &gtk::ApplicationWindow) {
+    ///     let dialog = gtk::FileChooserNative::new(
+    ///         Some("Open Files"),
+    ///         Some(window),
+    ///         gtk::FileChooserAction::Open,
+    ///         None,
+    ///         None,
+    ///     );
+    ///     dialog.set_select_multiple(true);
+    ///     gtk::glib::MainContext::default().spawn_local(async move {
+    ///         if let Some(files) = dialog.run_files_future().await {
+    ///             for file in files {
+    ///                 println!("{}", file.uri());
+    ///             }
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn run_files_future(&self) -> Pin<Box<dyn Future<Output = Option<Vec<gio::File>>>>> {
+        let dialog = self.clone();
+        Box::pin(async move {
+            let response = dialog.run_future().await;
+            let files = if is_accept(response) {
+                Some(dialog.files())
             } else {
-                ResponseType::None
-            }
+                None
+            };
+            dialog.hide();
+            files
         })
     }
 }
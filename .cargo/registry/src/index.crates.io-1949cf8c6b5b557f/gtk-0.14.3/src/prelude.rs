@@ -42,6 +42,7 @@ pub use crate::menu::GtkMenuExtManual;
 #[cfg(any(feature = "v3_20", feature = "dox"))]
 pub use crate::native_dialog::NativeDialogExtManual;
 pub use crate::notebook::NotebookExtManual;
+pub use crate::reactive::Observable;
 #[cfg(any(feature = "v3_20", feature = "dox"))]
 pub use crate::stack_switcher::StackSwitcherExtManual;
 pub use crate::style_context::StyleContextExtManual;
@@ -0,0 +1,438 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Observable values that keep widgets in sync with application state.
+//!
+//! A [`State`] holds a value and notifies its subscribers when it changes. A
+//! [`Computed`] derives a value from other observables and recomputes it
+//! whenever one of the observables it read changes. Both can be bound to
+//! widget properties with [`Observable::bind()`], which stops updating a
+//! widget once it is destroyed.
+//!
+//! ```no_run
+//! use gtk::prelude::*;
+//! use gtk::reactive::{Computed, State};
+//!
+//! fn counter(label: &gtk::Label, button: &gtk::Button) {
+//!     let count = State::new(0u32);
+//!     let text = {
+//!         let count = count.clone();
+//!         Computed::new(move || format!("Clicked {} times", count.get()))
+//!     };
+//!     text.bind(label, "label");
+//!     button.connect_clicked(move |_| count.update(|count| *count += 1));
+//! }
+//! ```
+
+use crate::prelude::*;
+use crate::Widget;
+use glib::value::FromValue;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+thread_local! {
+    // The dependencies read by each `Computed` that is currently computing.
+    static TRACKING: RefCell<Vec<Vec<Rc<dyn Source>>>> = RefCell::new(Vec::new());
+}
+
+// Runs `f` and returns the observables it read.
+fn tracked<R, F: FnOnce() -> R>(f: F) -> (R, Vec<Rc<dyn Source>>) {
+    TRACKING.with(|tracking| tracking.borrow_mut().push(Vec::new()));
+    let result = f();
+    let dependencies = TRACKING.with(|tracking| tracking.borrow_mut().pop().unwrap_or_default());
+    (result, dependencies)
+}
+
+// The type independent part of a `Node`.
+trait Source {
+    fn subscribe_any(self: Rc<Self>, f: Rc<dyn Fn()>) -> Subscription;
+    fn unsubscribe(&self, id: usize);
+}
+
+struct Node<T> {
+    value: RefCell<T>,
+    listeners: RefCell<Vec<(usize, Rc<dyn Fn(&T)>)>>,
+    next_id: Cell<usize>,
+}
+
+impl<T: Clone + 'static> Node<T> {
+    fn new(value: T) -> Rc<Self> {
+        Rc::new(Self {
+            value: RefCell::new(value),
+            listeners: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+        })
+    }
+
+    fn get(self: &Rc<Self>) -> T {
+        TRACKING.with(|tracking| {
+            if let Some(dependencies) = tracking.borrow_mut().last_mut() {
+                let ptr = Rc::as_ptr(self) as *const u8;
+                if !dependencies
+                    .iter()
+                    .any(|dependency| Rc::as_ptr(dependency) as *const u8 == ptr)
+                {
+                    dependencies.push(self.clone());
+                }
+            }
+        });
+        self.value.borrow().clone()
+    }
+
+    fn subscribe(self: &Rc<Self>, f: Rc<dyn Fn(&T)>) -> Subscription {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.listeners.borrow_mut().push((id, f));
+        let node: Weak<Self> = Rc::downgrade(self);
+        let source: Weak<dyn Source> = node;
+        Subscription { id, source }
+    }
+
+    fn notify(&self) {
+        // Listeners may change the subscriptions or set other values.
+        let value = self.value.borrow().clone();
+        let listeners = self
+            .listeners
+            .borrow()
+            .iter()
+            .map(|(_, f)| f.clone())
+            .collect::<Vec<_>>();
+        for listener in listeners {
+            listener(&value);
+        }
+    }
+}
+
+impl<T: Clone + 'static> Source for Node<T> {
+    fn subscribe_any(self: Rc<Self>, f: Rc<dyn Fn()>) -> Subscription {
+        self.subscribe(Rc::new(move |_| f()))
+    }
+
+    fn unsubscribe(&self, id: usize) {
+        self.listeners.borrow_mut().retain(|(i, _)| *i != id);
+    }
+}
+
+/// A subscription to an [`Observable`].
+///
+/// Dropping it does not unsubscribe.
+#[derive(Clone)]
+pub struct Subscription {
+    id: usize,
+    source: Weak<dyn Source>,
+}
+
+impl Subscription {
+    /// Stops calling the subscriber. Does nothing if it was already stopped.
+    pub fn unsubscribe(&self) {
+        if let Some(source) = self.source.upgrade() {
+            source.unsubscribe(self.id);
+        }
+    }
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// A value that notifies subscribers when it changes.
+pub trait Observable<T: Clone + 'static> {
+    /// Returns the current value.
+    ///
+    /// When called while a [`Computed`] computes its value, the computed
+    /// value is recomputed whenever this value changes.
+    fn get(&self) -> T;
+
+    /// Calls `f` with the new value after every change.
+    fn subscribe<F: Fn(&T) + 'static>(&self, f: F) -> Subscription;
+
+    /// Sets `property` of `widget` to the value now and after every change,
+    /// until `widget` is destroyed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `widget` has no `property` of type `T`.
+    fn bind<W: IsA<Widget>>(&self, widget: &W, property: &str) -> Subscription
+    where
+        T: ToValue,
+    {
+        let widget = widget.upcast_ref::<Widget>();
+        widget
+            .set_property(property, self.get())
+            .expect("failed to set bound property");
+
+        let weak = widget.downgrade();
+        let property = property.to_owned();
+        let subscription = self.subscribe(move |value| {
+            if let Some(widget) = weak.upgrade() {
+                widget
+                    .set_property_from_value(property.as_str(), &value.to_value())
+                    .expect("failed to set bound property");
+            }
+        });
+        let destroyed = subscription.clone();
+        widget.connect_destroy(move |_| destroyed.unsubscribe());
+        subscription
+    }
+}
+
+/// An observable cell.
+pub struct State<T>(Rc<Node<T>>);
+
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for State<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("State")
+            .field(&self.0.value.borrow())
+            .finish()
+    }
+}
+
+impl<T: Clone + Default + 'static> Default for State<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Clone + 'static> State<T> {
+    pub fn new(value: T) -> Self {
+        Self(Node::new(value))
+    }
+
+    /// Creates a state that follows `property` of `object`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `object` has no `property` of type `T`.
+    pub fn from_property<O: IsA<glib::Object>>(object: &O, property: &str) -> Self
+    where
+        T: for<'a> FromValue<'a> + PartialEq,
+    {
+        let value = property_value(object.upcast_ref(), property);
+        let state = Self::new(value);
+
+        let weak = Rc::downgrade(&state.0);
+        let name = property.to_owned();
+        object.upcast_ref::<glib::Object>().connect_notify_local(
+            Some(property),
+            move |object, _| {
+                if let Some(node) = weak.upgrade() {
+                    State(node).set(property_value(object, &name));
+                }
+            },
+        );
+        state
+    }
+
+    /// Sets the value, notifying subscribers if it differs from the
+    /// current value.
+    pub fn set(&self, value: T)
+    where
+        T: PartialEq,
+    {
+        if *self.0.value.borrow() == value {
+            return;
+        }
+        self.0.value.replace(value);
+        self.0.notify();
+    }
+
+    /// Changes a copy of the value, stores it and notifies subscribers.
+    ///
+    /// The state is not borrowed while `f` runs, so `f` may read it and
+    /// sees the old value.
+    pub fn update<F: FnOnce(&mut T)>(&self, f: F) {
+        let mut value = self.0.value.borrow().clone();
+        f(&mut value);
+        self.0.value.replace(value);
+        self.0.notify();
+    }
+
+    /// Like [`bind()`](Observable::bind), but also sets the state when
+    /// `property` of `widget` changes, e.g. because of user input.
+    pub fn bind_bidirectional<W: IsA<Widget>>(&self, widget: &W, property: &str) -> Subscription
+    where
+        T: ToValue + for<'a> FromValue<'a> + PartialEq,
+    {
+        let subscription = self.bind(widget, property);
+
+        let weak = Rc::downgrade(&self.0);
+        let name = property.to_owned();
+        widget
+            .upcast_ref::<Widget>()
+            .upcast_ref::<glib::Object>()
+            .connect_notify_local(Some(property), move |widget, _| {
+                if let Some(node) = weak.upgrade() {
+                    State(node).set(property_value(widget, &name));
+                }
+            });
+        subscription
+    }
+}
+
+impl<T: Clone + 'static> Observable<T> for State<T> {
+    fn get(&self) -> T {
+        self.0.get()
+    }
+
+    fn subscribe<F: Fn(&T) + 'static>(&self, f: F) -> Subscription {
+        self.0.subscribe(Rc::new(f))
+    }
+}
+
+fn property_value<T: for<'a> FromValue<'a>>(object: &glib::Object, property: &str) -> T {
+    object
+        .property(property)
+        .expect("failed to get property")
+        .get()
+        .unwrap_or_else(|_| panic!("property `{}` has a different type", property))
+}
+
+struct ComputedInner<T> {
+    node: Rc<Node<T>>,
+    compute: Box<dyn Fn() -> T>,
+    dependencies: RefCell<Vec<Subscription>>,
+}
+
+impl<T: Clone + PartialEq + 'static> ComputedInner<T> {
+    fn set_dependencies(self: &Rc<Self>, dependencies: Vec<Rc<dyn Source>>) {
+        let subscriptions = dependencies
+            .into_iter()
+            .map(|dependency| {
+                let inner = self.clone();
+                dependency.subscribe_any(Rc::new(move || inner.recompute()))
+            })
+            .collect();
+        for old in self.dependencies.replace(subscriptions) {
+            old.unsubscribe();
+        }
+    }
+
+    fn recompute(self: &Rc<Self>) {
+        let (value, dependencies) = tracked(&self.compute);
+        self.set_dependencies(dependencies);
+        if *self.node.value.borrow() != value {
+            self.node.value.replace(value);
+            self.node.notify();
+        }
+    }
+}
+
+/// A value derived from other observables.
+///
+/// The observables read by the computation are tracked, so only changes to
+/// them cause a recomputation. Subscribers are only notified if the result
+/// changed. A computed value stays alive as long as the observables it
+/// depends on.
+pub struct Computed<T>(Rc<ComputedInner<T>>);
+
+impl<T> Clone for Computed<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Computed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Computed")
+            .field(&self.0.node.value.borrow())
+            .finish()
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> Computed<T> {
+    pub fn new<F: Fn() -> T + 'static>(f: F) -> Self {
+        let (value, dependencies) = tracked(&f);
+        let inner = Rc::new(ComputedInner {
+            node: Node::new(value),
+            compute: Box::new(f),
+            dependencies: RefCell::new(Vec::new()),
+        });
+        inner.set_dependencies(dependencies);
+        Self(inner)
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> Observable<T> for Computed<T> {
+    fn get(&self) -> T {
+        self.0.node.get()
+    }
+
+    fn subscribe<F: Fn(&T) + 'static>(&self, f: F) -> Subscription {
+        self.0.node.subscribe(Rc::new(f))
+    }
+}
+
+/// Binds `source_property` of `source` to `target_property` of `widget`
+/// with [`ObjectExt::bind_property()`], and removes the binding when
+/// `widget` is destroyed.
+pub fn bind_property_to_widget<O: IsA<glib::Object>, W: IsA<Widget>>(
+    source: &O,
+    source_property: &str,
+    widget: &W,
+    target_property: &str,
+    flags: glib::BindingFlags,
+) -> Option<glib::Binding> {
+    let binding = source
+        .bind_property(source_property, widget, target_property)
+        .flags(flags)
+        .build()?;
+    let weak = binding.downgrade();
+    widget.connect_destroy(move |_| {
+        if let Some(binding) = weak.upgrade() {
+            binding.unbind();
+        }
+    });
+    Some(binding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computed_tracks_dependencies() {
+        let flag = State::new(true);
+        let a = State::new(1);
+        let b = State::new(10);
+        let computed = {
+            let (flag, a, b) = (flag.clone(), a.clone(), b.clone());
+            Computed::new(move || if flag.get() { a.get() } else { b.get() })
+        };
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let subscription = {
+            let seen = seen.clone();
+            computed.subscribe(move |value| seen.borrow_mut().push(*value))
+        };
+
+        b.set(11);
+        a.set(2);
+        flag.set(false);
+        a.set(3);
+        b.update(|b| *b += 1);
+        assert_eq!(computed.get(), 12);
+        assert_eq!(*seen.borrow(), vec![2, 11, 12]);
+
+        subscription.unsubscribe();
+        b.set(13);
+        assert_eq!(computed.get(), 13);
+        assert_eq!(seen.borrow().len(), 3);
+    }
+
+    #[test]
+    fn update_reads_state() {
+        let count = State::new(1);
+        let other = count.clone();
+        count.update(|count| *count += other.get());
+        assert_eq!(count.get(), 2);
+    }
+}
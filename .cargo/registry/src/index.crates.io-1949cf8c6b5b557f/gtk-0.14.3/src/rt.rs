@@ -105,6 +105,15 @@ pub unsafe fn set_initialized() {
 /// initialized otherwise an Err is returned.
 #[doc(alias = "gtk_init")]
 pub fn init() -> Result<(), glib::BoolError> {
+    skip_assert_initialized!();
+    // We just want to keep the program's name since more arguments could lead to unwanted
+    // behaviors...
+    init_with_args(::std::env::args().take(1).collect())
+}
+
+// Like `init()`, but passes `argv` to `gtk_init_check()`, e.g. to select the
+// display with `--display`.
+pub(crate) fn init_with_args(argv: Vec<String>) -> Result<(), glib::BoolError> {
     skip_assert_initialized!();
     if is_initialized_main_thread() {
         return Ok(());
@@ -112,11 +121,9 @@ pub fn init() -> Result<(), glib::BoolError> {
         panic!("Attempted to initialize GTK from two different threads.");
     }
     unsafe {
-        // We just want to keep the program's name since more arguments could lead to unwanted
-        // behaviors...
-        let argv = ::std::env::args().take(1).collect::<Vec<_>>();
+        let mut argc = argv.len() as i32;
 
-        if from_glib(ffi::gtk_init_check(&mut 1, &mut argv.to_glib_none().0)) {
+        if from_glib(ffi::gtk_init_check(&mut argc, &mut argv.to_glib_none().0)) {
             // See https://github.com/gtk-rs/gtk-rs-core/issues/186 for reasoning behind
             // acquiring and leaking the main context here.
             let result: bool = from_glib(glib::ffi::g_main_context_acquire(
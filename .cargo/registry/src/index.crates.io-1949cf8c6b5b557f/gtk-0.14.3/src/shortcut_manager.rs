@@ -0,0 +1,954 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::prelude::*;
+use crate::{AccelFlags, AccelGroup, Application, Widget, Window};
+#[cfg(any(feature = "v3_20", feature = "dox"))]
+use crate::{ShortcutsGroup, ShortcutsSection, ShortcutsShortcut, ShortcutsWindow};
+use gdk::keys::Key;
+use gdk::ModifierType;
+use glib::signal::Inhibit;
+use glib::translate::*;
+use glib::{SignalHandlerId, Variant};
+use std::cell::RefCell;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::rc::Rc;
+
+/// A key with modifiers, e.g. `<Control>s`.
+///
+/// Keys are stored in lower case, and modifiers are limited to the ones
+/// used for accelerators, so strokes parsed from different spellings of the
+/// same shortcut compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyStroke {
+    key: u32,
+    modifiers: ModifierType,
+}
+
+impl KeyStroke {
+    pub fn new(key: u32, modifiers: ModifierType) -> Self {
+        assert_initialized_main_thread!();
+        Self {
+            key: *Key::from(key).to_lower(),
+            modifiers: modifiers & crate::accelerator_get_default_mod_mask(),
+        }
+    }
+
+    /// Parses an accelerator in the syntax of [`accelerator_parse`], e.g.
+    /// `<Control><Shift>s` or `F11`.
+    ///
+    /// [`accelerator_parse`]: crate::accelerator_parse
+    pub fn parse(accelerator: &str) -> Result<Self, glib::BoolError> {
+        assert_initialized_main_thread!();
+        match crate::accelerator_parse(accelerator) {
+            (0, _) => Err(glib::bool_error!("Invalid accelerator `{}`", accelerator)),
+            (key, modifiers) => Ok(Self::new(key, modifiers)),
+        }
+    }
+
+    /// The stroke of a key press, or `None` if only a modifier was pressed.
+    pub fn from_event(event: &gdk::EventKey) -> Option<Self> {
+        assert_initialized_main_thread!();
+        if event.is_modifier() {
+            None
+        } else {
+            Some(Self::new(*event.keyval(), event.state()))
+        }
+    }
+
+    pub fn key(&self) -> u32 {
+        self.key
+    }
+
+    pub fn modifiers(&self) -> ModifierType {
+        self.modifiers
+    }
+
+    /// The accelerator in the syntax of [`accelerator_parse`].
+    ///
+    /// [`accelerator_parse`]: crate::accelerator_parse
+    pub fn name(&self) -> String {
+        crate::accelerator_name(self.key, self.modifiers)
+            .map(Into::into)
+            .unwrap_or_default()
+    }
+
+    /// A label to show to the user, e.g. `Ctrl+S`.
+    pub fn label(&self) -> String {
+        crate::accelerator_get_label(self.key, self.modifiers)
+            .map(Into::into)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for KeyStroke {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+/// One or more key strokes pressed in turn, e.g. `<Control>x <Control>s`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(Vec<KeyStroke>);
+
+impl KeySequence {
+    /// Panics if `strokes` is empty.
+    pub fn new(strokes: Vec<KeyStroke>) -> Self {
+        assert!(
+            !strokes.is_empty(),
+            "a key sequence needs at least one stroke"
+        );
+        Self(strokes)
+    }
+
+    /// Parses accelerators separated by whitespace.
+    pub fn parse(sequence: &str) -> Result<Self, glib::BoolError> {
+        assert_initialized_main_thread!();
+        let strokes = sequence
+            .split_whitespace()
+            .map(KeyStroke::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if strokes.is_empty() {
+            return Err(glib::bool_error!("Empty key sequence"));
+        }
+        Ok(Self(strokes))
+    }
+
+    pub fn strokes(&self) -> &[KeyStroke] {
+        &self.0
+    }
+
+    /// Whether the sequence has more than one stroke.
+    pub fn is_chord(&self) -> bool {
+        self.0.len() > 1
+    }
+
+    /// Whether `self` starts with all the strokes of `prefix`.
+    pub fn starts_with(&self, prefix: &KeySequence) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+
+    /// A label to show to the user, e.g. `Ctrl+X Ctrl+S`.
+    pub fn label(&self) -> String {
+        self.0
+            .iter()
+            .map(KeyStroke::label)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, stroke) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", stroke)?;
+        }
+        Ok(())
+    }
+}
+
+/// An action registered with a [`ShortcutManager`] and its bindings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    /// The detailed action name, e.g. `app.quit` or `win.zoom(2)`.
+    pub action: String,
+    pub title: String,
+    /// The group the shortcut is listed in by
+    /// [`ShortcutManager::shortcuts_window`].
+    pub group: String,
+    pub bindings: Vec<KeySequence>,
+}
+
+/// Two bindings that can't both work.
+///
+/// Either both actions are bound to the same sequence, or the sequence of
+/// the first action is a prefix of the chord of the second, which is then
+/// never completed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutConflict {
+    pub action: String,
+    pub sequence: KeySequence,
+    pub other_action: String,
+    pub other_sequence: KeySequence,
+}
+
+impl ShortcutConflict {
+    /// Whether the conflict is caused by a prefix rather than a duplicate.
+    pub fn is_prefix(&self) -> bool {
+        self.sequence != self.other_sequence
+    }
+}
+
+impl fmt::Display for ShortcutConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_prefix() {
+            write!(
+                f,
+                "`{}` of {} blocks `{}` of {}",
+                self.sequence, self.action, self.other_sequence, self.other_action
+            )
+        } else {
+            write!(
+                f,
+                "`{}` is bound to both {} and {}",
+                self.sequence, self.action, self.other_action
+            )
+        }
+    }
+}
+
+/// A problem found while loading a shortcut configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutError {
+    /// The file with the problem, `None` for configuration loaded from a
+    /// string.
+    pub file: Option<PathBuf>,
+    /// The line, starting at 1, or 0 if the problem is not in a line.
+    pub line: u32,
+    pub message: String,
+}
+
+impl fmt::Display for ShortcutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}", file.display())?,
+            None => f.write_str("<data>")?,
+        }
+        if self.line > 0 {
+            write!(f, ":{}", self.line)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ShortcutError {}
+
+struct Entry {
+    action: String,
+    title: String,
+    group: String,
+    defaults: Vec<KeySequence>,
+    bindings: Vec<KeySequence>,
+}
+
+struct Inner {
+    entries: RefCell<Vec<Entry>>,
+    accel_group: AccelGroup,
+    accel_closures: RefCell<Vec<glib::Closure>>,
+    applications: RefCell<Vec<glib::WeakRef<Application>>>,
+    changed: RefCell<Vec<Rc<dyn Fn(&ShortcutManager)>>>,
+}
+
+/// Keyboard shortcuts for actions, with defaults set by the application and
+/// bindings loaded from a configuration file.
+///
+/// Bindings of a single stroke are installed as accelerators, either on an
+/// [`Application`] with [`add_to_application`] or through the
+/// [`accel_group`] for other windows. Chords like `<Control>x <Control>s`
+/// are handled by the key handler installed by [`attach_to_window`]. Only
+/// use one of [`add_to_application`] and [`accel_group`] for a window, or
+/// its actions are activated twice.
+///
+/// The configuration syntax follows Hyprland: `bind` takes either
+/// Hyprland-style modifiers and a key, or GTK accelerators, followed by a
+/// detailed action name. `unbind` removes a key sequence or all bindings of
+/// an action, and `$name = value` defines a variable.
+///
+/// ```text
+/// $mod = CTRL
+///
+/// # Replaces the default bindings of `app.quit`.
+/// bind = $mod, Q, app.quit
+/// bind = $mod SHIFT, S, win.save-as
+/// bind = <Control>x <Control>s, win.save
+/// bind = <Control>plus, win.zoom(1)
+/// unbind = <Control>w
+/// unbind = win.print
+/// ```
+///
+/// ```no_run
+/// use gtk::prelude::*;
+///
+/// fn setup_shortcuts(app: &gtk::Application, window: &gtk::ApplicationWindow) {
+///     let shortcuts = gtk::ShortcutManager::new();
+///     shortcuts
+///         .register("app.quit", "Quit", "General", &["<Control>q"])
+///         .unwrap();
+///     shortcuts
+///         .register("win.save", "Save", "Files", &["<Control>s", "<Control>x <Control>s"])
+///         .unwrap();
+///     for error in shortcuts.load_path("shortcuts.conf") {
+///         eprintln!("{}", error);
+///     }
+///     for conflict in shortcuts.conflicts() {
+///         eprintln!("{}", conflict);
+///     }
+///     shortcuts.add_to_application(app);
+///     shortcuts.attach_to_window(window);
+///     #[cfg(any(feature = "v3_20", feature = "dox"))]
+///     shortcuts.shortcuts_window().set_transient_for(Some(window));
+/// }
+/// ```
+///
+/// [`add_to_application`]: ShortcutManager::add_to_application
+/// [`accel_group`]: ShortcutManager::accel_group
+/// [`attach_to_window`]: ShortcutManager::attach_to_window
+#[derive(Clone)]
+pub struct ShortcutManager(Rc<Inner>);
+
+impl fmt::Debug for ShortcutManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShortcutManager")
+            .field("shortcuts", &self.shortcuts())
+            .field("accel_group", &self.0.accel_group)
+            .finish()
+    }
+}
+
+impl Default for ShortcutManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShortcutManager {
+    pub fn new() -> Self {
+        assert_initialized_main_thread!();
+        Self(Rc::new(Inner {
+            entries: RefCell::new(Vec::new()),
+            accel_group: AccelGroup::new(),
+            accel_closures: RefCell::new(Vec::new()),
+            applications: RefCell::new(Vec::new()),
+            changed: RefCell::new(Vec::new()),
+        }))
+    }
+
+    /// Registers the detailed action name `action` with the key sequences
+    /// in `defaults`, replacing any previous registration.
+    pub fn register(
+        &self,
+        action: &str,
+        title: &str,
+        group: &str,
+        defaults: &[&str],
+    ) -> Result<(), glib::BoolError> {
+        parse_action(action)?;
+        let defaults = defaults
+            .iter()
+            .map(|sequence| KeySequence::parse(sequence))
+            .collect::<Result<Vec<_>, _>>()?;
+        let entry = Entry {
+            action: action.to_owned(),
+            title: title.to_owned(),
+            group: group.to_owned(),
+            bindings: dedup(defaults.clone()),
+            defaults,
+        };
+        {
+            let mut entries = self.0.entries.borrow_mut();
+            match entries.iter_mut().find(|e| e.action == action) {
+                Some(e) => *e = entry,
+                None => entries.push(entry),
+            }
+        }
+        self.apply();
+        Ok(())
+    }
+
+    /// The registered actions with their current bindings, in the order
+    /// they were registered.
+    pub fn shortcuts(&self) -> Vec<Shortcut> {
+        self.0
+            .entries
+            .borrow()
+            .iter()
+            .map(|entry| Shortcut {
+                action: entry.action.clone(),
+                title: entry.title.clone(),
+                group: entry.group.clone(),
+                bindings: entry.bindings.clone(),
+            })
+            .collect()
+    }
+
+    pub fn bindings(&self, action: &str) -> Vec<KeySequence> {
+        self.0
+            .entries
+            .borrow()
+            .iter()
+            .find(|entry| entry.action == action)
+            .map(|entry| entry.bindings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Adds `sequence` to the bindings of the registered `action`.
+    pub fn bind(&self, action: &str, sequence: &str) -> Result<(), glib::BoolError> {
+        let sequence = KeySequence::parse(sequence)?;
+        self.with_entry(action, |entry| {
+            if !entry.bindings.contains(&sequence) {
+                entry.bindings.push(sequence);
+            }
+        })
+    }
+
+    /// Removes all bindings of the registered `action`.
+    pub fn unbind(&self, action: &str) -> Result<(), glib::BoolError> {
+        self.with_entry(action, |entry| entry.bindings.clear())
+    }
+
+    /// Restores the default bindings of all actions.
+    pub fn reset(&self) {
+        for entry in self.0.entries.borrow_mut().iter_mut() {
+            entry.bindings = dedup(entry.defaults.clone());
+        }
+        self.apply();
+    }
+
+    /// The action bound to `sequence`, if any.
+    pub fn action_for(&self, sequence: &KeySequence) -> Option<String> {
+        self.0
+            .entries
+            .borrow()
+            .iter()
+            .find(|entry| entry.bindings.contains(sequence))
+            .map(|entry| entry.action.clone())
+    }
+
+    /// Restores the default bindings and applies the configuration file at
+    /// `path` on top of them.
+    ///
+    /// Returns the problems found. Lines with problems are ignored.
+    pub fn load_path<P: AsRef<Path>>(&self, path: P) -> Vec<ShortcutError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(config) => self.load(&config, Some(path)),
+            Err(err) => {
+                self.reset();
+                vec![ShortcutError {
+                    file: Some(path.to_owned()),
+                    line: 0,
+                    message: err.to_string(),
+                }]
+            }
+        }
+    }
+
+    /// Like [`load_path`](Self::load_path) for configuration in a string.
+    pub fn load_data(&self, config: &str) -> Vec<ShortcutError> {
+        self.load(config, None)
+    }
+
+    /// Bindings that can't all work, see [`ShortcutConflict`].
+    pub fn conflicts(&self) -> Vec<ShortcutConflict> {
+        let entries = self.0.entries.borrow();
+        let bindings = entries
+            .iter()
+            .flat_map(|entry| entry.bindings.iter().map(move |seq| (&entry.action, seq)))
+            .collect::<Vec<_>>();
+
+        let mut conflicts = Vec::new();
+        for (i, &(action, sequence)) in bindings.iter().enumerate() {
+            for &(other_action, other_sequence) in &bindings[i + 1..] {
+                let (first, second) = if other_sequence.0.len() < sequence.0.len() {
+                    ((other_action, other_sequence), (action, sequence))
+                } else {
+                    ((action, sequence), (other_action, other_sequence))
+                };
+                if second.1.starts_with(first.1) {
+                    conflicts.push(ShortcutConflict {
+                        action: first.0.clone(),
+                        sequence: first.1.clone(),
+                        other_action: second.0.clone(),
+                        other_sequence: second.1.clone(),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Sets the accelerators of `app` for all registered actions, and keeps
+    /// them up to date when bindings change.
+    pub fn add_to_application<P: IsA<Application>>(&self, app: &P) {
+        self.0
+            .applications
+            .borrow_mut()
+            .push(app.upcast_ref::<Application>().downgrade());
+        self.apply_to_application(app.upcast_ref());
+    }
+
+    /// An accelerator group activating the actions bound to single strokes,
+    /// for windows without an [`Application`].
+    ///
+    /// Actions are looked up with [`WidgetExt::action_group`] on the window.
+    pub fn accel_group(&self) -> &AccelGroup {
+        &self.0.accel_group
+    }
+
+    /// Handles chords pressed in `window`.
+    ///
+    /// While a chord is incomplete, its strokes are consumed. A stroke that
+    /// doesn't continue any chord cancels it and rings the bell.
+    pub fn attach_to_window<P: IsA<Window>>(&self, window: &P) -> SignalHandlerId {
+        let weak = Rc::downgrade(&self.0);
+        let pending = RefCell::new(Vec::new());
+        let window = window.upcast_ref::<Window>();
+        window.connect_key_press_event(move |window, event| {
+            let inner = match weak.upgrade() {
+                Some(inner) => inner,
+                None => return Inhibit(false),
+            };
+            let stroke = match KeyStroke::from_event(event) {
+                Some(stroke) => stroke,
+                None => return Inhibit(false),
+            };
+
+            let mut pending = pending.borrow_mut();
+            pending.push(stroke);
+            let mut action = None;
+            let mut is_prefix = false;
+            for entry in inner.entries.borrow().iter() {
+                for sequence in entry.bindings.iter().filter(|seq| seq.is_chord()) {
+                    if sequence.0 == *pending {
+                        action = Some(entry.action.clone());
+                    } else if sequence.0.starts_with(&pending) {
+                        is_prefix = true;
+                    }
+                }
+            }
+
+            if let Some(action) = action {
+                pending.clear();
+                activate(window.upcast_ref(), &action);
+                Inhibit(true)
+            } else if is_prefix {
+                Inhibit(true)
+            } else if pending.len() > 1 {
+                pending.clear();
+                window.error_bell();
+                Inhibit(true)
+            } else {
+                pending.clear();
+                Inhibit(false)
+            }
+        })
+    }
+
+    /// Creates a [`ShortcutsWindow`] listing the registered actions that
+    /// have bindings, grouped by their group.
+    #[cfg(any(feature = "v3_20", feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(feature = "v3_20")))]
+    pub fn shortcuts_window(&self) -> ShortcutsWindow {
+        let section = ShortcutsSection::builder()
+            .section_name("shortcuts")
+            .visible(true)
+            .build();
+
+        let entries = self.0.entries.borrow();
+        let mut groups: Vec<&str> = Vec::new();
+        for entry in entries.iter() {
+            if !groups.contains(&entry.group.as_str()) {
+                groups.push(&entry.group);
+            }
+        }
+        for title in groups {
+            let group = ShortcutsGroup::builder().title(title).visible(true).build();
+            let mut empty = true;
+            for entry in entries.iter().filter(|e| e.group == title) {
+                if entry.bindings.is_empty() {
+                    continue;
+                }
+                // Alternatives are separated by spaces and the strokes of a
+                // sequence by `&`.
+                let accelerator = entry
+                    .bindings
+                    .iter()
+                    .map(|seq| {
+                        seq.0
+                            .iter()
+                            .map(KeyStroke::name)
+                            .collect::<Vec<_>>()
+                            .join("&")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let shortcut = ShortcutsShortcut::builder()
+                    .title(&entry.title)
+                    .accelerator(&accelerator)
+                    .visible(true)
+                    .build();
+                group.add(&shortcut);
+                empty = false;
+            }
+            if !empty {
+                section.add(&group);
+            }
+        }
+
+        let window = ShortcutsWindow::builder().modal(true).build();
+        window.add(&section);
+        window
+    }
+
+    /// Calls `f` whenever the bindings change.
+    pub fn connect_changed<F: Fn(&Self) + 'static>(&self, f: F) {
+        self.0.changed.borrow_mut().push(Rc::new(f));
+    }
+
+    fn with_entry<F: FnOnce(&mut Entry)>(&self, action: &str, f: F) -> Result<(), glib::BoolError> {
+        {
+            let mut entries = self.0.entries.borrow_mut();
+            let entry = entries
+                .iter_mut()
+                .find(|entry| entry.action == action)
+                .ok_or_else(|| glib::bool_error!("Unknown action `{}`", action))?;
+            f(entry);
+        }
+        self.apply();
+        Ok(())
+    }
+
+    fn load(&self, config: &str, file: Option<&Path>) -> Vec<ShortcutError> {
+        let mut errors = Vec::new();
+        let mut vars = Vec::new();
+        // Actions whose defaults were replaced by a `bind`.
+        let mut rebound = Vec::new();
+
+        let mut entries = self.0.entries.borrow_mut();
+        for entry in entries.iter_mut() {
+            entry.bindings = dedup(entry.defaults.clone());
+        }
+
+        for (i, line) in config.lines().enumerate() {
+            let result = parse_line(line, &mut vars).and_then(|line| match line {
+                Some(ConfigLine::Bind(keys, action)) => {
+                    let sequence = keys.to_sequence()?;
+                    let entry = entries
+                        .iter_mut()
+                        .find(|entry| entry.action == action)
+                        .ok_or_else(|| format!("unknown action `{}`", action))?;
+                    if !rebound.contains(&action) {
+                        entry.bindings.clear();
+                        rebound.push(action);
+                    }
+                    if !entry.bindings.contains(&sequence) {
+                        entry.bindings.push(sequence);
+                    }
+                    Ok(())
+                }
+                Some(ConfigLine::UnbindKeys(keys)) => {
+                    let sequence = keys.to_sequence()?;
+                    for entry in entries.iter_mut() {
+                        entry.bindings.retain(|seq| *seq != sequence);
+                    }
+                    Ok(())
+                }
+                Some(ConfigLine::UnbindAction(action)) => {
+                    let entry = entries
+                        .iter_mut()
+                        .find(|entry| entry.action == action)
+                        .ok_or_else(|| format!("unknown action `{}`", action))?;
+                    entry.bindings.clear();
+                    Ok(())
+                }
+                None => Ok(()),
+            });
+            if let Err(message) = result {
+                errors.push(ShortcutError {
+                    file: file.map(ToOwned::to_owned),
+                    line: i as u32 + 1,
+                    message,
+                });
+            }
+        }
+        drop(entries);
+
+        self.apply();
+        errors
+    }
+
+    fn apply(&self) {
+        let inner = &self.0;
+        for closure in inner.accel_closures.borrow_mut().drain(..) {
+            AccelGroupExt::disconnect(&inner.accel_group, Some(&closure));
+        }
+        for entry in inner.entries.borrow().iter() {
+            for sequence in entry.bindings.iter().filter(|seq| !seq.is_chord()) {
+                let stroke = sequence.0[0];
+                let action = entry.action.clone();
+                let closure = inner.accel_group.connect_accel_group(
+                    stroke.key,
+                    stroke.modifiers,
+                    AccelFlags::VISIBLE,
+                    move |_, object, _, _| match object.downcast_ref::<Widget>() {
+                        Some(widget) => activate(widget, &action),
+                        None => false,
+                    },
+                );
+                inner.accel_closures.borrow_mut().push(closure);
+            }
+        }
+
+        let applications = {
+            let mut applications = inner.applications.borrow_mut();
+            applications.retain(|app| app.upgrade().is_some());
+            applications
+                .iter()
+                .filter_map(|app| app.upgrade())
+                .collect::<Vec<_>>()
+        };
+        for app in applications {
+            self.apply_to_application(&app);
+        }
+
+        let callbacks = inner.changed.borrow().clone();
+        for callback in callbacks {
+            callback(self);
+        }
+    }
+
+    fn apply_to_application(&self, app: &Application) {
+        for entry in self.0.entries.borrow().iter() {
+            let accels = entry
+                .bindings
+                .iter()
+                .filter(|seq| !seq.is_chord())
+                .map(|seq| seq.0[0].name())
+                .collect::<Vec<_>>();
+            let accels = accels.iter().map(String::as_str).collect::<Vec<_>>();
+            app.set_accels_for_action(&entry.action, &accels);
+        }
+    }
+}
+
+fn dedup(sequences: Vec<KeySequence>) -> Vec<KeySequence> {
+    let mut unique = Vec::with_capacity(sequences.len());
+    for sequence in sequences {
+        if !unique.contains(&sequence) {
+            unique.push(sequence);
+        }
+    }
+    unique
+}
+
+// Splits a detailed action name like `win.zoom(2)` into its prefix, name and
+// target.
+fn parse_action(detailed: &str) -> Result<(String, String, Option<Variant>), glib::BoolError> {
+    let (name, target) = unsafe {
+        let mut name = ptr::null_mut();
+        let mut target = ptr::null_mut();
+        let mut error = ptr::null_mut();
+        gio::ffi::g_action_parse_detailed_name(
+            detailed.to_glib_none().0,
+            &mut name,
+            &mut target,
+            &mut error,
+        );
+        if !error.is_null() {
+            let error: glib::Error = from_glib_full(error);
+            return Err(glib::bool_error!(
+                "Invalid action `{}`: {}",
+                detailed,
+                error
+            ));
+        }
+        let name: String = from_glib_full(name);
+        let target: Option<Variant> = from_glib_full(target);
+        (name, target)
+    };
+    match name.find('.') {
+        Some(dot) => Ok((name[..dot].to_owned(), name[dot + 1..].to_owned(), target)),
+        None => Err(glib::bool_error!(
+            "Action `{}` has no prefix like `app.` or `win.`",
+            detailed
+        )),
+    }
+}
+
+// Activates `action` in the action groups visible from `widget`, falling
+// back to the application of its window for `app.` actions.
+fn activate(widget: &Widget, action: &str) -> bool {
+    let (prefix, name, target) = match parse_action(action) {
+        Ok(action) => action,
+        Err(_) => return false,
+    };
+    let group = widget.action_group(&prefix).or_else(|| {
+        if prefix != "app" {
+            return None;
+        }
+        widget
+            .toplevel()
+            .and_then(|toplevel| toplevel.downcast::<Window>().ok())
+            .and_then(|window| window.application())
+            .map(|app| app.upcast())
+    });
+    match group {
+        Some(group) if group.has_action(&name) => {
+            group.activate_action(&name, target.as_ref());
+            true
+        }
+        _ => {
+            glib::g_warning!("Gtk", "No action `{}` for shortcut", action);
+            false
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ConfigKeys {
+    // GTK accelerators separated by whitespace.
+    Accelerators(String),
+    // Hyprland modifiers and a key.
+    Hyprland(String, String),
+}
+
+impl ConfigKeys {
+    fn to_sequence(&self) -> Result<KeySequence, String> {
+        match self {
+            ConfigKeys::Accelerators(accelerators) => {
+                KeySequence::parse(accelerators).map_err(|err| err.to_string())
+            }
+            ConfigKeys::Hyprland(modifiers, key) => {
+                let combination = gdk::KeyCombination::parse_hyprland_bind(modifiers, key)
+                    .map_err(|err| err.to_string())?;
+                Ok(KeySequence::new(vec![KeyStroke::new(
+                    *combination.key(),
+                    combination.modifiers(),
+                )]))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ConfigLine {
+    Bind(ConfigKeys, String),
+    UnbindKeys(ConfigKeys),
+    UnbindAction(String),
+}
+
+fn is_hyprland_modifiers(s: &str) -> bool {
+    // Any valid key will do, only the modifiers are checked.
+    gdk::KeyCombination::parse_hyprland_bind(s, "a").is_ok()
+}
+
+// Parses a line of a configuration file, expanding and defining `$name`
+// variables.
+fn parse_line(line: &str, vars: &mut Vec<(String, String)>) -> Result<Option<ConfigLine>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let eq = line
+        .find('=')
+        .ok_or_else(|| format!("expected `keyword = value`, got `{}`", line))?;
+    let keyword = line[..eq].trim();
+    let mut value = line[eq + 1..].trim().to_owned();
+    // Longer names first, so `$mod` doesn't replace part of `$modShift`.
+    let mut sorted = vars.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    for (name, var) in sorted {
+        value = value.replace(&format!("${}", name), var);
+    }
+
+    if let Some(name) = keyword.strip_prefix('$') {
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("invalid variable name `{}`", keyword));
+        }
+        vars.retain(|(n, _)| n != name);
+        vars.push((name.to_owned(), value));
+        return Ok(None);
+    }
+    if value.contains('$') {
+        return Err(format!("undefined variable in `{}`", value));
+    }
+
+    let fields = value.splitn(3, ',').map(str::trim).collect::<Vec<_>>();
+    match keyword {
+        "bind" => match fields[..] {
+            [modifiers, key, action] if is_hyprland_modifiers(modifiers) && !key.is_empty() => {
+                Ok(Some(ConfigLine::Bind(
+                    ConfigKeys::Hyprland(modifiers.to_owned(), key.to_owned()),
+                    action.to_owned(),
+                )))
+            }
+            [keys, ..] if fields.len() > 1 => {
+                let action = value[value.find(',').unwrap() + 1..].trim();
+                if keys.is_empty() || action.is_empty() {
+                    Err(format!("expected `keys, action`, got `{}`", value))
+                } else {
+                    Ok(Some(ConfigLine::Bind(
+                        ConfigKeys::Accelerators(keys.to_owned()),
+                        action.to_owned(),
+                    )))
+                }
+            }
+            _ => Err(format!("expected `keys, action`, got `{}`", value)),
+        },
+        "unbind" => match fields[..] {
+            [modifiers, key] if is_hyprland_modifiers(modifiers) && !key.is_empty() => Ok(Some(
+                ConfigLine::UnbindKeys(ConfigKeys::Hyprland(modifiers.to_owned(), key.to_owned())),
+            )),
+            [target] if target.contains('.') => {
+                Ok(Some(ConfigLine::UnbindAction(target.to_owned())))
+            }
+            [keys] if !keys.is_empty() => Ok(Some(ConfigLine::UnbindKeys(
+                ConfigKeys::Accelerators(keys.to_owned()),
+            ))),
+            _ => Err(format!("expected keys or an action, got `{}`", value)),
+        },
+        _ => Err(format!("unknown keyword `{}`", keyword)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_lines_are_parsed() {
+        let mut vars = Vec::new();
+        let mut parse = |line: &str| parse_line(line, &mut vars);
+
+        assert_eq!(parse("  # comment"), Ok(None));
+        assert_eq!(parse("$mainMod = SUPER"), Ok(None));
+        assert_eq!(
+            parse("bind = $mainMod SHIFT, Q, app.quit"),
+            Ok(Some(ConfigLine::Bind(
+                ConfigKeys::Hyprland("SUPER SHIFT".to_owned(), "Q".to_owned()),
+                "app.quit".to_owned()
+            )))
+        );
+        assert_eq!(
+            parse("bind = , F11, win.fullscreen"),
+            Ok(Some(ConfigLine::Bind(
+                ConfigKeys::Hyprland("".to_owned(), "F11".to_owned()),
+                "win.fullscreen".to_owned()
+            )))
+        );
+        assert_eq!(
+            parse("bind = <Control>x <Control>s, win.move((1, 2))"),
+            Ok(Some(ConfigLine::Bind(
+                ConfigKeys::Accelerators("<Control>x <Control>s".to_owned()),
+                "win.move((1, 2))".to_owned()
+            )))
+        );
+        assert_eq!(
+            parse("unbind = CTRL, W"),
+            Ok(Some(ConfigLine::UnbindKeys(ConfigKeys::Hyprland(
+                "CTRL".to_owned(),
+                "W".to_owned()
+            ))))
+        );
+        assert_eq!(
+            parse("unbind = win.print"),
+            Ok(Some(ConfigLine::UnbindAction("win.print".to_owned())))
+        );
+        assert!(parse("bind = $other, Q, app.quit").is_err());
+        assert!(parse("exec = foot").is_err());
+    }
+}
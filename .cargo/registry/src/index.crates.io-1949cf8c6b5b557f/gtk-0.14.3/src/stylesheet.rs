@@ -0,0 +1,424 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::prelude::*;
+use crate::{CssProvider, CssProviderError, CssSection, StyleContext};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+// Editors write files in several steps, so wait for the events to settle.
+const RELOAD_DELAY: Duration = Duration::from_millis(100);
+
+/// A problem found while loading a [`Stylesheet`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CssError {
+    /// The file with the problem, `None` for CSS loaded from a string.
+    pub file: Option<PathBuf>,
+    /// The line, starting at 1, or 0 if the problem is not in a line.
+    pub line: u32,
+    /// The column in characters, starting at 1, or 0 if unknown.
+    pub column: u32,
+    pub message: String,
+    /// The kind of problem, `None` if it was not reported by the CSS parser.
+    pub kind: Option<CssProviderError>,
+}
+
+impl CssError {
+    fn from_section(section: &CssSection, error: &glib::Error) -> Self {
+        Self {
+            file: section.file().and_then(|file| file.path()),
+            line: section.start_line() + 1,
+            column: section.start_position() + 1,
+            message: error.to_string(),
+            kind: error.kind::<CssProviderError>(),
+        }
+    }
+
+    /// Whether this only reports deprecated syntax, which is still applied.
+    pub fn is_warning(&self) -> bool {
+        self.kind == Some(CssProviderError::Deprecated)
+    }
+}
+
+impl fmt::Display for CssError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}", file.display())?,
+            None => f.write_str("<data>")?,
+        }
+        if self.line > 0 {
+            write!(f, ":{}", self.line)?;
+            if self.column > 0 {
+                write!(f, ":{}", self.column)?;
+            }
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for CssError {}
+
+#[derive(Clone)]
+enum Source {
+    None,
+    Path(PathBuf),
+    Data(String),
+}
+
+struct Inner {
+    provider: CssProvider,
+    source: RefCell<Source>,
+    errors: RefCell<Vec<CssError>>,
+    watching: Cell<bool>,
+    watched: RefCell<Vec<PathBuf>>,
+    monitors: RefCell<Vec<gio::FileMonitor>>,
+    reload_pending: Cell<bool>,
+    reloaded: RefCell<Vec<Rc<dyn Fn(&Stylesheet, &[CssError])>>>,
+}
+
+/// A [`CssProvider`] that reports errors as [`CssError`]s and can reload
+/// its file when it or a file it `@import`s changes.
+///
+/// ```no_run
+/// use gtk::prelude::*;
+///
+/// fn load_style() -> gtk::Stylesheet {
+///     let stylesheet = gtk::Stylesheet::new();
+///     stylesheet.set_watching(true);
+///     stylesheet.connect_reloaded(|_, errors| {
+///         for error in errors {
+///             eprintln!("{}", error);
+///         }
+///     });
+///     for error in stylesheet.load_path("style.css") {
+///         eprintln!("{}", error);
+///     }
+///     let screen = gtk::gdk::Screen::default().expect("no default screen");
+///     stylesheet.add_to_screen(&screen, gtk::STYLE_PROVIDER_PRIORITY_USER);
+///     stylesheet
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Stylesheet(Rc<Inner>);
+
+impl fmt::Debug for Stylesheet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stylesheet")
+            .field("provider", &self.0.provider)
+            .field("errors", &self.0.errors.borrow())
+            .field("watching", &self.0.watching.get())
+            .field("watched", &self.0.watched.borrow())
+            .finish()
+    }
+}
+
+impl Default for Stylesheet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stylesheet {
+    pub fn new() -> Self {
+        assert_initialized_main_thread!();
+        let inner = Rc::new(Inner {
+            provider: CssProvider::new(),
+            source: RefCell::new(Source::None),
+            errors: RefCell::new(Vec::new()),
+            watching: Cell::new(false),
+            watched: RefCell::new(Vec::new()),
+            monitors: RefCell::new(Vec::new()),
+            reload_pending: Cell::new(false),
+            reloaded: RefCell::new(Vec::new()),
+        });
+
+        let weak = Rc::downgrade(&inner);
+        inner
+            .provider
+            .connect_parsing_error(move |_, section, error| {
+                if let Some(inner) = weak.upgrade() {
+                    inner
+                        .errors
+                        .borrow_mut()
+                        .push(CssError::from_section(section, error));
+                }
+            });
+        Self(inner)
+    }
+
+    pub fn provider(&self) -> &CssProvider {
+        &self.0.provider
+    }
+
+    /// Loads the CSS file at `path`, replacing the previous contents.
+    ///
+    /// Returns the problems found, including warnings.
+    pub fn load_path<P: AsRef<Path>>(&self, path: P) -> Vec<CssError> {
+        self.0
+            .source
+            .replace(Source::Path(path.as_ref().to_owned()));
+        self.load()
+    }
+
+    /// Loads CSS from a string, replacing the previous contents.
+    ///
+    /// Files `@import`ed by `css` are not watched.
+    pub fn load_data(&self, css: &str) -> Vec<CssError> {
+        self.0.source.replace(Source::Data(css.to_owned()));
+        self.load()
+    }
+
+    /// Loads the current file or string again.
+    pub fn reload(&self) -> Vec<CssError> {
+        self.load()
+    }
+
+    /// The problems found by the last load.
+    pub fn errors(&self) -> Vec<CssError> {
+        self.0.errors.borrow().clone()
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.0.watching.get()
+    }
+
+    /// Sets whether the loaded file and its imports are reloaded when they
+    /// change.
+    pub fn set_watching(&self, watching: bool) {
+        if self.0.watching.replace(watching) != watching {
+            self.update_monitors();
+        }
+    }
+
+    /// The loaded file and the files it imports, directly or indirectly.
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        self.0.watched.borrow().clone()
+    }
+
+    /// Calls `f` with the problems found after every load, including
+    /// automatic reloads.
+    pub fn connect_reloaded<F: Fn(&Self, &[CssError]) + 'static>(&self, f: F) {
+        self.0.reloaded.borrow_mut().push(Rc::new(f));
+    }
+
+    pub fn add_to_screen(&self, screen: &gdk::Screen, priority: u32) {
+        StyleContext::add_provider_for_screen(screen, &self.0.provider, priority);
+    }
+
+    pub fn remove_from_screen(&self, screen: &gdk::Screen) {
+        StyleContext::remove_provider_for_screen(screen, &self.0.provider);
+    }
+
+    fn load(&self) -> Vec<CssError> {
+        self.0.errors.borrow_mut().clear();
+        let source = self.0.source.borrow().clone();
+        let (result, file, watched) = match source {
+            Source::None => (Ok(()), None, Vec::new()),
+            Source::Path(path) => {
+                let result = self.0.provider.load_from_file(&gio::File::for_path(&path));
+                let watched = import_closure(&path);
+                (result, Some(path), watched)
+            }
+            Source::Data(css) => (
+                self.0.provider.load_from_data(css.as_bytes()),
+                None,
+                Vec::new(),
+            ),
+        };
+
+        // Errors such as a missing file may not go through `parsing-error`.
+        if let Err(err) = result {
+            let mut errors = self.0.errors.borrow_mut();
+            let message = err.to_string();
+            if !errors.iter().any(|error| error.message == message) {
+                errors.push(CssError {
+                    file,
+                    line: 0,
+                    column: 0,
+                    message,
+                    kind: err.kind::<CssProviderError>(),
+                });
+            }
+        }
+
+        if *self.0.watched.borrow() != watched {
+            self.0.watched.replace(watched);
+            self.update_monitors();
+        }
+
+        let errors = self.errors();
+        let callbacks = self.0.reloaded.borrow().clone();
+        for callback in callbacks {
+            callback(self, &errors);
+        }
+        errors
+    }
+
+    fn update_monitors(&self) {
+        let mut monitors = self.0.monitors.borrow_mut();
+        for monitor in monitors.drain(..) {
+            monitor.cancel();
+        }
+        if !self.0.watching.get() {
+            return;
+        }
+
+        for path in self.0.watched.borrow().iter() {
+            let monitor = match gio::File::for_path(path)
+                .monitor_file(gio::FileMonitorFlags::NONE, gio::NONE_CANCELLABLE)
+            {
+                Ok(monitor) => monitor,
+                Err(err) => {
+                    glib::g_warning!("Gtk", "Can't watch stylesheet {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            let weak = Rc::downgrade(&self.0);
+            monitor.connect_changed(move |_, _, _, event| match event {
+                gio::FileMonitorEvent::Changed
+                | gio::FileMonitorEvent::ChangesDoneHint
+                | gio::FileMonitorEvent::Created
+                | gio::FileMonitorEvent::Deleted
+                | gio::FileMonitorEvent::MovedIn
+                | gio::FileMonitorEvent::Renamed => schedule_reload(&weak),
+                _ => (),
+            });
+            monitors.push(monitor);
+        }
+    }
+}
+
+fn schedule_reload(weak: &Weak<Inner>) {
+    let inner = match weak.upgrade() {
+        Some(inner) => inner,
+        None => return,
+    };
+    if inner.reload_pending.replace(true) {
+        return;
+    }
+    let weak = weak.clone();
+    glib::timeout_add_local_once(RELOAD_DELAY, move || {
+        if let Some(inner) = weak.upgrade() {
+            inner.reload_pending.set(false);
+            Stylesheet(inner).load();
+        }
+    });
+}
+
+// Returns `path` and all files it imports, directly or indirectly. Files that
+// can't be read are included so that creating them triggers a reload.
+fn import_closure(path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![path.to_owned()];
+    let mut i = 0;
+    while i < files.len() {
+        if let Ok(css) = std::fs::read_to_string(&files[i]) {
+            let dir = files[i].parent().unwrap_or_else(|| Path::new(""));
+            for import in imports(&css, dir) {
+                if !files.contains(&import) {
+                    files.push(import);
+                }
+            }
+        }
+        i += 1;
+    }
+    files
+}
+
+// Returns the local files of the `@import` rules in `css`, with relative
+// paths resolved against `dir`. Comments and strings are skipped.
+fn imports(css: &str, dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let bytes = css.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let rest = &bytes[pos..];
+        if rest.starts_with(b"/*") {
+            pos += find(&rest[2..], b"*/").map_or(rest.len(), |end| end + 4);
+        } else if rest[0] == b'"' || rest[0] == b'\'' {
+            pos += string_len(rest);
+        } else if rest.starts_with(b"@import") {
+            let after = css[pos + "@import".len()..].trim_start();
+            pos = css.len() - after.len();
+            let (url, len) = match import_url(after) {
+                Some(url) => url,
+                None => continue,
+            };
+            pos += len;
+
+            if url.starts_with("file:") {
+                if let Some(path) = gio::File::for_uri(url).path() {
+                    files.push(path);
+                }
+            } else if !url.contains(':') {
+                files.push(dir.join(url));
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    files
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// The length of the quoted string at the start of `s`, including the quotes.
+fn string_len(s: &[u8]) -> usize {
+    let mut escaped = false;
+    for (i, &b) in s.iter().enumerate().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if b == b'\\' {
+            escaped = true;
+        } else if b == s[0] {
+            return i + 1;
+        }
+    }
+    s.len()
+}
+
+// Parses `url(...)` or a quoted string, returning the URL and the length of
+// the parsed text.
+fn import_url(s: &str) -> Option<(&str, usize)> {
+    if let Some(inner) = s.strip_prefix("url(") {
+        let end = inner.find(')')?;
+        let url = inner[..end].trim().trim_matches(|c| c == '"' || c == '\'');
+        Some((url, "url(".len() + end + 1))
+    } else {
+        let quote = s.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let end = s[1..].find(quote)?;
+        Some((&s[1..end + 1], end + 2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_are_resolved() {
+        let css = r#"
+            /* @import "commented.css"; don't */
+            label { content: "@import 'quoted.css';"; }
+            button { content: '\'@import "escaped.css"'; }
+            @import url("theme.css");
+            @import url(colors/dark.css);
+            @import 'https://example.com/remote.css';
+            @import "/etc/gtk/base.css";
+            window { color: red; }
+        "#;
+        assert_eq!(
+            imports(css, Path::new("/home/user/.config/waybar")),
+            vec![
+                PathBuf::from("/home/user/.config/waybar/theme.css"),
+                PathBuf::from("/home/user/.config/waybar/colors/dark.css"),
+                PathBuf::from("/etc/gtk/base.css"),
+            ]
+        );
+    }
+}
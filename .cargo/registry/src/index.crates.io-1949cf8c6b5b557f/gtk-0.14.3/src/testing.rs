@@ -0,0 +1,533 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Helpers for testing widgets without a user.
+//!
+//! [`run()`] executes a test on a thread that owns GTK, initialized on a
+//! virtual display if no display is available. Widgets can then be looked
+//! up, fed synthetic input events and drawn into an image.
+//!
+//! ```no_run
+//! use gtk::prelude::*;
+//! use gtk::testing;
+//! use std::cell::Cell;
+//! use std::rc::Rc;
+//!
+//! fn button_counts_clicks() {
+//!     testing::run(|| {
+//!         let button = gtk::Button::with_label("Count");
+//!         let clicks = Rc::new(Cell::new(0));
+//!         let counter = clicks.clone();
+//!         button.connect_clicked(move |_| counter.set(counter.get() + 1));
+//!
+//!         let window = testing::show_in_window(&button);
+//!         let found = testing::find_by_label(&window, "Count").unwrap();
+//!         testing::click(&found);
+//!         testing::wait_until_idle();
+//!         assert_eq!(clicks.get(), 1);
+//!
+//!         let image = testing::snapshot(&window).unwrap();
+//!         assert!(image.width() > 0);
+//!         window.close();
+//!     });
+//! }
+//! ```
+
+use crate::prelude::*;
+use crate::{Label, Widget, Window, WindowType};
+use glib::translate::*;
+use once_cell::sync::Lazy;
+use std::env;
+use std::net::TcpStream;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A display server that runs without a screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtualDisplay {
+    /// The X virtual framebuffer, `Xvfb`.
+    Xvfb,
+    /// The GTK Broadway server, `broadwayd`.
+    Broadway,
+}
+
+const FIRST_DISPLAY: u32 = 99;
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Initializes GTK for tests.
+///
+/// The display given by `DISPLAY`, `WAYLAND_DISPLAY` or `BROADWAY_DISPLAY` is
+/// used if set. Otherwise `Xvfb` is started, or `broadwayd` if `Xvfb` is not
+/// installed, and GTK is pointed to it with `--display`; the environment is
+/// left alone because changing it is not thread safe. Like
+/// [`fn@crate::init`], this makes the calling thread the main thread, so
+/// tests should use [`run()`] instead.
+pub fn init() -> Result<(), glib::BoolError> {
+    if crate::is_initialized() {
+        return Ok(());
+    }
+    let has_display = ["DISPLAY", "WAYLAND_DISPLAY", "BROADWAY_DISPLAY"]
+        .iter()
+        .any(|name| env::var_os(name).map_or(false, |value| !value.is_empty()));
+    if has_display {
+        return crate::init();
+    }
+
+    let (display, name) = start_virtual_display(VirtualDisplay::Xvfb)
+        .map(|name| (VirtualDisplay::Xvfb, name))
+        .or_else(|_| {
+            start_virtual_display(VirtualDisplay::Broadway)
+                .map(|name| (VirtualDisplay::Broadway, name))
+        })?;
+    let backend = match display {
+        VirtualDisplay::Xvfb => "x11",
+        VirtualDisplay::Broadway => "broadway",
+    };
+    unsafe {
+        gdk::ffi::gdk_set_allowed_backends(backend.to_glib_none().0);
+    }
+    let program = env::args().next().unwrap_or_default();
+    crate::rt::init_with_args(vec![program, "--display".to_owned(), name])
+}
+
+/// Starts `display` and returns its name, e.g. `:99`.
+///
+/// GTK can be pointed to it with the `--display` command line option, or
+/// with `DISPLAY` for `Xvfb` and `BROADWAY_DISPLAY` for `broadwayd`. The
+/// environment must only be changed before any other threads are started,
+/// which in tests means before the test harness runs.
+///
+/// The server keeps running after the tests; `Xvfb` exits when its last
+/// client disconnects.
+pub fn start_virtual_display(display: VirtualDisplay) -> Result<String, glib::BoolError> {
+    for number in FIRST_DISPLAY..FIRST_DISPLAY + 100 {
+        if is_display_ready(display, number)
+            || Path::new(&format!("/tmp/.X{}-lock", number)).exists()
+        {
+            continue;
+        }
+
+        let name = format!(":{}", number);
+        let mut command = match display {
+            VirtualDisplay::Xvfb => {
+                let mut command = Command::new("Xvfb");
+                command.args(&[
+                    &name,
+                    "-screen",
+                    "0",
+                    "1280x1024x24",
+                    "-nolisten",
+                    "tcp",
+                    "-terminate",
+                ]);
+                command
+            }
+            VirtualDisplay::Broadway => {
+                let mut command = Command::new("broadwayd");
+                command.arg(&name);
+                command
+            }
+        };
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| glib::bool_error!("Failed to start {:?}: {}", display, err))?;
+
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            if is_display_ready(display, number) {
+                return Ok(name);
+            }
+            // Another server may have taken the number in the meantime.
+            if let Ok(Some(_)) = child.try_wait() {
+                break;
+            }
+            if Instant::now() > deadline {
+                let _ = child.kill();
+                return Err(glib::bool_error!("{:?} did not start in time", display));
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+    Err(glib::bool_error!(
+        "No free display number for {:?}",
+        display
+    ))
+}
+
+fn is_display_ready(display: VirtualDisplay, number: u32) -> bool {
+    match display {
+        VirtualDisplay::Xvfb => Path::new(&format!("/tmp/.X11-unix/X{}", number)).exists(),
+        VirtualDisplay::Broadway => TcpStream::connect(("127.0.0.1", 8080 + number as u16)).is_ok(),
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+static WORKER: Lazy<Mutex<mpsc::Sender<Job>>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    thread::Builder::new()
+        .name("gtk-test".to_owned())
+        .spawn(move || {
+            for job in receiver {
+                job();
+            }
+        })
+        .expect("Failed to start the GTK test thread");
+    Mutex::new(sender)
+});
+
+/// Runs `f` on the GTK test thread after calling [`init()`] and returns its
+/// result.
+///
+/// All tests run on the same thread, one at a time, so they can share the
+/// GTK state. Panics in `f` are passed on to the caller.
+pub fn run<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let job: Job = Box::new(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            init().expect("Failed to initialize GTK for tests");
+            f()
+        }));
+        let _ = sender.send(result);
+    });
+    WORKER
+        .lock()
+        .unwrap()
+        .send(job)
+        .expect("The GTK test thread stopped");
+    match receiver.recv().expect("The GTK test thread stopped") {
+        Ok(result) => result,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Processes pending events and idle callbacks until there are none left.
+pub fn wait_until_idle() {
+    while crate::events_pending() {
+        crate::main_iteration_do(false);
+    }
+}
+
+/// Runs the main loop until `condition` returns `true` or `timeout` passes.
+///
+/// Returns the last result of `condition`.
+pub fn wait_for<F: FnMut() -> bool>(timeout: Duration, mut condition: F) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        wait_until_idle();
+        if condition() {
+            return true;
+        }
+        if Instant::now() > deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// Shows `widget` in a new toplevel window and waits until it is drawn.
+pub fn show_in_window<W: IsA<Widget>>(widget: &W) -> Window {
+    let window = Window::new(WindowType::Toplevel);
+    window.add(widget);
+    window.show_all();
+    wait_until_idle();
+    window
+}
+
+/// Returns `root` and all widgets in it, including internal children, in
+/// depth-first order.
+pub fn descendants<W: IsA<Widget>>(root: &W) -> Vec<Widget> {
+    fn collect(widget: &Widget, widgets: &mut Vec<Widget>) {
+        widgets.push(widget.clone());
+        if let Some(container) = widget.downcast_ref::<crate::Container>() {
+            container.forall(|child| collect(child, widgets));
+        }
+    }
+
+    let mut widgets = Vec::new();
+    collect(root.upcast_ref(), &mut widgets);
+    widgets
+}
+
+/// Finds the first widget whose widget name or builder id is `name`.
+pub fn find_by_name<W: IsA<Widget>>(root: &W, name: &str) -> Option<Widget> {
+    descendants(root).into_iter().find(|widget| {
+        widget.widget_name() == name || widget.buildable_name().as_deref() == Some(name)
+    })
+}
+
+/// Finds the first widget of type `T`.
+pub fn find_by_type<T: IsA<Widget>, W: IsA<Widget>>(root: &W) -> Option<T> {
+    descendants(root)
+        .into_iter()
+        .find_map(|widget| widget.downcast().ok())
+}
+
+/// Finds all widgets of type `T`.
+pub fn find_all_by_type<T: IsA<Widget>, W: IsA<Widget>>(root: &W) -> Vec<T> {
+    descendants(root)
+        .into_iter()
+        .filter_map(|widget| widget.downcast().ok())
+        .collect()
+}
+
+/// Finds the first widget with a `label` property or label text equal to
+/// `label`, e.g. a button or a label.
+pub fn find_by_label<W: IsA<Widget>>(root: &W, label: &str) -> Option<Widget> {
+    descendants(root).into_iter().find(|widget| {
+        if let Some(text) = widget.downcast_ref::<Label>().map(|l| l.text()) {
+            if text == label {
+                return true;
+            }
+        }
+        widget.find_property("label").is_some()
+            && widget
+                .property("label")
+                .ok()
+                .and_then(|value| value.get::<Option<String>>().ok())
+                .flatten()
+                .map_or(false, |text| text == label)
+    })
+}
+
+/// Clicks the primary button in the center of `widget`.
+pub fn click<W: IsA<Widget>>(widget: &W) {
+    let widget = widget.upcast_ref::<Widget>();
+    click_at(
+        widget,
+        widget.allocated_width() as f64 / 2.0,
+        widget.allocated_height() as f64 / 2.0,
+        gdk::BUTTON_PRIMARY,
+    );
+}
+
+/// Moves the pointer to `x`, `y` in widget coordinates and presses and
+/// releases `button` there.
+///
+/// # Panics
+///
+/// Panics if `widget` is not realized.
+pub fn click_at<W: IsA<Widget>>(widget: &W, x: f64, y: f64, button: u32) {
+    let (window, x, y) = pick_window(widget.upcast_ref(), x, y);
+    send_crossing(&window, x, y);
+    for &type_ in &[gdk::EventType::ButtonPress, gdk::EventType::ButtonRelease] {
+        let mut event = gdk::Event::new(type_);
+        event.set_device(pointer(&window).as_ref());
+        unsafe {
+            let ptr = event_ptr::<gdk::ffi::GdkEventButton>(&mut event);
+            (*ptr).window = window.to_glib_full();
+            (*ptr).send_event = 1;
+            (*ptr).x = x;
+            (*ptr).y = y;
+            let (x_root, y_root) = root_coords(&window, x, y);
+            (*ptr).x_root = x_root;
+            (*ptr).y_root = y_root;
+            (*ptr).button = button;
+            if type_ == gdk::EventType::ButtonRelease {
+                (*ptr).state = button_mask(button).into_glib();
+            }
+        }
+        crate::main_do_event(&mut event);
+    }
+}
+
+/// Scrolls over the center of `widget` by `delta_x`, `delta_y` in smooth
+/// scrolling units.
+pub fn scroll<W: IsA<Widget>>(widget: &W, delta_x: f64, delta_y: f64) {
+    let widget = widget.upcast_ref::<Widget>();
+    let (window, x, y) = pick_window(
+        widget,
+        widget.allocated_width() as f64 / 2.0,
+        widget.allocated_height() as f64 / 2.0,
+    );
+    send_crossing(&window, x, y);
+    let mut event = gdk::Event::new(gdk::EventType::Scroll);
+    event.set_device(pointer(&window).as_ref());
+    unsafe {
+        let ptr = event_ptr::<gdk::ffi::GdkEventScroll>(&mut event);
+        (*ptr).window = window.to_glib_full();
+        (*ptr).send_event = 1;
+        (*ptr).x = x;
+        (*ptr).y = y;
+        let (x_root, y_root) = root_coords(&window, x, y);
+        (*ptr).x_root = x_root;
+        (*ptr).y_root = y_root;
+        (*ptr).direction = gdk::ScrollDirection::Smooth.into_glib();
+        (*ptr).delta_x = delta_x;
+        (*ptr).delta_y = delta_y;
+    }
+    crate::main_do_event(&mut event);
+}
+
+/// Focuses `widget` and presses and releases `key` with `modifiers` held.
+///
+/// # Panics
+///
+/// Panics if `widget` is not in a realized toplevel window.
+pub fn press_key<W: IsA<Widget>>(widget: &W, key: gdk::keys::Key, modifiers: gdk::ModifierType) {
+    let widget = widget.upcast_ref::<Widget>();
+    let toplevel = widget
+        .toplevel()
+        .and_then(|toplevel| toplevel.window())
+        .expect("Widget is not in a realized window");
+    widget.grab_focus();
+    send_focus_in(widget, &toplevel);
+
+    let keymap_key = gdk::Keymap::for_display(&toplevel.display())
+        .and_then(|keymap| keymap.entries_for_keyval(*key).into_iter().next());
+    let keyboard = pointer(&toplevel).and_then(|pointer| pointer.associated_device());
+    for &type_ in &[gdk::EventType::KeyPress, gdk::EventType::KeyRelease] {
+        let mut event = gdk::Event::new(type_);
+        event.set_device(keyboard.as_ref());
+        unsafe {
+            let ptr = event_ptr::<gdk::ffi::GdkEventKey>(&mut event);
+            (*ptr).window = toplevel.to_glib_full();
+            (*ptr).send_event = 1;
+            (*ptr).state = modifiers.into_glib();
+            (*ptr).keyval = *key;
+            if let Some(keymap_key) = keymap_key {
+                (*ptr).hardware_keycode = keymap_key.keycode as u16;
+                (*ptr).group = keymap_key.group as u8;
+            }
+        }
+        crate::main_do_event(&mut event);
+    }
+}
+
+/// Types `text` into `widget` one key press per character.
+pub fn type_text<W: IsA<Widget>>(widget: &W, text: &str) {
+    for c in text.chars() {
+        press_key(
+            widget,
+            gdk::keys::Key::from_unicode(c),
+            gdk::ModifierType::empty(),
+        );
+    }
+}
+
+/// Draws `widget` into a new image surface of its allocated size.
+pub fn snapshot<W: IsA<Widget>>(widget: &W) -> Result<cairo::ImageSurface, cairo::Error> {
+    wait_until_idle();
+    let widget = widget.upcast_ref::<Widget>();
+    let surface = cairo::ImageSurface::create(
+        cairo::Format::ARgb32,
+        widget.allocated_width().max(1),
+        widget.allocated_height().max(1),
+    )?;
+    let cr = cairo::Context::new(&surface)?;
+    widget.draw(&cr);
+    drop(cr);
+    surface.flush();
+    Ok(surface)
+}
+
+unsafe fn event_ptr<T>(event: &mut gdk::Event) -> *mut T {
+    ToGlibPtrMut::<*mut gdk::ffi::GdkEvent>::to_glib_none_mut(event).0 as *mut T
+}
+
+// Returns the innermost GDK window at `x`, `y` in widget coordinates, with
+// the position translated into it, like the windowing system does for real
+// input.
+fn pick_window(widget: &Widget, x: f64, y: f64) -> (gdk::Window, f64, f64) {
+    let window = widget.window().expect("Widget is not realized");
+    let (mut x, mut y) = (x, y);
+    if !widget.has_window() {
+        let allocation = widget.allocation();
+        x += allocation.x as f64;
+        y += allocation.y as f64;
+    }
+
+    let mut window = window;
+    'descend: loop {
+        for child in window.children() {
+            if !child.is_visible() {
+                continue;
+            }
+            let (child_x, child_y) = child.position();
+            let (cx, cy) = (x - child_x as f64, y - child_y as f64);
+            if cx >= 0.0 && cy >= 0.0 && cx < child.width() as f64 && cy < child.height() as f64 {
+                window = child;
+                x = cx;
+                y = cy;
+                continue 'descend;
+            }
+        }
+        return (window, x, y);
+    }
+}
+
+fn root_coords(window: &gdk::Window, x: f64, y: f64) -> (f64, f64) {
+    let (x_root, y_root) = window.root_coords(x as i32, y as i32);
+    (x_root as f64 + x.fract(), y_root as f64 + y.fract())
+}
+
+#[allow(deprecated)]
+fn pointer(window: &gdk::Window) -> Option<gdk::Device> {
+    window.display().device_manager()?.client_pointer()
+}
+
+fn button_mask(button: u32) -> gdk::ModifierType {
+    match button {
+        1 => gdk::ModifierType::BUTTON1_MASK,
+        2 => gdk::ModifierType::BUTTON2_MASK,
+        3 => gdk::ModifierType::BUTTON3_MASK,
+        4 => gdk::ModifierType::BUTTON4_MASK,
+        5 => gdk::ModifierType::BUTTON5_MASK,
+        _ => gdk::ModifierType::empty(),
+    }
+}
+
+// Widgets such as buttons only react to clicks after the pointer entered.
+fn send_crossing(window: &gdk::Window, x: f64, y: f64) {
+    let mut event = gdk::Event::new(gdk::EventType::EnterNotify);
+    event.set_device(pointer(window).as_ref());
+    unsafe {
+        let ptr = event_ptr::<gdk::ffi::GdkEventCrossing>(&mut event);
+        (*ptr).window = window.to_glib_full();
+        (*ptr).send_event = 1;
+        (*ptr).x = x;
+        (*ptr).y = y;
+        let (x_root, y_root) = root_coords(window, x, y);
+        (*ptr).x_root = x_root;
+        (*ptr).y_root = y_root;
+        (*ptr).mode = gdk::ffi::GDK_CROSSING_NORMAL;
+        (*ptr).detail = gdk::ffi::GDK_NOTIFY_ANCESTOR;
+    }
+    crate::main_do_event(&mut event);
+}
+
+// Without a window manager toplevels don't get the focus on their own.
+fn send_focus_in(widget: &Widget, toplevel: &gdk::Window) {
+    let has_focus = widget
+        .toplevel()
+        .and_then(|toplevel| toplevel.downcast::<Window>().ok())
+        .map_or(false, |window| window.has_toplevel_focus());
+    if has_focus {
+        return;
+    }
+
+    let mut event = gdk::Event::new(gdk::EventType::FocusChange);
+    event.set_device(
+        pointer(toplevel)
+            .and_then(|p| p.associated_device())
+            .as_ref(),
+    );
+    unsafe {
+        let ptr = event_ptr::<gdk::ffi::GdkEventFocus>(&mut event);
+        (*ptr).window = toplevel.to_glib_full();
+        (*ptr).send_event = 1;
+        (*ptr).in_ = 1;
+    }
+    crate::main_do_event(&mut event);
+}
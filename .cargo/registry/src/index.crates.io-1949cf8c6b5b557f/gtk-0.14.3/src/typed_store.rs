@@ -0,0 +1,460 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::prelude::*;
+use crate::{
+    CellRenderer, CellRendererPixbuf, CellRendererProgress, CellRendererText, CellRendererToggle,
+    ListStore, TreeIter, TreeModel, TreeStore, TreeView, TreeViewColumn,
+};
+use glib::{StaticType, ToValue, Type, Value};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A struct stored as the rows of a [`TypedListStore`] or [`TypedTreeStore`],
+/// one model column per field.
+///
+/// Usually implemented with `#[derive(gtk::ModelRow)]`.
+pub trait ModelRow: Sized + 'static {
+    /// The model columns, in the order of the values of `to_values()`.
+    fn columns() -> Vec<ModelColumn>;
+
+    fn to_values(&self) -> Vec<Value>;
+
+    /// Builds a row from the values of its columns.
+    ///
+    /// Panics if a value doesn't have the type of its column.
+    fn from_values(values: &[Value]) -> Self;
+}
+
+/// The cell renderer shown for a [`ModelColumn`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellKind {
+    /// A toggle for `bool`, a pixbuf for `gdk_pixbuf::Pixbuf` and text for
+    /// everything else.
+    Auto,
+    Text,
+    Toggle,
+    Pixbuf,
+    /// A progress bar for values from 0 to 100.
+    Progress,
+    /// The column is stored but not shown.
+    Hidden,
+}
+
+/// A column of a [`ModelRow`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelColumn {
+    /// The name of the field stored in this column.
+    pub field: &'static str,
+    pub title: &'static str,
+    pub type_: Type,
+    pub kind: CellKind,
+}
+
+impl ModelColumn {
+    /// The renderer kind, with [`CellKind::Auto`] resolved from the type.
+    pub fn cell_kind(&self) -> CellKind {
+        match self.kind {
+            CellKind::Auto if self.type_ == bool::static_type() => CellKind::Toggle,
+            CellKind::Auto if self.type_.is_a(gdk_pixbuf::Pixbuf::static_type()) => {
+                CellKind::Pixbuf
+            }
+            CellKind::Auto => CellKind::Text,
+            kind => kind,
+        }
+    }
+
+    /// Creates a column showing model column `index` with a matching cell
+    /// renderer, or `None` for hidden columns.
+    pub fn tree_view_column(&self, index: i32) -> Option<TreeViewColumn> {
+        assert_initialized_main_thread!();
+        let (renderer, attribute): (CellRenderer, _) = match self.cell_kind() {
+            CellKind::Hidden => return None,
+            CellKind::Toggle => (CellRendererToggle::new().upcast(), "active"),
+            CellKind::Pixbuf => (CellRendererPixbuf::new().upcast(), "pixbuf"),
+            CellKind::Progress => (CellRendererProgress::new().upcast(), "value"),
+            CellKind::Auto | CellKind::Text => (CellRendererText::new().upcast(), "text"),
+        };
+        let column = TreeViewColumn::new();
+        column.set_title(self.title);
+        column.set_resizable(true);
+        column.set_sort_column_id(index);
+        column.pack_start(&renderer, true);
+        column.add_attribute(&renderer, attribute, index);
+        Some(column)
+    }
+}
+
+fn column_types<R: ModelRow>() -> Vec<Type> {
+    R::columns().iter().map(|column| column.type_).collect()
+}
+
+fn check_column_types<R: ModelRow, M: IsA<TreeModel>>(model: &M) -> Result<(), glib::BoolError> {
+    let types = column_types::<R>();
+    let n_columns = model.n_columns();
+    if n_columns as usize != types.len() {
+        return Err(glib::bool_error!(
+            "Model has {} columns but the row has {}",
+            n_columns,
+            types.len()
+        ));
+    }
+    for (index, type_) in types.into_iter().enumerate() {
+        let column_type = model.column_type(index as i32);
+        if column_type != type_ {
+            return Err(glib::bool_error!(
+                "Model column {} has type {} but the row needs {}",
+                index,
+                column_type,
+                type_
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn read_row<R: ModelRow, M: IsA<TreeModel>>(model: &M, iter: &TreeIter) -> R {
+    let values = (0..model.n_columns())
+        .map(|column| model.value(iter, column))
+        .collect::<Vec<_>>();
+    R::from_values(&values)
+}
+
+fn row_values<R: ModelRow>(row: &R) -> Vec<Value> {
+    let values = row.to_values();
+    assert_eq!(
+        values.len(),
+        R::columns().len(),
+        "ModelRow::to_values() returned the wrong number of values"
+    );
+    values
+}
+
+fn with_columns<T>(values: &[Value], f: impl FnOnce(&[(u32, &dyn ToValue)]) -> T) -> T {
+    let columns = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| (index as u32, value as &dyn ToValue))
+        .collect::<Vec<_>>();
+    f(&columns)
+}
+
+fn find_row<R: ModelRow, M: IsA<TreeModel>, P: FnMut(&R) -> bool>(
+    model: &M,
+    mut predicate: P,
+) -> Option<TreeIter> {
+    let mut found = None;
+    model.foreach(|model, _, iter| {
+        if predicate(&read_row(model, iter)) {
+            found = Some(iter.clone());
+            true
+        } else {
+            false
+        }
+    });
+    found
+}
+
+/// A [`ListStore`] whose rows are `R`s.
+///
+/// APIs taking an `IsA<TreeModel>`, such as the model of a [`TreeView`], need
+/// the underlying [`store()`](Self::store). Rows written through it must set
+/// every column, as [`get()`](Self::get) panics on unset values of types
+/// such as `String`.
+///
+/// ```no_run
+/// use gtk::prelude::*;
+///
+/// #[derive(gtk::ModelRow)]
+/// struct Contact {
+///     name: String,
+///     #[model(title = "E-mail")]
+///     email: String,
+///     favorite: bool,
+/// }
+///
+/// let store = gtk::TypedListStore::<Contact>::new();
+/// let iter = store.insert(&Contact {
+///     name: "Ada".to_owned(),
+///     email: "ada@example.com".to_owned(),
+///     favorite: false,
+/// });
+/// store.update(&iter, |contact| contact.favorite = true);
+/// assert!(store.get(&iter).favorite);
+///
+/// let tree_view = gtk::TreeView::with_model(store.store());
+/// gtk::append_columns::<Contact>(&tree_view);
+/// ```
+pub struct TypedListStore<R> {
+    store: ListStore,
+    row: PhantomData<fn() -> R>,
+}
+
+impl<R: ModelRow> TypedListStore<R> {
+    pub fn new() -> Self {
+        assert_initialized_main_thread!();
+        Self {
+            store: ListStore::new(&column_types::<R>()),
+            row: PhantomData,
+        }
+    }
+
+    /// Wraps an existing store, e.g. one created by a `GtkBuilder` UI file.
+    ///
+    /// Fails if its columns don't have the types of the columns of `R`.
+    pub fn from_store(store: ListStore) -> Result<Self, glib::BoolError> {
+        skip_assert_initialized!();
+        check_column_types::<R, _>(&store)?;
+        Ok(Self {
+            store,
+            row: PhantomData,
+        })
+    }
+
+    pub fn store(&self) -> &ListStore {
+        &self.store
+    }
+
+    /// Appends `row`.
+    pub fn insert(&self, row: &R) -> TreeIter {
+        self.insert_at(None, row)
+    }
+
+    /// Inserts `row` at `position`, or appends it if `position` is `None`
+    /// or past the end.
+    pub fn insert_at(&self, position: Option<u32>, row: &R) -> TreeIter {
+        with_columns(&row_values(row), |columns| {
+            self.store.insert_with_values(position, columns)
+        })
+    }
+
+    pub fn get(&self, iter: &TreeIter) -> R {
+        read_row(&self.store, iter)
+    }
+
+    /// Replaces the row at `iter`.
+    pub fn set(&self, iter: &TreeIter, row: &R) {
+        with_columns(&row_values(row), |columns| self.store.set(iter, columns))
+    }
+
+    /// Reads the row at `iter`, lets `f` modify it and writes it back.
+    pub fn update<F: FnOnce(&mut R)>(&self, iter: &TreeIter, f: F) {
+        let mut row = self.get(iter);
+        f(&mut row);
+        self.set(iter, &row);
+    }
+
+    /// Removes the row at `iter` and moves `iter` to the next row, returning
+    /// `false` if there is none.
+    pub fn remove(&self, iter: &TreeIter) -> bool {
+        self.store.remove(iter)
+    }
+
+    pub fn clear(&self) {
+        self.store.clear()
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.iter_n_children(None) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.iter_first().is_none()
+    }
+
+    pub fn rows(&self) -> Vec<R> {
+        let mut rows = Vec::with_capacity(self.len());
+        if let Some(iter) = self.store.iter_first() {
+            loop {
+                rows.push(self.get(&iter));
+                if !self.store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        rows
+    }
+
+    /// Returns the first row matching `predicate`.
+    pub fn find<P: FnMut(&R) -> bool>(&self, predicate: P) -> Option<TreeIter> {
+        find_row(&self.store, predicate)
+    }
+}
+
+impl<R: ModelRow> Default for TypedListStore<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> Clone for TypedListStore<R> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            row: PhantomData,
+        }
+    }
+}
+
+impl<R> fmt::Debug for TypedListStore<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypedListStore")
+            .field("store", &self.store)
+            .field("row", &std::any::type_name::<R>())
+            .finish()
+    }
+}
+
+impl<R> AsRef<TreeModel> for TypedListStore<R> {
+    fn as_ref(&self) -> &TreeModel {
+        self.store.upcast_ref()
+    }
+}
+
+/// A [`TreeStore`] whose rows are `R`s.
+///
+/// Like with [`TypedListStore`], APIs taking an `IsA<TreeModel>` need the
+/// underlying [`store()`](Self::store).
+pub struct TypedTreeStore<R> {
+    store: TreeStore,
+    row: PhantomData<fn() -> R>,
+}
+
+impl<R: ModelRow> TypedTreeStore<R> {
+    pub fn new() -> Self {
+        assert_initialized_main_thread!();
+        Self {
+            store: TreeStore::new(&column_types::<R>()),
+            row: PhantomData,
+        }
+    }
+
+    /// Wraps an existing store, e.g. one created by a `GtkBuilder` UI file.
+    ///
+    /// Fails if its columns don't have the types of the columns of `R`.
+    pub fn from_store(store: TreeStore) -> Result<Self, glib::BoolError> {
+        skip_assert_initialized!();
+        check_column_types::<R, _>(&store)?;
+        Ok(Self {
+            store,
+            row: PhantomData,
+        })
+    }
+
+    pub fn store(&self) -> &TreeStore {
+        &self.store
+    }
+
+    /// Appends `row` to the children of `parent`, or to the top level if
+    /// `parent` is `None`.
+    pub fn insert(&self, parent: Option<&TreeIter>, row: &R) -> TreeIter {
+        self.insert_at(parent, None, row)
+    }
+
+    /// Inserts `row` at `position` among the children of `parent`, or
+    /// appends it if `position` is `None` or past the end.
+    pub fn insert_at(&self, parent: Option<&TreeIter>, position: Option<u32>, row: &R) -> TreeIter {
+        with_columns(&row_values(row), |columns| {
+            self.store.insert_with_values(parent, position, columns)
+        })
+    }
+
+    pub fn get(&self, iter: &TreeIter) -> R {
+        read_row(&self.store, iter)
+    }
+
+    /// Replaces the row at `iter`, keeping its children.
+    pub fn set(&self, iter: &TreeIter, row: &R) {
+        with_columns(&row_values(row), |columns| self.store.set(iter, columns))
+    }
+
+    /// Reads the row at `iter`, lets `f` modify it and writes it back.
+    pub fn update<F: FnOnce(&mut R)>(&self, iter: &TreeIter, f: F) {
+        let mut row = self.get(iter);
+        f(&mut row);
+        self.set(iter, &row);
+    }
+
+    /// Removes the row at `iter` with its children and moves `iter` to the
+    /// next sibling, returning `false` if there is none.
+    pub fn remove(&self, iter: &TreeIter) -> bool {
+        self.store.remove(iter)
+    }
+
+    pub fn clear(&self) {
+        self.store.clear()
+    }
+
+    /// The children of `parent`, or the top level rows if `parent` is
+    /// `None`.
+    pub fn children(&self, parent: Option<&TreeIter>) -> Vec<R> {
+        let mut rows = Vec::new();
+        if let Some(iter) = self.store.iter_children(parent) {
+            loop {
+                rows.push(self.get(&iter));
+                if !self.store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        rows
+    }
+
+    /// Returns the first row matching `predicate`, searching depth-first.
+    pub fn find<P: FnMut(&R) -> bool>(&self, predicate: P) -> Option<TreeIter> {
+        find_row(&self.store, predicate)
+    }
+}
+
+impl<R: ModelRow> Default for TypedTreeStore<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> Clone for TypedTreeStore<R> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            row: PhantomData,
+        }
+    }
+}
+
+impl<R> fmt::Debug for TypedTreeStore<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypedTreeStore")
+            .field("store", &self.store)
+            .field("row", &std::any::type_name::<R>())
+            .finish()
+    }
+}
+
+impl<R> AsRef<TreeModel> for TypedTreeStore<R> {
+    fn as_ref(&self) -> &TreeModel {
+        self.store.upcast_ref()
+    }
+}
+
+/// Creates a column with a matching cell renderer for every visible column
+/// of `R`.
+///
+/// The columns are resizable and sort by their model column.
+pub fn tree_view_columns<R: ModelRow>() -> Vec<TreeViewColumn> {
+    assert_initialized_main_thread!();
+    R::columns()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, column)| column.tree_view_column(index as i32))
+        .collect()
+}
+
+/// Appends the columns of [`tree_view_columns`] to `tree_view`, whose model
+/// should store `R`s, and returns them.
+pub fn append_columns<R: ModelRow>(tree_view: &impl IsA<TreeView>) -> Vec<TreeViewColumn> {
+    skip_assert_initialized!();
+    let columns = tree_view_columns::<R>();
+    for column in &columns {
+        tree_view.append_column(column);
+    }
+    columns
+}
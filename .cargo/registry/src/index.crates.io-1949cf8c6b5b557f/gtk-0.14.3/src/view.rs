@@ -0,0 +1,543 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Widget trees described by the [`view!`](crate::view!) macro.
+//!
+//! A [`Node`] describes a widget: its type, properties, signal handlers and
+//! children. A [`View`] builds the widgets of a node and can later update
+//! them from a new description, only setting the properties that changed.
+//! This allows writing small applications in a component style, where a
+//! function renders the state as a node:
+//!
+//! ```no_run
+//! use gtk::prelude::*;
+//! use std::cell::Cell;
+//! use std::rc::Rc;
+//!
+//! fn render(count: u32, clicks: Rc<Cell<u32>>, view: gtk::view::View) -> gtk::view::Node {
+//!     gtk::view! {
+//!         gtk::Box {
+//!             orientation: gtk::Orientation::Vertical,
+//!             spacing: 6,
+//!             gtk::Label {
+//!                 label: &format!("Clicked {} times", count),
+//!             },
+//!             #[child(expand = false)]
+//!             gtk::Button {
+//!                 label: "Click",
+//!                 connect_clicked: move |_| {
+//!                     clicks.set(clicks.get() + 1);
+//!                     view.update(render(clicks.get(), clicks.clone(), view.clone()));
+//!                 },
+//!             },
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Properties are matched by name. Children with a key are matched by key,
+//! wherever they move among their siblings, and children without one by
+//! position. When the type, key or a construct-only property of a widget
+//! changes, it is built again instead of updated. Signal handlers are
+//! reconnected on every update, so they can capture the current state.
+
+use crate::prelude::*;
+use crate::{Container, Widget, Window};
+use glib::translate::*;
+use glib::{ParamFlags, ParamSpec, SignalHandlerId, Type, Value};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+type ConnectFn = Box<dyn FnOnce(&glib::Object) -> SignalHandlerId>;
+
+/// The description of a widget and its children.
+///
+/// Usually created with the [`view!`](crate::view!) macro.
+pub struct Node {
+    type_: Type,
+    properties: Vec<(&'static str, Value)>,
+    signals: Vec<ConnectFn>,
+    child_properties: Vec<(&'static str, Value)>,
+    key: Option<String>,
+    children: Vec<Node>,
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("type", &self.type_)
+            .field("properties", &self.properties)
+            .field("signals", &self.signals.len())
+            .field("child_properties", &self.child_properties)
+            .field("key", &self.key)
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+impl Node {
+    pub fn new<T: IsA<Widget>>() -> Self {
+        Self::with_type(T::static_type())
+    }
+
+    /// Panics if `type_` is not a widget type.
+    pub fn with_type(type_: Type) -> Self {
+        assert!(
+            type_.is_a(Widget::static_type()),
+            "{} is not a widget type",
+            type_
+        );
+        Self {
+            type_,
+            properties: Vec::new(),
+            signals: Vec::new(),
+            child_properties: Vec::new(),
+            key: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn type_(&self) -> Type {
+        self.type_
+    }
+
+    /// Sets the property `name`. The value needs to have the exact type of
+    /// the property.
+    pub fn property(mut self, name: &'static str, value: Value) -> Self {
+        self.properties.retain(|(n, _)| *n != name);
+        self.properties.push((name, value));
+        self
+    }
+
+    /// Adds a signal handler, connected by `connect` on the widget.
+    pub fn signal<F: FnOnce(&glib::Object) -> SignalHandlerId + 'static>(
+        mut self,
+        connect: F,
+    ) -> Self {
+        self.signals.push(Box::new(connect));
+        self
+    }
+
+    /// Sets the child property `name`, which controls the layout of the
+    /// widget in its parent, e.g. `expand` in a [`Box`](crate::Box).
+    pub fn child_property(mut self, name: &'static str, value: Value) -> Self {
+        self.child_properties.retain(|(n, _)| *n != name);
+        self.child_properties.push((name, value));
+        self
+    }
+
+    /// Identifies the node among its siblings, so that its widget is kept
+    /// when the node moves, and a widget is built again instead of updated if
+    /// a node with another key takes its place.
+    pub fn key<K: ToString>(mut self, key: K) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub fn child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn children<I: IntoIterator<Item = Node>>(mut self, children: I) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    /// Builds the widgets without keeping track of them for updates.
+    pub fn build(self) -> Widget {
+        Mounted::build(self).widget
+    }
+}
+
+/// Widgets built from a [`Node`], which can be updated from new nodes.
+#[derive(Clone)]
+pub struct View(Rc<ViewInner>);
+
+struct ViewInner {
+    root: RefCell<Mounted>,
+    // The root widget as of the last completed update.
+    widget: RefCell<Widget>,
+    // A node passed to `update` while an update is running, e.g. from a
+    // signal handler emitted by a property change.
+    pending: RefCell<Option<Node>>,
+}
+
+impl fmt::Debug for View {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("View").field(&self.widget()).finish()
+    }
+}
+
+impl View {
+    pub fn new(node: Node) -> Self {
+        assert_initialized_main_thread!();
+        let root = Mounted::build(node);
+        Self(Rc::new(ViewInner {
+            widget: RefCell::new(root.widget.clone()),
+            root: RefCell::new(root),
+            pending: RefCell::new(None),
+        }))
+    }
+
+    /// The root widget.
+    ///
+    /// This changes if an update replaces the root, e.g. because its type
+    /// changed. During an update, e.g. in a signal handler, this is the root
+    /// from before the update.
+    pub fn widget(&self) -> Widget {
+        self.0.widget.borrow().clone()
+    }
+
+    /// Updates the widgets to match `node`.
+    ///
+    /// If the root widget can't be updated, the new one takes its place in
+    /// its parent, with the same child properties. A root without a parent,
+    /// e.g. a window, needs to be swapped in by the caller, see
+    /// [`widget()`](View::widget).
+    ///
+    /// Calls from signal handlers during an update are applied after it.
+    pub fn update(&self, node: Node) {
+        let mut root = match self.0.root.try_borrow_mut() {
+            Ok(root) => root,
+            Err(_) => {
+                self.0.pending.replace(Some(node));
+                return;
+            }
+        };
+        let mut node = Some(node);
+        while let Some(next) = node.take().or_else(|| self.0.pending.take()) {
+            if root.can_update(&next) {
+                root.update(next);
+            } else {
+                let new = Mounted::build(next);
+                replace_in_parent(&root.widget, &new.widget);
+                *root = new;
+            }
+        }
+        self.0.widget.replace(root.widget.clone());
+    }
+}
+
+// A widget built from a node, with the parts of the node needed to diff it
+// against the next one.
+struct Mounted {
+    widget: Widget,
+    properties: Vec<(&'static str, Value)>,
+    construct_only: Vec<&'static str>,
+    handlers: Vec<SignalHandlerId>,
+    child_properties: Vec<(&'static str, Value)>,
+    key: Option<String>,
+    children: Vec<Mounted>,
+}
+
+impl Mounted {
+    fn build(node: Node) -> Self {
+        let Node {
+            type_,
+            properties,
+            signals,
+            child_properties,
+            key,
+            children,
+        } = node;
+
+        let values = properties
+            .iter()
+            .map(|(name, value)| (*name, value as &dyn ToValue))
+            .collect::<Vec<_>>();
+        let widget = glib::Object::with_type(type_, &values)
+            .unwrap_or_else(|err| panic!("Can't create {}: {}", type_, err))
+            .downcast::<Widget>()
+            .unwrap();
+        if !properties.iter().any(|(name, _)| *name == "visible") && !widget.is::<Window>() {
+            widget.show();
+        }
+        let construct_only = properties
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| {
+                widget.find_property(*name).map_or(false, |pspec| {
+                    pspec.flags().contains(ParamFlags::CONSTRUCT_ONLY)
+                })
+            })
+            .collect();
+        let handlers = signals
+            .into_iter()
+            .map(|connect| connect(widget.upcast_ref()))
+            .collect();
+
+        let mut mounted = Self {
+            widget,
+            properties,
+            construct_only,
+            handlers,
+            child_properties,
+            key,
+            children: Vec::new(),
+        };
+        if !children.is_empty() {
+            let container = mounted.container();
+            for child in children {
+                let child = Self::build(child);
+                child.attach(&container);
+                mounted.children.push(child);
+            }
+        }
+        mounted
+    }
+
+    fn container(&self) -> Container {
+        match self.widget.clone().downcast::<Container>() {
+            Ok(container) => container,
+            Err(widget) => panic!("{} can't have children", widget.type_()),
+        }
+    }
+
+    fn can_update(&self, node: &Node) -> bool {
+        self.widget.type_() == node.type_
+            && self.key == node.key
+            && self.construct_only.iter().all(|name| {
+                let old = self.properties.iter().find(|(n, _)| n == name);
+                let new = node.properties.iter().find(|(n, _)| n == name);
+                match (old, new) {
+                    (Some((_, old)), Some((_, new))) => {
+                        let pspec = self.widget.find_property(*name);
+                        values_equal(pspec.as_ref(), old, new)
+                    }
+                    _ => false,
+                }
+            })
+    }
+
+    fn attach(&self, container: &Container) {
+        container.add(&self.widget);
+        for (name, value) in &self.child_properties {
+            set_child_property(container, &self.widget, name, value);
+        }
+    }
+
+    // Updates the widget in place, leaving the child properties to the
+    // parent. Returns the child properties of `node`.
+    fn update(&mut self, node: Node) -> Vec<(&'static str, Value)> {
+        let Node {
+            properties,
+            signals,
+            child_properties,
+            children,
+            ..
+        } = node;
+
+        for (name, value) in &properties {
+            let pspec = self.widget.find_property(*name);
+            let changed = match self.properties.iter().find(|(n, _)| n == name) {
+                Some((_, old)) => !values_equal(pspec.as_ref(), old, value),
+                None => true,
+            };
+            if changed {
+                self.widget
+                    .set_property_from_value(*name, value)
+                    .unwrap_or_else(|err| panic!("{}", err));
+            }
+        }
+        for (name, _) in &self.properties {
+            if properties.iter().any(|(n, _)| n == name) {
+                continue;
+            }
+            if *name == "visible" {
+                if !self.widget.is::<Window>() {
+                    self.widget.show();
+                }
+            } else if let Some(pspec) = self.widget.find_property(*name) {
+                let _ = self
+                    .widget
+                    .set_property_from_value(*name, pspec.default_value());
+            }
+        }
+        self.properties = properties;
+
+        for handler in self.handlers.drain(..) {
+            self.widget.disconnect(handler);
+        }
+        self.handlers = signals
+            .into_iter()
+            .map(|connect| connect(self.widget.upcast_ref()))
+            .collect();
+
+        if !children.is_empty() || !self.children.is_empty() {
+            self.update_children(children);
+        }
+        child_properties
+    }
+
+    fn update_children(&mut self, nodes: Vec<Node>) {
+        let container = self.container();
+        let mut old = std::mem::take(&mut self.children)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        // Once a child is built or moved, the following children are added
+        // again to keep their order.
+        let mut reattach = false;
+        let mut last_index = 0;
+        for (position, node) in nodes.into_iter().enumerate() {
+            let index = match node.key {
+                Some(ref key) => old.iter().position(|child| {
+                    child
+                        .as_ref()
+                        .map_or(false, |child| child.key.as_ref() == Some(key))
+                }),
+                None => Some(position).filter(|&index| {
+                    old.get(index)
+                        .and_then(Option::as_ref)
+                        .map_or(false, |child| child.key.is_none())
+                }),
+            };
+            match index.and_then(|index| old[index].take().map(|child| (index, child))) {
+                Some((index, mut child)) if child.can_update(&node) => {
+                    let child_properties = child.update(node);
+                    if index < last_index {
+                        reattach = true;
+                    }
+                    last_index = index;
+                    if reattach {
+                        container.remove(&child.widget);
+                        child.child_properties = child_properties;
+                        child.attach(&container);
+                    } else {
+                        for (name, value) in &child_properties {
+                            let changed =
+                                match child.child_properties.iter().find(|(n, _)| n == name) {
+                                    Some((_, old)) => !values_equal(None, old, value),
+                                    None => true,
+                                };
+                            if changed {
+                                set_child_property(&container, &child.widget, name, value);
+                            }
+                        }
+                        child.child_properties = child_properties;
+                    }
+                    self.children.push(child);
+                }
+                replaced => {
+                    if let Some((_, child)) = replaced {
+                        container.remove(&child.widget);
+                    }
+                    reattach = true;
+                    let child = Self::build(node);
+                    child.attach(&container);
+                    self.children.push(child);
+                }
+            }
+        }
+        for child in old.into_iter().flatten() {
+            container.remove(&child.widget);
+        }
+    }
+}
+
+extern "C" {
+    // Not in the `ffi` crate because of a mismatch of the class type.
+    fn gtk_container_class_list_child_properties(
+        cclass: *mut glib::gobject_ffi::GObjectClass,
+        n_properties: *mut u32,
+    ) -> *mut *mut glib::gobject_ffi::GParamSpec;
+}
+
+// Puts `new` in the place of `old` in the parent of `old`, if any.
+fn replace_in_parent(old: &Widget, new: &Widget) {
+    let container = match old
+        .parent()
+        .and_then(|parent| parent.downcast::<Container>().ok())
+    {
+        Some(container) => container,
+        None => return,
+    };
+    let properties = unsafe {
+        let class = (*(container.as_ptr() as *mut glib::gobject_ffi::GTypeInstance)).g_class;
+        let mut n_properties = 0;
+        let pspecs = gtk_container_class_list_child_properties(
+            class as *mut glib::gobject_ffi::GObjectClass,
+            &mut n_properties,
+        );
+        let pspecs: Vec<ParamSpec> =
+            FromGlibContainer::from_glib_container_num(pspecs, n_properties as usize);
+        pspecs
+            .into_iter()
+            .filter(|pspec| pspec.flags().contains(ParamFlags::READWRITE))
+            .map(|pspec| {
+                let mut value = Value::from_type(pspec.value_type());
+                ffi::gtk_container_child_get_property(
+                    container.to_glib_none().0,
+                    old.to_glib_none().0,
+                    pspec.name().to_glib_none().0,
+                    value.to_glib_none_mut().0,
+                );
+                (pspec, value)
+            })
+            .collect::<Vec<_>>()
+    };
+    container.remove(old);
+    container.add(new);
+    for (pspec, value) in &properties {
+        set_child_property(&container, new, pspec.name(), value);
+    }
+}
+
+fn set_child_property(container: &Container, child: &Widget, name: &str, value: &Value) {
+    unsafe {
+        ffi::gtk_container_child_set_property(
+            container.to_glib_none().0,
+            child.to_glib_none().0,
+            name.to_glib_none().0,
+            value.to_glib_none().0,
+        );
+    }
+}
+
+// Compares with the property's spec if known, or else compares the values
+// of simple types.
+fn values_equal(pspec: Option<&ParamSpec>, a: &Value, b: &Value) -> bool {
+    let type_ = a.type_();
+    if type_ != b.type_() {
+        return false;
+    }
+    if let Some(pspec) = pspec.filter(|pspec| type_.is_a(pspec.value_type())) {
+        return unsafe {
+            glib::gobject_ffi::g_param_values_cmp(
+                pspec.to_glib_none().0,
+                a.to_glib_none().0,
+                b.to_glib_none().0,
+            ) == 0
+        };
+    }
+    if let (Ok(a), Ok(b)) = (a.get::<f64>(), b.get::<f64>()) {
+        return a == b;
+    }
+    if let (Ok(a), Ok(b)) = (a.get::<f32>(), b.get::<f32>()) {
+        return a == b;
+    }
+    let simple = [
+        Type::BOOL,
+        Type::I8,
+        Type::U8,
+        Type::I32,
+        Type::U32,
+        Type::I_LONG,
+        Type::U_LONG,
+        Type::I64,
+        Type::U64,
+        Type::ENUM,
+        Type::FLAGS,
+        Type::STRING,
+    ];
+    simple.iter().any(|&simple| type_.is_a(simple)) && contents(a) == contents(b)
+}
+
+fn contents(value: &Value) -> String {
+    unsafe {
+        from_glib_full(glib::gobject_ffi::g_strdup_value_contents(
+            value.to_glib_none().0,
+        ))
+    }
+}
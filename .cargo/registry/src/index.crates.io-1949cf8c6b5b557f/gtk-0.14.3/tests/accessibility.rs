@@ -0,0 +1,62 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gtk::accessibility::{AccessibilityTree, AuditRule};
+use gtk::gdk_pixbuf::{Colorspace, Pixbuf};
+use gtk::prelude::*;
+use gtk::testing;
+
+fn image() -> gtk::Image {
+    let pixbuf = Pixbuf::new(Colorspace::Rgb, false, 8, 16, 16).unwrap();
+    gtk::Image::from_pixbuf(Some(&pixbuf))
+}
+
+#[test]
+fn audit() {
+    testing::run(|| {
+        let ok = gtk::Button::with_label("OK");
+        let unlabeled = gtk::Button::new();
+        let unfocusable = gtk::Button::with_label("Unfocusable");
+        unfocusable.set_can_focus(false);
+        let logo = image();
+        logo.accessible().unwrap().set_description("Logo");
+        let undescribed = image();
+
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        container.add(&ok);
+        container.add(&unlabeled);
+        container.add(&unfocusable);
+        container.add(&logo);
+        container.add(&undescribed);
+        let window = testing::show_in_window(&container);
+
+        let tree = AccessibilityTree::for_widget(&container).unwrap();
+        assert_eq!(tree.root().widget.as_ref(), Some(container.upcast_ref()));
+        assert_eq!(tree.root().children.len(), 5);
+        assert!(tree
+            .root()
+            .children
+            .iter()
+            .all(|&child| tree.nodes()[child].parent == Some(0)));
+
+        let node = tree.find_by_name("OK").unwrap();
+        assert_eq!(node.role, atk::Role::PushButton);
+        assert!(node.actions.iter().any(|action| action.name == "click"));
+        assert_eq!(tree.find_by_role(atk::Role::PushButton).len(), 3);
+        assert!(tree.to_string().contains("push button \"OK\""));
+
+        let issues = tree
+            .audit()
+            .into_iter()
+            .map(|issue| (issue.rule, issue.widget.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            [
+                (AuditRule::UnlabeledButton, unlabeled.upcast()),
+                (AuditRule::NotKeyboardAccessible, unfocusable.upcast()),
+                (AuditRule::ImageWithoutDescription, undescribed.upcast()),
+            ]
+        );
+        window.close();
+    });
+}
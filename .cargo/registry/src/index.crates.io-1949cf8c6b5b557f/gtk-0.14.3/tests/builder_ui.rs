@@ -0,0 +1,69 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gtk::prelude::*;
+use gtk::testing;
+use std::cell::Cell;
+use std::rc::Rc;
+
+gtk::builder_ui!(MainWindowUi, file = "tests/builder_ui.ui");
+
+gtk::builder_ui!(
+    ResourcesUi,
+    string = r#"<interface>
+  <object class="GdkPixbuf" id="icon"/>
+  <object class="GdkPixbufAnimation" id="spinner"/>
+  <object class="GdkDisplay" id="display"/>
+  <object class="GListStore" id="store"/>
+  <menu id="app_menu"/>
+  <object class="MyCustomWidget" id="custom"/>
+</interface>"#,
+    types(MyCustomWidget = gtk::Label),
+);
+
+// Only checks the field types, building `ResourcesUi` would need a display.
+#[allow(dead_code)]
+fn resources_field_types(ui: &ResourcesUi) {
+    let _: &gtk::gdk_pixbuf::Pixbuf = &ui.icon;
+    let _: &gtk::gdk_pixbuf::PixbufAnimation = &ui.spinner;
+    let _: &gtk::gdk::Display = &ui.display;
+    let _: &gtk::gio::ListStore = &ui.store;
+    let _: &gtk::gio::Menu = &ui.app_menu;
+    let _: &gtk::Label = &ui.custom;
+}
+
+#[derive(Default)]
+struct Handlers {
+    ok_clicked: Cell<u32>,
+}
+
+impl MainWindowUiHandlers for Handlers {
+    fn on_ok_clicked(&self, _args: &[glib::Value]) -> Option<glib::Value> {
+        self.ok_clicked.set(self.ok_clicked.get() + 1);
+        None
+    }
+
+    fn on_delete(&self, _args: &[glib::Value]) -> Option<glib::Value> {
+        Some(true.to_value())
+    }
+}
+
+#[test]
+fn builder_ui() {
+    assert!(MainWindowUi::UI.contains(r#"id="main-window""#));
+
+    testing::run(|| {
+        let ui = MainWindowUi::new();
+        assert_eq!(ui.main_window.title().as_deref(), Some("builder_ui!"));
+        assert_eq!(ui.status_label.label(), "<b>Ready</b>");
+        assert_eq!(ui.ok_button.label().as_deref(), Some("OK & close"));
+
+        let handlers = Rc::new(Handlers::default());
+        ui.connect_signals(handlers.clone());
+        ui.ok_button.clicked();
+        ui.ok_button.clicked();
+        assert_eq!(handlers.ok_clicked.get(), 2);
+
+        let copy = MainWindowUi::from_builder(ui.builder.clone());
+        assert_eq!(copy.ok_button, ui.ok_button);
+    });
+}
@@ -0,0 +1,247 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+#[cfg(feature = "v3_20")]
+use gtk::glib::translate::IntoGlib;
+use gtk::prelude::*;
+use gtk::{testing, ResponseType};
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+// Polls the wrapped future once, to start it without waiting for it.
+struct PollOnce<'a, T>(&'a mut Pin<Box<dyn Future<Output = T>>>);
+
+impl<'a, T> Future for PollOnce<'a, T> {
+    type Output = Poll<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Poll<T>> {
+        Poll::Ready(self.0.as_mut().poll(cx))
+    }
+}
+
+fn poll_once<T>(future: &mut Pin<Box<dyn Future<Output = T>>>) -> Poll<T> {
+    gtk::glib::MainContext::default().block_on(PollOnce(future))
+}
+
+// Runs `future` on the main context and stores its output in the returned
+// cell once it resolves.
+fn spawn<T: 'static>(future: Pin<Box<dyn Future<Output = T>>>) -> Rc<RefCell<Option<T>>> {
+    let output = Rc::new(RefCell::new(None));
+    let output_clone = output.clone();
+    gtk::glib::MainContext::default().spawn_local(async move {
+        *output_clone.borrow_mut() = Some(future.await);
+    });
+    testing::wait_until_idle();
+    output
+}
+
+fn message_dialog() -> gtk::MessageDialog {
+    gtk::Window::list_toplevels()
+        .into_iter()
+        .find_map(|window| window.downcast::<gtk::MessageDialog>().ok())
+        .expect("No message dialog")
+}
+
+fn connect_destroyed<W: IsA<gtk::Widget>>(widget: &W) -> Rc<Cell<bool>> {
+    let destroyed = Rc::new(Cell::new(false));
+    let destroyed_clone = destroyed.clone();
+    widget.connect_destroy(move |_| destroyed_clone.set(true));
+    destroyed
+}
+
+#[test]
+fn dialog_response() {
+    testing::run(|| {
+        let dialog = gtk::Dialog::new();
+        let response = spawn(dialog.run_future());
+        assert!(dialog.is_visible());
+        assert_eq!(*response.borrow(), None);
+
+        dialog.response(ResponseType::Ok);
+        testing::wait_until_idle();
+        assert_eq!(*response.borrow(), Some(ResponseType::Ok));
+
+        // The handler is gone, another response doesn't reach the future.
+        dialog.response(ResponseType::Cancel);
+        assert_eq!(*response.borrow(), Some(ResponseType::Ok));
+        unsafe { dialog.destroy() };
+    });
+}
+
+#[test]
+fn dialog_cancel() {
+    testing::run(|| {
+        let dialog = gtk::Dialog::new();
+        let mut future = dialog.run_future();
+        assert_eq!(poll_once(&mut future), Poll::Pending);
+        assert!(dialog.is_visible());
+
+        drop(future);
+        assert!(!dialog.is_visible());
+        unsafe { dialog.destroy() };
+    });
+}
+
+#[test]
+fn dialog_delete_event() {
+    testing::run(|| {
+        let dialog = gtk::Dialog::new();
+        let destroyed = connect_destroyed(&dialog);
+        let response = spawn(dialog.run_future());
+
+        // Destroying the dialog, as closing it does, disconnects the handler
+        // before the future disconnects it.
+        dialog.response(ResponseType::DeleteEvent);
+        unsafe { dialog.destroy() };
+        testing::wait_until_idle();
+        assert!(destroyed.get());
+        assert_eq!(*response.borrow(), Some(ResponseType::DeleteEvent));
+    });
+}
+
+#[test]
+fn file_chooser_dialog_response() {
+    testing::run(|| {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let dialog = gtk::FileChooserDialog::new(
+            Some("Open"),
+            None::<&gtk::Window>,
+            gtk::FileChooserAction::Open,
+        );
+        let files = spawn(dialog.run_files_future());
+        assert!(dialog.is_visible());
+        dialog.set_filename(&path);
+        assert!(testing::wait_for(Duration::from_secs(5), || !dialog
+            .files()
+            .is_empty()));
+
+        dialog.response(ResponseType::Accept);
+        testing::wait_until_idle();
+        let files = files.borrow_mut().take().expect("Future didn't resolve");
+        let paths = files
+            .unwrap()
+            .iter()
+            .map(|file| file.path().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, [path]);
+        assert!(!dialog.is_visible());
+
+        let files = spawn(dialog.run_files_future());
+        dialog.response(ResponseType::Cancel);
+        testing::wait_until_idle();
+        assert_eq!(*files.borrow(), Some(None));
+        unsafe { dialog.destroy() };
+    });
+}
+
+#[test]
+fn file_chooser_dialog_cancel() {
+    testing::run(|| {
+        let dialog = gtk::FileChooserDialog::new(
+            Some("Open"),
+            None::<&gtk::Window>,
+            gtk::FileChooserAction::Open,
+        );
+        let mut future = dialog.run_files_future();
+        assert_eq!(poll_once(&mut future), Poll::Pending);
+        assert!(dialog.is_visible());
+
+        drop(future);
+        assert!(!dialog.is_visible());
+        unsafe { dialog.destroy() };
+    });
+}
+
+#[test]
+fn message_dialog_response() {
+    testing::run(|| {
+        let response = spawn(gtk::MessageDialog::run_message_future(
+            None::<&gtk::Window>,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::YesNo,
+            "Discard unsaved changes?",
+        ));
+        let dialog = message_dialog();
+        assert!(dialog.is_visible());
+        assert!(dialog.is_modal());
+        let destroyed = connect_destroyed(&dialog);
+
+        dialog.response(ResponseType::Yes);
+        testing::wait_until_idle();
+        assert_eq!(*response.borrow(), Some(ResponseType::Yes));
+        assert!(destroyed.get());
+    });
+}
+
+#[test]
+fn message_dialog_cancel() {
+    testing::run(|| {
+        let mut future = gtk::MessageDialog::run_message_future(
+            None::<&gtk::Window>,
+            gtk::MessageType::Info,
+            gtk::ButtonsType::Ok,
+            "Saved",
+        );
+        assert_eq!(poll_once(&mut future), Poll::Pending);
+        let destroyed = connect_destroyed(&message_dialog());
+
+        drop(future);
+        assert!(destroyed.get());
+    });
+}
+
+#[cfg(feature = "v3_20")]
+#[test]
+fn native_dialog_response() {
+    testing::run(|| {
+        let dialog = gtk::FileChooserNative::new(
+            Some("Open"),
+            None::<&gtk::Window>,
+            gtk::FileChooserAction::Open,
+            None,
+            None,
+        );
+        let response = spawn(dialog.run_future());
+        assert!(dialog.is_visible());
+
+        dialog
+            .emit_by_name("response", &[&ResponseType::Accept.into_glib()])
+            .unwrap();
+        testing::wait_until_idle();
+        assert_eq!(*response.borrow(), Some(ResponseType::Accept));
+
+        let files = spawn(dialog.run_files_future());
+        dialog
+            .emit_by_name("response", &[&ResponseType::Cancel.into_glib()])
+            .unwrap();
+        testing::wait_until_idle();
+        assert_eq!(*files.borrow(), Some(None));
+        assert!(!dialog.is_visible());
+    });
+}
+
+#[cfg(feature = "v3_20")]
+#[test]
+fn native_dialog_cancel() {
+    testing::run(|| {
+        let dialog = gtk::FileChooserNative::new(
+            Some("Open"),
+            None::<&gtk::Window>,
+            gtk::FileChooserAction::Open,
+            None,
+            None,
+        );
+        let mut future = dialog.run_future();
+        assert_eq!(poll_once(&mut future), Poll::Pending);
+        assert!(dialog.is_visible());
+
+        drop(future);
+        assert!(!dialog.is_visible());
+    });
+}
@@ -0,0 +1,61 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gtk::gdk::keys::constants as keys;
+use gtk::gdk::{EventKey, EventType, KeyCombination, Keymap, ModifierType};
+use gtk::glib::translate::IntoGlib;
+use gtk::testing;
+
+// A key press of `keyval` as the display's keymap would report it.
+fn key_press(keymap: &Keymap, keyval: gtk::gdk::keys::Key, state: ModifierType) -> EventKey {
+    let entry = keymap
+        .entries_for_keyval(*keyval)
+        .into_iter()
+        .next()
+        .expect("Key not in the keymap");
+    let mut event = gtk::gdk::Event::new(EventType::KeyPress)
+        .downcast::<EventKey>()
+        .unwrap();
+    let ffi = event.as_mut();
+    ffi.keyval = *keyval;
+    ffi.state = state.into_glib();
+    ffi.hardware_keycode = entry.keycode as u16;
+    ffi.group = entry.group as u8;
+    event
+}
+
+#[test]
+fn from_event_with_keymap() {
+    testing::run(|| {
+        let display = gtk::gdk::Display::default().unwrap();
+        let keymap = Keymap::for_display(&display).unwrap();
+
+        let event = key_press(&keymap, keys::s, ModifierType::CONTROL_MASK);
+        assert_eq!(
+            KeyCombination::from_event_with_keymap(&event, &keymap),
+            Some(KeyCombination::new(keys::s, ModifierType::CONTROL_MASK))
+        );
+
+        // The key is taken from the base level, like Hyprland does.
+        let event = key_press(&keymap, keys::exclam, ModifierType::SHIFT_MASK);
+        let combination = KeyCombination::from_event_with_keymap(&event, &keymap).unwrap();
+        assert_eq!(*combination.key(), *keys::_1);
+        assert_eq!(
+            combination.to_notation(gtk::gdk::KeyNotation::Hyprland),
+            "SHIFT+1"
+        );
+
+        // Real modifiers become virtual ones, without duplicates.
+        let mut super_mask = ModifierType::SUPER_MASK;
+        keymap.map_virtual_modifiers(&mut super_mask);
+        let event = key_press(&keymap, keys::q, super_mask - ModifierType::SUPER_MASK);
+        let combination = KeyCombination::from_event_with_keymap(&event, &keymap).unwrap();
+        assert_eq!(combination.modifiers(), ModifierType::SUPER_MASK);
+
+        let mut event = key_press(&keymap, keys::Shift_L, ModifierType::empty());
+        event.as_mut().is_modifier = 1;
+        assert_eq!(
+            KeyCombination::from_event_with_keymap(&event, &keymap),
+            None
+        );
+    });
+}
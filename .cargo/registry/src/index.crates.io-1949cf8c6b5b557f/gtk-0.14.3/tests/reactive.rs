@@ -0,0 +1,102 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gtk::prelude::*;
+use gtk::reactive::{bind_property_to_widget, Observable, State};
+use gtk::testing;
+use std::cell::Cell;
+use std::rc::Rc;
+
+#[test]
+fn bind() {
+    testing::run(|| {
+        let text = State::new(String::from("a"));
+        let label = gtk::Label::new(None);
+        text.bind(&label, "label");
+        assert_eq!(label.label(), "a");
+
+        text.set(String::from("b"));
+        assert_eq!(label.label(), "b");
+    });
+}
+
+#[test]
+fn bind_bidirectional() {
+    testing::run(|| {
+        let text = State::new(String::from("a"));
+        let entry = gtk::Entry::new();
+        text.bind_bidirectional(&entry, "text");
+        assert_eq!(entry.text(), "a");
+
+        let changes = Rc::new(Cell::new(0));
+        {
+            let changes = changes.clone();
+            text.subscribe(move |_| changes.set(changes.get() + 1));
+        }
+
+        text.set(String::from("b"));
+        assert_eq!(entry.text(), "b");
+        assert_eq!(changes.get(), 1);
+
+        // Setting the widget updates the state once, without bouncing back.
+        entry.set_text("typed");
+        assert_eq!(text.get(), "typed");
+        assert_eq!(entry.text(), "typed");
+        assert_eq!(changes.get(), 2);
+    });
+}
+
+#[test]
+fn from_property() {
+    testing::run(|| {
+        let label = gtk::Label::new(Some("a"));
+        let text = State::<String>::from_property(&label, "label");
+        assert_eq!(text.get(), "a");
+
+        let seen = Rc::new(Cell::new(0));
+        {
+            let seen = seen.clone();
+            text.subscribe(move |_| seen.set(seen.get() + 1));
+        }
+        label.set_label("b");
+        assert_eq!(text.get(), "b");
+        assert_eq!(seen.get(), 1);
+    });
+}
+
+#[test]
+fn unsubscribe_on_destroy() {
+    testing::run(|| {
+        let text = State::new(String::from("a"));
+        let label = gtk::Label::new(None);
+        text.bind(&label, "label");
+
+        unsafe { label.destroy() };
+        text.set(String::from("b"));
+        assert_eq!(label.label(), "a");
+    });
+}
+
+#[test]
+fn bind_property_to_widget_unbinds_on_destroy() {
+    testing::run(|| {
+        let entry = gtk::Entry::new();
+        let label = gtk::Label::new(None);
+        let binding = bind_property_to_widget(
+            &entry,
+            "text",
+            &label,
+            "label",
+            glib::BindingFlags::SYNC_CREATE,
+        )
+        .unwrap();
+        entry.set_text("a");
+        assert_eq!(label.label(), "a");
+
+        let weak = binding.downgrade();
+        drop(binding);
+        unsafe { label.destroy() };
+        assert!(weak.upgrade().is_none());
+        entry.set_text("b");
+        assert_eq!(label.label(), "a");
+    });
+}
@@ -0,0 +1,187 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gtk::gdk::keys::constants as keys;
+use gtk::gdk::ModifierType;
+use gtk::prelude::*;
+use gtk::{testing, KeySequence, ShortcutManager};
+use std::cell::Cell;
+use std::rc::Rc;
+
+fn sequences(sequences: &[&str]) -> Vec<KeySequence> {
+    sequences
+        .iter()
+        .map(|sequence| KeySequence::parse(sequence).unwrap())
+        .collect()
+}
+
+fn manager() -> ShortcutManager {
+    let shortcuts = ShortcutManager::new();
+    shortcuts
+        .register("app.quit", "Quit", "General", &["<Control>q"])
+        .unwrap();
+    shortcuts
+        .register(
+            "win.save",
+            "Save",
+            "Files",
+            &["<Control>s", "<Control>x <Control>s"],
+        )
+        .unwrap();
+    shortcuts
+        .register("win.close", "Close", "Files", &["<Control>w"])
+        .unwrap();
+    shortcuts
+}
+
+#[test]
+fn conflicts() {
+    testing::run(|| {
+        let shortcuts = manager();
+        assert!(shortcuts.conflicts().is_empty());
+
+        shortcuts.bind("win.close", "<Control>q").unwrap();
+        shortcuts.bind("app.quit", "<Control>x").unwrap();
+        let conflicts = shortcuts.conflicts();
+        assert_eq!(conflicts.len(), 2);
+
+        let duplicate = conflicts.iter().find(|c| !c.is_prefix()).unwrap();
+        assert_eq!(duplicate.action, "app.quit");
+        assert_eq!(duplicate.other_action, "win.close");
+        assert_eq!(duplicate.sequence, duplicate.other_sequence);
+        assert_eq!(
+            duplicate.to_string(),
+            format!(
+                "`{}` is bound to both app.quit and win.close",
+                duplicate.sequence
+            )
+        );
+
+        let prefix = conflicts.iter().find(|c| c.is_prefix()).unwrap();
+        assert_eq!(prefix.action, "app.quit");
+        assert_eq!(prefix.sequence, sequences(&["<Control>x"])[0]);
+        assert_eq!(prefix.other_action, "win.save");
+        assert_eq!(
+            prefix.other_sequence,
+            sequences(&["<Control>x <Control>s"])[0]
+        );
+    });
+}
+
+#[test]
+fn load_data_errors() {
+    testing::run(|| {
+        let shortcuts = manager();
+        let errors = shortcuts.load_data(
+            "# comment\n\
+             bind = CTRL, NoSuchKey, app.quit\n\
+             \n\
+             exec = foot\n\
+             bind = CTRL, W, app.unknown\n\
+             bind = $mod, Q, app.quit\n\
+             bind = CTRL SHIFT, Q, app.quit\n",
+        );
+        let lines = errors.iter().map(|error| error.line).collect::<Vec<_>>();
+        assert_eq!(lines, [2, 4, 5, 6]);
+        assert!(errors.iter().all(|error| error.file.is_none()));
+        assert_eq!(errors[2].message, "unknown action `app.unknown`");
+        assert!(errors[0].to_string().starts_with("<data>:2: "));
+
+        // Valid lines still apply.
+        assert_eq!(
+            shortcuts.bindings("app.quit"),
+            sequences(&["<Control><Shift>q"])
+        );
+    });
+}
+
+#[test]
+fn rebinding_replaces_defaults() {
+    testing::run(|| {
+        let shortcuts = manager();
+        let changes = Rc::new(Cell::new(0));
+        {
+            let changes = changes.clone();
+            shortcuts.connect_changed(move |_| changes.set(changes.get() + 1));
+        }
+
+        let errors = shortcuts.load_data(
+            "$mod = CTRL\n\
+             bind = $mod, E, win.save\n\
+             bind = <Control>x <Control>w, win.save\n\
+             bind = MOD3, H, app.quit\n\
+             unbind = <Control>w\n",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(changes.get(), 1);
+        assert_eq!(
+            shortcuts.bindings("win.save"),
+            sequences(&["<Control>e", "<Control>x <Control>w"])
+        );
+        // Hyprland's MOD3 is Hyper, as for `gdk::KeyCombination`.
+        assert_eq!(shortcuts.bindings("app.quit"), sequences(&["<Hyper>h"]));
+        assert!(shortcuts.bindings("win.close").is_empty());
+        assert_eq!(
+            shortcuts
+                .action_for(&sequences(&["<Control>e"])[0])
+                .as_deref(),
+            Some("win.save")
+        );
+        assert_eq!(shortcuts.action_for(&sequences(&["<Control>s"])[0]), None);
+
+        // Loading again starts from the defaults.
+        assert!(shortcuts.load_data("bind = CTRL, R, win.close").is_empty());
+        assert_eq!(
+            shortcuts.bindings("win.save"),
+            sequences(&["<Control>s", "<Control>x <Control>s"])
+        );
+        assert_eq!(shortcuts.bindings("win.close"), sequences(&["<Control>r"]));
+    });
+}
+
+#[test]
+fn reset() {
+    testing::run(|| {
+        let shortcuts = manager();
+        shortcuts.unbind("app.quit").unwrap();
+        shortcuts.bind("win.close", "<Control>F4").unwrap();
+        assert!(shortcuts.bind("win.unknown", "<Control>u").is_err());
+        assert!(shortcuts.bindings("app.quit").is_empty());
+
+        shortcuts.reset();
+        assert_eq!(shortcuts.bindings("app.quit"), sequences(&["<Control>q"]));
+        assert_eq!(shortcuts.bindings("win.close"), sequences(&["<Control>w"]));
+    });
+}
+
+#[test]
+fn chords() {
+    testing::run(|| {
+        let shortcuts = manager();
+        let button = gtk::Button::with_label("Focus");
+        let window = testing::show_in_window(&button);
+        shortcuts.attach_to_window(&window);
+
+        let saved = Rc::new(Cell::new(0));
+        let save = gtk::gio::SimpleAction::new("save", None);
+        {
+            let saved = saved.clone();
+            save.connect_activate(move |_, _| saved.set(saved.get() + 1));
+        }
+        let group = gtk::gio::SimpleActionGroup::new();
+        group.add_action(&save);
+        window.insert_action_group("win", Some(&group));
+
+        testing::press_key(&button, keys::x, ModifierType::CONTROL_MASK);
+        assert_eq!(saved.get(), 0);
+        testing::press_key(&button, keys::s, ModifierType::CONTROL_MASK);
+        assert_eq!(saved.get(), 1);
+
+        // A stroke that doesn't continue the chord cancels it.
+        testing::press_key(&button, keys::x, ModifierType::CONTROL_MASK);
+        testing::press_key(&button, keys::a, ModifierType::empty());
+        testing::press_key(&button, keys::s, ModifierType::CONTROL_MASK);
+        assert_eq!(saved.get(), 1);
+
+        unsafe { window.destroy() };
+    });
+}
@@ -0,0 +1,137 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gtk::testing;
+use gtk::{CssProviderError, Stylesheet};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::rc::Rc;
+use std::time::Duration;
+
+// The unknown property starts at line 2, column 3.
+const INVALID: &str = "window {\n  colr: red;\n}\n";
+
+// Runs the default main context until `done` returns true or `timeout` has
+// passed.
+fn iterate_until(timeout: Duration, done: impl Fn() -> bool) {
+    let context = glib::MainContext::default();
+    let timed_out = Rc::new(Cell::new(false));
+    let source = {
+        let timed_out = timed_out.clone();
+        glib::timeout_add_local_once(timeout, move || timed_out.set(true))
+    };
+    while !done() && !timed_out.get() {
+        context.iteration(true);
+    }
+    if !timed_out.get() {
+        glib::source_remove(source);
+    }
+}
+
+#[test]
+fn load_data_errors() {
+    testing::run(|| {
+        let stylesheet = Stylesheet::new();
+        assert!(stylesheet.load_data("window { color: red; }").is_empty());
+
+        let errors = stylesheet.load_data(INVALID);
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!(error.file, None);
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(error.kind, Some(CssProviderError::Name));
+        assert!(!error.is_warning());
+        assert!(error.to_string().starts_with("<data>:2:3: "));
+        assert_eq!(stylesheet.errors(), errors);
+    });
+}
+
+#[test]
+fn load_path_errors() {
+    testing::run(|| {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("style.css");
+        fs::write(&path, INVALID).unwrap();
+
+        let stylesheet = Stylesheet::new();
+        let errors = stylesheet.load_path(&path);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].file.as_deref(), Some(path.as_path()));
+        assert_eq!((errors[0].line, errors[0].column), (2, 3));
+        assert_eq!(errors[0].kind, Some(CssProviderError::Name));
+
+        let missing = stylesheet.load_path(dir.path().join("missing.css"));
+        assert_eq!(missing.len(), 1);
+        assert_eq!((missing[0].line, missing[0].column), (0, 0));
+    });
+}
+
+#[test]
+fn reload() {
+    testing::run(|| {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("style.css");
+        let theme = dir.path().join("theme.css");
+        fs::write(&path, "@import url(\"theme.css\");\n").unwrap();
+        fs::write(&theme, "window { color: red; }\n").unwrap();
+
+        let stylesheet = Stylesheet::new();
+        let reloads = Rc::new(RefCell::new(Vec::new()));
+        {
+            let reloads = reloads.clone();
+            stylesheet.connect_reloaded(move |_, errors| reloads.borrow_mut().push(errors.len()));
+        }
+        assert!(stylesheet.load_path(&path).is_empty());
+        assert_eq!(stylesheet.watched_files(), [path.clone(), theme.clone()]);
+
+        fs::write(&theme, INVALID).unwrap();
+        let errors = stylesheet.reload();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].file.as_deref(), Some(theme.as_path()));
+        assert_eq!(errors[0].line, 2);
+
+        fs::write(&theme, "window { color: blue; }\n").unwrap();
+        assert!(stylesheet.reload().is_empty());
+        assert_eq!(*reloads.borrow(), [0, 1, 0]);
+    });
+}
+
+#[test]
+fn reload_on_change() {
+    testing::run(|| {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("style.css");
+        let theme = dir.path().join("theme.css");
+        fs::write(&path, "@import url(\"theme.css\");\n").unwrap();
+        fs::write(&theme, "window { color: red; }\n").unwrap();
+
+        let stylesheet = Stylesheet::new();
+        stylesheet.set_watching(true);
+        assert!(stylesheet.load_path(&path).is_empty());
+
+        let reloads = Rc::new(RefCell::new(Vec::new()));
+        {
+            let reloads = reloads.clone();
+            stylesheet
+                .connect_reloaded(move |_, errors| reloads.borrow_mut().push(errors.to_vec()));
+        }
+
+        // A change of an imported file reloads the stylesheet.
+        fs::write(&theme, INVALID).unwrap();
+        iterate_until(Duration::from_secs(5), || !reloads.borrow().is_empty());
+        {
+            let reloads = reloads.borrow();
+            assert!(!reloads.is_empty(), "no reload after the file changed");
+            let errors = reloads.last().unwrap();
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].file.as_deref(), Some(theme.as_path()));
+        }
+
+        // No more reloads once watching stops.
+        stylesheet.set_watching(false);
+        iterate_until(Duration::from_millis(500), || false);
+        let count = reloads.borrow().len();
+        fs::write(&theme, "window { color: blue; }\n").unwrap();
+        iterate_until(Duration::from_millis(500), || false);
+        assert_eq!(reloads.borrow().len(), count);
+    });
+}
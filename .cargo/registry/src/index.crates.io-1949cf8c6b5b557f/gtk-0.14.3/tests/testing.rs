@@ -0,0 +1,107 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use gtk::prelude::*;
+use gtk::testing;
+use std::cell::Cell;
+use std::panic;
+use std::rc::Rc;
+
+#[test]
+fn run() {
+    assert_eq!(testing::run(|| 42), 42);
+
+    // Panics are passed on, and the thread stays usable.
+    let result = panic::catch_unwind(|| testing::run(|| panic!("in the test")));
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"in the test"));
+
+    let thread = testing::run(|| std::thread::current().id());
+    assert_eq!(testing::run(|| std::thread::current().id()), thread);
+    assert_ne!(std::thread::current().id(), thread);
+    assert!(testing::run(gtk::is_initialized_main_thread));
+}
+
+#[test]
+fn click() {
+    testing::run(|| {
+        let button = gtk::Button::with_label("Count");
+        let clicks = Rc::new(Cell::new(0));
+        let counter = clicks.clone();
+        button.connect_clicked(move |_| counter.set(counter.get() + 1));
+
+        let window = testing::show_in_window(&button);
+        let found = testing::find_by_label(&window, "Count").unwrap();
+        assert_eq!(found, button.clone().upcast::<gtk::Widget>());
+        testing::click(&found);
+        testing::click(&found);
+        testing::wait_until_idle();
+        assert_eq!(clicks.get(), 2);
+
+        // Clicking outside of the button does nothing.
+        testing::click_at(&button, -10.0, -10.0, gdk::BUTTON_PRIMARY);
+        testing::wait_until_idle();
+        assert_eq!(clicks.get(), 2);
+        window.close();
+    });
+}
+
+#[test]
+fn press_key() {
+    testing::run(|| {
+        let entry = gtk::Entry::new();
+        let activated = Rc::new(Cell::new(false));
+        let flag = activated.clone();
+        entry.connect_activate(move |_| flag.set(true));
+
+        let window = testing::show_in_window(&entry);
+        testing::type_text(&entry, "hello");
+        testing::wait_until_idle();
+        assert_eq!(entry.text(), "hello");
+
+        testing::press_key(
+            &entry,
+            gdk::keys::constants::BackSpace,
+            gdk::ModifierType::empty(),
+        );
+        testing::press_key(
+            &entry,
+            gdk::keys::constants::a,
+            gdk::ModifierType::CONTROL_MASK,
+        );
+        testing::press_key(
+            &entry,
+            gdk::keys::constants::Return,
+            gdk::ModifierType::empty(),
+        );
+        testing::wait_until_idle();
+        assert_eq!(entry.text(), "hell");
+        assert_eq!(entry.selection_bounds(), Some((0, 4)));
+        assert!(activated.get());
+        window.close();
+    });
+}
+
+#[test]
+fn snapshot() {
+    testing::run(|| {
+        let area = gtk::DrawingArea::new();
+        area.set_size_request(40, 30);
+        area.connect_draw(|_, cr| {
+            cr.set_source_rgb(1.0, 0.0, 0.0);
+            cr.paint().unwrap();
+            Inhibit(false)
+        });
+
+        let window = testing::show_in_window(&area);
+        let mut image = testing::snapshot(&area).unwrap();
+        assert_eq!((image.width(), image.height()), (40, 30));
+        let stride = image.stride() as usize;
+        let data = image.data().unwrap();
+        // Opaque red in native endian ARGB.
+        let red = 0xffff_0000u32.to_ne_bytes();
+        assert_eq!(data[..4], red);
+        assert_eq!(data[29 * stride + 39 * 4..][..4], red);
+        drop(data);
+        window.close();
+    });
+}
@@ -0,0 +1,192 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::{StaticType, ToValue};
+use gtk::prelude::*;
+use gtk::{testing, CellKind, ModelColumn, ModelRow, TypedListStore, TypedTreeStore};
+
+#[derive(Debug, Clone, Default, PartialEq, ModelRow)]
+struct Download {
+    file_name: String,
+    #[model(title = "Size (bytes)")]
+    size: u64,
+    #[model(renderer = "progress")]
+    progress: u32,
+    #[model(skip)]
+    cache: Vec<u8>,
+    done: bool,
+    #[model(hidden)]
+    id: i32,
+}
+
+fn download(file_name: &str, id: i32) -> Download {
+    Download {
+        file_name: file_name.to_owned(),
+        size: 1 << 40,
+        progress: 50,
+        cache: vec![1, 2, 3],
+        done: false,
+        id,
+    }
+}
+
+#[test]
+fn derived_columns() {
+    let column = |field, title, type_, kind| ModelColumn {
+        field,
+        title,
+        type_,
+        kind,
+    };
+    assert_eq!(
+        Download::columns(),
+        [
+            column(
+                "file_name",
+                "File name",
+                String::static_type(),
+                CellKind::Auto
+            ),
+            column("size", "Size (bytes)", u64::static_type(), CellKind::Auto),
+            column(
+                "progress",
+                "Progress",
+                u32::static_type(),
+                CellKind::Progress
+            ),
+            column("done", "Done", bool::static_type(), CellKind::Auto),
+            column("id", "Id", i32::static_type(), CellKind::Hidden),
+        ]
+    );
+    let kinds = Download::columns()
+        .iter()
+        .map(ModelColumn::cell_kind)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            CellKind::Text,
+            CellKind::Text,
+            CellKind::Progress,
+            CellKind::Toggle,
+            CellKind::Hidden
+        ]
+    );
+
+    // Skipped fields are not stored and read back as their default.
+    let row = download("a.iso", 1);
+    let values = row.to_values();
+    assert_eq!(values.len(), 5);
+    assert_eq!(values[0].get::<String>().unwrap(), "a.iso");
+    assert_eq!(values[4].get::<i32>().unwrap(), 1);
+    let read = Download::from_values(&values);
+    assert_eq!(
+        read,
+        Download {
+            cache: Vec::new(),
+            ..row
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "model column `size` holds a value of another type")]
+fn from_values_checks_types() {
+    let values = [
+        "a.iso".to_value(),
+        1i32.to_value(),
+        50u32.to_value(),
+        false.to_value(),
+        1i32.to_value(),
+    ];
+    Download::from_values(&values);
+}
+
+#[test]
+fn list_store() {
+    testing::run(|| {
+        let store = TypedListStore::<Download>::new();
+        assert!(store.is_empty());
+        let a = store.insert(&download("a.iso", 1));
+        store.insert(&download("c.iso", 3));
+        store.insert_at(Some(1), &download("b.iso", 2));
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(&a).file_name, "a.iso");
+
+        store.update(&a, |row| {
+            row.done = true;
+            row.cache.push(4);
+        });
+        let row = store.get(&a);
+        assert!(row.done);
+        assert!(row.cache.is_empty());
+
+        let b = store.find(|row| row.id == 2).unwrap();
+        store.set(&b, &download("renamed.iso", 2));
+        let names = store
+            .rows()
+            .into_iter()
+            .map(|row| row.file_name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a.iso", "renamed.iso", "c.iso"]);
+        assert!(store.find(|row| row.id == 4).is_none());
+
+        let tree_view = gtk::TreeView::with_model(store.store());
+        let columns = gtk::append_columns::<Download>(&tree_view);
+        let titles = columns
+            .iter()
+            .map(|column| column.title().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["File name", "Size (bytes)", "Progress", "Done"]);
+        assert_eq!(columns[3].sort_column_id(), 3);
+
+        store.clear();
+        assert!(store.is_empty());
+    });
+}
+
+#[test]
+fn tree_store() {
+    testing::run(|| {
+        let store = TypedTreeStore::<Download>::new();
+        let parent = store.insert(None, &download("folder", 1));
+        store.insert(Some(&parent), &download("b.iso", 3));
+        store.insert_at(Some(&parent), Some(0), &download("a.iso", 2));
+        store.update(&parent, |row| row.done = true);
+
+        let children = store.children(Some(&parent));
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].file_name, "a.iso");
+        assert!(store.children(None)[0].done);
+
+        let b = store.find(|row| row.file_name == "b.iso").unwrap();
+        assert_eq!(store.get(&b).id, 3);
+    });
+}
+
+#[test]
+fn from_store_checks_column_types() {
+    testing::run(|| {
+        let types = Download::columns()
+            .iter()
+            .map(|column| column.type_)
+            .collect::<Vec<_>>();
+        let store = gtk::ListStore::new(&types);
+        store.insert_with_values(None, &[(0, &"a.iso"), (4, &7i32)]);
+        let typed = TypedListStore::<Download>::from_store(store).unwrap();
+        assert_eq!(typed.rows()[0].id, 7);
+        assert!(TypedTreeStore::<Download>::from_store(gtk::TreeStore::new(&types)).is_ok());
+
+        let mut wrong = types.clone();
+        wrong[1] = i32::static_type();
+        let err = TypedListStore::<Download>::from_store(gtk::ListStore::new(&wrong)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Model column 1 has type gint but the row needs guint64"
+        );
+        assert!(TypedTreeStore::<Download>::from_store(gtk::TreeStore::new(&wrong)).is_err());
+
+        let err =
+            TypedListStore::<Download>::from_store(gtk::ListStore::new(&types[..4])).unwrap_err();
+        assert_eq!(err.to_string(), "Model has 4 columns but the row has 5");
+    });
+}
@@ -0,0 +1,188 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::ToValue;
+use gtk::prelude::*;
+use gtk::testing;
+use gtk::view::{Node, View};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+fn label(text: &str) -> Node {
+    Node::new::<gtk::Label>().property("label", text.to_value())
+}
+
+fn list(keys: &[&str]) -> Node {
+    Node::new::<gtk::Box>().children(keys.iter().map(|&key| label(key).key(key)))
+}
+
+fn children(view: &View) -> Vec<gtk::Label> {
+    view.widget()
+        .downcast::<gtk::Container>()
+        .unwrap()
+        .children()
+        .into_iter()
+        .map(|child| child.downcast().unwrap())
+        .collect()
+}
+
+fn texts(view: &View) -> Vec<String> {
+    children(view)
+        .iter()
+        .map(|label| label.label().to_string())
+        .collect()
+}
+
+#[test]
+fn keyed_reorder() {
+    testing::run(|| {
+        let view = View::new(list(&["a", "b", "c"]));
+        let root = view.widget();
+        let old = children(&view);
+
+        // Moved keys keep their widget.
+        view.update(list(&["c", "a", "b"]));
+        assert_eq!(view.widget(), root);
+        assert_eq!(texts(&view), ["c", "a", "b"]);
+        assert_eq!(
+            children(&view),
+            [old[2].clone(), old[0].clone(), old[1].clone()]
+        );
+
+        view.update(list(&["b", "d", "c"]));
+        assert_eq!(texts(&view), ["b", "d", "c"]);
+        let updated = children(&view);
+        assert_eq!(updated[0], old[1]);
+        assert!(!old.contains(&updated[1]));
+        assert_eq!(updated[2], old[2]);
+    });
+}
+
+#[test]
+fn keyed_and_unkeyed_children() {
+    testing::run(|| {
+        let node = |keys: &[&str]| {
+            Node::new::<gtk::Box>()
+                .child(label("header"))
+                .children(keys.iter().map(|&key| label(key).key(key)))
+                .child(label("footer"))
+        };
+        let view = View::new(node(&["a", "b"]));
+        let old = children(&view);
+
+        view.update(node(&["b", "a"]));
+        assert_eq!(texts(&view), ["header", "b", "a", "footer"]);
+        assert_eq!(
+            children(&view),
+            [
+                old[0].clone(),
+                old[2].clone(),
+                old[1].clone(),
+                old[3].clone()
+            ]
+        );
+
+        // Unkeyed children are matched by position.
+        view.update(node(&["b"]));
+        let updated = children(&view);
+        assert_eq!(texts(&view), ["header", "b", "footer"]);
+        assert_eq!(updated[0], old[0]);
+        assert_eq!(updated[1], old[2]);
+        assert!(!old.contains(&updated[2]));
+    });
+}
+
+#[test]
+fn widget_during_update() {
+    testing::run(|| {
+        let current = Rc::new(RefCell::new(None::<View>));
+        let seen = Rc::new(RefCell::new(None));
+        let node = |text: &str, node: Node| {
+            let current = current.clone();
+            let seen = seen.clone();
+            node.property("label", text.to_value())
+                .signal(move |widget| {
+                    widget.connect_notify_local(Some("label"), move |_, _| {
+                        *seen.borrow_mut() = current.borrow().as_ref().map(View::widget);
+                    })
+                })
+        };
+
+        let view = View::new(node("One", Node::new::<gtk::Label>()));
+        let root = view.widget();
+        current.replace(Some(view.clone()));
+
+        view.update(node("Two", Node::new::<gtk::Label>()));
+        assert_eq!(seen.borrow_mut().take(), Some(root.clone()));
+
+        view.update(Node::new::<gtk::Button>());
+        assert_ne!(view.widget(), root);
+        current.replace(None);
+    });
+}
+
+#[test]
+fn property_only_update() {
+    testing::run(|| {
+        let clicks = Rc::new(Cell::new(0));
+        let button = |label: &str, step: u32| {
+            let clicks = clicks.clone();
+            Node::new::<gtk::Button>()
+                .property("label", label.to_value())
+                .signal(move |widget| {
+                    let clicks = clicks.clone();
+                    widget
+                        .downcast_ref::<gtk::Button>()
+                        .unwrap()
+                        .connect_clicked(move |_| clicks.set(clicks.get() + step))
+                })
+        };
+
+        let view = View::new(button("One", 1).property("use-underline", true.to_value()));
+        let widget = view.widget().downcast::<gtk::Button>().unwrap();
+        assert!(widget.uses_underline());
+        widget.clicked();
+        assert_eq!(clicks.get(), 1);
+
+        view.update(button("Two", 10));
+        assert_eq!(view.widget(), widget.clone().upcast::<gtk::Widget>());
+        assert_eq!(widget.label().as_deref(), Some("Two"));
+        // Properties that are not set anymore go back to their default.
+        assert!(!widget.uses_underline());
+        // Only the handler of the latest node is connected.
+        widget.clicked();
+        assert_eq!(clicks.get(), 11);
+    });
+}
+
+#[test]
+fn root_type_change() {
+    testing::run(|| {
+        let view = View::new(label("Label"));
+        let parent = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        parent.add(&view.widget());
+        let last = gtk::Label::new(Some("Last"));
+        parent.add(&last);
+        parent.reorder_child(&last, 0);
+        parent.set_child_expand(&view.widget(), true);
+
+        view.update(Node::new::<gtk::Button>().property("label", "Button".to_value()));
+        let widget = view.widget().downcast::<gtk::Button>().unwrap();
+        assert_eq!(widget.label().as_deref(), Some("Button"));
+        assert_eq!(widget.parent(), Some(parent.clone().upcast()));
+        assert_eq!(parent.children().len(), 2);
+        assert_eq!(parent.child_position(&widget), 1);
+        assert!(parent.child_expands(&widget));
+
+        // A key change replaces the root as well.
+        view.update(label("Keyed").key("keyed"));
+        let widget = view.widget();
+        assert!(widget.is::<gtk::Label>());
+        assert_eq!(parent.child_position(&widget), 1);
+
+        // Roots without a parent are only replaced in the view.
+        let view = View::new(label("Label"));
+        view.update(Node::new::<gtk::Entry>());
+        assert!(view.widget().is::<gtk::Entry>());
+        assert!(view.widget().parent().is_none());
+    });
+}
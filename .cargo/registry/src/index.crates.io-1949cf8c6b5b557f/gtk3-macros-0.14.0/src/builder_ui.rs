@@ -0,0 +1,318 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use heck::SnakeCase;
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, Ident, LitStr, Path, Token, Visibility};
+
+use std::collections::HashMap;
+
+use crate::ui_file::{parse_ui, UiFile};
+use crate::util::*;
+
+enum UiSource {
+    File(LitStr),
+    String(LitStr),
+}
+
+struct TypeOverride {
+    class: Ident,
+    path: Path,
+}
+
+impl Parse for TypeOverride {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let class = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let path = input.parse()?;
+        Ok(Self { class, path })
+    }
+}
+
+pub struct BuilderUiInput {
+    vis: Visibility,
+    name: Ident,
+    source: UiSource,
+    types: Vec<TypeOverride>,
+}
+
+impl Parse for BuilderUiInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let vis = input.parse()?;
+        let name: Ident = input.parse()?;
+        let mut source = None;
+        let mut types = Vec::new();
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            match key.to_string().as_str() {
+                "file" | "string" if source.is_some() => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "specify only one of `file` or `string`",
+                    ))
+                }
+                "file" => {
+                    input.parse::<Token![=]>()?;
+                    source = Some(UiSource::File(input.parse()?));
+                }
+                "string" => {
+                    input.parse::<Token![=]>()?;
+                    source = Some(UiSource::String(input.parse()?));
+                }
+                "types" => {
+                    let content;
+                    parenthesized!(content in input);
+                    let overrides: Punctuated<TypeOverride, Token![,]> =
+                        content.parse_terminated(TypeOverride::parse)?;
+                    types.extend(overrides);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "unknown argument, expected `file`, `string` or `types`",
+                    ))
+                }
+            }
+        }
+
+        let source = match source {
+            Some(source) => source,
+            None => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "missing `file = \"...\"` or `string = \"...\"`",
+                ))
+            }
+        };
+        Ok(Self {
+            vis,
+            name,
+            source,
+            types,
+        })
+    }
+}
+
+// Maps a GType name to the Rust type of the bindings.
+fn class_type(class: &str, types: &[TypeOverride], span: Span) -> TokenStream {
+    if let Some(o) = types.iter().find(|o| o.class == class) {
+        let path = &o.path;
+        return quote! { #path };
+    }
+
+    let crate_ident = crate_ident_new();
+    let split = |prefix: &str| {
+        class
+            .strip_prefix(prefix)
+            .filter(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()))
+            .filter(|rest| is_valid_ident(rest))
+            .map(|rest| Ident::new(rest, span))
+    };
+    if let Some(ty) = split("Gtk") {
+        quote! { #crate_ident::#ty }
+    } else if let Some(ty) = split("Gdk").filter(|ty| ty.to_string().starts_with("Pixbuf")) {
+        quote! { #crate_ident::gdk::gdk_pixbuf::#ty }
+    } else if let Some(ty) = split("Gdk") {
+        quote! { #crate_ident::gdk::#ty }
+    } else if let Some(ty) = split("G") {
+        quote! { #crate_ident::gio::#ty }
+    } else {
+        abort!(
+            span,
+            "unknown class `{}`, map it to a Rust type with `types({} = path::to::Type)`",
+            class,
+            class
+        )
+    }
+}
+
+fn is_valid_ident(name: &str) -> bool {
+    syn::parse_str::<Ident>(name).is_ok()
+}
+
+pub fn impl_builder_ui(input: BuilderUiInput) -> TokenStream {
+    let crate_ident = crate_ident_new();
+    let BuilderUiInput {
+        vis,
+        name,
+        source,
+        types,
+    } = input;
+
+    let (ui_str, source_lit, file_name) = match source {
+        UiSource::File(file) => {
+            let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_owned());
+            let path = std::path::Path::new(&dir).join(file.value());
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(err) => abort!(file, "failed to read `{}`: {}", path.display(), err),
+            };
+            let path = path.to_string_lossy().into_owned();
+            (
+                contents,
+                quote! { include_str!(#path) },
+                (file.value(), file.span()),
+            )
+        }
+        UiSource::String(string) => (
+            string.value(),
+            quote! { #string },
+            ("UI string".to_owned(), string.span()),
+        ),
+    };
+    let (file_name, span) = file_name;
+
+    let ui: UiFile = match parse_ui(&ui_str) {
+        Ok(ui) => ui,
+        Err(err) => abort!(span, "{}: {}", file_name, err),
+    };
+
+    // Typed fields for named objects.
+    let mut fields = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for object in &ui.objects {
+        let field = object.id.replace('-', "_").to_snake_case();
+        if !is_valid_ident(&field) || field == "builder" {
+            abort!(
+                span,
+                "{}:{}: id `{}` can't be used as field name `{}`",
+                file_name,
+                object.line,
+                object.id,
+                field
+            );
+        }
+        if let Some(line) = seen.insert(field.clone(), object.line) {
+            abort!(
+                span,
+                "{}:{}: id `{}` clashes with the id on line {}",
+                file_name,
+                object.line,
+                object.id,
+                line
+            );
+        }
+        let ty = class_type(&object.class, &types, span);
+        fields.push((Ident::new(&field, span), object.id.clone(), ty));
+    }
+
+    // One handler method per distinct handler name.
+    let mut handlers: Vec<(String, Vec<String>)> = Vec::new();
+    for signal in &ui.signals {
+        if !is_valid_ident(&signal.handler) {
+            abort!(
+                span,
+                "{}:{}: handler `{}` is not a valid Rust method name",
+                file_name,
+                signal.line,
+                signal.handler
+            );
+        }
+        let connection = match signal.object_id {
+            Some(ref id) => format!("`{}` of `{}`", signal.name, id),
+            None => format!("`{}`", signal.name),
+        };
+        match handlers.iter_mut().find(|(h, _)| *h == signal.handler) {
+            Some((_, connections)) => connections.push(connection),
+            None => handlers.push((signal.handler.clone(), vec![connection])),
+        }
+    }
+
+    let trait_ident = format_ident!("{}Handlers", name);
+    let field_defs = fields.iter().map(|(field, id, ty)| {
+        let doc = format!("The object with id `{}`.", id);
+        quote! {
+            #[doc = #doc]
+            pub #field: #ty,
+        }
+    });
+    let field_inits = fields.iter().map(|(field, id, ty)| {
+        let msg = format!("object `{}` is missing or has the wrong type", id);
+        quote! {
+            #field: #crate_ident::prelude::BuilderExtManual::object::<#ty>(&builder, #id)
+                .expect(#msg),
+        }
+    });
+    let handler_decls = handlers.iter().map(|(handler, connections)| {
+        let method = Ident::new(handler, span);
+        let doc = format!("Handles {}.", connections.join(", "));
+        quote! {
+            #[doc = #doc]
+            fn #method(&self, args: &[#crate_ident::glib::Value]) -> Option<#crate_ident::glib::Value>;
+        }
+    });
+    let handler_arms = handlers.iter().map(|(handler, _)| {
+        let method = Ident::new(handler, span);
+        quote! {
+            #handler => {
+                let handlers = handlers.clone();
+                Box::new(move |args| handlers.#method(args))
+            }
+        }
+    });
+
+    let trait_doc = format!("The signal handlers of [`{}`].", name);
+    let struct_doc = format!("The objects of {}, generated by `builder_ui!`.", file_name);
+
+    quote! {
+        #[doc = #struct_doc]
+        #[derive(Debug, Clone)]
+        #vis struct #name {
+            pub builder: #crate_ident::Builder,
+            #(#field_defs)*
+        }
+
+        #[doc = #trait_doc]
+        #vis trait #trait_ident {
+            #(#handler_decls)*
+        }
+
+        impl Default for #name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl #name {
+            /// The UI definition.
+            pub const UI: &'static str = #source_lit;
+
+            /// Builds the UI definition.
+            pub fn new() -> Self {
+                Self::from_builder(#crate_ident::Builder::from_string(Self::UI))
+            }
+
+            /// Looks up the objects in a builder that loaded the UI definition.
+            pub fn from_builder(builder: #crate_ident::Builder) -> Self {
+                Self {
+                    #(#field_inits)*
+                    builder,
+                }
+            }
+
+            /// Connects all signal handlers of the UI definition to `handlers`.
+            #[allow(unused_variables)]
+            pub fn connect_signals<H: #trait_ident + 'static>(&self, handlers: std::rc::Rc<H>) {
+                #crate_ident::prelude::BuilderExtManual::connect_signals(
+                    &self.builder,
+                    move |_, handler_name| -> Box<
+                        dyn Fn(&[#crate_ident::glib::Value]) -> Option<#crate_ident::glib::Value>,
+                    > {
+                        match handler_name {
+                            #(#handler_arms)*
+                            _ => Box::new(|_| None),
+                        }
+                    },
+                );
+            }
+        }
+    }
+}
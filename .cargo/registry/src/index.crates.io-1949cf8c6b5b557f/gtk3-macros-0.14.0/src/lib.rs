@@ -1,8 +1,12 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
 mod attribute_parser;
+mod builder_ui;
 mod composite_template_derive;
+mod model_row_derive;
+mod ui_file;
 mod util;
+mod view;
 
 use proc_macro::TokenStream;
 use proc_macro_error::proc_macro_error;
@@ -61,3 +65,160 @@ pub fn composite_template_derive(input: TokenStream) -> TokenStream {
     let gen = composite_template_derive::impl_composite_template(&input);
     gen.into()
 }
+
+/// Generates typed access to the objects and signal handlers of a
+/// `GtkBuilder` UI file, checked at compile time.
+///
+/// The macro takes the visibility and name of the struct to generate and
+/// either a `file` path relative to `CARGO_MANIFEST_DIR` or the UI
+/// definition as a `string`. It generates:
+///
+/// * A struct with a `builder` field and one field for every object with an
+///   id. Fields are named after the id in snake case and typed after the
+///   class, e.g. `GtkButton` becomes `gtk::Button`.
+/// * A `<Name>Handlers` trait with one method per signal handler name, which
+///   `connect_signals()` wires up through `BuilderExtManual::connect_signals`.
+///
+/// Duplicate or invalid ids, malformed XML, handler names that aren't valid
+/// method names and signals using `object` or `swapped` are compile errors,
+/// as are classes without a matching type. Classes outside the `Gtk`, `Gdk`
+/// and `G` namespaces need a mapping in `types(...)`.
+///
+/// # Example
+///
+/// ```compile_fail
+/// use gtk::prelude::*;
+/// use std::rc::Rc;
+///
+/// gtk::builder_ui!(
+///     pub MainWindowUi,
+///     file = "ui/main_window.ui",
+///     types(GtkSourceView = sourceview4::View),
+/// );
+///
+/// struct Handlers;
+///
+/// impl MainWindowUiHandlers for Handlers {
+///     fn on_ok_clicked(&self, _args: &[glib::Value]) -> Option<glib::Value> {
+///         println!("OK");
+///         None
+///     }
+/// }
+///
+/// let ui = MainWindowUi::new();
+/// ui.connect_signals(Rc::new(Handlers));
+/// ui.ok_button.set_label("Save");
+/// ui.main_window.show_all();
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn builder_ui(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as builder_ui::BuilderUiInput);
+    builder_ui::impl_builder_ui(input).into()
+}
+
+/// Derive macro for storing a struct as the rows of a `gtk::TypedListStore`
+/// or `gtk::TypedTreeStore`.
+///
+/// Every field becomes a model column of the field's type, in declaration
+/// order, so field types need to implement `glib::StaticType`, `ToValue`
+/// and `FromValue`. Columns created by `gtk::append_columns()` are titled
+/// after the field, e.g. `file_size` becomes "File size", and use a cell
+/// renderer matching the type.
+///
+/// The `model` attribute customizes a field:
+///
+/// * `title = "..."` sets the column title.
+/// * `renderer = "..."` picks the cell renderer: `text`, `toggle`, `pixbuf`
+///   or `progress`.
+/// * `hidden` stores the field without showing a column for it.
+/// * `skip` doesn't store the field; it is `Default::default()` when read
+///   back from the model.
+///
+/// # Example
+///
+/// ```compile_fail
+/// use gtk::prelude::*;
+/// use gtk::ModelRow;
+///
+/// #[derive(Debug, Clone, ModelRow)]
+/// struct Download {
+///     #[model(hidden)]
+///     id: u64,
+///     file_name: String,
+///     #[model(title = "Done", renderer = "progress")]
+///     progress: i32,
+///     paused: bool,
+/// }
+///
+/// let store = gtk::TypedListStore::<Download>::new();
+/// let iter = store.insert(&Download {
+///     id: 1,
+///     file_name: "gtk-3.24.tar.xz".to_owned(),
+///     progress: 0,
+///     paused: false,
+/// });
+/// store.update(&iter, |row| row.progress = 50);
+///
+/// let tree_view = gtk::TreeView::with_model(store.store());
+/// gtk::append_columns::<Download>(&tree_view);
+/// ```
+#[proc_macro_derive(ModelRow, attributes(model))]
+#[proc_macro_error]
+pub fn model_row_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let gen = model_row_derive::impl_model_row(&input);
+    gen.into()
+}
+
+/// Describes a widget tree as a `gtk::view::Node`.
+///
+/// Every widget is written as its type followed by braces holding, separated
+/// by commas:
+///
+/// * Properties as `name: value`, with `_` in the name standing for `-`. The
+///   value needs to have the exact type of the property, e.g. `i32` for
+///   `spacing`.
+/// * Signal handlers as `connect_signal: handler`, using the `connect_*`
+///   method of the widget type. The handler expression is evaluated when the
+///   widget is connected, moving the variables it uses.
+/// * Child widgets, which can be preceded by `#[child(name = value, ...)]` to
+///   set child properties in their parent, and by `#[key = value]` to
+///   identify them among their siblings.
+/// * `..children` to add the nodes of an iterator, e.g. from a `map()`.
+///
+/// A `gtk::view::View` builds the widgets of a node and updates them from
+/// later nodes, setting only the properties that changed.
+///
+/// # Example
+///
+/// ```compile_fail
+/// use gtk::prelude::*;
+///
+/// fn render(items: &[String]) -> gtk::view::Node {
+///     gtk::view! {
+///         gtk::Box {
+///             orientation: gtk::Orientation::Vertical,
+///             #[child(expand = false)]
+///             gtk::Entry {
+///                 placeholder_text: "Add an item",
+///                 connect_activate: |entry| println!("{}", entry.text()),
+///             },
+///             ..items.iter().map(|item| gtk::view! {
+///                 #[key = item]
+///                 gtk::Label { label: item, xalign: 0.0f32 }
+///             }),
+///         }
+///     }
+/// }
+///
+/// let view = gtk::view::View::new(render(&[]));
+/// window.add(&view.widget());
+/// view.update(render(&["Milk".to_owned()]));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn view(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as view::ViewInput);
+    view::impl_view(input).into()
+}
@@ -0,0 +1,285 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::{abort, abort_call_site};
+use quote::{quote, quote_spanned};
+use syn::parse::Error;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, Field, Fields, Lit, Meta, NestedMeta};
+
+use crate::util::*;
+
+#[derive(Default)]
+struct ModelAttrs {
+    title: Option<String>,
+    renderer: Option<(String, proc_macro2::Span)>,
+    hidden: bool,
+    skip: bool,
+}
+
+fn parse_model_attrs(attrs: &[Attribute]) -> Result<ModelAttrs, Error> {
+    let mut model = ModelAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("model")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(Error::new(
+                    meta.span(),
+                    "invalid attribute, expected a list like #[model(title = \"Name\")]",
+                ))
+            }
+        };
+        for nested in &list.nested {
+            let meta = match nested {
+                NestedMeta::Meta(meta) => meta,
+                _ => return Err(Error::new(nested.span(), "unexpected literal")),
+            };
+            let ident = match meta.path().get_ident() {
+                Some(ident) => ident.to_string(),
+                None => {
+                    return Err(Error::new(
+                        meta.path().span(),
+                        "invalid name type - expected identifier",
+                    ))
+                }
+            };
+            match (ident.as_str(), meta) {
+                ("hidden", Meta::Path(_)) => model.hidden = true,
+                ("skip", Meta::Path(_)) => model.skip = true,
+                ("title", Meta::NameValue(nv)) => match &nv.lit {
+                    Lit::Str(s) => model.title = Some(s.value()),
+                    lit => return Err(Error::new(lit.span(), "expected a string literal")),
+                },
+                ("renderer", Meta::NameValue(nv)) => match &nv.lit {
+                    Lit::Str(s) => model.renderer = Some((s.value(), s.span())),
+                    lit => return Err(Error::new(lit.span(), "expected a string literal")),
+                },
+                _ => {
+                    return Err(Error::new(
+                        meta.span(),
+                        format!(
+                            "unknown attribute argument: `{}`, expected `title = \"...\"`, \
+                            `renderer = \"...\"`, `hidden` or `skip`",
+                            ident
+                        ),
+                    ))
+                }
+            }
+        }
+    }
+    if model.skip && (model.hidden || model.title.is_some() || model.renderer.is_some()) {
+        return Err(Error::new(
+            attrs[0].span(),
+            "`skip` can't be combined with other arguments",
+        ));
+    }
+    Ok(model)
+}
+
+// "file_size" becomes "File size".
+fn default_title(field: &str) -> String {
+    let mut words = field
+        .trim_start_matches("r#")
+        .split('_')
+        .filter(|w| !w.is_empty());
+    let mut title = String::new();
+    if let Some(first) = words.next() {
+        let mut chars = first.chars();
+        if let Some(c) = chars.next() {
+            title.extend(c.to_uppercase());
+            title.push_str(chars.as_str());
+        }
+    }
+    for word in words {
+        title.push(' ');
+        title.push_str(word);
+    }
+    title
+}
+
+fn gen_cell_kind(
+    crate_ident: &proc_macro2::Ident,
+    field: &Field,
+    model: &ModelAttrs,
+) -> TokenStream {
+    if model.hidden {
+        return quote! { #crate_ident::CellKind::Hidden };
+    }
+    match &model.renderer {
+        None => quote! { #crate_ident::CellKind::Auto },
+        Some((renderer, span)) => match renderer.as_str() {
+            "text" => quote! { #crate_ident::CellKind::Text },
+            "toggle" => quote! { #crate_ident::CellKind::Toggle },
+            "pixbuf" => quote! { #crate_ident::CellKind::Pixbuf },
+            "progress" => quote! { #crate_ident::CellKind::Progress },
+            _ => abort!(
+                span,
+                "unknown renderer `{}` for field `{}`, expected one of \
+                `text`, `toggle`, `pixbuf` or `progress`",
+                renderer,
+                field.ident.as_ref().unwrap()
+            ),
+        },
+    }
+}
+
+pub fn impl_model_row(input: &syn::DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let crate_ident = crate_ident_new();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => abort_call_site!("derive(ModelRow) only supports structs with named fields"),
+        },
+        _ => abort_call_site!("derive(ModelRow) only supports structs"),
+    };
+
+    let mut columns = Vec::new();
+    let mut to_values = Vec::new();
+    let mut from_values = Vec::new();
+    let mut index = 0usize;
+    for field in fields {
+        let model = match parse_model_attrs(&field.attrs) {
+            Ok(model) => model,
+            Err(err) => abort!(err.span(), err.to_string()),
+        };
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        if model.skip {
+            from_values.push(quote_spanned! { ty.span() =>
+                #ident: ::std::default::Default::default()
+            });
+            continue;
+        }
+
+        let field_name = ident.to_string();
+        let field_name = field_name.trim_start_matches("r#");
+        let title = model
+            .title
+            .clone()
+            .unwrap_or_else(|| default_title(field_name));
+        let kind = gen_cell_kind(&crate_ident, field, &model);
+        let mismatch = format!(
+            "model column `{}` holds a value of another type",
+            field_name
+        );
+
+        columns.push(quote_spanned! { ty.span() =>
+            #crate_ident::ModelColumn {
+                field: #field_name,
+                title: #title,
+                type_: <#ty as #crate_ident::glib::StaticType>::static_type(),
+                kind: #kind,
+            }
+        });
+        to_values.push(quote! {
+            #crate_ident::glib::ToValue::to_value(&self.#ident)
+        });
+        from_values.push(quote_spanned! { ty.span() =>
+            #ident: values[#index].get::<#ty>().expect(#mismatch)
+        });
+        index += 1;
+    }
+
+    if index == 0 {
+        abort_call_site!("derive(ModelRow) needs at least one field without `skip`");
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #crate_ident::ModelRow for #name #ty_generics #where_clause {
+            fn columns() -> ::std::vec::Vec<#crate_ident::ModelColumn> {
+                vec![#(#columns),*]
+            }
+
+            fn to_values(&self) -> ::std::vec::Vec<#crate_ident::glib::Value> {
+                vec![#(#to_values),*]
+            }
+
+            fn from_values(values: &[#crate_ident::glib::Value]) -> Self {
+                Self {
+                    #(#from_values),*
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parses the attributes of the field in `field`.
+    fn attrs(field: TokenStream) -> Result<ModelAttrs, String> {
+        let input: syn::DeriveInput = syn::parse_quote! { struct Row { #field } };
+        let field = match input.data {
+            Data::Struct(data) => data.fields.into_iter().next().unwrap(),
+            _ => unreachable!(),
+        };
+        parse_model_attrs(&field.attrs).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn default_titles() {
+        assert_eq!(default_title("name"), "Name");
+        assert_eq!(default_title("file_size"), "File size");
+        assert_eq!(default_title("_private__field_"), "Private field");
+        assert_eq!(default_title("r#type"), "Type");
+        assert_eq!(default_title("état"), "État");
+    }
+
+    #[test]
+    fn model_attributes() {
+        let model = attrs(quote! { name: String }).unwrap();
+        assert!(model.title.is_none() && model.renderer.is_none());
+        assert!(!model.hidden && !model.skip);
+
+        let model = attrs(quote! {
+            #[model(title = "Progress", renderer = "progress")]
+            #[model(hidden)]
+            done: u32
+        })
+        .unwrap();
+        assert_eq!(model.title.as_deref(), Some("Progress"));
+        assert_eq!(model.renderer.map(|(r, _)| r).as_deref(), Some("progress"));
+        assert!(model.hidden && !model.skip);
+
+        let model = attrs(quote! {
+            #[doc = "Not a model attribute."]
+            #[model(skip)]
+            cache: Vec<u8>
+        })
+        .unwrap();
+        assert!(model.skip && !model.hidden);
+    }
+
+    #[test]
+    fn invalid_model_attributes() {
+        assert_eq!(
+            attrs(quote! { #[model(skip, title = "Cache")] cache: Vec<u8> })
+                .err()
+                .unwrap(),
+            "`skip` can't be combined with other arguments"
+        );
+        assert_eq!(
+            attrs(quote! { #[model(title = 1)] name: String })
+                .err()
+                .unwrap(),
+            "expected a string literal"
+        );
+        assert_eq!(
+            attrs(quote! { #[model] name: String }).err().unwrap(),
+            "invalid attribute, expected a list like #[model(title = \"Name\")]"
+        );
+        assert!(attrs(quote! { #[model(sortable)] name: String })
+            .err()
+            .unwrap()
+            .starts_with("unknown attribute argument: `sortable`"));
+        assert!(attrs(quote! { #[model(hidden = "yes")] name: String })
+            .err()
+            .unwrap()
+            .starts_with("unknown attribute argument: `hidden`"));
+    }
+}
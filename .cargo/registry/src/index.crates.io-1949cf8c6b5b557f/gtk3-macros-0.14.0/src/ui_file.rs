@@ -0,0 +1,403 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// A small reader for GtkBuilder UI files, which only extracts what
+// `builder_ui!` needs: named objects and signal handlers.
+
+use anyhow::{bail, Result};
+
+pub struct UiObject {
+    pub class: String,
+    pub id: String,
+    pub line: usize,
+}
+
+pub struct UiSignal {
+    pub name: String,
+    pub handler: String,
+    pub object_id: Option<String>,
+    pub line: usize,
+}
+
+#[derive(Default)]
+pub struct UiFile {
+    pub objects: Vec<UiObject>,
+    pub signals: Vec<UiSignal>,
+}
+
+struct Tag {
+    name: String,
+    attrs: Vec<(String, String)>,
+    self_closing: bool,
+}
+
+impl Tag {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub fn parse_ui(source: &str) -> Result<UiFile> {
+    let mut ui = UiFile::default();
+    // Open elements, with the id of the object for `object` elements.
+    let mut stack: Vec<(String, Option<String>)> = Vec::new();
+    let mut pos = 0;
+    // The line of `line_start`, counted incrementally as `pos` only moves on.
+    let mut line = 1;
+    let mut line_start = 0;
+
+    while let Some(offset) = source[pos..].find('<') {
+        let start = pos + offset;
+        line += source[line_start..start].matches('\n').count();
+        line_start = start;
+        let rest = &source[start..];
+
+        if rest.starts_with("<!--") {
+            pos = start + skip_past(rest, "-->", line)?;
+        } else if rest.starts_with("<![CDATA[") {
+            pos = start + skip_past(rest, "]]>", line)?;
+        } else if rest.starts_with("<?") {
+            pos = start + skip_past(rest, "?>", line)?;
+        } else if rest.starts_with("<!") {
+            pos = start + skip_past(rest, ">", line)?;
+        } else if let Some(rest) = rest.strip_prefix("</") {
+            let end = skip_past(rest, ">", line)?;
+            let name = rest[..end - 1].trim();
+            match stack.pop() {
+                Some((open, _)) if open == name => (),
+                Some((open, _)) => bail!(
+                    "line {}: closing tag </{}> does not match <{}>",
+                    line,
+                    name,
+                    open
+                ),
+                None => bail!("line {}: unexpected closing tag </{}>", line, name),
+            }
+            pos = start + 2 + end;
+        } else {
+            let (tag, len) = parse_tag(&rest[1..], line)?;
+            pos = start + 1 + len;
+
+            let mut object_id = None;
+            match tag.name.as_str() {
+                "object" | "menu" => {
+                    let class = match (tag.name.as_str(), tag.attr("class")) {
+                        ("menu", _) => "GMenu",
+                        (_, Some(class)) => class,
+                        (_, None) => bail!("line {}: <object> without a class", line),
+                    };
+                    if let Some(id) = tag.attr("id") {
+                        ui.objects.push(UiObject {
+                            class: class.to_owned(),
+                            id: id.to_owned(),
+                            line,
+                        });
+                        object_id = Some(id.to_owned());
+                    }
+                }
+                "template" => bail!(
+                    "line {}: templates are not supported, use derive(CompositeTemplate)",
+                    line
+                ),
+                "signal" => {
+                    let name = match tag.attr("name") {
+                        Some(name) => name,
+                        None => bail!("line {}: <signal> without a name", line),
+                    };
+                    let handler = match tag.attr("handler") {
+                        Some(handler) => handler,
+                        None => bail!("line {}: signal `{}` without a handler", line, name),
+                    };
+                    if tag.attr("object").is_some() {
+                        bail!(
+                            "line {}: signal `{}` uses `object`, which connect_signals doesn't support",
+                            line,
+                            name
+                        );
+                    }
+                    if tag
+                        .attr("swapped")
+                        .filter(|v| matches!(*v, "yes" | "true" | "1" | "True"))
+                        .is_some()
+                    {
+                        bail!(
+                            "line {}: signal `{}` is swapped, which connect_signals doesn't support",
+                            line,
+                            name
+                        );
+                    }
+                    let object_id = stack
+                        .iter()
+                        .rev()
+                        .find(|(name, _)| name == "object")
+                        .and_then(|(_, id)| id.clone());
+                    ui.signals.push(UiSignal {
+                        name: name.to_owned(),
+                        handler: handler.to_owned(),
+                        object_id,
+                        line,
+                    });
+                }
+                _ => (),
+            }
+            if !tag.self_closing {
+                stack.push((tag.name, object_id));
+            }
+        }
+    }
+
+    if let Some((open, _)) = stack.pop() {
+        bail!("element <{}> is not closed", open);
+    }
+    Ok(ui)
+}
+
+// Returns the length of `s` up to and including `end`.
+fn skip_past(s: &str, end: &str, line: usize) -> Result<usize> {
+    match s.find(end) {
+        Some(i) => Ok(i + end.len()),
+        None => bail!("line {}: missing `{}`", line, end),
+    }
+}
+
+// Parses the part of a start tag after `<`, returning the tag and its length
+// including the closing `>`.
+fn parse_tag(s: &str, line: usize) -> Result<(Tag, usize)> {
+    let name_len = s
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(s.len());
+    if name_len == 0 {
+        bail!("line {}: invalid tag", line);
+    }
+    let mut tag = Tag {
+        name: s[..name_len].to_owned(),
+        attrs: Vec::new(),
+        self_closing: false,
+    };
+
+    let mut pos = name_len;
+    loop {
+        let rest = &s[pos..];
+        let trimmed = rest.trim_start();
+        pos += rest.len() - trimmed.len();
+        if trimmed.starts_with("/>") {
+            tag.self_closing = true;
+            return Ok((tag, pos + 2));
+        } else if trimmed.starts_with('>') {
+            return Ok((tag, pos + 1));
+        } else if trimmed.is_empty() {
+            bail!("line {}: unterminated <{}> tag", line, tag.name);
+        }
+
+        let eq = match trimmed.find('=') {
+            Some(eq) => eq,
+            None => bail!("line {}: invalid attribute in <{}>", line, tag.name),
+        };
+        let name = trimmed[..eq].trim();
+        let value = trimmed[eq + 1..].trim_start();
+        let quote = match value.chars().next() {
+            Some(quote @ '"') | Some(quote @ '\'') => quote,
+            _ => bail!("line {}: unquoted value of `{}`", line, name),
+        };
+        let end = match value[1..].find(quote) {
+            Some(end) => end + 1,
+            None => bail!("line {}: unterminated value of `{}`", line, name),
+        };
+        tag.attrs
+            .push((name.to_owned(), unescape(&value[1..end], line)?));
+        pos += trimmed.len() - value.len() + end + 1;
+    }
+}
+
+fn unescape(s: &str, line: usize) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let semi = match rest[amp..].find(';') {
+            Some(semi) => amp + semi,
+            None => bail!("line {}: unterminated entity", line),
+        };
+        let entity = &rest[amp + 1..semi];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                match code.and_then(std::char::from_u32) {
+                    Some(c) => c,
+                    None => bail!("line {}: unknown entity `&{};`", line, entity),
+                }
+            }
+        };
+        out.push(c);
+        rest = &rest[semi + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> String {
+        match parse_ui(source) {
+            Ok(_) => panic!("parsing succeeded"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn objects_and_signals() {
+        let ui = parse_ui(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<interface>
+  <object class="GtkWindow" id="window">
+    <child>
+      <object class="GtkButton" id="ok_button">
+        <signal name="clicked" handler="on_ok_clicked" swapped="no"/>
+      </object>
+    </child>
+    <signal name="destroy" handler="on_destroy"/>
+  </object>
+  <object class="GtkSizeGroup"/>
+  <menu id="app_menu"/>
+</interface>"#,
+        )
+        .unwrap();
+
+        let objects: Vec<_> = ui
+            .objects
+            .iter()
+            .map(|o| (o.class.as_str(), o.id.as_str(), o.line))
+            .collect();
+        assert_eq!(
+            objects,
+            [
+                ("GtkWindow", "window", 3),
+                ("GtkButton", "ok_button", 5),
+                ("GMenu", "app_menu", 12),
+            ]
+        );
+
+        let signals: Vec<_> = ui
+            .signals
+            .iter()
+            .map(|s| (s.name.as_str(), s.handler.as_str(), s.object_id.as_deref()))
+            .collect();
+        assert_eq!(
+            signals,
+            [
+                ("clicked", "on_ok_clicked", Some("ok_button")),
+                ("destroy", "on_destroy", Some("window")),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_and_cdata() {
+        let ui = parse_ui(
+            r#"<interface>
+  <!-- <object class="GtkLabel" id="commented"> -->
+  <object class="GtkLabel" id="label">
+    <property name="label"><![CDATA[<b>bold</b> </object>]]></property>
+  </object>
+</interface>"#,
+        )
+        .unwrap();
+        assert_eq!(ui.objects.len(), 1);
+        assert_eq!(ui.objects[0].id, "label");
+
+        assert_eq!(error("<interface>\n<!-- open"), "line 2: missing `-->`");
+        assert_eq!(error("<interface><![CDATA[ open"), "line 1: missing `]]>`");
+    }
+
+    #[test]
+    fn entities() {
+        let ui =
+            parse_ui(r#"<object class="GtkLabel" id="a&amp;b&lt;&gt;&quot;&apos;&#65;&#x42;"/>"#)
+                .unwrap();
+        assert_eq!(ui.objects[0].id, "a&b<>\"'AB");
+
+        assert_eq!(
+            error(r#"<object class="GtkLabel" id="&nbsp;"/>"#),
+            "line 1: unknown entity `&nbsp;`"
+        );
+        assert_eq!(
+            error(r#"<object class="GtkLabel" id="&#xd800;"/>"#),
+            "line 1: unknown entity `&#xd800;`"
+        );
+        assert_eq!(
+            error(r#"<object class="GtkLabel" id="&amp"/>"#),
+            "line 1: unterminated entity"
+        );
+    }
+
+    #[test]
+    fn mismatched_tags() {
+        assert_eq!(
+            error("<interface>\n  <object class=\"GtkBox\">\n</interface>"),
+            "line 3: closing tag </interface> does not match <object>"
+        );
+        assert_eq!(
+            error("<interface/>\n</interface>"),
+            "line 2: unexpected closing tag </interface>"
+        );
+        assert_eq!(
+            error("<interface>\n  <object class=\"GtkBox\"/>"),
+            "element <interface> is not closed"
+        );
+        assert_eq!(
+            error("<interface>\n  <object id=\"box\"/>\n</interface>"),
+            "line 2: <object> without a class"
+        );
+        assert_eq!(
+            error("<template class=\"MyWidget\" parent=\"GtkBox\"/>"),
+            "line 1: templates are not supported, use derive(CompositeTemplate)"
+        );
+    }
+
+    #[test]
+    fn unsupported_signals() {
+        assert_eq!(
+            error(
+                r#"<object class="GtkButton" id="button">
+  <signal name="clicked" handler="on_clicked" object="label"/>
+</object>"#
+            ),
+            "line 2: signal `clicked` uses `object`, which connect_signals doesn't support"
+        );
+        for swapped in &["yes", "true", "1", "True"] {
+            let source = format!(
+                r#"<object class="GtkButton" id="button">
+  <signal name="clicked" handler="on_clicked" swapped="{}"/>
+</object>"#,
+                swapped
+            );
+            assert_eq!(
+                error(&source),
+                "line 2: signal `clicked` is swapped, which connect_signals doesn't support"
+            );
+        }
+        assert_eq!(
+            error(r#"<signal handler="on_clicked"/>"#),
+            "line 1: <signal> without a name"
+        );
+        assert_eq!(
+            error(r#"<signal name="clicked"/>"#),
+            "line 1: signal `clicked` without a handler"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{braced, Attribute, Expr, Path, Token};
+
+use crate::util::*;
+
+struct ChildProperty {
+    name: Ident,
+    value: Expr,
+}
+
+impl Parse for ChildProperty {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(Self { name, value })
+    }
+}
+
+enum Item {
+    Property { name: Ident, value: Expr },
+    Element(Element),
+    Splice(Expr),
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![..]) {
+            input.parse::<Token![..]>()?;
+            Ok(Item::Splice(input.parse()?))
+        } else if input.peek(syn::Ident) && input.peek2(Token![:]) && !input.peek2(Token![::]) {
+            let name = input.parse()?;
+            input.parse::<Token![:]>()?;
+            let value = input.parse()?;
+            Ok(Item::Property { name, value })
+        } else {
+            Ok(Item::Element(input.parse()?))
+        }
+    }
+}
+
+pub struct Element {
+    child_properties: Vec<ChildProperty>,
+    key: Option<Expr>,
+    path: Path,
+    items: Punctuated<Item, Token![,]>,
+}
+
+impl Parse for Element {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut child_properties = Vec::new();
+        let mut key = None;
+        for attr in input.call(Attribute::parse_outer)? {
+            if attr.path.is_ident("child") {
+                let properties =
+                    attr.parse_args_with(Punctuated::<ChildProperty, Token![,]>::parse_terminated)?;
+                child_properties.extend(properties);
+            } else if attr.path.is_ident("key") {
+                if key.is_some() {
+                    return Err(syn::Error::new(attr.span(), "duplicate `key` attribute"));
+                }
+                let parser = |input: ParseStream| {
+                    input.parse::<Token![=]>()?;
+                    input.parse::<Expr>()
+                };
+                key = Some(parser.parse2(attr.tokens.clone())?);
+            } else {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "unknown attribute, expected `#[child(name = value, ...)]` or `#[key = value]`",
+                ));
+            }
+        }
+        let path = input.parse()?;
+        let content;
+        braced!(content in input);
+        let items = content.parse_terminated(Item::parse)?;
+        Ok(Self {
+            child_properties,
+            key,
+            path,
+            items,
+        })
+    }
+}
+
+pub struct ViewInput(Element);
+
+impl Parse for ViewInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let element = input.parse()?;
+        if !input.is_empty() {
+            return Err(input.error("expected a single root widget"));
+        }
+        Ok(Self(element))
+    }
+}
+
+// `wrap_mode` becomes `wrap-mode`.
+fn property_name(ident: &Ident) -> String {
+    ident.to_string().trim_start_matches("r#").replace('_', "-")
+}
+
+fn gen_element(crate_ident: &Ident, element: &Element) -> TokenStream {
+    let path = &element.path;
+    let mut calls = Vec::new();
+
+    for item in &element.items {
+        match item {
+            Item::Property { name, value } if name.to_string().starts_with("connect_") => {
+                calls.push(quote_spanned! { name.span() =>
+                    .signal(move |object: &#crate_ident::glib::Object| {
+                        use #crate_ident::prelude::*;
+                        let widget = #crate_ident::glib::Cast::downcast_ref::<#path>(object)
+                            .expect("view! signal connected on a widget of another type");
+                        widget.#name(#value)
+                    })
+                });
+            }
+            Item::Property { name, value } => {
+                let name = property_name(name);
+                calls.push(quote_spanned! { value.span() =>
+                    .property(#name, #crate_ident::glib::ToValue::to_value(&(#value)))
+                });
+            }
+            Item::Element(child) => {
+                let child = gen_element(crate_ident, child);
+                calls.push(quote! { .child(#child) });
+            }
+            Item::Splice(children) => {
+                calls.push(quote_spanned! { children.span() =>
+                    .children(#children)
+                });
+            }
+        }
+    }
+    for ChildProperty { name, value } in &element.child_properties {
+        let name = property_name(name);
+        calls.push(quote_spanned! { value.span() =>
+            .child_property(#name, #crate_ident::glib::ToValue::to_value(&(#value)))
+        });
+    }
+    if let Some(key) = &element.key {
+        calls.push(quote! { .key(#key) });
+    }
+
+    quote! {
+        #crate_ident::view::Node::new::<#path>()
+            #(#calls)*
+    }
+}
+
+pub fn impl_view(input: ViewInput) -> TokenStream {
+    let crate_ident = crate_ident_new();
+    gen_element(&crate_ident, &input.0)
+}
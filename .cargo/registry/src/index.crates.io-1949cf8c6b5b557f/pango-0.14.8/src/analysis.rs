@@ -16,6 +16,10 @@ impl Analysis {
         unsafe { from_glib_none(self.0.font) }
     }
 
+    pub(crate) fn has_font(&self) -> bool {
+        !self.0.font.is_null()
+    }
+
     pub fn level(&self) -> u8 {
         self.0.level
     }
@@ -246,6 +246,14 @@ impl Attribute {
     }
 }
 
+/// An attribute type that can be viewed as a generic [`Attribute`].
+///
+/// # Safety
+///
+/// Implementors must have the same layout as [`Attribute`], wrapping a pointer
+/// to a struct whose first member is a `PangoAttribute`, and `ATTR_TYPES` must
+/// only list attribute types whose instances are such structs, as
+/// [`Attribute::downcast_ref()`] casts references based on them.
 pub unsafe trait IsAttribute:
     FromGlibPtrFull<*const ffi::PangoAttribute>
     + FromGlibPtrFull<*mut ffi::PangoAttribute>
@@ -373,7 +381,6 @@ define_attribute_struct!(
         AttrType::AllowBreaks,
         AttrType::BackgroundAlpha,
         AttrType::Fallback,
-        AttrType::FontFeatures,
         AttrType::ForegroundAlpha,
         AttrType::Gravity,
         AttrType::GravityHint,
@@ -421,3 +428,23 @@ impl AttrLanguage {
         unsafe { from_glib_none(self.0.value) }
     }
 }
+
+define_attribute_struct!(AttrString, ffi::PangoAttrString, &[AttrType::Family]);
+
+impl AttrString {
+    pub fn value(&self) -> glib::GString {
+        unsafe { from_glib_none(self.0.value) }
+    }
+}
+
+define_attribute_struct!(
+    AttrFontFeatures,
+    ffi::PangoAttrFontFeatures,
+    &[AttrType::FontFeatures]
+);
+
+impl AttrFontFeatures {
+    pub fn features(&self) -> glib::GString {
+        unsafe { from_glib_none(self.0.features) }
+    }
+}
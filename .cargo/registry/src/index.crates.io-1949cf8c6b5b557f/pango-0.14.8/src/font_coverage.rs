@@ -0,0 +1,225 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::prelude::*;
+use crate::{AttrList, Attribute, Context, CoverageLevel, Font, FontDescription};
+use std::fmt;
+use std::ops::Range;
+
+/// A character that no font could display, see [`CoverageReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MissingGlyph {
+    /// The byte index in the text.
+    pub index: usize,
+    pub character: char,
+}
+
+/// A run of text that was resolved to a single font.
+#[derive(Debug, Clone)]
+pub struct FontRun {
+    range: Range<usize>,
+    font: Option<Font>,
+    missing: Vec<MissingGlyph>,
+}
+
+impl FontRun {
+    /// The byte range of the run in the text.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// The font the run resolved to, `None` if no font was available.
+    pub fn font(&self) -> Option<&Font> {
+        self.font.as_ref()
+    }
+
+    pub fn font_description(&self) -> Option<FontDescription> {
+        self.font.as_ref().and_then(|font| font.describe())
+    }
+
+    /// The characters of the run that the font has no glyphs for.
+    pub fn missing(&self) -> &[MissingGlyph] {
+        &self.missing
+    }
+}
+
+/// The fonts that the characters of a text resolve to after fallback, see
+/// [`Context::coverage_report()`].
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    runs: Vec<FontRun>,
+}
+
+impl CoverageReport {
+    pub fn runs(&self) -> &[FontRun] {
+        &self.runs
+    }
+
+    /// All characters that would be drawn as missing glyph boxes.
+    pub fn missing(&self) -> Vec<MissingGlyph> {
+        self.runs
+            .iter()
+            .flat_map(|run| run.missing.iter().copied())
+            .collect()
+    }
+
+    /// Whether all characters are covered by the resolved fonts.
+    pub fn is_complete(&self) -> bool {
+        self.runs.iter().all(|run| run.missing.is_empty())
+    }
+
+    /// The family names of the resolved fonts, without duplicates.
+    pub fn families(&self) -> Vec<glib::GString> {
+        let mut families = Vec::new();
+        for run in &self.runs {
+            if let Some(family) = run.font_description().and_then(|desc| desc.family()) {
+                if !families.contains(&family) {
+                    families.push(family);
+                }
+            }
+        }
+        families
+    }
+}
+
+/// Lists the missing characters, one per line.
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for run in &self.runs {
+            let font = run
+                .font_description()
+                .map_or_else(|| String::from("no font"), |desc| desc.to_string());
+            for glyph in &run.missing {
+                writeln!(
+                    f,
+                    "U+{:04X} {:?} at byte {} is not covered by {}",
+                    glyph.character as u32, glyph.character, glyph.index, font
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Context {
+    /// Reports which font each run of `text` resolves to when laid out with
+    /// `desc`, and which characters none of the fonts can display.
+    ///
+    /// Whitespace, control and zero width characters are never reported as
+    /// missing. This can be used to check that icon fonts are installed
+    /// before their code points show up as boxes.
+    ///
+    /// ```no_run
+    /// # fn check(context: &pango::Context) {
+    /// let desc = pango::FontDescription::from_string("Symbols Nerd Font 12");
+    /// let report = context.coverage_report("\u{f0e7} 42%", &desc);
+    /// if !report.is_complete() {
+    ///     eprint!("{}", report);
+    /// }
+    /// # }
+    /// ```
+    pub fn coverage_report(&self, text: &str, desc: &FontDescription) -> CoverageReport {
+        let attrs = AttrList::new();
+        attrs.insert(Attribute::new_font_desc(desc));
+
+        let items = crate::itemize(self, text, 0, text.len() as i32, &attrs, None);
+        let runs = items
+            .iter()
+            .map(|item| {
+                let start = item.offset() as usize;
+                let range = start..start + item.length() as usize;
+                let analysis = item.analysis();
+                let font = if analysis.has_font() {
+                    Some(analysis.font())
+                } else {
+                    None
+                };
+                let coverage = font
+                    .as_ref()
+                    .and_then(|font| font.coverage(&analysis.language()));
+
+                let missing = text[range.clone()]
+                    .char_indices()
+                    .filter(|&(_, c)| {
+                        !(c.is_whitespace() || c.is_control() || crate::is_zero_width(c))
+                    })
+                    .filter(|&(_, c)| {
+                        coverage.as_ref().map_or(true, |coverage| {
+                            coverage.get(c as i32) == CoverageLevel::None
+                        })
+                    })
+                    .map(|(index, character)| MissingGlyph {
+                        index: start + index,
+                        character,
+                    })
+                    .collect();
+
+                FontRun {
+                    range,
+                    font,
+                    missing,
+                }
+            })
+            .collect();
+
+        CoverageReport { runs }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc() -> FontDescription {
+        FontDescription::from_string("Sans 12")
+    }
+
+    #[test]
+    fn items_have_fonts() {
+        let context = crate::test_util::context();
+        let attrs = AttrList::new();
+        attrs.insert(Attribute::new_font_desc(&desc()));
+        let items = crate::itemize(&context, "abc αβγ", 0, 10, &attrs, None);
+        assert!(!items.is_empty());
+        assert!(items.iter().all(|item| item.analysis().has_font()));
+    }
+
+    #[test]
+    fn complete_coverage() {
+        let context = crate::test_util::context();
+        // Printable ASCII, which any fallback font covers.
+        let mut text = (' '..='~').collect::<String>();
+        text.push_str("\t\u{200b}");
+        let report = context.coverage_report(&text, &desc());
+        assert!(report.is_complete());
+        assert!(report.missing().is_empty());
+        assert_eq!(report.to_string(), "");
+        assert!(!report.families().is_empty());
+
+        let runs = report.runs();
+        assert_eq!(runs[0].range().start, 0);
+        assert_eq!(runs.last().unwrap().range().end, text.len());
+        assert!(runs.iter().all(|run| run.font().is_some()));
+    }
+
+    #[test]
+    fn missing_glyphs() {
+        let context = crate::test_util::context();
+        // A noncharacter that no font covers.
+        let report = context.coverage_report("ok \u{10ffff}", &desc());
+        assert!(!report.is_complete());
+        let missing = MissingGlyph {
+            index: 3,
+            character: '\u{10ffff}',
+        };
+        assert_eq!(report.missing(), [missing]);
+        let run = report
+            .runs()
+            .iter()
+            .find(|run| run.range().contains(&3))
+            .unwrap();
+        assert_eq!(run.missing(), [missing]);
+        assert!(report
+            .to_string()
+            .starts_with("U+10FFFF '\\u{10ffff}' at byte 3 is not covered by "));
+    }
+}
@@ -0,0 +1,608 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Reuse of shaped layouts for text that is drawn repeatedly.
+
+use crate::{
+    AttrColor, AttrFloat, AttrFontDesc, AttrFontFeatures, AttrInt, AttrLanguage, AttrList,
+    AttrString, Attribute, Context, FontDescription, Layout, LayoutIter, LayoutLine, Rectangle,
+};
+use glib::translate::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+/// A laid out text with its measurements, see [`LayoutCache`].
+#[derive(Debug)]
+pub struct CachedLayout {
+    layout: Layout,
+    pixel_size: (i32, i32),
+    ink_rect: Rectangle,
+    logical_rect: Rectangle,
+    baseline: i32,
+}
+
+impl CachedLayout {
+    /// The layout, including its shaped glyph strings.
+    pub fn layout(&self) -> ReadOnlyLayout<'_> {
+        ReadOnlyLayout(&self.layout)
+    }
+
+    pub fn pixel_size(&self) -> (i32, i32) {
+        self.pixel_size
+    }
+
+    /// The ink and logical extents in pixels.
+    pub fn pixel_extents(&self) -> (Rectangle, Rectangle) {
+        (self.ink_rect, self.logical_rect)
+    }
+
+    /// The baseline of the first line in Pango units.
+    pub fn baseline(&self) -> i32 {
+        self.baseline
+    }
+}
+
+/// Read access to a layout shared with a [`LayoutCache`].
+///
+/// Setters are not available because the layout is reused for later lookups.
+/// Draw it line by line, e.g. with `pangocairo::show_layout_line()` at the
+/// positions given by [`iter()`](Self::iter), or take a [`copy()`](Self::copy)
+/// to modify it.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOnlyLayout<'a>(&'a Layout);
+
+impl<'a> ReadOnlyLayout<'a> {
+    /// A modifiable copy, which has to be shaped again when it is drawn.
+    pub fn copy(&self) -> Option<Layout> {
+        self.0.copy()
+    }
+
+    pub fn text(&self) -> Option<glib::GString> {
+        self.0.text()
+    }
+
+    pub fn attributes(&self) -> Option<AttrList> {
+        self.0.attributes()
+    }
+
+    pub fn font_description(&self) -> Option<FontDescription> {
+        self.0.font_description()
+    }
+
+    pub fn width(&self) -> i32 {
+        self.0.width()
+    }
+
+    pub fn baseline(&self) -> i32 {
+        self.0.baseline()
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        self.0.size()
+    }
+
+    pub fn pixel_size(&self) -> (i32, i32) {
+        self.0.pixel_size()
+    }
+
+    pub fn extents(&self) -> (Rectangle, Rectangle) {
+        self.0.extents()
+    }
+
+    pub fn pixel_extents(&self) -> (Rectangle, Rectangle) {
+        self.0.pixel_extents()
+    }
+
+    pub fn is_ellipsized(&self) -> bool {
+        self.0.is_ellipsized()
+    }
+
+    pub fn is_wrapped(&self) -> bool {
+        self.0.is_wrapped()
+    }
+
+    pub fn unknown_glyphs_count(&self) -> i32 {
+        self.0.unknown_glyphs_count()
+    }
+
+    pub fn line_count(&self) -> i32 {
+        self.0.line_count()
+    }
+
+    pub fn line(&self, line: i32) -> Option<LayoutLine> {
+        self.0.line_readonly(line)
+    }
+
+    pub fn lines(&self) -> Vec<LayoutLine> {
+        self.0.lines_readonly()
+    }
+
+    pub fn iter(&self) -> Option<LayoutIter> {
+        self.0.iter()
+    }
+
+    pub fn index_to_pos(&self, index: i32) -> Rectangle {
+        self.0.index_to_pos(index)
+    }
+
+    pub fn xy_to_index(&self, x: i32, y: i32) -> (bool, i32, i32) {
+        self.0.xy_to_index(x, y)
+    }
+
+    pub fn cursor_pos(&self, index: i32) -> (Rectangle, Rectangle) {
+        self.0.cursor_pos(index)
+    }
+}
+
+/// Hit and miss counters of a [`LayoutCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayoutCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl LayoutCacheStats {
+    /// The fraction of lookups that were hits, 0 without lookups.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Key {
+    text: String,
+    font: FontDescription,
+    width: Option<i32>,
+    // Compared with `pango_attribute_equal()` so that attributes without
+    // accessors are told apart too.
+    attrs: Option<Vec<Attribute>>,
+}
+
+impl Key {
+    fn new(
+        text: &str,
+        font: &FontDescription,
+        width: Option<i32>,
+        attrs: Option<&AttrList>,
+    ) -> Self {
+        Self {
+            text: text.to_owned(),
+            font: font.clone(),
+            width,
+            attrs: attrs.map(|list| {
+                let mut attrs = Vec::new();
+                list.filter(|attr| {
+                    attrs.push(attr.clone());
+                    false
+                });
+                attrs
+            }),
+        }
+    }
+
+    // Compares the key with borrowed parts without copying them.
+    fn matches(
+        &self,
+        text: &str,
+        font: &FontDescription,
+        width: Option<i32>,
+        attrs: Option<&AttrList>,
+    ) -> bool {
+        if self.text != text || self.width != width || self.font != *font {
+            return false;
+        }
+        match (&self.attrs, attrs) {
+            (None, None) => true,
+            (Some(own), Some(list)) => {
+                let mut index = 0;
+                let mut equal = true;
+                list.filter(|attr| {
+                    equal = equal && own.get(index).map_or(false, |own| attr_equal(own, attr));
+                    index += 1;
+                    false
+                });
+                equal && index == own.len()
+            }
+            _ => false,
+        }
+    }
+}
+
+fn attr_equal(a: &Attribute, b: &Attribute) -> bool {
+    a.start_index() == b.start_index()
+        && a.end_index() == b.end_index()
+        && unsafe {
+            from_glib(ffi::pango_attribute_equal(
+                a.to_glib_none().0,
+                b.to_glib_none().0,
+            ))
+        }
+}
+
+fn key_hash(
+    text: &str,
+    font: &FontDescription,
+    width: Option<i32>,
+    attrs: Option<&AttrList>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (text, font, width).hash(&mut hasher);
+    if let Some(list) = attrs {
+        list.filter(|attr| {
+            hash_attribute(attr, &mut hasher);
+            false
+        });
+    }
+    hasher.finish()
+}
+
+#[derive(Debug)]
+struct Slot {
+    key: Key,
+    layout: CachedLayout,
+    last_used: u64,
+}
+
+/// A least recently used cache of laid out texts.
+///
+/// Entries are keyed by the text, font description, wrap width and
+/// attributes. Text such as a clock or workspace names that is redrawn every
+/// frame is then only shaped and measured when it changes. Lookups that hit
+/// don't copy any of the key. All entries are dropped when the [`Context`]
+/// changes, e.g. because of a new font map or resolution.
+///
+/// ```
+/// fn clock_width(cache: &mut pango::LayoutCache, time: &str) -> i32 {
+///     let font = pango::FontDescription::from_string("Sans 10");
+///     cache.get(time, &font, None, None).layout().pixel_size().0
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LayoutCache {
+    context: Context,
+    capacity: usize,
+    // Slots by the hash of their key; keys with the same hash share a bucket.
+    entries: HashMap<u64, Vec<Slot>>,
+    len: usize,
+    // Key hashes by the clock value of their last use, oldest first.
+    lru: BTreeMap<u64, u64>,
+    serial: u32,
+    clock: u64,
+    stats: LayoutCacheStats,
+}
+
+impl LayoutCache {
+    /// Creates a cache for layouts in `context` holding at most `capacity`
+    /// entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(context: &Context, capacity: usize) -> Self {
+        assert!(capacity > 0, "LayoutCache capacity must not be zero");
+        Self {
+            context: context.clone(),
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            len: 0,
+            lru: BTreeMap::new(),
+            serial: context.serial(),
+            clock: 0,
+            stats: LayoutCacheStats::default(),
+        }
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn stats(&self) -> LayoutCacheStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = LayoutCacheStats::default();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.len = 0;
+    }
+
+    /// Returns the layout of `text`, creating it on a miss.
+    ///
+    /// `width` is the wrap width in Pango units. The attributes are copied
+    /// into new layouts, so later changes of `attrs` don't affect them.
+    pub fn get(
+        &mut self,
+        text: &str,
+        font: &FontDescription,
+        width: Option<i32>,
+        attrs: Option<&AttrList>,
+    ) -> &CachedLayout {
+        let serial = self.context.serial();
+        if serial != self.serial {
+            self.clear();
+            self.serial = serial;
+        }
+        self.clock += 1;
+
+        let hash = key_hash(text, font, width, attrs);
+        let found = self.entries.get(&hash).and_then(|bucket| {
+            bucket
+                .iter()
+                .position(|slot| slot.key.matches(text, font, width, attrs))
+        });
+        let index = match found {
+            Some(index) => {
+                self.stats.hits += 1;
+                let slot = &mut self.entries.get_mut(&hash).unwrap()[index];
+                self.lru.remove(&slot.last_used);
+                slot.last_used = self.clock;
+                index
+            }
+            None => {
+                self.stats.misses += 1;
+                if self.len >= self.capacity {
+                    self.evict();
+                }
+                let slot = Slot {
+                    key: Key::new(text, font, width, attrs),
+                    layout: self.create(text, font, width, attrs),
+                    last_used: self.clock,
+                };
+                let bucket = self.entries.entry(hash).or_default();
+                bucket.push(slot);
+                self.len += 1;
+                bucket.len() - 1
+            }
+        };
+        self.lru.insert(self.clock, hash);
+
+        &self.entries[&hash][index].layout
+    }
+
+    /// Returns the size of `text` in pixels without wrapping or attributes.
+    pub fn pixel_size(&mut self, text: &str, font: &FontDescription) -> (i32, i32) {
+        self.get(text, font, None, None).pixel_size()
+    }
+
+    fn evict(&mut self) {
+        let oldest = self.lru.keys().next().copied();
+        let (tick, hash) = match oldest.and_then(|tick| Some((tick, self.lru.remove(&tick)?))) {
+            Some(oldest) => oldest,
+            None => return,
+        };
+        if let Some(bucket) = self.entries.get_mut(&hash) {
+            bucket.retain(|slot| slot.last_used != tick);
+            if bucket.is_empty() {
+                self.entries.remove(&hash);
+            }
+            self.len -= 1;
+            self.stats.evictions += 1;
+        }
+    }
+
+    fn create(
+        &self,
+        text: &str,
+        font: &FontDescription,
+        width: Option<i32>,
+        attrs: Option<&AttrList>,
+    ) -> CachedLayout {
+        let layout = Layout::new(&self.context);
+        layout.set_font_description(Some(font));
+        if let Some(width) = width {
+            layout.set_width(width);
+        }
+        if let Some(attrs) = attrs {
+            layout.set_attributes(attrs.copy().as_ref());
+        }
+        layout.set_text(text);
+
+        // Measuring shapes the text, which is what the cache saves later.
+        let (ink_rect, logical_rect) = layout.pixel_extents();
+        CachedLayout {
+            pixel_size: layout.pixel_size(),
+            ink_rect,
+            logical_rect,
+            baseline: layout.baseline(),
+            layout,
+        }
+    }
+}
+
+// Attributes of types without accessors only contribute their type and
+// range, `Attrs` compares them in full.
+fn hash_attribute(attr: &Attribute, hasher: &mut DefaultHasher) {
+    (attr.type_(), attr.start_index(), attr.end_index()).hash(hasher);
+    if let Some(attr) = attr.downcast_ref::<AttrInt>() {
+        attr.value().hash(hasher);
+    } else if let Some(attr) = attr.downcast_ref::<AttrFloat>() {
+        attr.value().to_bits().hash(hasher);
+    } else if let Some(attr) = attr.downcast_ref::<AttrColor>() {
+        let color = attr.color();
+        (color.red(), color.green(), color.blue()).hash(hasher);
+    } else if let Some(attr) = attr.downcast_ref::<AttrString>() {
+        attr.value().hash(hasher);
+    } else if let Some(attr) = attr.downcast_ref::<AttrFontFeatures>() {
+        attr.features().hash(hasher);
+    } else if let Some(attr) = attr.downcast_ref::<AttrFontDesc>() {
+        attr.value().hash(hasher);
+    } else if let Some(attr) = attr.downcast_ref::<AttrLanguage>() {
+        attr.value().to_string().hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Weight, SCALE};
+
+    fn font() -> FontDescription {
+        FontDescription::from_string("Sans 10")
+    }
+
+    #[test]
+    fn lru_eviction() {
+        let mut cache = LayoutCache::new(&crate::test_util::context(), 2);
+        let font = font();
+        cache.get("a", &font, None, None);
+        cache.get("b", &font, None, None);
+        cache.get("a", &font, None, None);
+        // "b" is the least recently used entry now.
+        cache.get("c", &font, None, None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().evictions, 1);
+
+        cache.reset_stats();
+        cache.get("a", &font, None, None);
+        cache.get("c", &font, None, None);
+        assert_eq!(cache.stats().hits, 2);
+        cache.get("b", &font, None, None);
+        assert_eq!(
+            cache.stats(),
+            LayoutCacheStats {
+                hits: 2,
+                misses: 1,
+                evictions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let mut cache = LayoutCache::new(&crate::test_util::context(), 8);
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+        let font = font();
+
+        let size = cache.pixel_size("Hello", &font);
+        assert!(size.0 > 0 && size.1 > 0);
+        let layout = cache.get("Hello", &font, None, None).layout();
+        assert_eq!(layout.text().unwrap(), "Hello");
+        assert_eq!(layout.pixel_size(), size);
+        assert_eq!(layout.lines().len(), 1);
+        cache.get("Hello", &font, Some(20 * SCALE), None);
+        cache.get(
+            "Hello",
+            &FontDescription::from_string("Sans 20"),
+            None,
+            None,
+        );
+        assert_eq!(cache.len(), 3);
+        assert_eq!(
+            cache.stats(),
+            LayoutCacheStats {
+                hits: 1,
+                misses: 3,
+                evictions: 0,
+            }
+        );
+        assert_eq!(cache.stats().hit_rate(), 0.25);
+
+        cache.reset_stats();
+        assert_eq!(cache.stats(), LayoutCacheStats::default());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn attributes() {
+        let mut cache = LayoutCache::new(&crate::test_util::context(), 8);
+        let font = font();
+        let list = |attr: Attribute| {
+            let list = AttrList::new();
+            list.insert(attr);
+            list
+        };
+        let shape = |width| {
+            let rect = Rectangle::new(0, 0, width, 10 * SCALE);
+            list(Attribute::new_shape(&rect, &rect))
+        };
+
+        cache.get(
+            "text",
+            &font,
+            None,
+            Some(&list(Attribute::new_weight(Weight::Bold))),
+        );
+        cache.get(
+            "text",
+            &font,
+            None,
+            Some(&list(Attribute::new_weight(Weight::Bold))),
+        );
+        cache.get(
+            "text",
+            &font,
+            None,
+            Some(&list(Attribute::new_weight(Weight::Light))),
+        );
+        let both = list(Attribute::new_weight(Weight::Bold));
+        both.insert(Attribute::new_weight(Weight::Light));
+        cache.get("text", &font, None, Some(&both));
+        cache.get("text", &font, None, Some(&both));
+        cache.get("text", &font, None, None);
+        assert_eq!(cache.stats().hits, 2);
+        assert_eq!(cache.stats().misses, 4);
+
+        // Shape attributes have no accessors and are compared in full.
+        cache.reset_stats();
+        let width = cache
+            .get("text", &font, None, Some(&shape(10 * SCALE)))
+            .pixel_size()
+            .0;
+        assert_eq!(
+            cache
+                .get("text", &font, None, Some(&shape(10 * SCALE)))
+                .pixel_size()
+                .0,
+            width
+        );
+        assert!(
+            cache
+                .get("text", &font, None, Some(&shape(30 * SCALE)))
+                .pixel_size()
+                .0
+                > width
+        );
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().misses, 2);
+    }
+
+    #[test]
+    fn context_changes() {
+        let context = crate::test_util::context();
+        let mut cache = LayoutCache::new(&context, 8);
+        let font = font();
+        cache.get("a", &font, None, None);
+        cache.get("b", &font, None, None);
+
+        context.changed();
+        cache.get("a", &font, None, None);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.stats().hits, 0);
+        assert_eq!(cache.stats().misses, 3);
+        cache.get("a", &font, None, None);
+        assert_eq!(cache.stats().hits, 1);
+    }
+}
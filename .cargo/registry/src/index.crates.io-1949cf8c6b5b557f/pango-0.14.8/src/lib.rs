@@ -55,19 +55,29 @@ pub mod attr_iterator;
 pub mod attr_list;
 pub mod attribute;
 pub use crate::attribute::{
-    AttrColor, AttrFloat, AttrFontDesc, AttrInt, AttrLanguage, IsAttribute,
+    AttrColor, AttrFloat, AttrFontDesc, AttrFontFeatures, AttrInt, AttrLanguage, AttrString,
+    IsAttribute,
 };
 pub mod color;
+mod font_coverage;
+pub use crate::font_coverage::{CoverageReport, FontRun, MissingGlyph};
 mod functions;
 pub mod item;
 pub mod language;
 pub use crate::language::Language;
 pub mod layout;
 pub use crate::layout::HitPosition;
+pub mod layout_cache;
+pub use crate::layout_cache::{CachedLayout, LayoutCache, LayoutCacheStats, ReadOnlyLayout};
 pub mod rectangle;
 pub use crate::rectangle::Rectangle;
+pub mod rich_text;
+pub use crate::rich_text::{attr_list_to_markup, RichText, TextStyle};
 pub mod glyph;
 pub use glyph::{GlyphGeometry, GlyphInfo};
 
 mod coverage;
 pub use crate::coverage::*;
+
+#[cfg(test)]
+mod test_util;
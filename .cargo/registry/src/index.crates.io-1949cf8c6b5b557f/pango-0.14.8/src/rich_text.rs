@@ -0,0 +1,442 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Styled text without markup strings.
+//!
+//! [`RichText`] collects text segments with a [`TextStyle`] each and turns
+//! them into an [`AttrList`] with the right byte indices, which avoids
+//! escaping user provided text for Pango markup.
+//!
+//! ```
+//! use pango::{RichText, TextStyle, Weight};
+//!
+//! fn show_greeting(layout: &pango::Layout, user_name: &str, unread: u32) {
+//!     let text = RichText::new()
+//!         .text("Hello ")
+//!         .styled(user_name, TextStyle::new().weight(Weight::Bold))
+//!         .text(", you have ")
+//!         .styled(
+//!             &unread.to_string(),
+//!             TextStyle::new().foreground(0xffff, 0, 0).font_features("tnum"),
+//!         )
+//!         .text(" new messages");
+//!
+//!     layout.set_text(text.as_str());
+//!     layout.set_attributes(Some(&text.attr_list()));
+//! }
+//! ```
+//!
+//! [`attr_list_to_markup()`] goes the other way, for APIs that only accept
+//! markup.
+
+use crate::{
+    AttrColor, AttrFontFeatures, AttrInt, AttrList, AttrString, AttrType, Attribute, Style,
+    Underline, Weight,
+};
+use std::fmt::Write;
+
+/// The style of a [`RichText`] segment. Unset properties keep the style of
+/// the surrounding text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextStyle {
+    weight: Option<Weight>,
+    style: Option<Style>,
+    family: Option<String>,
+    size: Option<i32>,
+    foreground: Option<(u16, u16, u16)>,
+    foreground_alpha: Option<u16>,
+    background: Option<(u16, u16, u16)>,
+    background_alpha: Option<u16>,
+    underline: Option<Underline>,
+    strikethrough: Option<bool>,
+    letter_spacing: Option<i32>,
+    font_features: Option<String>,
+}
+
+impl TextStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn weight(mut self, weight: Weight) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn bold(self) -> Self {
+        self.weight(Weight::Bold)
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    pub fn italic(self) -> Self {
+        self.style(Style::Italic)
+    }
+
+    pub fn family(mut self, family: &str) -> Self {
+        self.family = Some(family.to_owned());
+        self
+    }
+
+    /// Sets the font size in Pango units, see [`SCALE`](crate::SCALE).
+    pub fn size(mut self, size: i32) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn foreground(mut self, red: u16, green: u16, blue: u16) -> Self {
+        self.foreground = Some((red, green, blue));
+        self
+    }
+
+    pub fn foreground_alpha(mut self, alpha: u16) -> Self {
+        self.foreground_alpha = Some(alpha);
+        self
+    }
+
+    pub fn background(mut self, red: u16, green: u16, blue: u16) -> Self {
+        self.background = Some((red, green, blue));
+        self
+    }
+
+    pub fn background_alpha(mut self, alpha: u16) -> Self {
+        self.background_alpha = Some(alpha);
+        self
+    }
+
+    pub fn underline(mut self, underline: Underline) -> Self {
+        self.underline = Some(underline);
+        self
+    }
+
+    pub fn strikethrough(mut self, strikethrough: bool) -> Self {
+        self.strikethrough = Some(strikethrough);
+        self
+    }
+
+    /// Sets the extra space between graphemes in Pango units.
+    pub fn letter_spacing(mut self, letter_spacing: i32) -> Self {
+        self.letter_spacing = Some(letter_spacing);
+        self
+    }
+
+    /// Sets OpenType font features in CSS syntax, e.g. `"tnum, smcp"`.
+    pub fn font_features(mut self, features: &str) -> Self {
+        self.font_features = Some(features.to_owned());
+        self
+    }
+
+    /// Creates the attributes for this style, covering all text.
+    pub fn attributes(&self) -> Vec<Attribute> {
+        let mut attrs = Vec::new();
+        if let Some(weight) = self.weight {
+            attrs.push(Attribute::new_weight(weight));
+        }
+        if let Some(style) = self.style {
+            attrs.push(Attribute::new_style(style));
+        }
+        if let Some(ref family) = self.family {
+            attrs.push(Attribute::new_family(family));
+        }
+        if let Some(size) = self.size {
+            attrs.push(Attribute::new_size(size));
+        }
+        if let Some((red, green, blue)) = self.foreground {
+            attrs.push(Attribute::new_foreground(red, green, blue));
+        }
+        if let Some(alpha) = self.foreground_alpha {
+            attrs.push(Attribute::new_foreground_alpha(alpha));
+        }
+        if let Some((red, green, blue)) = self.background {
+            attrs.push(Attribute::new_background(red, green, blue));
+        }
+        if let Some(alpha) = self.background_alpha {
+            attrs.push(Attribute::new_background_alpha(alpha));
+        }
+        if let Some(underline) = self.underline {
+            attrs.push(Attribute::new_underline(underline));
+        }
+        if let Some(strikethrough) = self.strikethrough {
+            attrs.push(Attribute::new_strikethrough(strikethrough));
+        }
+        if let Some(letter_spacing) = self.letter_spacing {
+            attrs.push(Attribute::new_letter_spacing(letter_spacing));
+        }
+        if let Some(ref features) = self.font_features {
+            attrs.push(Attribute::new_font_features(features));
+        }
+        attrs
+    }
+}
+
+/// Text built from segments with individual styles.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RichText {
+    text: String,
+    // Byte ranges into `text`.
+    spans: Vec<(usize, usize, TextStyle)>,
+}
+
+impl RichText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends unstyled text.
+    pub fn text(mut self, text: &str) -> Self {
+        self.push(text);
+        self
+    }
+
+    /// Appends text with `style`.
+    pub fn styled(mut self, text: &str, style: TextStyle) -> Self {
+        self.push_styled(text, style);
+        self
+    }
+
+    pub fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    pub fn push_styled(&mut self, text: &str, style: TextStyle) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        if !text.is_empty() {
+            self.spans.push((start, self.text.len(), style));
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Creates the attributes of all segments, with indices into
+    /// [`as_str()`](Self::as_str).
+    pub fn attr_list(&self) -> AttrList {
+        let list = AttrList::new();
+        for &(start, end, ref style) in &self.spans {
+            for mut attr in style.attributes() {
+                attr.set_start_index(start as u32);
+                attr.set_end_index(end as u32);
+                list.insert(attr);
+            }
+        }
+        list
+    }
+
+    /// Converts the text to Pango markup, see [`attr_list_to_markup()`].
+    pub fn to_markup(&self) -> String {
+        attr_list_to_markup(&self.text, &self.attr_list())
+    }
+}
+
+/// Converts `text` with the attributes `attrs` to Pango markup.
+///
+/// Every run of text with the same attributes becomes one `<span>`. Only
+/// attributes that [`TextStyle`] can set and the rise are converted, others
+/// are dropped.
+pub fn attr_list_to_markup(text: &str, attrs: &AttrList) -> String {
+    let mut iter = match attrs.iterator() {
+        Some(iter) => iter,
+        None => return glib::markup_escape_text(text).into(),
+    };
+
+    let mut markup = String::with_capacity(text.len());
+    loop {
+        let (start, end) = iter.range();
+        let start = char_boundary(text, start);
+        let end = char_boundary(text, end);
+        if start < end {
+            let escaped = glib::markup_escape_text(&text[start..end]);
+            let span = span_attributes(&iter.attrs());
+            if span.is_empty() {
+                markup.push_str(&escaped);
+            } else {
+                let _ = write!(markup, "<span{}>{}</span>", span, escaped);
+            }
+        }
+        if !iter.next() {
+            break;
+        }
+    }
+    markup
+}
+
+// Rounds a byte index from Pango up to the next character boundary.
+fn char_boundary(text: &str, index: i32) -> usize {
+    let mut index = (index.max(0) as usize).min(text.len());
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+fn span_attributes(attrs: &[Attribute]) -> String {
+    let int = |attr: &Attribute| attr.downcast_ref::<AttrInt>().map_or(0, AttrInt::value);
+    let color = |attr: &Attribute| {
+        attr.downcast_ref::<AttrColor>()
+            .map(|attr| attr.color().to_str().to_string())
+    };
+
+    let mut span = String::new();
+    for attr in attrs {
+        let (name, value) = match attr.type_() {
+            AttrType::Weight => ("weight", int(attr).to_string()),
+            AttrType::Style => (
+                "style",
+                match int(attr) {
+                    ffi::PANGO_STYLE_OBLIQUE => "oblique",
+                    ffi::PANGO_STYLE_ITALIC => "italic",
+                    _ => "normal",
+                }
+                .to_owned(),
+            ),
+            AttrType::Family => match attr.downcast_ref::<AttrString>() {
+                Some(attr) => ("font_family", attr.value().to_string()),
+                None => continue,
+            },
+            AttrType::Size => ("size", int(attr).to_string()),
+            AttrType::Foreground => match color(attr) {
+                Some(color) => ("foreground", color),
+                None => continue,
+            },
+            AttrType::ForegroundAlpha => ("fgalpha", int(attr).to_string()),
+            AttrType::Background => match color(attr) {
+                Some(color) => ("background", color),
+                None => continue,
+            },
+            AttrType::BackgroundAlpha => ("bgalpha", int(attr).to_string()),
+            AttrType::Underline => (
+                "underline",
+                match int(attr) {
+                    ffi::PANGO_UNDERLINE_NONE => "none",
+                    ffi::PANGO_UNDERLINE_DOUBLE | ffi::PANGO_UNDERLINE_DOUBLE_LINE => "double",
+                    ffi::PANGO_UNDERLINE_LOW => "low",
+                    ffi::PANGO_UNDERLINE_ERROR | ffi::PANGO_UNDERLINE_ERROR_LINE => "error",
+                    _ => "single",
+                }
+                .to_owned(),
+            ),
+            AttrType::Strikethrough => ("strikethrough", (int(attr) != 0).to_string()),
+            AttrType::LetterSpacing => ("letter_spacing", int(attr).to_string()),
+            AttrType::Rise => ("rise", int(attr).to_string()),
+            AttrType::FontFeatures => match attr.downcast_ref::<AttrFontFeatures>() {
+                Some(attr) => ("font_features", attr.features().to_string()),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let _ = write!(span, " {}=\"{}\"", name, glib::markup_escape_text(&value));
+    }
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Start and end index of each attribute type, in iteration order.
+    fn ranges(list: &AttrList) -> Vec<(AttrType, i32, i32)> {
+        let mut iter = list.iterator().unwrap();
+        let mut ranges = Vec::new();
+        loop {
+            let (start, end) = iter.range();
+            for attr in iter.attrs() {
+                ranges.push((attr.type_(), start, end));
+            }
+            if !iter.next() {
+                break;
+            }
+        }
+        ranges
+    }
+
+    #[test]
+    fn multibyte_text() {
+        let text = RichText::new()
+            .text("Grüße, ")
+            .styled("Zoë", TextStyle::new().bold())
+            .text(" ✓");
+        assert_eq!(text.as_str(), "Grüße, Zoë ✓");
+        let start = "Grüße, ".len() as i32;
+        let end = start + "Zoë".len() as i32;
+        assert_eq!(ranges(&text.attr_list()), [(AttrType::Weight, start, end)]);
+        assert_eq!(text.to_markup(), "Grüße, <span weight=\"700\">Zoë</span> ✓");
+    }
+
+    #[test]
+    fn empty_segments() {
+        let text = RichText::new()
+            .styled("", TextStyle::new().italic())
+            .text("plain");
+        assert!(!text.is_empty());
+        assert!(ranges(&text.attr_list()).is_empty());
+        assert_eq!(text.to_markup(), "plain");
+        assert!(RichText::new().is_empty());
+    }
+
+    #[test]
+    fn nested_spans() {
+        let list = AttrList::new();
+        let mut bold = Attribute::new_weight(Weight::Bold);
+        bold.set_start_index(0);
+        bold.set_end_index(9);
+        list.insert(bold);
+        let mut italic = Attribute::new_style(Style::Italic);
+        italic.set_start_index(3);
+        italic.set_end_index(6);
+        list.insert(italic);
+        let mut underline = Attribute::new_underline(Underline::Single);
+        underline.set_start_index(5);
+        underline.set_end_index(12);
+        list.insert(underline);
+
+        assert_eq!(
+            attr_list_to_markup("abcdefghijkl", &list),
+            "<span weight=\"700\">abc</span>\
+             <span weight=\"700\" style=\"italic\">de</span>\
+             <span weight=\"700\" style=\"italic\" underline=\"single\">f</span>\
+             <span weight=\"700\" underline=\"single\">ghi</span>\
+             <span underline=\"single\">jkl</span>"
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        let text = RichText::new()
+            .text("a < b & ")
+            .styled("<b>\"c\"</b>", TextStyle::new().family("Sans & Serif"));
+        assert_eq!(
+            text.to_markup(),
+            "a &lt; b &amp; <span font_family=\"Sans &amp; Serif\">\
+             &lt;b&gt;&quot;c&quot;&lt;/b&gt;</span>"
+        );
+        assert_eq!(
+            attr_list_to_markup("<&>", &AttrList::new()),
+            "&lt;&amp;&gt;"
+        );
+    }
+
+    #[test]
+    fn markup_round_trip() {
+        let markup = "plain <span weight=\"700\" style=\"italic\">both</span>\
+                      <span foreground=\"#ffff00000000\" underline=\"double\">ünïcödé</span>\
+                      <span size=\"12288\" letter_spacing=\"1024\" strikethrough=\"true\">&lt;tag&gt;</span>";
+        let (list, text, _) = crate::parse_markup(markup, '\0').unwrap();
+        let converted = attr_list_to_markup(&text, &list);
+
+        let (round_trip, round_trip_text, _) = crate::parse_markup(&converted, '\0').unwrap();
+        assert_eq!(round_trip_text, text);
+        assert_eq!(ranges(&round_trip), ranges(&list));
+        assert_eq!(
+            attr_list_to_markup(&round_trip_text, &round_trip),
+            converted
+        );
+    }
+}
@@ -0,0 +1,11 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::prelude::*;
+use crate::{Context, FontMap};
+use glib::translate::*;
+
+// Pango has no font backend of its own, so the tests borrow PangoCairo's.
+pub fn context() -> Context {
+    let font_map: FontMap = unsafe { from_glib_full(pango_cairo_sys::pango_cairo_font_map_new()) };
+    font_map.create_context().unwrap()
+}